@@ -1,9 +1,12 @@
 use crate::{Assist, AssistId, AssistCtx, ast_editor::{AstEditor, AstBuilder}};
 
 use hir::db::HirDatabase;
-use ra_syntax::{SmolStr, TreeArc};
-use ra_syntax::ast::{self, AstNode, FnDef, ImplItem, ImplItemKind, NameOwner};
+use ra_syntax::{SmolStr, SyntaxKind, SyntaxKind::*, TextRange, TreeArc};
+use ra_syntax::ast::{
+    self, AstNode, DefaultTypeParamOwner, ImplItem, ImplItemKind, NameOwner, TypeParamsOwner,
+};
 use ra_db::FilePosition;
+use ra_text_edit::TextEditBuilder;
 
 enum AddMissingImplMembersMode {
     DefaultMethodsOnly,
@@ -37,32 +40,21 @@ fn add_missing_impl_members_inner(
     let impl_node = ctx.node_at_offset::<ast::ImplBlock>()?;
     let impl_item_list = impl_node.item_list()?;
 
-    let trait_def = {
+    let missing_fns: Vec<_> = {
         let file_id = ctx.frange.file_id;
         let position = FilePosition { file_id, offset: impl_node.syntax().range().start() };
         let analyzer = hir::SourceAnalyzer::new(ctx.db, position.file_id, impl_node.syntax(), None);
 
-        resolve_target_trait_def(ctx.db, &analyzer, impl_node)?
-    };
-
-    let missing_fns: Vec<_> = {
-        let fn_def_opt = |kind| if let ImplItemKind::FnDef(def) = kind { Some(def) } else { None };
-        let def_name = |def| -> Option<&SmolStr> { FnDef::name(def).map(ast::Name::text) };
-
-        let trait_items =
-            trait_def.syntax().descendants().find_map(ast::ItemList::cast)?.impl_items();
-        let impl_items = impl_item_list.impl_items();
-
-        let trait_fns = trait_items.map(ImplItem::kind).filter_map(fn_def_opt);
-        let impl_fns = impl_items.map(ImplItem::kind).filter_map(fn_def_opt).collect::<Vec<_>>();
-
-        trait_fns
-            .filter(|t| def_name(t).is_some())
+        missing_impl_items(ctx.db, &analyzer, impl_node)
+            .into_iter()
+            .filter_map(|it| match it.kind() {
+                ImplItemKind::FnDef(def) => Some(def.to_owned()),
+                _ => None,
+            })
             .filter(|t| match mode {
                 AddMissingImplMembersMode::DefaultMethodsOnly => t.body().is_some(),
                 AddMissingImplMembersMode::NoDefaultMethods => t.body().is_none(),
             })
-            .filter(|t| impl_fns.iter().all(|i| def_name(i) != def_name(t)))
             .collect()
     };
     if missing_fns.is_empty() {
@@ -71,7 +63,7 @@ fn add_missing_impl_members_inner(
 
     ctx.add_action(AssistId(assist_id), label, |edit| {
         let n_existing_items = impl_item_list.impl_items().count();
-        let fns = missing_fns.into_iter().map(add_body_and_strip_docstring).collect::<Vec<_>>();
+        let fns = missing_fns.iter().map(|it| add_body_and_strip_docstring(it)).collect::<Vec<_>>();
 
         let mut ast_editor = AstEditor::new(impl_item_list);
         if n_existing_items == 0 {
@@ -88,7 +80,111 @@ fn add_missing_impl_members_inner(
     ctx.build()
 }
 
-fn add_body_and_strip_docstring(fn_def: &ast::FnDef) -> TreeArc<ast::FnDef> {
+/// Returns the items of the trait implemented by `impl_block` which the impl
+/// block does not define yet. The type parameters of the trait are replaced
+/// by the type arguments of the impl, so `fn f(&self, t: T)` from `trait
+/// Foo<T>` becomes `fn f(&self, t: u32)` for `impl Foo<u32> for S`.
+pub fn missing_impl_items(
+    db: &impl HirDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    impl_block: &ast::ImplBlock,
+) -> Vec<TreeArc<ast::ImplItem>> {
+    let trait_def = match resolve_target_trait_def(db, analyzer, impl_block) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let trait_items = match trait_def.item_list() {
+        Some(it) => it.impl_items(),
+        None => return Vec::new(),
+    };
+    let impl_items = match impl_block.item_list() {
+        Some(it) => it.impl_items().filter_map(item_key).collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    let substs = trait_substs(&trait_def, impl_block);
+
+    trait_items
+        .filter(|t| match item_key(t) {
+            Some(key) => !impl_items.contains(&key),
+            None => false,
+        })
+        .map(|t| substitute_type_params(t, &substs))
+        .collect()
+}
+
+/// Identifies an impl item by its kind and name, as functions, types and
+/// constants live in different namespaces.
+fn item_key(item: &ImplItem) -> Option<(SyntaxKind, &SmolStr)> {
+    let name = match item.kind() {
+        ImplItemKind::FnDef(it) => it.name(),
+        ImplItemKind::TypeAliasDef(it) => it.name(),
+        ImplItemKind::ConstDef(it) => it.name(),
+    };
+    Some((item.syntax().kind(), name?.text()))
+}
+
+/// Pairs the names of the type parameters of the trait with the text of the
+/// types the impl block uses for them.
+fn trait_substs(trait_def: &ast::TraitDef, impl_block: &ast::ImplBlock) -> Vec<(SmolStr, String)> {
+    let type_params = match trait_def.type_param_list() {
+        Some(it) => it.type_params(),
+        None => return Vec::new(),
+    };
+    let type_args = impl_block
+        .target_trait()
+        .map(AstNode::syntax)
+        .and_then(ast::PathType::cast)
+        .and_then(|it| it.path()?.segment()?.type_arg_list())
+        .map(|it| it.type_args().filter_map(|it| it.type_ref()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    type_params
+        .enumerate()
+        .filter_map(|(idx, param)| {
+            let ty = match type_args.get(idx) {
+                Some(arg) => arg.syntax().text().to_string(),
+                None => param.default_type()?.syntax().text().to_string(),
+            };
+            Some((param.name()?.text().clone(), ty))
+        })
+        .collect()
+}
+
+fn substitute_type_params(
+    item: &ast::ImplItem,
+    substs: &[(SmolStr, String)],
+) -> TreeArc<ast::ImplItem> {
+    if substs.is_empty() {
+        return item.to_owned();
+    }
+    let item_start = item.syntax().range().start();
+    let mut edit = TextEditBuilder::default();
+    for path in item.syntax().descendants().filter_map(ast::Path::cast) {
+        let name_ref = Some(path)
+            .filter(|it| it.qualifier().is_none())
+            .and_then(|it| it.segment())
+            .filter(|it| it.type_arg_list().is_none())
+            .and_then(|it| it.name_ref());
+        let ty = match name_ref {
+            Some(name_ref) => substs.iter().find(|(param, _)| param == name_ref.text()),
+            None => None,
+        };
+        let ty = match (ty, path.syntax().parent().map(|it| it.kind())) {
+            (Some((_, ty)), Some(PATH_TYPE)) => ty.clone(),
+            // The first segment of a qualified path, like `T` in `T::Item`.
+            (Some((_, ty)), Some(PATH)) => format!("<{}>", ty),
+            _ => continue,
+        };
+        let range = path.syntax().range();
+        edit.replace(TextRange::offset_len(range.start() - item_start, range.len()), ty);
+    }
+    let text = edit.finish().apply(&item.syntax().text().to_string());
+    AstBuilder::<ast::ImplItem>::from_text(&text)
+}
+
+/// Renders a trait method as a method of the impl, with a dummy body if the
+/// trait does not provide a default one.
+pub fn add_body_and_strip_docstring(fn_def: &ast::FnDef) -> TreeArc<ast::FnDef> {
     let mut ast_editor = AstEditor::new(fn_def);
     if fn_def.body().is_none() {
         ast_editor.set_body(&AstBuilder::<ast::Block>::single_expr(
@@ -275,6 +371,48 @@ impl Foo for S {
         )
     }
 
+    #[test]
+    fn test_substitute_type_params() {
+        check_assist(
+            add_missing_impl_members,
+            "
+trait Foo<T, U = Self> {
+    fn foo(&self, t: T, u: U) -> Vec<T>;
+}
+struct S;
+impl Foo<u32> for S { <|> }",
+            "
+trait Foo<T, U = Self> {
+    fn foo(&self, t: T, u: U) -> Vec<T>;
+}
+struct S;
+impl Foo<u32> for S {
+    <|>fn foo(&self, t: u32, u: Self) -> Vec<u32> { unimplemented!() }
+}",
+        )
+    }
+
+    #[test]
+    fn test_substitute_type_params_in_qualified_paths() {
+        check_assist(
+            add_missing_impl_members,
+            "
+trait Foo<T> {
+    fn foo(&self, t: T::Item) -> <T as Iterator>::Item;
+}
+struct S;
+impl Foo<Vec<u32>> for S { <|> }",
+            "
+trait Foo<T> {
+    fn foo(&self, t: T::Item) -> <T as Iterator>::Item;
+}
+struct S;
+impl Foo<Vec<u32>> for S {
+    <|>fn foo(&self, t: <Vec<u32>>::Item) -> <Vec<u32> as Iterator>::Item { unimplemented!() }
+}",
+        )
+    }
+
     #[test]
     fn test_default_methods() {
         check_assist(
@@ -276,6 +276,12 @@ impl AstBuilder<ast::Expr> {
     }
 }
 
+impl AstBuilder<ast::ImplItem> {
+    pub fn from_text(text: &str) -> TreeArc<ast::ImplItem> {
+        ast_node_from_file_text(&format!("impl S {{ {} }}", text))
+    }
+}
+
 impl AstBuilder<ast::NameRef> {
     pub fn new(text: &str) -> TreeArc<ast::NameRef> {
         ast_node_from_file_text(&format!("fn f() {{ {}; }}", text))
@@ -70,6 +70,7 @@ fn compare_path_segment(a: &SmolStr, b: &ast::PathSegment) -> bool {
             ast::PathSegmentKind::SelfKw => a == "self",
             ast::PathSegmentKind::SuperKw => a == "super",
             ast::PathSegmentKind::CrateKw => a == "crate",
+            ast::PathSegmentKind::Type { .. } => false,
         }
     } else {
         false
@@ -501,6 +502,7 @@ pub fn collect_hir_path_segments(path: &hir::Path) -> Vec<SmolStr> {
         hir::PathKind::Plain => {}
         hir::PathKind::Self_ => ps.push("self".into()),
         hir::PathKind::Super => ps.push("super".into()),
+        hir::PathKind::DollarCrate(_) => ps.push("$crate".into()),
     }
     for s in path.segments.iter() {
         ps.push(s.name.to_string().into());
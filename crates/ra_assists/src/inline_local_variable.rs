@@ -51,6 +51,7 @@ pub(crate) fn inline_local_varialbe(mut ctx: AssistCtx<impl HirDatabase>) -> Opt
             | (ExprKind::MethodCallExpr(_), _)
             | (ExprKind::FieldExpr(_), _)
             | (ExprKind::TryExpr(_), _)
+            | (ExprKind::AwaitExpr(_), _)
             | (ExprKind::RefExpr(_), _)
             | (ExprKind::Literal(_), _)
             | (ExprKind::TupleExpr(_), _)
@@ -17,6 +17,7 @@ use ra_db::FileRange;
 use hir::db::HirDatabase;
 
 pub(crate) use crate::assist_ctx::{AssistCtx, Assist};
+pub use crate::add_missing_impl_members::{missing_impl_items, add_body_and_strip_docstring};
 
 /// Unique identifier of the assist, should not be shown to the user
 /// directly.
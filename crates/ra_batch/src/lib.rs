@@ -1,17 +1,19 @@
 mod vfs_filter;
 
 use std::sync::Arc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::HashSet;
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use relative_path::RelativePathBuf;
 
 use ra_db::{
     CrateGraph, FileId, SourceRoot, SourceRootId, SourceDatabase, salsa,
 };
 use ra_hir::db;
-use ra_project_model::ProjectWorkspace;
-use ra_vfs::{Vfs, VfsChange};
+use ra_ide_api::{AnalysisChange, AnalysisHost};
+use ra_project_model::{ProjectWorkspace, get_rustc_cfg_options};
+use ra_vfs::{Vfs, VfsChange, VfsFile};
 use vfs_filter::IncludeRustFiles;
 
 type Result<T> = std::result::Result<T, failure::Error>;
@@ -40,77 +42,135 @@ impl BatchDatabase {
         let mut db = BatchDatabase { runtime: salsa::Runtime::default() };
         db.set_crate_graph(Arc::new(crate_graph));
 
-        // wait until Vfs has loaded all roots
-        let receiver = vfs.task_receiver().clone();
-        let mut roots_loaded = HashSet::new();
-        for task in receiver {
-            vfs.handle_task(task);
-            let mut done = false;
-            for change in vfs.commit_changes() {
-                match change {
-                    VfsChange::AddRoot { root, files } => {
-                        let source_root_id = vfs_root_to_id(root);
-                        log::debug!(
-                            "loaded source root {:?} with path {:?}",
-                            source_root_id,
-                            vfs.root2path(root)
-                        );
-                        let mut file_map = FxHashMap::default();
-                        for (vfs_file, path, text) in files {
-                            let file_id = vfs_file_to_id(vfs_file);
-                            db.set_file_text(file_id, text);
-                            db.set_file_relative_path(file_id, path.clone());
-                            db.set_file_source_root(file_id, source_root_id);
-                            file_map.insert(path, file_id);
-                        }
-                        let source_root = SourceRoot { files: file_map };
-                        db.set_source_root(source_root_id, Arc::new(source_root));
-                        roots_loaded.insert(source_root_id);
-                        if roots_loaded.len() == vfs.n_roots() {
-                            done = true;
-                        }
-                    }
-                    VfsChange::AddFile { .. }
-                    | VfsChange::RemoveFile { .. }
-                    | VfsChange::ChangeFile { .. } => {
-                        // We just need the first scan, so just ignore these
-                    }
-                }
+        load_roots(vfs, |source_root_id, _root_path, files| {
+            let mut file_map = FxHashMap::default();
+            for (vfs_file, path, text) in files {
+                let file_id = vfs_file_to_id(vfs_file);
+                db.set_file_text(file_id, text);
+                db.set_file_reparse_hint(file_id, None);
+                db.set_file_relative_path(file_id, path.clone());
+                db.set_file_source_root(file_id, source_root_id);
+                file_map.insert(path, file_id);
             }
-            if done {
-                break;
-            }
-        }
+            let source_root = SourceRoot { files: file_map };
+            db.set_source_root(source_root_id, Arc::new(source_root));
+        });
 
         db
     }
 
     pub fn load_cargo(root: impl AsRef<Path>) -> Result<(BatchDatabase, Vec<SourceRootId>)> {
-        let root = std::env::current_dir()?.join(root);
-        let ws = ProjectWorkspace::discover(root.as_ref())?;
-        let mut roots = Vec::new();
-        roots.push(IncludeRustFiles::member(root.clone()));
-        roots.extend(IncludeRustFiles::from_roots(ws.to_roots()));
-        let (mut vfs, roots) = Vfs::new(roots);
-        let mut load = |path: &Path| {
-            let vfs_file = vfs.load(path);
-            log::debug!("vfs file {:?} -> {:?}", path, vfs_file);
-            vfs_file.map(vfs_file_to_id)
-        };
-        let crate_graph = ws.to_crate_graph(&mut load);
-        log::debug!("crate graph: {:?}", crate_graph);
-
-        let local_roots = roots
-            .into_iter()
-            .filter(|r| vfs.root2path(*r).starts_with(&root))
-            .map(vfs_root_to_id)
-            .collect();
-
+        let (crate_graph, mut vfs, local_roots) = load_cargo_workspace(root.as_ref())?;
         let db = BatchDatabase::load(crate_graph, &mut vfs);
         Ok((db, local_roots))
     }
 }
 
+/// Loads a cargo workspace into an `AnalysisHost`, for the tools which need
+/// the IDE features rather than the `hir` database. Returns the files of the
+/// workspace members too, with their paths on disk.
+pub fn load_cargo_analysis_host(
+    root: impl AsRef<Path>,
+) -> Result<(AnalysisHost, Vec<(PathBuf, FileId)>)> {
+    let (crate_graph, mut vfs, local_roots) = load_cargo_workspace(root.as_ref())?;
+    let mut change = AnalysisChange::new();
+    change.set_crate_graph(crate_graph);
+    let mut local_files = Vec::new();
+    load_roots(&mut vfs, |source_root_id, root_path, files| {
+        let is_local = local_roots.contains(&source_root_id);
+        change.add_root(source_root_id, is_local);
+        for (vfs_file, path, text) in files {
+            let file_id = vfs_file_to_id(vfs_file);
+            if is_local {
+                local_files.push((path.to_path(&root_path), file_id));
+            }
+            change.add_file(source_root_id, file_id, path, text);
+        }
+    });
+    local_files.sort();
+
+    let mut host = AnalysisHost::default();
+    host.apply_change(change);
+    Ok((host, local_files))
+}
+
+/// Discovers the cargo workspace at `root` and starts loading its source
+/// roots. Returns the crate graph, the `Vfs` and the source roots of the
+/// workspace members.
+fn load_cargo_workspace(root: &Path) -> Result<(CrateGraph, Vfs, Vec<SourceRootId>)> {
+    let root = std::env::current_dir()?.join(root);
+    let ws = ProjectWorkspace::discover(root.as_ref())?;
+    let mut roots = Vec::new();
+    roots.push(IncludeRustFiles::member(root.clone()));
+    let ws_roots = ws.to_roots();
+    // Packages which are not workspace members are libraries, even if they are
+    // inside of `root`.
+    let library_roots: FxHashSet<PathBuf> =
+        ws_roots.iter().filter(|it| !it.is_member()).map(|it| it.path().clone()).collect();
+    roots.extend(IncludeRustFiles::from_roots(ws_roots));
+    let (mut vfs, roots) = Vfs::new(roots);
+    let mut load = |path: &Path| {
+        let vfs_file = vfs.load(path);
+        log::debug!("vfs file {:?} -> {:?}", path, vfs_file);
+        vfs_file.map(vfs_file_to_id)
+    };
+    // `cfg(test)` code is always analyzed, so that tests get completion and
+    // diagnostics like the rest of the code.
+    let default_cfg_options = get_rustc_cfg_options().atom("test".into());
+
+    let crate_graph = ws.to_crate_graph(&default_cfg_options, &mut load);
+    log::debug!("crate graph: {:?}", crate_graph);
+
+    let local_roots = roots
+        .into_iter()
+        .filter(|r| {
+            let path = vfs.root2path(*r);
+            !library_roots.contains(&path) && path.starts_with(&root)
+        })
+        .map(vfs_root_to_id)
+        .collect();
+    Ok((crate_graph, vfs, local_roots))
+}
+
+/// Waits until the `Vfs` has scanned all roots, passing the directory and the
+/// files of every root to `f`.
+fn load_roots(
+    vfs: &mut Vfs,
+    mut f: impl FnMut(SourceRootId, PathBuf, Vec<(VfsFile, RelativePathBuf, Arc<String>)>),
+) {
+    let receiver = vfs.task_receiver().clone();
+    let mut roots_loaded = HashSet::new();
+    for task in receiver {
+        vfs.handle_task(task);
+        let mut done = false;
+        for change in vfs.commit_changes() {
+            match change {
+                VfsChange::AddRoot { root, files } => {
+                    let source_root_id = vfs_root_to_id(root);
+                    log::debug!(
+                        "loaded source root {:?} with path {:?}",
+                        source_root_id,
+                        vfs.root2path(root)
+                    );
+                    f(source_root_id, vfs.root2path(root), files);
+                    roots_loaded.insert(source_root_id);
+                    if roots_loaded.len() == vfs.n_roots() {
+                        done = true;
+                    }
+                }
+                VfsChange::AddFile { .. }
+                | VfsChange::RemoveFile { .. }
+                | VfsChange::ChangeFile { .. } => {
+                    // We just need the first scan, so just ignore these
+                }
+            }
+        }
+        if done {
+            break;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ra_hir::Crate;
@@ -0,0 +1,105 @@
+//! ra_cfg defines conditional compiling options, `cfg` attibute parser and evaluator
+mod cfg_expr;
+
+use rustc_hash::FxHashSet;
+use ra_syntax::SmolStr;
+use tt::Subtree;
+
+pub use cfg_expr::{parse_cfg, CfgExpr};
+
+/// Configuration options used for conditional compilition on items with `cfg` attributes.
+/// We have two kind of options in different namespaces: atomic options like `unix`, and
+/// key-value options like `target_arch="x86"`.
+///
+/// Note that for key-value options, one key can have multiple values (but not none).
+/// `feature` is an example. We have both `feature="foo"` and `feature="bar"` if features
+/// `foo` and `bar` are both enabled. And here, we store key-value options as a set of tuple
+/// of key and value in `key_values`.
+///
+/// See: https://doc.rust-lang.org/reference/conditional-compilation.html#set-configuration-options
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CfgOptions {
+    atoms: FxHashSet<SmolStr>,
+    key_values: FxHashSet<(SmolStr, SmolStr)>,
+}
+
+impl CfgOptions {
+    pub fn check(&self, cfg: &CfgExpr) -> Option<bool> {
+        cfg.fold(&|key, value| match value {
+            None => self.atoms.contains(key),
+            Some(value) => self.key_values.contains(&(key.clone(), value.clone())),
+        })
+    }
+
+    /// Evaluates the `cfg` predicate given as the argument token tree of a
+    /// `#[cfg(...)]` attribute. Returns `None` if the predicate is malformed.
+    pub fn is_cfg_enabled(&self, attr: &Subtree) -> Option<bool> {
+        self.check(&parse_cfg(attr))
+    }
+
+    pub fn atom(mut self, name: SmolStr) -> CfgOptions {
+        self.atoms.insert(name);
+        self
+    }
+
+    pub fn key_value(mut self, key: SmolStr, value: SmolStr) -> CfgOptions {
+        self.key_values.insert((key, value));
+        self
+    }
+
+    /// Shortcut for `key_value("feature".into(), name)`.
+    pub fn feature(self, name: SmolStr) -> CfgOptions {
+        self.key_value("feature".into(), name)
+    }
+
+    pub fn remove_atom(mut self, name: &SmolStr) -> CfgOptions {
+        self.atoms.remove(name);
+        self
+    }
+
+    /// Parses a single line of `rustc --print cfg` output, like `unix` or
+    /// `target_os="linux"`, and adds it to the options.
+    pub fn add_from_rustc_output(self, line: &str) -> CfgOptions {
+        let line = line.trim();
+        match line.find('=') {
+            None => self.atom(line.into()),
+            Some(pos) => {
+                let key = line[..pos].trim();
+                let value = line[pos + 1..].trim().trim_start_matches('"').trim_end_matches('"');
+                self.key_value(key.into(), value.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cfg_options_check() {
+        let cfg = CfgOptions::default()
+            .atom("unix".into())
+            .add_from_rustc_output("target_os=\"linux\"")
+            .feature("std".into());
+
+        let check = |expr| cfg.check(&expr);
+        assert_eq!(check(CfgExpr::Atom("unix".into())), Some(true));
+        assert_eq!(check(CfgExpr::Atom("windows".into())), Some(false));
+        assert_eq!(
+            check(CfgExpr::KeyValue { key: "target_os".into(), value: "linux".into() }),
+            Some(true)
+        );
+        assert_eq!(
+            check(CfgExpr::Not(Box::new(CfgExpr::KeyValue {
+                key: "feature".into(),
+                value: "std".into()
+            }))),
+            Some(false)
+        );
+        assert_eq!(
+            check(CfgExpr::Any(vec![CfgExpr::Atom("windows".into()), CfgExpr::Invalid])),
+            None
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use ra_batch::load_cargo_analysis_host;
+use ra_ide_api::{Analysis, FileId, LineCol, Severity};
+
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Human,
+    Json,
+}
+
+/// Prints the diagnostics of all files in the workspace member source roots
+/// and returns whether any of them is an error.
+pub fn run(path: &str, format: Format) -> Result<bool> {
+    let (host, files) = load_cargo_analysis_host(path)?;
+    let current_dir = std::env::current_dir()?;
+    let files = files
+        .into_iter()
+        .map(|(path, file_id)| {
+            (path.strip_prefix(&current_dir).map(PathBuf::from).unwrap_or(path), file_id)
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = Vec::new();
+    let counts = check(&host.analysis(), &files, format, &mut out)?;
+    for line in out {
+        println!("{}", line);
+    }
+    if format == Format::Human {
+        println!("{} errors, {} warnings", counts.errors, counts.warnings);
+    }
+    Ok(counts.errors > 0)
+}
+
+/// Number of diagnostics found, by severity.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Counts {
+    errors: usize,
+    warnings: usize,
+}
+
+/// Formats the diagnostics of `files` into `out` and counts them. Hints,
+/// like the ones for code disabled by `#[cfg]`, are skipped.
+fn check(
+    analysis: &Analysis,
+    files: &[(PathBuf, FileId)],
+    format: Format,
+    out: &mut Vec<String>,
+) -> Result<Counts> {
+    let mut counts = Counts::default();
+    for (path, file_id) in files {
+        let line_index = analysis.file_line_index(*file_id);
+        for diagnostic in analysis.diagnostics(*file_id)? {
+            match diagnostic.severity {
+                Severity::Error => counts.errors += 1,
+                Severity::WeakWarning => counts.warnings += 1,
+                Severity::Hint => continue,
+            }
+            let start = line_index.line_col(diagnostic.range.start());
+            let end = line_index.line_col(diagnostic.range.end());
+            let severity = diagnostic.severity;
+            let message = &diagnostic.message;
+            out.push(format_diagnostic(format, path, severity, start, end, message));
+        }
+    }
+    Ok(counts)
+}
+
+fn format_diagnostic(
+    format: Format,
+    path: &Path,
+    severity: Severity,
+    start: LineCol,
+    end: LineCol,
+    message: &str,
+) -> String {
+    let severity = match severity {
+        Severity::Error => "error",
+        Severity::WeakWarning => "warning",
+        Severity::Hint => "hint",
+    };
+    match format {
+        Format::Human => format!(
+            "{}:{}:{}: {}: {}",
+            path.display(),
+            start.line + 1,
+            start.col_utf16 + 1,
+            severity,
+            message
+        ),
+        Format::Json => serde_json::json!({
+            "file": path.display().to_string(),
+            "start": position_to_json(start),
+            "end": position_to_json(end),
+            "severity": severity,
+            "message": message,
+        })
+        .to_string(),
+    }
+}
+
+fn position_to_json(line_col: LineCol) -> serde_json::Value {
+    serde_json::json!({ "line": line_col.line + 1, "column": line_col.col_utf16 + 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_ide_api::mock_analysis::MockAnalysis;
+
+    use super::*;
+
+    #[test]
+    fn test_check_counts_errors_and_skips_inactive_code() {
+        let mock = MockAnalysis::with_files(
+            r#"
+//- /lib.rs
+mod foo;
+mod missing;
+
+#[cfg(feature = "never")]
+fn inactive() {}
+
+//- /foo.rs
+#[cfg(feature = "never")]
+mod inactive;
+"#,
+        );
+        let files = vec![
+            (PathBuf::from("src/lib.rs"), mock.id_of("/lib.rs")),
+            (PathBuf::from("src/foo.rs"), mock.id_of("/foo.rs")),
+        ];
+        let analysis = mock.analysis();
+
+        let mut out = Vec::new();
+        let counts = check(&analysis, &files, Format::Human, &mut out).unwrap();
+        assert_eq!(counts, Counts { errors: 1, warnings: 0 });
+        assert_eq!(out, vec!["src/lib.rs:2:1: error: unresolved module".to_string()]);
+
+        // Only inactive code is no reason to fail.
+        let mut out = Vec::new();
+        let counts = check(&analysis, &files[1..], Format::Human, &mut out).unwrap();
+        assert_eq!(counts, Counts { errors: 0, warnings: 0 });
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_format_diagnostic() {
+        let path = Path::new("src/lib.rs");
+        let start = LineCol { line: 2, col_utf16: 4 };
+        let end = LineCol { line: 2, col_utf16: 9 };
+
+        let human = format_diagnostic(
+            Format::Human,
+            path,
+            Severity::Error,
+            start,
+            end,
+            "unresolved module",
+        );
+        assert_eq!(human, "src/lib.rs:3:5: error: unresolved module");
+
+        let json = format_diagnostic(
+            Format::Json,
+            path,
+            Severity::WeakWarning,
+            start,
+            end,
+            "unused variable",
+        );
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "file": "src/lib.rs",
+                "start": { "line": 3, "column": 5 },
+                "end": { "line": 3, "column": 10 },
+                "severity": "warning",
+                "message": "unused variable",
+            })
+        );
+    }
+}
@@ -1,4 +1,5 @@
 mod analysis_stats;
+mod diagnostics;
 
 use std::io::Read;
 
@@ -22,6 +23,17 @@ fn main() -> Result<()> {
                 .arg(Arg::with_name("only").short("o").takes_value(true))
                 .arg(Arg::with_name("path")),
         )
+        .subcommand(
+            SubCommand::with_name("diagnostics")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["human", "json"])
+                        .default_value("human"),
+                )
+                .arg(Arg::with_name("path")),
+        )
         .get_matches();
     match matches.subcommand() {
         ("parse", Some(matches)) => {
@@ -44,6 +56,17 @@ fn main() -> Result<()> {
             let only = matches.value_of("only");
             analysis_stats::run(verbose, path, only)?;
         }
+        ("diagnostics", Some(matches)) => {
+            let path = matches.value_of("path").unwrap_or("");
+            let format = match matches.value_of("format") {
+                Some("json") => diagnostics::Format::Json,
+                _ => diagnostics::Format::Human,
+            };
+            let has_errors = diagnostics::run(path, format)?;
+            if has_errors {
+                std::process::exit(1);
+            }
+        }
         _ => unreachable!(),
     }
     Ok(())
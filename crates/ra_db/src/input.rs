@@ -5,9 +5,12 @@
 /// Note that neither this module, nor any other part of the analyzer's core do
 /// actual IO. See `vfs` and `project_model` in the `ra_lsp_server` crate for how
 /// actual IO is done and lowered to input.
+use std::path::{Path, PathBuf};
+
 use relative_path::RelativePathBuf;
 use rustc_hash::FxHashMap;
 
+use ra_cfg::CfgOptions;
 use ra_syntax::SmolStr;
 use rustc_hash::FxHashSet;
 
@@ -25,7 +28,7 @@ pub struct FileId(pub u32);
 /// the nearest enclosing source root. Paths to files are always relative to a
 /// source root, and the analyzer does not know the root path of the source root at
 /// all. So, a file from one source root can't refer to a file in another source
-/// root by path.
+/// root by path, except for the absolute paths of a crate's `ExternSource`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct SourceRootId(pub u32);
 
@@ -36,7 +39,7 @@ pub struct SourceRoot {
 
 /// `CrateGraph` is a bit of information which turns a set of text files into a
 /// number of Rust crates. Each crate is defined by the `FileId` of its root module,
-/// the set of cfg flags (not yet implemented) and the set of dependencies. Note
+/// the set of cfg flags and the set of dependencies. Note
 /// that, due to cfg's, there might be several crates for a single `FileId`! As
 /// in the rust-lang proper, a crate does not have a name. Instead, names are
 /// specified on dependency edges. That is, a crate might be known under
@@ -71,16 +74,70 @@ impl Edition {
     }
 }
 
+/// The environment variables visible to a crate at compile time, as read by
+/// the `env!` macro.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Env {
+    entries: FxHashMap<String, String>,
+}
+
+impl Env {
+    pub fn set(&mut self, env: &str, value: String) {
+        self.entries.insert(env.to_owned(), value);
+    }
+
+    pub fn get(&self, env: &str) -> Option<String> {
+        self.entries.get(env).cloned()
+    }
+}
+
+/// The source roots outside of the crate which it includes files from by
+/// absolute path, like the code generated by its build script into `OUT_DIR`,
+/// together with the absolute paths of the source roots.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ExternSource {
+    extern_paths: FxHashMap<PathBuf, SourceRootId>,
+}
+
+impl ExternSource {
+    pub fn set_extern_path(&mut self, root_path: &Path, root: SourceRootId) {
+        self.extern_paths.insert(root_path.to_path_buf(), root);
+    }
+
+    /// Returns the source root of the file at the absolute `path`, and the path
+    /// of the file relative to it. Like for nested source roots, the file
+    /// belongs to the nearest enclosing root.
+    pub fn extern_path(&self, path: &Path) -> Option<(SourceRootId, RelativePathBuf)> {
+        let (root_path, root) = self
+            .extern_paths
+            .iter()
+            .filter(|(root_path, _)| path.starts_with(root_path))
+            .max_by_key(|(root_path, _)| root_path.components().count())?;
+        let rel_path = RelativePathBuf::from_path(path.strip_prefix(root_path).ok()?).ok()?;
+        Some((*root, rel_path))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct CrateData {
     file_id: FileId,
     edition: Edition,
+    cfg_options: CfgOptions,
+    env: Env,
+    extern_source: ExternSource,
     dependencies: Vec<Dependency>,
 }
 
 impl CrateData {
-    fn new(file_id: FileId, edition: Edition) -> CrateData {
-        CrateData { file_id, edition, dependencies: Vec::new() }
+    fn new(file_id: FileId, edition: Edition, cfg_options: CfgOptions, env: Env) -> CrateData {
+        CrateData {
+            file_id,
+            edition,
+            cfg_options,
+            env,
+            extern_source: ExternSource::default(),
+            dependencies: Vec::new(),
+        }
     }
 
     fn add_dep(&mut self, name: SmolStr, crate_id: CrateId) {
@@ -101,9 +158,15 @@ impl Dependency {
 }
 
 impl CrateGraph {
-    pub fn add_crate_root(&mut self, file_id: FileId, edition: Edition) -> CrateId {
+    pub fn add_crate_root(
+        &mut self,
+        file_id: FileId,
+        edition: Edition,
+        cfg_options: CfgOptions,
+        env: Env,
+    ) -> CrateId {
         let crate_id = CrateId(self.arena.len() as u32);
-        let prev = self.arena.insert(crate_id, CrateData::new(file_id, edition));
+        let prev = self.arena.insert(crate_id, CrateData::new(file_id, edition, cfg_options, env));
         assert!(prev.is_none());
         crate_id
     }
@@ -136,6 +199,22 @@ impl CrateGraph {
         self.arena[&crate_id].edition
     }
 
+    pub fn cfg_options(&self, crate_id: CrateId) -> &CfgOptions {
+        &self.arena[&crate_id].cfg_options
+    }
+
+    pub fn env(&self, crate_id: CrateId) -> &Env {
+        &self.arena[&crate_id].env
+    }
+
+    pub fn set_extern_source(&mut self, crate_id: CrateId, extern_source: ExternSource) {
+        self.arena.get_mut(&crate_id).unwrap().extern_source = extern_source;
+    }
+
+    pub fn extern_source(&self, crate_id: CrateId) -> &ExternSource {
+        &self.arena[&crate_id].extern_source
+    }
+
     // FIXME: this only finds one crate with the given root; we could have multiple
     pub fn crate_id_for_crate_root(&self, file_id: FileId) -> Option<CrateId> {
         let (&crate_id, _) = self.arena.iter().find(|(_crate_id, data)| data.file_id == file_id)?;
@@ -183,14 +262,22 @@ impl CrateGraph {
 
 #[cfg(test)]
 mod tests {
-    use super::{CrateGraph, FileId, SmolStr, Edition::Edition2018};
+    use std::path::Path;
+
+    use super::{
+        CfgOptions, CrateGraph, Env, ExternSource, FileId, SmolStr, SourceRootId,
+        Edition::Edition2018,
+    };
 
     #[test]
     fn it_should_panic_because_of_cycle_dependencies() {
         let mut graph = CrateGraph::default();
-        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
-        let crate2 = graph.add_crate_root(FileId(2u32), Edition2018);
-        let crate3 = graph.add_crate_root(FileId(3u32), Edition2018);
+        let crate1 =
+            graph.add_crate_root(FileId(1u32), Edition2018, CfgOptions::default(), Env::default());
+        let crate2 =
+            graph.add_crate_root(FileId(2u32), Edition2018, CfgOptions::default(), Env::default());
+        let crate3 =
+            graph.add_crate_root(FileId(3u32), Edition2018, CfgOptions::default(), Env::default());
         assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
         assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
         assert!(graph.add_dep(crate3, SmolStr::new("crate1"), crate1).is_err());
@@ -199,10 +286,24 @@ mod tests {
     #[test]
     fn it_works() {
         let mut graph = CrateGraph::default();
-        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
-        let crate2 = graph.add_crate_root(FileId(2u32), Edition2018);
-        let crate3 = graph.add_crate_root(FileId(3u32), Edition2018);
+        let crate1 =
+            graph.add_crate_root(FileId(1u32), Edition2018, CfgOptions::default(), Env::default());
+        let crate2 =
+            graph.add_crate_root(FileId(2u32), Edition2018, CfgOptions::default(), Env::default());
+        let crate3 =
+            graph.add_crate_root(FileId(3u32), Edition2018, CfgOptions::default(), Env::default());
         assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
         assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
     }
+
+    #[test]
+    fn extern_path_is_relative_to_the_nearest_root() {
+        let mut extern_source = ExternSource::default();
+        extern_source.set_extern_path(Path::new("/target"), SourceRootId(1));
+        extern_source.set_extern_path(Path::new("/target/out"), SourceRootId(2));
+        let (root, rel_path) = extern_source.extern_path(Path::new("/target/out/gen.rs")).unwrap();
+        assert_eq!(root, SourceRootId(2));
+        assert_eq!(rel_path, "gen.rs");
+        assert!(extern_source.extern_path(Path::new("/src/lib.rs")).is_none());
+    }
 }
@@ -4,15 +4,18 @@ mod input;
 
 use std::{panic, sync::Arc};
 
-use ra_syntax::{TextUnit, TextRange, SourceFile, TreeArc};
+use ra_syntax::{TextUnit, TextRange, SourceFile, TreeArc, AstNode};
+use ra_text_edit::AtomTextEdit;
 use relative_path::RelativePathBuf;
 use ra_prof::profile;
+use parking_lot::Mutex;
 
 pub use ::salsa as salsa;
 pub use crate::{
     cancellation::Canceled,
     input::{
-        FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, Dependency, Edition,
+        FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, Dependency, Edition, Env,
+        ExternSource,
     },
 };
 
@@ -65,6 +68,35 @@ pub struct FileRange {
     pub range: TextRange,
 }
 
+/// The syntax tree of the previous version of a file, together with the edit
+/// which turned it into the current text of the file.
+#[derive(Debug)]
+pub struct ReparseHint {
+    /// Taken by `parse` once it has reparsed the file incrementally, so that
+    /// the hint doesn't keep the old tree alive.
+    old_tree: Mutex<Option<TreeArc<SourceFile>>>,
+    edit: AtomTextEdit,
+}
+
+impl ReparseHint {
+    pub fn new(old_tree: TreeArc<SourceFile>, edit: AtomTextEdit) -> ReparseHint {
+        ReparseHint { old_tree: Mutex::new(Some(old_tree)), edit }
+    }
+
+    /// Whether the file has been reparsed incrementally using this hint.
+    pub fn is_used(&self) -> bool {
+        self.old_tree.lock().is_none()
+    }
+}
+
+impl PartialEq for ReparseHint {
+    fn eq(&self, other: &ReparseHint) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for ReparseHint {}
+
 /// Database which stores all significant input facts: source code and project
 /// model. Everything else in rust-analyzer is derived from these queries.
 #[salsa::query_group(SourceDatabaseStorage)]
@@ -72,6 +104,10 @@ pub trait SourceDatabase: CheckCanceled + std::fmt::Debug {
     /// Text of the file.
     #[salsa::input]
     fn file_text(&self, file_id: FileId) -> Arc<String>;
+    /// If set, `parse` tries to reparse the file incrementally instead of
+    /// parsing the whole text from scratch.
+    #[salsa::input]
+    fn file_reparse_hint(&self, file_id: FileId) -> Option<Arc<ReparseHint>>;
     // Parses the file into the syntax tree.
     #[salsa::invoke(parse_query)]
     fn parse(&self, file_id: FileId) -> TreeArc<SourceFile>;
@@ -101,5 +137,18 @@ fn source_root_crates(db: &impl SourceDatabase, id: SourceRootId) -> Arc<Vec<Cra
 fn parse_query(db: &impl SourceDatabase, file_id: FileId) -> TreeArc<SourceFile> {
     let _p = profile("parse_query");
     let text = db.file_text(file_id);
+    if let Some(hint) = db.file_reparse_hint(file_id) {
+        let mut old_tree = hint.old_tree.lock();
+        // The hint might be outdated if the text was set without it, so make
+        // sure that the reparsed tree matches the current text.
+        let tree = old_tree
+            .as_ref()
+            .and_then(|it| it.incremental_reparse(&hint.edit))
+            .filter(|it| it.syntax().text() == text.as_str());
+        if let Some(tree) = tree {
+            *old_tree = None;
+            return tree;
+        }
+    }
     SourceFile::parse(&*text)
 }
@@ -4,6 +4,7 @@
 use std::sync::Arc;
 
 use ra_arena::{RawId, Arena, impl_arena_id};
+use ra_cfg::CfgOptions;
 use ra_syntax::{
     TreeArc,
     ast::{self, NameOwner, StructKind, TypeAscriptionOwner}
@@ -13,6 +14,7 @@ use crate::{
     Name, AsName, Struct, Enum, EnumVariant, Crate,
     HirDatabase, HirFileId, StructField, FieldSource,
     type_ref::TypeRef, DefDatabase,
+    attr::{is_node_cfg_enabled, crate_cfg_options},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -45,21 +47,30 @@ pub struct StructData {
 }
 
 impl StructData {
-    fn new(struct_def: &ast::StructDef) -> StructData {
+    fn new(struct_def: &ast::StructDef, cfg_options: &CfgOptions) -> StructData {
         let name = struct_def.name().map(|n| n.as_name());
-        let variant_data = VariantData::new(struct_def.kind());
+        let variant_data = VariantData::new(struct_def.kind(), cfg_options);
         let variant_data = Arc::new(variant_data);
         StructData { name, variant_data }
     }
 
     pub(crate) fn struct_data_query(db: &impl DefDatabase, struct_: Struct) -> Arc<StructData> {
         let (_, struct_def) = struct_.source(db);
-        Arc::new(StructData::new(&*struct_def))
+        let cfg_options = crate_cfg_options(db, struct_.module(db).krate(db));
+        Arc::new(StructData::new(&*struct_def, &cfg_options))
     }
 }
 
-fn variants(enum_def: &ast::EnumDef) -> impl Iterator<Item = &ast::EnumVariant> {
-    enum_def.variant_list().into_iter().flat_map(|it| it.variants())
+/// Variants of the enum which are not disabled by `#[cfg(..)]` attributes.
+fn variants<'a>(
+    enum_def: &'a ast::EnumDef,
+    cfg_options: &'a CfgOptions,
+) -> impl Iterator<Item = &'a ast::EnumVariant> {
+    enum_def
+        .variant_list()
+        .into_iter()
+        .flat_map(|it| it.variants())
+        .filter(move |var| is_node_cfg_enabled(*var, cfg_options))
 }
 
 impl EnumVariant {
@@ -68,7 +79,8 @@ impl EnumVariant {
         db: &impl DefDatabase,
     ) -> (HirFileId, TreeArc<ast::EnumVariant>) {
         let (file_id, enum_def) = self.parent.source(db);
-        let var = variants(&*enum_def)
+        let cfg_options = crate_cfg_options(db, self.parent.module(db).krate(db));
+        let var = variants(&*enum_def, &cfg_options)
             .zip(db.enum_data(self.parent).variants.iter())
             .find(|(_syntax, (id, _))| *id == self.id)
             .unwrap()
@@ -90,11 +102,12 @@ pub struct EnumData {
 impl EnumData {
     pub(crate) fn enum_data_query(db: &impl DefDatabase, e: Enum) -> Arc<EnumData> {
         let (_file_id, enum_def) = e.source(db);
+        let cfg_options = crate_cfg_options(db, e.module(db).krate(db));
         let name = enum_def.name().map(|n| n.as_name());
-        let variants = variants(&*enum_def)
+        let variants = variants(&*enum_def, &cfg_options)
             .map(|var| EnumVariantData {
                 name: var.name().map(|it| it.as_name()),
-                variant_data: Arc::new(VariantData::new(var.kind())),
+                variant_data: Arc::new(VariantData::new(var.kind(), &cfg_options)),
             })
             .collect();
         Arc::new(EnumData { name, variants })
@@ -143,11 +156,12 @@ impl VariantData {
 }
 
 impl VariantData {
-    fn new(flavor: StructKind) -> Self {
+    fn new(flavor: StructKind, cfg_options: &CfgOptions) -> Self {
         let inner = match flavor {
             ast::StructKind::Tuple(fl) => {
                 let fields = fl
                     .fields()
+                    .filter(|fd| is_node_cfg_enabled(*fd, cfg_options))
                     .enumerate()
                     .map(|(i, fd)| StructFieldData {
                         name: Name::tuple_field_name(i),
@@ -159,6 +173,7 @@ impl VariantData {
             ast::StructKind::Named(fl) => {
                 let fields = fl
                     .fields()
+                    .filter(|fd| is_node_cfg_enabled(*fd, cfg_options))
                     .map(|fd| StructFieldData {
                         name: fd.name().map(|n| n.as_name()).unwrap_or_else(Name::missing),
                         type_ref: TypeRef::from_ast_opt(fd.ascribed_type()),
@@ -186,6 +201,12 @@ impl VariantDef {
             VariantDef::EnumVariant(it) => it.field(db, name),
         }
     }
+    pub(crate) fn fields(self, db: &impl HirDatabase) -> Vec<StructField> {
+        match self {
+            VariantDef::Struct(it) => it.fields(db),
+            VariantDef::EnumVariant(it) => it.fields(db),
+        }
+    }
     pub(crate) fn variant_data(self, db: &impl DefDatabase) -> Arc<VariantData> {
         match self {
             VariantDef::Struct(it) => it.variant_data(db),
@@ -200,26 +221,31 @@ impl StructField {
         let fields = var_data.fields().unwrap();
         let ss;
         let es;
-        let (file_id, struct_kind) = match self.parent {
+        let (file_id, struct_kind, module) = match self.parent {
             VariantDef::Struct(s) => {
                 let (file_id, source) = s.source(db);
                 ss = source;
-                (file_id, ss.kind())
+                (file_id, ss.kind(), s.module(db))
             }
             VariantDef::EnumVariant(e) => {
                 let (file_id, source) = e.source(db);
                 es = source;
-                (file_id, es.kind())
+                (file_id, es.kind(), e.parent.module(db))
             }
         };
+        let cfg_options = crate_cfg_options(db, module.krate(db));
 
         let field_sources = match struct_kind {
-            ast::StructKind::Tuple(fl) => {
-                fl.fields().map(|it| FieldSource::Pos(it.to_owned())).collect()
-            }
-            ast::StructKind::Named(fl) => {
-                fl.fields().map(|it| FieldSource::Named(it.to_owned())).collect()
-            }
+            ast::StructKind::Tuple(fl) => fl
+                .fields()
+                .filter(|it| is_node_cfg_enabled(*it, &cfg_options))
+                .map(|it| FieldSource::Pos(it.to_owned()))
+                .collect(),
+            ast::StructKind::Named(fl) => fl
+                .fields()
+                .filter(|it| is_node_cfg_enabled(*it, &cfg_options))
+                .map(|it| FieldSource::Named(it.to_owned()))
+                .collect(),
             ast::StructKind::Unit => Vec::new(),
         };
         let field = field_sources
@@ -0,0 +1,98 @@
+//! A higher level attributes based on TokenTree, with also some shortcuts.
+
+use std::sync::Arc;
+
+use mbe::ast_to_token_tree;
+use ra_cfg::CfgOptions;
+use ra_syntax::{
+    SmolStr,
+    ast::{self, AttrsOwner},
+};
+use tt::Subtree;
+
+use crate::{DefDatabase, Crate};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Attr {
+    pub(crate) path: SmolStr,
+    pub(crate) input: Option<AttrInput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AttrInput {
+    Literal(SmolStr),
+    TokenTree(Subtree),
+}
+
+impl Attr {
+    pub(crate) fn from_src(attr: &ast::Attr) -> Option<Attr> {
+        if let Some((path, tt)) = attr.as_call() {
+            let (input, _) = ast_to_token_tree(tt)?;
+            return Some(Attr { path, input: Some(AttrInput::TokenTree(input)) });
+        }
+        if let Some((path, value)) = attr.as_key_value() {
+            return Some(Attr { path, input: Some(AttrInput::Literal(value)) });
+        }
+        let path = attr.as_atom()?;
+        Some(Attr { path, input: None })
+    }
+
+    pub(crate) fn from_attrs_owner(owner: &impl AttrsOwner) -> Option<Arc<[Attr]>> {
+        let mut attrs = owner.attrs().peekable();
+        if attrs.peek().is_none() {
+            // Avoid heap allocation
+            return None;
+        }
+        Some(attrs.flat_map(Attr::from_src).collect())
+    }
+
+    /// Checks whether this is a `#[name]` attribute without any input.
+    pub(crate) fn is_simple_atom(&self, name: &str) -> bool {
+        self.path == name && self.input.is_none()
+    }
+
+    /// Returns the value of a `#[path = "..."]` attribute.
+    pub(crate) fn as_path(&self) -> Option<&SmolStr> {
+        if self.path != "path" {
+            return None;
+        }
+        match &self.input {
+            Some(AttrInput::Literal(it)) => Some(it),
+            _ => None,
+        }
+    }
+
+    /// Returns `Some(false)` if this is a `#[cfg(..)]` attribute which is
+    /// disabled by `cfg_options`, `Some(true)` if it is enabled and `None` if
+    /// this isn't a (valid) `cfg` attribute.
+    pub(crate) fn is_cfg_enabled(&self, cfg_options: &CfgOptions) -> Option<bool> {
+        if self.path != "cfg" {
+            return None;
+        }
+        match &self.input {
+            Some(AttrInput::TokenTree(subtree)) => cfg_options.is_cfg_enabled(subtree),
+            _ => None,
+        }
+    }
+}
+
+/// Returns `false` if any of the `#[cfg(..)]` attributes in `attrs` is
+/// disabled by `cfg_options`.
+pub(crate) fn is_cfg_enabled(attrs: Option<&[Attr]>, cfg_options: &CfgOptions) -> bool {
+    attrs.unwrap_or_default().iter().all(|attr| attr.is_cfg_enabled(cfg_options) != Some(false))
+}
+
+/// Checks the `#[cfg(..)]` attributes of a syntax node against `cfg_options`.
+pub(crate) fn is_node_cfg_enabled(node: &impl AttrsOwner, cfg_options: &CfgOptions) -> bool {
+    let attrs = Attr::from_attrs_owner(node);
+    is_cfg_enabled(attrs.as_ref().map(|it| &**it), cfg_options)
+}
+
+/// Returns the cfg options of `krate`, or the empty set of options for items
+/// which don't belong to any crate.
+pub(crate) fn crate_cfg_options(db: &impl DefDatabase, krate: Option<Crate>) -> CfgOptions {
+    match krate {
+        Some(krate) => db.crate_graph().cfg_options(krate.crate_id()).clone(),
+        None => CfgOptions::default(),
+    }
+}
@@ -0,0 +1,266 @@
+//! Expansion of the function-like macros built into the compiler, like
+//! `concat!` or `include!`. std declares them as `macro_rules!` with the
+//! `#[rustc_builtin_macro]` attribute and a dummy body.
+
+use std::path::Path;
+
+use ra_db::FileId;
+use ra_syntax::{AstNode, ast};
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::{
+    DefDatabase,
+    ids::MacroCallId,
+    name::{AsName, KnownName, Name},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BuiltinFnLikeExpander {
+    Concat,
+    Env,
+    File,
+    FormatArgs,
+    Include,
+    Line,
+    Stringify,
+}
+
+impl BuiltinFnLikeExpander {
+    pub(crate) fn by_name(name: &Name) -> Option<BuiltinFnLikeExpander> {
+        let expander = match name.as_known_name()? {
+            KnownName::Concat => BuiltinFnLikeExpander::Concat,
+            KnownName::Env => BuiltinFnLikeExpander::Env,
+            KnownName::File => BuiltinFnLikeExpander::File,
+            KnownName::FormatArgs => BuiltinFnLikeExpander::FormatArgs,
+            KnownName::Include => BuiltinFnLikeExpander::Include,
+            KnownName::Line => BuiltinFnLikeExpander::Line,
+            KnownName::Stringify => BuiltinFnLikeExpander::Stringify,
+            _ => return None,
+        };
+        Some(expander)
+    }
+
+    pub(crate) fn expand(
+        self,
+        db: &impl DefDatabase,
+        id: MacroCallId,
+        tt: &tt::Subtree,
+    ) -> Result<tt::Subtree, String> {
+        let text = match self {
+            BuiltinFnLikeExpander::FormatArgs => expand_format_args(tt),
+            // In item position, the included file is turned into a file of its
+            // own by `MacroCallId::as_file` instead.
+            BuiltinFnLikeExpander::Include => {
+                let file_id = include_file(db, id).ok_or("included file not found")?;
+                db.file_text(file_id).to_string()
+            }
+            _ => self.expand_to_literal(db, id, tt)?,
+        };
+        text_to_token_tree(&text)
+    }
+
+    /// Returns the source text of the literal the macro expands to.
+    fn expand_to_literal(
+        self,
+        db: &impl DefDatabase,
+        id: MacroCallId,
+        tt: &tt::Subtree,
+    ) -> Result<String, String> {
+        let literal = match self {
+            BuiltinFnLikeExpander::Concat => format!("\"{}\"", eval_args(db, id, tt)?.concat()),
+            BuiltinFnLikeExpander::Env => {
+                let name =
+                    eval_args(db, id, tt)?.into_iter().next().ok_or("expected an argument")?;
+                let krate = id.loc(db).krate;
+                match db.crate_graph().env(krate.crate_id()).get(&unescape(&name)) {
+                    Some(value) => format!("{:?}", value),
+                    None => return Err(format!("environment variable `{}` not defined", name)),
+                }
+            }
+            BuiltinFnLikeExpander::File => {
+                let (file_id, _) = id.call_site(db);
+                format!("{:?}", db.file_relative_path(file_id).as_str())
+            }
+            BuiltinFnLikeExpander::Line => {
+                let (file_id, offset) = id.call_site(db);
+                let text = db.file_text(file_id);
+                let line = text[..offset.to_usize()].matches('\n').count() + 1;
+                format!("{}u32", line)
+            }
+            BuiltinFnLikeExpander::Stringify => format!("{:?}", without_delimiter(tt).to_string()),
+            BuiltinFnLikeExpander::FormatArgs | BuiltinFnLikeExpander::Include => {
+                return Err("macro does not expand to a literal".to_string());
+            }
+        };
+        Ok(literal)
+    }
+}
+
+/// Resolves the file included by an `include!` call, relative to the file the
+/// call is in. Absolute paths, usually coming from `env!("OUT_DIR")`, are
+/// looked up in the extern source roots of the calling crate.
+pub(crate) fn include_file(db: &impl DefDatabase, id: MacroCallId) -> Option<FileId> {
+    let arg = db.macro_arg(id)?;
+    let path = eval_args(db, id, &arg).ok()?.into_iter().next()?;
+    let path = unescape(&path);
+    if Path::new(&path).is_absolute() {
+        let krate = id.loc(db).krate.crate_id();
+        let (root, rel_path) =
+            db.crate_graph().extern_source(krate).extern_path(Path::new(&path))?;
+        return db.source_root(root).files.get(&rel_path).cloned();
+    }
+    let file_id = id.loc(db).ast_id.file_id().original_file(db);
+    let file_path = db.file_relative_path(file_id);
+    let root = RelativePathBuf::default();
+    let dir_path = file_path.parent().unwrap_or(&root);
+    let included = dir_path.join(RelativePath::new(&path)).normalize();
+    let source_root = db.source_root(db.file_source_root(file_id));
+    source_root.files.get(&included).cloned()
+}
+
+/// Evaluates the comma-separated arguments of a macro like `concat!` to the
+/// (still escaped) contents of the string literals they produce. Nested calls
+/// of built-in macros are expanded eagerly.
+fn eval_args(
+    db: &impl DefDatabase,
+    id: MacroCallId,
+    tt: &tt::Subtree,
+) -> Result<Vec<String>, String> {
+    split_args(&tt.token_trees)
+        .into_iter()
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| eval_arg(db, id, arg))
+        .collect()
+}
+
+fn eval_arg(
+    db: &impl DefDatabase,
+    id: MacroCallId,
+    arg: &[tt::TokenTree],
+) -> Result<String, String> {
+    match arg {
+        [tt::TokenTree::Leaf(tt::Leaf::Literal(lit))] => literal_contents(&lit.text),
+        [minus, tt::TokenTree::Leaf(tt::Leaf::Literal(lit))] if is_punct(minus, '-') => {
+            Ok(format!("-{}", literal_contents(&lit.text)?))
+        }
+        // FIXME: nested macro calls are matched by name instead of being
+        // resolved.
+        [tt::TokenTree::Leaf(tt::Leaf::Ident(name)), bang, tt::TokenTree::Subtree(args)]
+            if is_punct(bang, '!') =>
+        {
+            let expander = BuiltinFnLikeExpander::by_name(&name.as_name())
+                .ok_or_else(|| format!("cannot expand `{}!` eagerly", name.text))?;
+            literal_contents(&expander.expand_to_literal(db, id, args)?)
+        }
+        _ => Err(format!("expected a literal, found `{}`", without_delimiter_slice(arg))),
+    }
+}
+
+/// Returns the contents of a literal as they would appear inside of a string
+/// literal.
+fn literal_contents(text: &str) -> Result<String, String> {
+    if text.starts_with('"') {
+        return Ok(text[1..text.len() - 1].to_string());
+    }
+    if text.starts_with('r') {
+        let contents = text.trim_start_matches('r').trim_matches('#');
+        let contents = &contents[1..contents.len() - 1];
+        return Ok(contents.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+    if text.starts_with('\'') {
+        let contents = &text[1..text.len() - 1];
+        return Ok(if contents == "\"" { "\\\"".to_string() } else { contents.to_string() });
+    }
+    if text.starts_with('b') {
+        return Err("cannot concatenate a byte literal".to_string());
+    }
+    const SUFFIXES: &[&str] = &[
+        "usize", "isize", "u128", "i128", "u64", "i64", "u32", "i32", "u16", "i16", "u8", "i8",
+        "f64", "f32",
+    ];
+    let number = SUFFIXES
+        .iter()
+        .find(|it| text.ends_with(*it))
+        .map_or(text, |suffix| text[..text.len() - suffix.len()].trim_end_matches('_'));
+    Ok(number.to_string())
+}
+
+/// Removes the escapes of a string literal that can appear in paths and
+/// environment variable names.
+fn unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('r') => res.push('\r'),
+            Some('0') => res.push('\0'),
+            Some(c) => res.push(c),
+            None => res.push('\\'),
+        }
+    }
+    res
+}
+
+/// Expands `format_args!("{} {x}", a, x = b)` into a call of
+/// `Arguments::new_v1` taking all the arguments, without looking at the format
+/// string. This is only good enough for type inference.
+fn expand_format_args(tt: &tt::Subtree) -> String {
+    let args = split_args(&tt.token_trees)
+        .into_iter()
+        .skip(1)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| match arg.get(1) {
+            // A named argument, `name = value`, as opposed to `name == value`
+            Some(tt::TokenTree::Leaf(tt::Leaf::Punct(tt::Punct {
+                char: '=',
+                spacing: tt::Spacing::Alone,
+            }))) if arg.len() > 2 => &arg[2..],
+            _ => arg,
+        })
+        .map(|arg| {
+            format!(
+                "std::fmt::ArgumentV1::new(&({}), std::fmt::Display::fmt)",
+                without_delimiter_slice(arg)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("std::fmt::Arguments::new_v1(&[], &[{}])", args.join(", "))
+}
+
+fn split_args(token_trees: &[tt::TokenTree]) -> Vec<&[tt::TokenTree]> {
+    token_trees.split(|it| is_punct(it, ',')).collect()
+}
+
+fn is_punct(tt: &tt::TokenTree, c: char) -> bool {
+    match tt {
+        tt::TokenTree::Leaf(tt::Leaf::Punct(punct)) => punct.char == c,
+        _ => false,
+    }
+}
+
+fn without_delimiter(tt: &tt::Subtree) -> tt::Subtree {
+    without_delimiter_slice(&tt.token_trees)
+}
+
+fn without_delimiter_slice(token_trees: &[tt::TokenTree]) -> tt::Subtree {
+    tt::Subtree { delimiter: tt::Delimiter::None, token_trees: token_trees.to_vec() }
+}
+
+fn text_to_token_tree(text: &str) -> Result<tt::Subtree, String> {
+    let source_file = ast::SourceFile::parse(&format!("m!({});", text));
+    let mut tt = source_file
+        .syntax()
+        .descendants()
+        .find_map(ast::TokenTree::cast)
+        .and_then(|it| mbe::ast_to_token_tree(it))
+        .map(|(tt, _)| tt)
+        .ok_or_else(|| format!("failed to convert `{}` to a token tree", text))?;
+    tt.delimiter = tt::Delimiter::None;
+    Ok(tt)
+}
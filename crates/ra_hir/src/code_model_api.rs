@@ -1,17 +1,17 @@
 use std::sync::Arc;
 
 use ra_db::{CrateId, SourceRootId, Edition};
-use ra_syntax::{ast::self, TreeArc};
+use ra_arena::{ArenaId, RawId};
+use ra_syntax::{ast::{self, NameOwner}, SmolStr, TreeArc};
 
 use crate::{
-    Name, Ty, HirFileId, Either,
+    Name, AsName, Ty, HirFileId, Either,
     HirDatabase, DefDatabase,
     type_ref::TypeRef,
     nameres::{ModuleScope, Namespace, ImportId, CrateModuleId},
     expr::{Body, BodySourceMap, validation::ExprValidator},
     ty::{ TraitRef, InferenceResult},
     adt::{EnumVariantId, StructFieldId, VariantDef},
-    generics::HasGenericParams,
     docs::{Documentation, Docs, docs_from_ast},
     ids::{FunctionId, StructId, EnumId, AstItemDef, ConstId, StaticId, TraitId, TypeAliasId},
     impl_block::ImplBlock,
@@ -91,6 +91,127 @@ impl_froms!(
     TypeAlias
 );
 
+/// A definition encoded as plain data by `ModuleDef::to_raw`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawModuleDef {
+    /// The kind of the definition, the ids of the crate and of the module it
+    /// is defined in, and the position of the item in the file of the module.
+    pub ids: [u32; 4],
+    /// The name of the definition. The positions of items change when their
+    /// file is edited, so the name is used to find the item again.
+    pub name: SmolStr,
+}
+
+impl ModuleDef {
+    /// Encodes the definition as plain data, so that it can be referred to
+    /// from outside of the analysis, like from a completion item which the
+    /// client resolves later. Use `from_raw` to get the definition back.
+    pub fn to_raw(self, db: &impl HirDatabase) -> RawModuleDef {
+        fn item<N: NameOwner, DEF: AstItemDef<N>>(
+            db: &impl DefDatabase,
+            kind: u32,
+            id: DEF,
+        ) -> RawModuleDef {
+            let module = id.clone().module(db);
+            let name = id.clone().source(db).1.name().map(|it| it.text().clone());
+            let ids =
+                [kind, module.krate.crate_id.0, module.module_id.into_raw().into(), id.to_raw(db)];
+            RawModuleDef { ids, name: name.unwrap_or_default() }
+        }
+        match self {
+            ModuleDef::Module(it) => {
+                let name = it.name(db).map(|it| it.as_smolstr().clone()).unwrap_or_default();
+                let ids = [0, it.krate.crate_id.0, it.module_id.into_raw().into(), 0];
+                RawModuleDef { ids, name }
+            }
+            ModuleDef::Function(it) => item(db, 1, it.id),
+            ModuleDef::Struct(it) => item(db, 2, it.id),
+            ModuleDef::Enum(it) => item(db, 3, it.id),
+            ModuleDef::EnumVariant(it) => {
+                let name = it.name(db).map(|it| it.as_smolstr().clone()).unwrap_or_default();
+                RawModuleDef { name, ..item(db, 4, it.parent.id) }
+            }
+            ModuleDef::Const(it) => item(db, 5, it.id),
+            ModuleDef::Static(it) => item(db, 6, it.id),
+            ModuleDef::Trait(it) => item(db, 7, it.id),
+            ModuleDef::TypeAlias(it) => item(db, 8, it.id),
+        }
+    }
+
+    /// Decodes a definition encoded by `to_raw`. The ids are not trusted, as
+    /// they may come from another session: the module is checked to exist,
+    /// and the item is looked up by its name if there is no item with that
+    /// name at its old position anymore. Returns `None` if the definition
+    /// doesn't exist anymore, or if there are several candidates.
+    pub fn from_raw(db: &impl HirDatabase, raw: &RawModuleDef) -> Option<ModuleDef> {
+        fn has_name(node: &impl NameOwner, name: &SmolStr) -> bool {
+            node.name().map_or(false, |it| it.text() == name)
+        }
+        fn item<N: NameOwner, DEF: AstItemDef<N>>(
+            db: &impl DefDatabase,
+            module: Module,
+            index: u32,
+            name: &SmolStr,
+        ) -> Option<DEF> {
+            DEF::from_raw(db, module, index, |it| has_name(it, name))
+        }
+        fn single<T>(mut iter: impl Iterator<Item = T>) -> Option<T> {
+            let res = iter.next()?;
+            if iter.next().is_some() {
+                return None;
+            }
+            Some(res)
+        }
+        let [kind, crate_id, module_id, index] = raw.ids;
+        let name = &raw.name;
+        let krate = Crate { crate_id: CrateId(crate_id) };
+        if !db.crate_graph().iter().any(|it| it == krate.crate_id) {
+            return None;
+        }
+        let module_id = CrateModuleId::from_raw(RawId::from(module_id));
+        if !db.crate_def_map(krate).modules().any(|it| it == module_id) {
+            return None;
+        }
+        let module = Module { krate, module_id };
+        let def = match kind {
+            0 => {
+                let is_module = |it: &Module| {
+                    it.name(db).map(|it| it.as_smolstr().clone()).unwrap_or_default() == *name
+                };
+                if is_module(&module) {
+                    module.into()
+                } else {
+                    let def_map = db.crate_def_map(krate);
+                    let modules = def_map.modules().map(|module_id| Module { krate, module_id });
+                    single(modules.filter(is_module))?.into()
+                }
+            }
+            1 => Function { id: item(db, module, index, name)? }.into(),
+            2 => Struct { id: item(db, module, index, name)? }.into(),
+            3 => Enum { id: item(db, module, index, name)? }.into(),
+            4 => {
+                let has_variant = |it: &ast::EnumDef| {
+                    it.variant_list()
+                        .map_or(false, |list| list.variants().any(|v| has_name(v, name)))
+                };
+                let parent = Enum { id: EnumId::from_raw(db, module, index, has_variant)? };
+                let (id, _) = db
+                    .enum_data(parent)
+                    .variants
+                    .iter()
+                    .find(|(_, data)| data.name.as_ref().map(Name::as_smolstr) == Some(name))?;
+                EnumVariant { parent, id }.into()
+            }
+            5 => Const { id: item(db, module, index, name)? }.into(),
+            6 => Static { id: item(db, module, index, name)? }.into(),
+            7 => Trait { id: item(db, module, index, name)? }.into(),
+            8 => TypeAlias { id: item(db, module, index, name)? }.into(),
+            _ => return None,
+        };
+        Some(def)
+    }
+}
+
 pub enum ModuleSource {
     SourceFile(TreeArc<ast::SourceFile>),
     Module(TreeArc<ast::Module>),
@@ -271,7 +392,7 @@ impl Struct {
         self.id.source(db)
     }
 
-    pub fn module(&self, db: &impl HirDatabase) -> Module {
+    pub fn module(&self, db: &impl DefDatabase) -> Module {
         self.id.module(db)
     }
 
@@ -313,8 +434,7 @@ impl Struct {
         // take the outer scope...
         let r = self.module(db).resolver(db);
         // ...and add generic params, if present
-        let p = self.generic_params(db);
-        let r = if !p.params.is_empty() { r.push_generic_params_scope(p) } else { r };
+        let r = r.push_generic_params_scope(db, (*self).into());
         r
     }
 }
@@ -335,7 +455,7 @@ impl Enum {
         self.id.source(db)
     }
 
-    pub fn module(&self, db: &impl HirDatabase) -> Module {
+    pub fn module(&self, db: &impl DefDatabase) -> Module {
         self.id.module(db)
     }
 
@@ -369,8 +489,7 @@ impl Enum {
         // take the outer scope...
         let r = self.module(db).resolver(db);
         // ...and add generic params, if present
-        let p = self.generic_params(db);
-        let r = if !p.params.is_empty() { r.push_generic_params_scope(p) } else { r };
+        let r = r.push_generic_params_scope(db, (*self).into());
         r
     }
 }
@@ -450,6 +569,14 @@ impl DefWithBody {
         db.body_with_source_map(*self).1
     }
 
+    pub fn krate(self, db: &impl HirDatabase) -> Option<Crate> {
+        match self {
+            DefWithBody::Const(c) => c.module(db).krate(db),
+            DefWithBody::Function(f) => f.module(db).krate(db),
+            DefWithBody::Static(s) => s.module(db).krate(db),
+        }
+    }
+
     /// Builds a resolver for code inside this item.
     pub(crate) fn resolver(&self, db: &impl HirDatabase) -> Resolver {
         match *self {
@@ -474,6 +601,13 @@ pub struct FnSignature {
     /// True if the first param is `self`. This is relevant to decide whether this
     /// can be called as a method.
     pub(crate) has_self_param: bool,
+    /// The ABI of an `extern "ABI" fn`, `None` for Rust functions.
+    pub(crate) abi: Option<SmolStr>,
+    /// True for functions declared in an `extern` block.
+    pub(crate) is_foreign: bool,
+    /// True for C-variadic functions, which take any number of additional
+    /// arguments.
+    pub(crate) is_variadic: bool,
 }
 
 impl FnSignature {
@@ -494,6 +628,20 @@ impl FnSignature {
     pub fn has_self_param(&self) -> bool {
         self.has_self_param
     }
+
+    pub fn abi(&self) -> Option<&SmolStr> {
+        self.abi.as_ref()
+    }
+
+    /// True for functions declared in an `extern` block. These are unsafe to
+    /// call.
+    pub fn is_foreign(&self) -> bool {
+        self.is_foreign
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        self.is_variadic
+    }
 }
 
 impl Function {
@@ -556,8 +704,7 @@ impl Function {
         // take the outer scope...
         let r = self.container(db).map_or_else(|| self.module(db).resolver(db), |c| c.resolver(db));
         // ...and add generic params, if present
-        let p = self.generic_params(db);
-        let r = if !p.params.is_empty() { r.push_generic_params_scope(p) } else { r };
+        let r = r.push_generic_params_scope(db, (*self).into());
         r
     }
 
@@ -593,6 +740,10 @@ impl Const {
         db.const_signature(*self)
     }
 
+    pub fn ty(&self, db: &impl HirDatabase) -> Ty {
+        db.type_for_def((*self).into(), Namespace::Values)
+    }
+
     pub fn infer(&self, db: &impl HirDatabase) -> Arc<InferenceResult> {
         db.infer((*self).into())
     }
@@ -603,6 +754,21 @@ impl Const {
         ImplBlock::containing(module_impls, (*self).into())
     }
 
+    /// The containing trait, if this is a trait const definition.
+    pub fn parent_trait(&self, db: &impl DefDatabase) -> Option<Trait> {
+        db.trait_items_index(self.module(db)).get_parent_trait((*self).into())
+    }
+
+    pub fn container(&self, db: &impl DefDatabase) -> Option<Container> {
+        if let Some(impl_block) = self.impl_block(db) {
+            Some(impl_block.into())
+        } else if let Some(trait_) = self.parent_trait(db) {
+            Some(trait_.into())
+        } else {
+            None
+        }
+    }
+
     // FIXME: move to a more general type for 'body-having' items
     /// Builds a resolver for code inside this item.
     pub(crate) fn resolver(&self, db: &impl HirDatabase) -> Resolver {
@@ -656,6 +822,22 @@ impl Static {
         db.static_signature(*self)
     }
 
+    pub fn ty(&self, db: &impl HirDatabase) -> Ty {
+        db.type_for_def((*self).into(), Namespace::Values)
+    }
+
+    /// True for statics declared in an `extern` block. These are unsafe to
+    /// access.
+    pub fn is_foreign(&self, db: &impl DefDatabase) -> bool {
+        self.source(db).1.extern_block().is_some()
+    }
+
+    /// The ABI of the `extern` block a foreign static is declared in.
+    pub fn abi(&self, db: &impl DefDatabase) -> Option<SmolStr> {
+        let (_, node) = self.source(db);
+        node.extern_block().map(|block| block.abi().map_or_else(|| "C".into(), |abi| abi.name()))
+    }
+
     /// Builds a resolver for code inside this item.
     pub(crate) fn resolver(&self, db: &impl HirDatabase) -> Resolver {
         // take the outer scope...
@@ -695,6 +877,10 @@ impl Trait {
         self.trait_data(db).items().to_vec()
     }
 
+    pub fn associated_type_by_name(self, db: &impl DefDatabase, name: &Name) -> Option<TypeAlias> {
+        self.trait_data(db).associated_type_by_name(db, name)
+    }
+
     pub(crate) fn trait_data(self, db: &impl DefDatabase) -> Arc<TraitData> {
         db.trait_data(self)
     }
@@ -710,8 +896,7 @@ impl Trait {
     pub(crate) fn resolver(&self, db: &impl DefDatabase) -> Resolver {
         let r = self.module(db).resolver(db);
         // add generic params, if present
-        let p = self.generic_params(db);
-        let r = if !p.params.is_empty() { r.push_generic_params_scope(p) } else { r };
+        let r = r.push_generic_params_scope(db, (*self).into());
         r
     }
 }
@@ -736,6 +921,10 @@ impl TypeAlias {
         self.id.module(db)
     }
 
+    pub fn name(&self, db: &impl DefDatabase) -> Name {
+        self.source(db).1.name().map(|it| it.as_name()).unwrap_or_else(Name::missing)
+    }
+
     /// The containing impl block, if this is a method.
     pub fn impl_block(&self, db: &impl DefDatabase) -> Option<ImplBlock> {
         let module_impls = db.impls_in_module(self.module(db));
@@ -769,8 +958,7 @@ impl TypeAlias {
             .map(|ib| ib.resolver(db))
             .unwrap_or_else(|| self.module(db).resolver(db));
         // ...and add generic params, if present
-        let p = self.generic_params(db);
-        let r = if !p.params.is_empty() { r.push_generic_params_scope(p) } else { r };
+        let r = r.push_generic_params_scope(db, (*self).into());
         r
     }
 }
@@ -14,6 +14,7 @@ impl FnSignature {
         let name = node.name().map(|n| n.as_name()).unwrap_or_else(Name::missing);
         let mut params = Vec::new();
         let mut has_self_param = false;
+        let mut is_variadic = false;
         if let Some(param_list) = node.param_list() {
             if let Some(self_param) = param_list.self_param() {
                 let self_type = if let Some(type_ref) = self_param.ascribed_type() {
@@ -37,6 +38,7 @@ impl FnSignature {
                 let type_ref = TypeRef::from_ast_opt(param.ascribed_type());
                 params.push(type_ref);
             }
+            is_variadic = param_list.is_variadic();
         }
         let ret_type = if let Some(type_ref) = node.ret_type().and_then(|rt| rt.type_ref()) {
             TypeRef::from_ast(type_ref)
@@ -44,7 +46,12 @@ impl FnSignature {
             TypeRef::unit()
         };
 
-        let sig = FnSignature { name, params, ret_type, has_self_param };
+        let extern_block = node.extern_block();
+        let is_foreign = extern_block.is_some();
+        let abi = node.abi().or_else(|| extern_block.and_then(|it| it.abi())).map(|it| it.name());
+
+        let sig =
+            FnSignature { name, params, ret_type, has_self_param, abi, is_foreign, is_variadic };
         Arc::new(sig)
     }
 }
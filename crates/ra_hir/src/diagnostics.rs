@@ -3,7 +3,7 @@ use std::{fmt, any::Any};
 use ra_syntax::{SyntaxNodePtr, TreeArc, AstPtr, TextRange, ast, SyntaxNode};
 use relative_path::RelativePathBuf;
 
-use crate::{HirFileId, HirDatabase, Name};
+use crate::{HirFileId, HirDatabase, Name, Ty};
 
 /// Diagnostic defines hir API for errors and warnings.
 ///
@@ -135,3 +135,98 @@ impl Diagnostic for MissingFields {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct TypeMismatch {
+    pub file: HirFileId,
+    pub expr: SyntaxNodePtr,
+    pub expected: Ty,
+    pub actual: Ty,
+}
+
+impl Diagnostic for TypeMismatch {
+    fn message(&self) -> String {
+        "type mismatch".to_string()
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.expr
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MismatchedArgCount {
+    pub file: HirFileId,
+    pub call_expr: SyntaxNodePtr,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl Diagnostic for MismatchedArgCount {
+    fn message(&self) -> String {
+        let s = if self.expected == 1 { "" } else { "s" };
+        format!("expected {} argument{}, found {}", self.expected, s, self.found)
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.call_expr
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnresolvedMethodCall {
+    pub file: HirFileId,
+    pub call_expr: SyntaxNodePtr,
+    pub method_name: Name,
+    pub receiver_ty: Ty,
+}
+
+impl Diagnostic for UnresolvedMethodCall {
+    fn message(&self) -> String {
+        format!("no method named `{}`", self.method_name)
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.call_expr
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingMatchArms {
+    pub file: HirFileId,
+    pub match_expr: SyntaxNodePtr,
+    pub arms: AstPtr<ast::MatchArmList>,
+    /// Patterns for the values which are not matched by any arm.
+    pub witnesses: Vec<String>,
+}
+
+impl Diagnostic for MissingMatchArms {
+    fn message(&self) -> String {
+        let witnesses: Vec<_> = self.witnesses.iter().map(|it| format!("`{}`", it)).collect();
+        format!("missing match arms: {} not covered", witnesses.join(", "))
+    }
+    fn file(&self) -> HirFileId {
+        self.file
+    }
+    fn syntax_node_ptr(&self) -> SyntaxNodePtr {
+        self.match_expr
+    }
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
@@ -4,8 +4,9 @@ use std::sync::Arc;
 use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
+use ra_cfg::CfgOptions;
 use ra_syntax::{
-    SyntaxNodePtr, AstPtr, AstNode,
+    SyntaxNodePtr, AstPtr, AstNode, T,
     ast::{self, LoopBodyOwner, ArgListOwner, NameOwner, LiteralKind,ArrayExprKind, TypeAscriptionOwner},
 };
 
@@ -13,6 +14,7 @@ use crate::{
     Path, Name, HirDatabase, Resolver,DefWithBody, Either, HirFileId, MacroCallLoc, MacroFileKind,
     name::AsName,
     type_ref::{Mutability, TypeRef},
+    attr::{is_node_cfg_enabled, crate_cfg_options},
 };
 use crate::{path::GenericArgs, ty::primitive::{IntTy, UncertainIntTy, FloatTy, UncertainFloatTy}};
 
@@ -170,18 +172,25 @@ pub enum Expr {
     Block {
         statements: Vec<Statement>,
         tail: Option<ExprId>,
+        label: Option<Name>,
     },
     Loop {
         body: ExprId,
+        label: Option<Name>,
     },
     While {
         condition: ExprId,
         body: ExprId,
+        label: Option<Name>,
     },
     For {
         iterable: ExprId,
         pat: PatId,
         body: ExprId,
+        label: Option<Name>,
+    },
+    TryBlock {
+        body: ExprId,
     },
     Call {
         callee: ExprId,
@@ -197,9 +206,12 @@ pub enum Expr {
         expr: ExprId,
         arms: Vec<MatchArm>,
     },
-    Continue,
+    Continue {
+        label: Option<Name>,
+    },
     Break {
         expr: Option<ExprId>,
+        label: Option<Name>,
     },
     Return {
         expr: Option<ExprId>,
@@ -216,6 +228,9 @@ pub enum Expr {
     Try {
         expr: ExprId,
     },
+    Await {
+        expr: ExprId,
+    },
     Cast {
         expr: ExprId,
         type_ref: TypeRef,
@@ -224,6 +239,9 @@ pub enum Expr {
         expr: ExprId,
         mutability: Mutability,
     },
+    Box {
+        expr: ExprId,
+    },
     UnaryOp {
         expr: ExprId,
         op: UnaryOp,
@@ -233,6 +251,15 @@ pub enum Expr {
         rhs: ExprId,
         op: Option<BinaryOp>,
     },
+    Index {
+        base: ExprId,
+        index: ExprId,
+    },
+    Range {
+        lhs: Option<ExprId>,
+        rhs: Option<ExprId>,
+        range_type: RangeOp,
+    },
     Lambda {
         args: Vec<PatId>,
         arg_types: Vec<Option<TypeRef>>,
@@ -247,6 +274,7 @@ pub enum Expr {
 
 pub use ra_syntax::ast::PrefixOp as UnaryOp;
 pub use ra_syntax::ast::BinOp as BinaryOp;
+pub use ra_syntax::ast::RangeOp;
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Array {
     ElementList(Vec<ExprId>),
@@ -284,7 +312,7 @@ impl Expr {
                     f(*else_branch);
                 }
             }
-            Expr::Block { statements, tail } => {
+            Expr::Block { statements, tail, .. } => {
                 for stmt in statements {
                     match stmt {
                         Statement::Let { initializer, .. } => {
@@ -299,8 +327,8 @@ impl Expr {
                     f(*expr);
                 }
             }
-            Expr::Loop { body } => f(*body),
-            Expr::While { condition, body } => {
+            Expr::Loop { body, .. } | Expr::TryBlock { body } => f(*body),
+            Expr::While { condition, body, .. } => {
                 f(*condition);
                 f(*body);
             }
@@ -326,8 +354,8 @@ impl Expr {
                     f(arm.expr);
                 }
             }
-            Expr::Continue => {}
-            Expr::Break { expr } | Expr::Return { expr } => {
+            Expr::Continue { .. } => {}
+            Expr::Break { expr, .. } | Expr::Return { expr } => {
                 if let Some(expr) = expr {
                     f(*expr);
                 }
@@ -347,8 +375,22 @@ impl Expr {
                 f(*lhs);
                 f(*rhs);
             }
+            Expr::Index { base, index } => {
+                f(*base);
+                f(*index);
+            }
+            Expr::Range { lhs, rhs, .. } => {
+                if let Some(lhs) = lhs {
+                    f(*lhs);
+                }
+                if let Some(rhs) = rhs {
+                    f(*rhs);
+                }
+            }
             Expr::Field { expr, .. }
             | Expr::Try { expr }
+            | Expr::Await { expr }
+            | Expr::Box { expr }
             | Expr::Cast { expr, .. }
             | Expr::Ref { expr, .. }
             | Expr::UnaryOp { expr, .. } => {
@@ -423,7 +465,11 @@ pub struct FieldPat {
 pub enum Pat {
     Missing,
     Wild,
-    Tuple(Vec<PatId>),
+    Tuple {
+        args: Vec<PatId>,
+        /// The number of `args` before the `..`, if there is one.
+        ellipsis: Option<usize>,
+    },
     Struct {
         path: Option<Path>,
         args: Vec<FieldPat>,
@@ -448,6 +494,8 @@ pub enum Pat {
     TupleStruct {
         path: Option<Path>,
         args: Vec<PatId>,
+        /// The number of `args` before the `..`, if there is one.
+        ellipsis: Option<usize>,
     },
     Ref {
         pat: PatId,
@@ -462,7 +510,7 @@ impl Pat {
             Pat::Bind { subpat, .. } => {
                 subpat.iter().map(|pat| *pat).for_each(f);
             }
-            Pat::Tuple(args) | Pat::TupleStruct { args, .. } => {
+            Pat::Tuple { args, .. } | Pat::TupleStruct { args, .. } => {
                 args.iter().map(|pat| *pat).for_each(f);
             }
             Pat::Ref { pat, .. } => f(*pat),
@@ -477,6 +525,16 @@ impl Pat {
     }
 }
 
+/// The index of the field matched by the `i`th of `pats` subpatterns, if the
+/// pattern has a `..` after `ellipsis` subpatterns.
+pub(crate) fn field_index(i: usize, ellipsis: usize, pats: usize, fields: usize) -> usize {
+    if i < ellipsis {
+        i
+    } else {
+        i + fields - pats
+    }
+}
+
 // Queries
 
 pub(crate) struct ExprCollector<DB> {
@@ -494,6 +552,7 @@ pub(crate) struct ExprCollector<DB> {
     // current == original (see #1196)
     original_file_id: HirFileId,
     current_file_id: HirFileId,
+    cfg_options: CfgOptions,
 }
 
 impl<'a, DB> ExprCollector<&'a DB>
@@ -501,6 +560,7 @@ where
     DB: HirDatabase,
 {
     fn new(owner: DefWithBody, file_id: HirFileId, resolver: Resolver, db: &'a DB) -> Self {
+        let cfg_options = crate_cfg_options(db, resolver.krate());
         ExprCollector {
             owner,
             resolver,
@@ -512,8 +572,15 @@ where
             body_expr: None,
             original_file_id: file_id,
             current_file_id: file_id,
+            cfg_options,
         }
     }
+
+    /// Checks the `#[cfg(..)]` attributes of statements, match arms and fields.
+    fn is_cfg_enabled(&self, node: &impl ast::AttrsOwner) -> bool {
+        is_node_cfg_enabled(node, &self.cfg_options)
+    }
+
     fn alloc_expr(&mut self, expr: Expr, syntax_ptr: SyntaxNodePtr) -> ExprId {
         let id = self.exprs.alloc(expr);
         if self.current_file_id == self.original_file_id {
@@ -535,7 +602,7 @@ where
     }
 
     fn empty_block(&mut self) -> ExprId {
-        let block = Expr::Block { statements: Vec::new(), tail: None };
+        let block = Expr::Block { statements: Vec::new(), tail: None, label: None };
         self.exprs.alloc(block)
     }
 
@@ -578,12 +645,21 @@ where
                     self.alloc_expr(Expr::If { condition, then_branch, else_branch }, syntax_ptr)
                 }
             }
-            ast::ExprKind::BlockExpr(e) => self.collect_block_opt(e.block()),
+            ast::ExprKind::BlockExpr(e) => match e.block() {
+                Some(block) => self.collect_labeled_block(block, e.label().map(label_name)),
+                None => self.alloc_expr(Expr::Missing, syntax_ptr),
+            },
+            ast::ExprKind::TryBlockExpr(e) => {
+                let body = self.collect_block_opt(e.block());
+                self.alloc_expr(Expr::TryBlock { body }, syntax_ptr)
+            }
             ast::ExprKind::LoopExpr(e) => {
+                let label = e.label().map(label_name);
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::Loop { body }, syntax_ptr)
+                self.alloc_expr(Expr::Loop { body, label }, syntax_ptr)
             }
             ast::ExprKind::WhileExpr(e) => {
+                let label = e.label().map(label_name);
                 let condition = if let Some(condition) = e.condition() {
                     if condition.pat().is_none() {
                         self.collect_expr_opt(condition.expr())
@@ -595,13 +671,14 @@ where
                     self.exprs.alloc(Expr::Missing)
                 };
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::While { condition, body }, syntax_ptr)
+                self.alloc_expr(Expr::While { condition, body, label }, syntax_ptr)
             }
             ast::ExprKind::ForExpr(e) => {
+                let label = e.label().map(label_name);
                 let iterable = self.collect_expr_opt(e.iterable());
                 let pat = self.collect_pat_opt(e.pat());
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::For { iterable, pat, body }, syntax_ptr)
+                self.alloc_expr(Expr::For { iterable, pat, body, label }, syntax_ptr)
             }
             ast::ExprKind::CallExpr(e) => {
                 let callee = self.collect_expr_opt(e.expr());
@@ -629,8 +706,9 @@ where
             ast::ExprKind::MatchExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
                 let arms = if let Some(match_arm_list) = e.match_arm_list() {
-                    match_arm_list
-                        .arms()
+                    let arms: Vec<_> =
+                        match_arm_list.arms().filter(|arm| self.is_cfg_enabled(*arm)).collect();
+                    arms.into_iter()
                         .map(|arm| MatchArm {
                             pats: arm.pats().map(|p| self.collect_pat(p)).collect(),
                             expr: self.collect_expr_opt(arm.expr()),
@@ -650,13 +728,14 @@ where
                     e.path().and_then(Path::from_ast).map(Expr::Path).unwrap_or(Expr::Missing);
                 self.alloc_expr(path, syntax_ptr)
             }
-            ast::ExprKind::ContinueExpr(_e) => {
-                // FIXME: labels
-                self.alloc_expr(Expr::Continue, syntax_ptr)
+            ast::ExprKind::ContinueExpr(e) => {
+                let label = e.lifetime_token().map(Name::new_lifetime);
+                self.alloc_expr(Expr::Continue { label }, syntax_ptr)
             }
             ast::ExprKind::BreakExpr(e) => {
+                let label = e.lifetime_token().map(Name::new_lifetime);
                 let expr = e.expr().map(|e| self.collect_expr(e));
-                self.alloc_expr(Expr::Break { expr }, syntax_ptr)
+                self.alloc_expr(Expr::Break { expr, label }, syntax_ptr)
             }
             ast::ExprKind::ParenExpr(e) => {
                 let inner = self.collect_expr_opt(e.expr());
@@ -672,8 +751,10 @@ where
                 let path = e.path().and_then(Path::from_ast);
                 let mut field_ptrs = Vec::new();
                 let struct_lit = if let Some(nfl) = e.named_field_list() {
-                    let fields = nfl
-                        .fields()
+                    let fields: Vec<_> =
+                        nfl.fields().filter(|field| self.is_cfg_enabled(*field)).collect();
+                    let fields = fields
+                        .into_iter()
                         .inspect(|field| field_ptrs.push(AstPtr::new(*field)))
                         .map(|field| StructLitField {
                             name: field
@@ -721,6 +802,10 @@ where
                 let expr = self.collect_expr_opt(e.expr());
                 self.alloc_expr(Expr::Try { expr }, syntax_ptr)
             }
+            ast::ExprKind::AwaitExpr(e) => {
+                let expr = self.collect_expr_opt(e.expr());
+                self.alloc_expr(Expr::Await { expr }, syntax_ptr)
+            }
             ast::ExprKind::CastExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
                 let type_ref = TypeRef::from_ast_opt(e.type_ref());
@@ -731,6 +816,10 @@ where
                 let mutability = Mutability::from_mutable(e.is_mut());
                 self.alloc_expr(Expr::Ref { expr, mutability }, syntax_ptr)
             }
+            ast::ExprKind::BoxExpr(e) => {
+                let expr = self.collect_expr_opt(e.expr());
+                self.alloc_expr(Expr::Box { expr }, syntax_ptr)
+            }
             ast::ExprKind::PrefixExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
                 if let Some(op) = e.op_kind() {
@@ -759,6 +848,21 @@ where
                 let op = e.op_kind();
                 self.alloc_expr(Expr::BinaryOp { lhs, rhs, op }, syntax_ptr)
             }
+            ast::ExprKind::IndexExpr(e) => {
+                let base = self.collect_expr_opt(e.base());
+                let index = self.collect_expr_opt(e.index());
+                self.alloc_expr(Expr::Index { base, index }, syntax_ptr)
+            }
+            ast::ExprKind::RangeExpr(e) => {
+                let lhs = e.start().map(|lhs| self.collect_expr(lhs));
+                let rhs = e.end().map(|rhs| self.collect_expr(rhs));
+                match e.op_kind() {
+                    Some(range_type) => {
+                        self.alloc_expr(Expr::Range { lhs, rhs, range_type }, syntax_ptr)
+                    }
+                    None => self.alloc_expr(Expr::Missing, syntax_ptr),
+                }
+            }
             ast::ExprKind::TupleExpr(e) => {
                 let exprs = e.exprs().map(|expr| self.collect_expr(expr)).collect();
                 self.alloc_expr(Expr::Tuple { exprs }, syntax_ptr)
@@ -808,19 +912,20 @@ where
                     LiteralKind::Byte => {
                         Literal::Int(Default::default(), UncertainIntTy::Known(IntTy::u8()))
                     }
-                    LiteralKind::Bool => Literal::Bool(Default::default()),
+                    LiteralKind::Bool => Literal::Bool(e.token().kind() == T![true]),
                     LiteralKind::Char => Literal::Char(Default::default()),
                 };
                 self.alloc_expr(Expr::Literal(lit), syntax_ptr)
             }
 
-            // FIXME implement HIR for these:
+            // A label is never an expression on its own, it is lowered as part of its loop
             ast::ExprKind::Label(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::ExprKind::IndexExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::ExprKind::RangeExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
             ast::ExprKind::MacroCall(e) => {
                 // very hacky.FIXME change to use the macro resolution
-                let path = e.path().and_then(Path::from_ast);
+                let path = e
+                    .path()
+                    .and_then(Path::from_ast)
+                    .map(|path| path.with_dollar_crate(self.current_file_id.macro_crate(self.db)));
 
                 let ast_id = self
                     .db
@@ -828,9 +933,10 @@ where
                     .ast_id(e)
                     .with_file_id(self.current_file_id);
 
-                if let Some(def) = self.resolver.resolve_macro_call(path) {
-                    let call_id = MacroCallLoc { def, ast_id }.id(self.db);
-                    let file_id = call_id.as_file(MacroFileKind::Expr);
+                let def = self.resolver.resolve_macro_call(self.db, path);
+                if let (Some(def), Some(krate)) = (def, self.resolver.krate()) {
+                    let call_id = MacroCallLoc { def, ast_id, krate }.id(self.db);
+                    let file_id = call_id.as_file(self.db, MacroFileKind::Expr);
                     if let Some(node) = self.db.parse_or_expand(file_id) {
                         if let Some(expr) = ast::Expr::cast(&*node) {
                             log::debug!("macro expansion {}", expr.syntax().debug_dump());
@@ -857,22 +963,33 @@ where
     }
 
     fn collect_block(&mut self, block: &ast::Block) -> ExprId {
+        self.collect_labeled_block(block, None)
+    }
+
+    fn collect_labeled_block(&mut self, block: &ast::Block, label: Option<Name>) -> ExprId {
         let statements = block
             .statements()
-            .map(|s| match s.kind() {
+            .filter_map(|s| match s.kind() {
                 ast::StmtKind::LetStmt(stmt) => {
+                    if !self.is_cfg_enabled(stmt) {
+                        return None;
+                    }
                     let pat = self.collect_pat_opt(stmt.pat());
                     let type_ref = stmt.ascribed_type().map(TypeRef::from_ast);
                     let initializer = stmt.initializer().map(|e| self.collect_expr(e));
-                    Statement::Let { pat, type_ref, initializer }
+                    Some(Statement::Let { pat, type_ref, initializer })
                 }
                 ast::StmtKind::ExprStmt(stmt) => {
-                    Statement::Expr(self.collect_expr_opt(stmt.expr()))
+                    if !self.is_cfg_enabled(stmt) {
+                        return None;
+                    }
+                    Some(Statement::Expr(self.collect_expr_opt(stmt.expr())))
                 }
             })
             .collect();
         let tail = block.expr().map(|e| self.collect_expr(e));
-        self.alloc_expr(Expr::Block { statements, tail }, SyntaxNodePtr::new(block.syntax()))
+        let syntax_ptr = SyntaxNodePtr::new(block.syntax());
+        self.alloc_expr(Expr::Block { statements, tail, label }, syntax_ptr)
     }
 
     fn collect_block_opt(&mut self, block: Option<&ast::Block>) -> ExprId {
@@ -894,7 +1011,7 @@ where
             ast::PatKind::TupleStructPat(p) => {
                 let path = p.path().and_then(Path::from_ast);
                 let args = p.args().map(|p| self.collect_pat(p)).collect();
-                Pat::TupleStruct { path, args }
+                Pat::TupleStruct { path, args, ellipsis: p.ellipsis_pos() }
             }
             ast::PatKind::RefPat(p) => {
                 let pat = self.collect_pat_opt(p.pat());
@@ -907,7 +1024,7 @@ where
             }
             ast::PatKind::TuplePat(p) => {
                 let args = p.args().map(|p| self.collect_pat(p)).collect();
-                Pat::Tuple(args)
+                Pat::Tuple { args, ellipsis: p.ellipsis_pos() }
             }
             ast::PatKind::PlaceholderPat(_) => Pat::Wild,
             ast::PatKind::StructPat(p) => {
@@ -934,9 +1051,30 @@ where
                 Pat::Struct { path, args: fields }
             }
 
+            ast::PatKind::LiteralPat(p) => match p.literal() {
+                Some(literal) => {
+                    let literal = ast::Expr::cast(literal.syntax()).expect("literal is an expr");
+                    let mut expr = self.collect_expr(literal);
+                    if p.is_negated() {
+                        expr = self.exprs.alloc(Expr::UnaryOp { expr, op: UnaryOp::Neg });
+                    }
+                    Pat::Lit(expr)
+                }
+                None => Pat::Missing,
+            },
+            ast::PatKind::SlicePat(p) => {
+                let mut args: Vec<_> = p.args().map(|p| self.collect_pat(p)).collect();
+                match p.ellipsis_pos() {
+                    Some(pos) => {
+                        let suffix = args.split_off(pos);
+                        let rest = Some(self.pats.alloc(Pat::Wild));
+                        Pat::Slice { prefix: args, rest, suffix }
+                    }
+                    None => Pat::Slice { prefix: args, rest: None, suffix: Vec::new() },
+                }
+            }
             // FIXME: implement
-            ast::PatKind::LiteralPat(_) => Pat::Missing,
-            ast::PatKind::SlicePat(_) | ast::PatKind::RangePat(_) => Pat::Missing,
+            ast::PatKind::RangePat(_) => Pat::Missing,
         };
         let ptr = AstPtr::new(pat);
         self.alloc_pat(pattern, Either::A(ptr))
@@ -1002,6 +1140,10 @@ where
     }
 }
 
+fn label_name(label: &ast::Label) -> Name {
+    label.lifetime_token().map(Name::new_lifetime).unwrap_or_else(Name::missing)
+}
+
 pub(crate) fn body_with_source_map_query(
     db: &impl HirDatabase,
     def: DefWithBody,
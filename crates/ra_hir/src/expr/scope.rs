@@ -39,6 +39,8 @@ impl ScopeEntry {
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct ScopeData {
     parent: Option<ScopeId>,
+    /// The label of the loop or block this scope is the body of, if any.
+    label: Option<(Name, ExprId)>,
     entries: Vec<ScopeEntry>,
 }
 
@@ -72,6 +74,14 @@ impl ExprScopes {
         std::iter::successors(scope, move |&scope| self.scopes[scope].parent)
     }
 
+    /// Finds the loop or block labeled `label` which encloses `scope`.
+    pub(crate) fn resolve_label(&self, scope: Option<ScopeId>, label: &Name) -> Option<ExprId> {
+        self.scope_chain(scope).find_map(|scope| match &self.scopes[scope].label {
+            Some((name, expr)) if name == label => Some(*expr),
+            _ => None,
+        })
+    }
+
     pub(crate) fn scope_for(&self, expr: ExprId) -> Option<ScopeId> {
         self.scope_by_expr.get(&expr).map(|&scope| scope)
     }
@@ -81,11 +91,15 @@ impl ExprScopes {
     }
 
     fn root_scope(&mut self) -> ScopeId {
-        self.scopes.alloc(ScopeData { parent: None, entries: vec![] })
+        self.scopes.alloc(ScopeData { parent: None, label: None, entries: vec![] })
     }
 
     fn new_scope(&mut self, parent: ScopeId) -> ScopeId {
-        self.scopes.alloc(ScopeData { parent: Some(parent), entries: vec![] })
+        self.scopes.alloc(ScopeData { parent: Some(parent), label: None, entries: vec![] })
+    }
+
+    fn new_labeled_scope(&mut self, parent: ScopeId, label: Option<(Name, ExprId)>) -> ScopeId {
+        self.scopes.alloc(ScopeData { parent: Some(parent), label, entries: vec![] })
     }
 
     fn add_bindings(&mut self, body: &Body, scope: ScopeId, pat: PatId) {
@@ -141,12 +155,25 @@ fn compute_block_scopes(
 fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut ExprScopes, scope: ScopeId) {
     scopes.set_scope(expr, scope);
     match &body[expr] {
-        Expr::Block { statements, tail } => {
+        Expr::Block { statements, tail, label: None } => {
             compute_block_scopes(&statements, *tail, body, scopes, scope);
         }
-        Expr::For { iterable, pat, body: body_expr } => {
+        Expr::Block { statements, tail, label: Some(label) } => {
+            let scope = scopes.new_labeled_scope(scope, Some((label.clone(), expr)));
+            compute_block_scopes(&statements, *tail, body, scopes, scope);
+        }
+        Expr::Loop { body: body_expr, label } => {
+            let scope = scopes.new_labeled_scope(scope, label.clone().map(|it| (it, expr)));
+            compute_expr_scopes(*body_expr, body, scopes, scope);
+        }
+        Expr::While { condition, body: body_expr, label } => {
+            compute_expr_scopes(*condition, body, scopes, scope);
+            let scope = scopes.new_labeled_scope(scope, label.clone().map(|it| (it, expr)));
+            compute_expr_scopes(*body_expr, body, scopes, scope);
+        }
+        Expr::For { iterable, pat, body: body_expr, label } => {
             compute_expr_scopes(*iterable, body, scopes, scope);
-            let scope = scopes.new_scope(scope);
+            let scope = scopes.new_labeled_scope(scope, label.clone().map(|it| (it, expr)));
             scopes.add_bindings(body, scope, *pat);
             compute_expr_scopes(*body_expr, body, scopes, scope);
         }
@@ -176,7 +203,7 @@ mod tests {
     use ra_syntax::{algo::find_node_at_offset, AstNode, SyntaxNodePtr, ast};
     use test_utils::{extract_offset, assert_eq_text};
 
-    use crate::{source_binder::SourceAnalyzer, mock::MockDatabase};
+    use crate::{Name, source_binder::SourceAnalyzer, mock::MockDatabase};
 
     fn do_check(code: &str, expected: &[&str]) {
         let (off, code) = extract_offset(code);
@@ -353,4 +380,40 @@ mod tests {
             53,
         );
     }
+
+    #[test]
+    fn labels_resolve_to_enclosing_loops() {
+        let (off, code) = extract_offset(
+            r"
+            fn foo() {
+                'outer: loop {
+                    'inner: while true {
+                        <|>bar;
+                    }
+                }
+                'other: loop {}
+            }",
+        );
+        let (db, _source_root, file_id) = MockDatabase::with_single_file(&code);
+        let file = db.parse(file_id);
+        let marker: &ast::PathExpr = find_node_at_offset(file.syntax(), off).unwrap();
+        let analyzer = SourceAnalyzer::new(&db, file_id, marker.syntax(), None);
+        let scopes = analyzer.scopes();
+        let source_map = analyzer.body_source_map();
+        let scope =
+            scopes.scope_for(source_map.syntax_expr(SyntaxNodePtr::new(marker.syntax())).unwrap());
+
+        let resolved = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Label::cast)
+            .map(|label| {
+                let name = Name::new_lifetime(label.lifetime_token().unwrap());
+                let loop_expr = scopes.resolve_label(scope, &name)?;
+                let loop_range = source_map.expr_syntax(loop_expr).unwrap().range();
+                Some(loop_range.start() == label.syntax().range().start())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(resolved, vec![Some(true), Some(true), None]);
+    }
 }
@@ -1,17 +1,19 @@
 use std::sync::Arc;
 use rustc_hash::FxHashSet;
 
-use ra_syntax::ast::{AstNode, StructLit};
+use ra_syntax::ast::{AstNode, StructLit, MatchExpr};
 
 use crate::{
     expr::AstPtr,
     HirDatabase, Function, Name,
-    diagnostics::{DiagnosticSink, MissingFields},
+    diagnostics::{DiagnosticSink, MissingFields, MissingMatchArms},
     adt::AdtDef,
     Path,
-    ty::InferenceResult,
+    ty::{Ty, InferenceResult},
 };
-use super::{Expr, StructLitField, ExprId};
+use super::{Expr, StructLitField, ExprId, MatchArm};
+
+mod exhaustiveness;
 
 pub(crate) struct ExprValidator<'a, 'b: 'a> {
     func: Function,
@@ -35,6 +37,7 @@ impl<'a, 'b> ExprValidator<'a, 'b> {
                 (id, Expr::StructLit { path, fields, spread }) => {
                     self.validate_struct_literal(id, path, fields, spread, db)
                 }
+                (id, Expr::Match { expr, arms }) => self.validate_match(id, *expr, arms, db),
                 _ => (),
             }
         }
@@ -90,4 +93,43 @@ impl<'a, 'b> ExprValidator<'a, 'b> {
             })
         }
     }
+
+    fn validate_match(
+        &mut self,
+        id: ExprId,
+        match_expr: ExprId,
+        arms: &[MatchArm],
+        db: &impl HirDatabase,
+    ) {
+        let match_ty = &self.infer[match_expr];
+        if *match_ty == Ty::Unknown {
+            return;
+        }
+        let body = self.func.body(db);
+        let resolver = self.func.resolver(db);
+        let witnesses = match exhaustiveness::missing_arms(db, &body, &resolver, arms, match_ty) {
+            Some(it) => it,
+            None => return,
+        };
+        if witnesses.is_empty() {
+            return;
+        }
+        let source_map = self.func.body_source_map(db);
+        let file_id = self.func.source(db).0;
+        let source_file = db.parse(file_id.original_file(db));
+        // `if let` is desugared to a `match`, but it doesn't need to be exhaustive
+        let arm_list = source_map
+            .expr_syntax(id)
+            .map(|ptr| ptr.to_node(source_file.syntax()))
+            .and_then(MatchExpr::cast)
+            .and_then(|match_expr| match_expr.match_arm_list());
+        if let (Some(match_expr), Some(arm_list)) = (source_map.expr_syntax(match_expr), arm_list) {
+            self.sink.push(MissingMatchArms {
+                file: file_id,
+                match_expr,
+                arms: AstPtr::new(arm_list),
+                witnesses,
+            })
+        }
+    }
 }
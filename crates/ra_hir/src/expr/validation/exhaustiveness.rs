@@ -0,0 +1,539 @@
+//! Checks whether the arms of a `match` cover all values of the matched type.
+//!
+//! This is the usefulness algorithm from "Warnings for pattern matching" by
+//! Luc Maranget, extended to compute the values which are not covered. Match
+//! ergonomics are made explicit while lowering the patterns, so the algorithm
+//! itself only deals with constructors and wildcards.
+//!
+//! We give up on anything we don't understand (range patterns, constants,
+//! unresolved paths, patterns which don't fit the matched type), so that no
+//! false positives are reported.
+
+use std::{cmp, iter};
+
+use crate::{
+    HirDatabase, Name, Path, Struct, EnumVariant, ModuleDef, AdtDef, Ty, TypeCtor, Substs,
+    adt::VariantDef,
+    expr::{self, Body, Expr, Literal, MatchArm, Pat, PatId},
+    resolve::{Resolver, Resolution},
+};
+
+/// Returns patterns for the values of type `match_ty` which are not covered by
+/// the `arms`, or `None` if we can't tell.
+pub(super) fn missing_arms(
+    db: &impl HirDatabase,
+    body: &Body,
+    resolver: &Resolver,
+    arms: &[MatchArm],
+    match_ty: &Ty,
+) -> Option<Vec<String>> {
+    let cx = MatchCheckCtx { db, body, resolver };
+    let mut rows = Vec::new();
+    // arms with a guard don't cover anything for sure
+    for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+        for &pat in arm.pats.iter() {
+            rows.push(vec![cx.lower_pat(pat, match_ty)?]);
+        }
+    }
+    if rows.is_empty() && is_uninhabited(db, match_ty) {
+        return Some(Vec::new());
+    }
+    let witnesses = cx.missing_witnesses(&rows, 1)?;
+    Some(witnesses.iter().map(|witness| cx.display(&witness[0])).collect())
+}
+
+fn is_uninhabited(db: &impl HirDatabase, ty: &Ty) -> bool {
+    match ty {
+        Ty::Apply(a_ty) => match a_ty.ctor {
+            TypeCtor::Never => true,
+            TypeCtor::Adt(AdtDef::Enum(e)) => e.variants(db).is_empty(),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constructor {
+    Bool(bool),
+    Tuple(usize),
+    Struct(Struct),
+    Variant(EnumVariant),
+    Ref,
+    /// Slices of exactly this length.
+    FixedLenSlice(usize),
+    /// Slices of at least `prefix + suffix` elements, of which the first
+    /// `prefix` and the last `suffix` ones are matched.
+    VarLenSlice {
+        prefix: usize,
+        suffix: usize,
+    },
+}
+
+impl Constructor {
+    fn arity(self, db: &impl HirDatabase) -> usize {
+        match self {
+            Constructor::Bool(_) => 0,
+            Constructor::Tuple(arity) => arity,
+            Constructor::Struct(s) => s.fields(db).len(),
+            Constructor::Variant(v) => v.fields(db).len(),
+            Constructor::Ref => 1,
+            Constructor::FixedLenSlice(len) => len,
+            Constructor::VarLenSlice { prefix, suffix } => prefix + suffix,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Wild,
+    Ctor(Constructor, Vec<Pattern>),
+    /// A slice or array pattern; `has_rest` is true if there's a `..` between
+    /// the `prefix` and the `suffix`.
+    Slice {
+        prefix: Vec<Pattern>,
+        suffix: Vec<Pattern>,
+        has_rest: bool,
+        is_array: bool,
+    },
+    /// A pattern which matches some values of a type with infinitely many
+    /// values, like an integer literal.
+    Opaque,
+}
+
+impl Pattern {
+    fn is_wild(&self) -> bool {
+        match self {
+            Pattern::Wild => true,
+            _ => false,
+        }
+    }
+}
+
+/// The constructors of the values of some type.
+enum ConstructorSet {
+    Finite(Vec<Constructor>),
+    /// Integers, strings etc., which can't be listed.
+    Infinite,
+}
+
+type Row = Vec<Pattern>;
+
+struct MatchCheckCtx<'a, DB> {
+    db: &'a DB,
+    body: &'a Body,
+    resolver: &'a Resolver,
+}
+
+impl<'a, DB: HirDatabase> MatchCheckCtx<'a, DB> {
+    fn lower_pat(&self, pat: PatId, ty: &Ty) -> Option<Pattern> {
+        let is_str_lit = match &self.body[pat] {
+            Pat::Wild => return Some(Pattern::Wild),
+            Pat::Bind { subpat: Some(subpat), .. } => return self.lower_pat(*subpat, ty),
+            Pat::Bind { name, subpat: None, .. } if self.is_binding(name) => {
+                return Some(Pattern::Wild)
+            }
+            Pat::Ref { pat, .. } => {
+                let inner_ty = ty.as_reference().map_or(Ty::Unknown, |(it, _)| it.clone());
+                let inner = self.lower_pat(*pat, &inner_ty)?;
+                return Some(Pattern::Ctor(Constructor::Ref, vec![inner]));
+            }
+            Pat::Lit(expr) => match &self.body[*expr] {
+                Expr::Literal(Literal::String(..)) | Expr::Literal(Literal::ByteString(..)) => true,
+                _ => false,
+            },
+            _ => false,
+        };
+        // match ergonomics: a non-reference pattern dereferences the value
+        if let Some((inner_ty, _)) = ty.as_reference() {
+            if !is_str_lit {
+                let inner = self.lower_pat(pat, inner_ty)?;
+                return Some(Pattern::Ctor(Constructor::Ref, vec![inner]));
+            }
+        }
+        match &self.body[pat] {
+            Pat::Lit(expr) => match &self.body[*expr] {
+                Expr::Literal(Literal::Bool(value)) => {
+                    Some(Pattern::Ctor(Constructor::Bool(*value), Vec::new()))
+                }
+                _ => Some(Pattern::Opaque),
+            },
+            Pat::Tuple { args, ellipsis } => {
+                let field_tys = match ty.as_tuple() {
+                    Some(substs) => substs.iter().cloned().collect(),
+                    None if ellipsis.is_none() => vec![Ty::Unknown; args.len()],
+                    None => return None,
+                };
+                let fields = self.lower_fields(args, *ellipsis, &field_tys)?;
+                Some(Pattern::Ctor(Constructor::Tuple(fields.len()), fields))
+            }
+            Pat::TupleStruct { path, args, ellipsis } => {
+                let variant = self.resolve_variant(path.as_ref()?)?;
+                let fields = self.lower_fields(args, *ellipsis, &self.field_tys(variant, ty))?;
+                Some(Pattern::Ctor(variant_constructor(variant), fields))
+            }
+            Pat::Struct { path, args } => {
+                let variant = self.resolve_variant(path.as_ref()?)?;
+                let field_defs = variant.fields(self.db);
+                let field_tys = self.field_tys(variant, ty);
+                let mut fields = vec![Pattern::Wild; field_defs.len()];
+                for field_pat in args {
+                    let idx = field_defs
+                        .iter()
+                        .position(|field| field.name(self.db) == field_pat.name)?;
+                    fields[idx] = self.lower_pat(field_pat.pat, &field_tys[idx])?;
+                }
+                Some(Pattern::Ctor(variant_constructor(variant), fields))
+            }
+            Pat::Path(path) => {
+                self.lower_unit_path(self.resolver.resolve_path(self.db, path).take_values())
+            }
+            Pat::Bind { name, .. } => {
+                self.lower_unit_path(self.resolver.resolve_name(self.db, name).take_values())
+            }
+            Pat::Slice { prefix, rest, suffix } => {
+                let (elem_ty, is_array) = match ty {
+                    Ty::Apply(a_ty) => match a_ty.ctor {
+                        TypeCtor::Slice => (a_ty.parameters.as_single(), false),
+                        TypeCtor::Array => (a_ty.parameters.as_single(), true),
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                let lower_all = |pats: &[PatId]| -> Option<Vec<Pattern>> {
+                    pats.iter().map(|&pat| self.lower_pat(pat, elem_ty)).collect()
+                };
+                Some(Pattern::Slice {
+                    prefix: lower_all(prefix)?,
+                    suffix: lower_all(suffix)?,
+                    has_rest: rest.is_some(),
+                    is_array,
+                })
+            }
+            Pat::Missing | Pat::Range { .. } => None,
+            Pat::Wild | Pat::Ref { .. } => unreachable!(),
+        }
+    }
+
+    /// A single identifier is a binding, unless it names a unit struct, an
+    /// enum variant or a constant.
+    fn is_binding(&self, name: &Name) -> bool {
+        match self.resolver.resolve_name(self.db, name).take_values() {
+            Some(Resolution::Def(ModuleDef::EnumVariant(_)))
+            | Some(Resolution::Def(ModuleDef::Struct(_)))
+            | Some(Resolution::Def(ModuleDef::Const(_)))
+            | Some(Resolution::Def(ModuleDef::Static(_))) => false,
+            _ => true,
+        }
+    }
+
+    fn lower_unit_path(&self, resolution: Option<Resolution>) -> Option<Pattern> {
+        let ctor = match resolution? {
+            Resolution::Def(ModuleDef::EnumVariant(v)) => Constructor::Variant(v),
+            Resolution::Def(ModuleDef::Struct(s)) => Constructor::Struct(s),
+            _ => return None,
+        };
+        if ctor.arity(self.db) != 0 {
+            return None;
+        }
+        Some(Pattern::Ctor(ctor, Vec::new()))
+    }
+
+    fn lower_fields(
+        &self,
+        args: &[PatId],
+        ellipsis: Option<usize>,
+        field_tys: &[Ty],
+    ) -> Option<Vec<Pattern>> {
+        let mut fields = vec![Pattern::Wild; field_tys.len()];
+        for (i, &arg) in args.iter().enumerate() {
+            let idx = match ellipsis {
+                Some(ellipsis) if field_tys.len() >= args.len() => {
+                    expr::field_index(i, ellipsis, args.len(), field_tys.len())
+                }
+                None if field_tys.len() == args.len() => i,
+                _ => return None,
+            };
+            fields[idx] = self.lower_pat(arg, &field_tys[idx])?;
+        }
+        Some(fields)
+    }
+
+    fn resolve_variant(&self, path: &Path) -> Option<VariantDef> {
+        match self.resolver.resolve_path(self.db, path).take_types()? {
+            Resolution::Def(ModuleDef::EnumVariant(v)) => Some(v.into()),
+            Resolution::Def(ModuleDef::Struct(s)) => Some(s.into()),
+            _ => None,
+        }
+    }
+
+    fn field_tys(&self, variant: VariantDef, ty: &Ty) -> Vec<Ty> {
+        let substs = ty.as_adt().map_or_else(Substs::empty, |(_, substs)| substs.clone());
+        variant.fields(self.db).iter().map(|field| field.ty(self.db).subst(&substs)).collect()
+    }
+
+    /// Computes the rows of `arity` patterns which match values not matched by
+    /// any of the `rows`.
+    fn missing_witnesses(&self, rows: &[Row], arity: usize) -> Option<Vec<Row>> {
+        if arity == 0 {
+            return Some(if rows.is_empty() { vec![Vec::new()] } else { Vec::new() });
+        }
+        let heads: Vec<&Pattern> =
+            rows.iter().map(|row| &row[0]).filter(|head| !head.is_wild()).collect();
+        let ctors = match self.constructors(&heads)? {
+            ConstructorSet::Finite(ctors) => ctors,
+            ConstructorSet::Infinite => {
+                // Some values of the first column are not covered by any
+                // pattern but a wildcard, so only the rows starting with a
+                // wildcard matter.
+                let default_rows: Vec<Row> = rows
+                    .iter()
+                    .filter(|row| row[0].is_wild())
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let witnesses = self.missing_witnesses(&default_rows, arity - 1)?;
+                let witnesses = witnesses
+                    .into_iter()
+                    .map(|witness| iter::once(Pattern::Wild).chain(witness).collect())
+                    .collect();
+                return Some(witnesses);
+            }
+        };
+        let mut witnesses = Vec::new();
+        for ctor in ctors {
+            let ctor_arity = ctor.arity(self.db);
+            let specialized: Vec<Row> =
+                rows.iter().filter_map(|row| specialize(row, ctor, ctor_arity)).collect();
+            for mut fields in self.missing_witnesses(&specialized, ctor_arity + arity - 1)? {
+                let rest = fields.split_off(ctor_arity);
+                witnesses.push(iter::once(Pattern::Ctor(ctor, fields)).chain(rest).collect());
+            }
+        }
+        Some(witnesses)
+    }
+
+    /// Lists the constructors of the type matched by the `heads`, or returns
+    /// `None` if they don't fit together.
+    fn constructors(&self, heads: &[&Pattern]) -> Option<ConstructorSet> {
+        let first = match heads.first() {
+            Some(it) => it,
+            None => return Some(ConstructorSet::Infinite),
+        };
+        let ctors = match first {
+            Pattern::Opaque => {
+                let all_opaque = heads.iter().all(|head| match head {
+                    Pattern::Opaque => true,
+                    _ => false,
+                });
+                return if all_opaque { Some(ConstructorSet::Infinite) } else { None };
+            }
+            Pattern::Slice { is_array, .. } => return slice_constructors(heads, *is_array),
+            Pattern::Ctor(Constructor::Bool(_), _) => {
+                vec![Constructor::Bool(true), Constructor::Bool(false)]
+            }
+            Pattern::Ctor(Constructor::Variant(v), _) => {
+                let e = v.parent_enum(self.db);
+                e.variants(self.db).into_iter().map(Constructor::Variant).collect()
+            }
+            Pattern::Ctor(ctor, _) => vec![*ctor],
+            Pattern::Wild => unreachable!(),
+        };
+        let all_fit = heads.iter().all(|head| match head {
+            Pattern::Ctor(ctor, _) => ctors.contains(ctor),
+            _ => false,
+        });
+        if all_fit {
+            Some(ConstructorSet::Finite(ctors))
+        } else {
+            None
+        }
+    }
+
+    fn display(&self, pat: &Pattern) -> String {
+        let (ctor, fields) = match pat {
+            Pattern::Ctor(ctor, fields) => (*ctor, fields),
+            _ => return "_".to_string(),
+        };
+        let fields: Vec<String> = fields.iter().map(|field| self.display(field)).collect();
+        match ctor {
+            Constructor::Bool(value) => value.to_string(),
+            Constructor::Tuple(1) => format!("({},)", fields[0]),
+            Constructor::Tuple(_) => format!("({})", fields.join(", ")),
+            Constructor::Ref => format!("&{}", fields[0]),
+            Constructor::FixedLenSlice(_) => format!("[{}]", fields.join(", ")),
+            Constructor::VarLenSlice { prefix, .. } => {
+                let mut elements = fields;
+                elements.insert(prefix, "..".to_string());
+                format!("[{}]", elements.join(", "))
+            }
+            Constructor::Struct(s) => {
+                let path = display_name(s.name(self.db));
+                self.display_variant(path, s.into(), fields)
+            }
+            Constructor::Variant(v) => {
+                let enum_name = display_name(v.parent_enum(self.db).name(self.db));
+                let path = format!("{}::{}", enum_name, display_name(v.name(self.db)));
+                self.display_variant(path, v.into(), fields)
+            }
+        }
+    }
+
+    fn display_variant(&self, path: String, variant: VariantDef, fields: Vec<String>) -> String {
+        let field_names: Vec<Name> =
+            variant.fields(self.db).iter().map(|field| field.name(self.db)).collect();
+        if field_names.is_empty() {
+            return path;
+        }
+        if field_names[0] == Name::tuple_field_name(0) {
+            return format!("{}({})", path, fields.join(", "));
+        }
+        let mut named_fields: Vec<String> = field_names
+            .iter()
+            .zip(fields.iter())
+            .filter(|(_, field)| *field != "_")
+            .map(|(name, field)| format!("{}: {}", name, field))
+            .collect();
+        if named_fields.len() < field_names.len() {
+            named_fields.push("..".to_string());
+        }
+        format!("{} {{ {} }}", path, named_fields.join(", "))
+    }
+}
+
+fn variant_constructor(variant: VariantDef) -> Constructor {
+    match variant {
+        VariantDef::Struct(s) => Constructor::Struct(s),
+        VariantDef::EnumVariant(v) => Constructor::Variant(v),
+    }
+}
+
+fn display_name(name: Option<Name>) -> String {
+    name.map_or_else(|| "_".to_string(), |name| name.to_string())
+}
+
+/// Lists the slice lengths which have to be considered separately for the
+/// slice patterns `heads`: all lengths up to the longest fixed-length pattern
+/// and all patterns with a `..` are distinct, and any longer slices are
+/// matched alike.
+fn slice_constructors(heads: &[&Pattern], is_array: bool) -> Option<ConstructorSet> {
+    let mut fixed_lens = Vec::new();
+    let (mut max_prefix, mut max_suffix) = (0, 0);
+    for head in heads {
+        match head {
+            Pattern::Slice { prefix, suffix, has_rest, is_array: head_is_array }
+                if *head_is_array == is_array =>
+            {
+                if *has_rest {
+                    max_prefix = cmp::max(max_prefix, prefix.len());
+                    max_suffix = cmp::max(max_suffix, suffix.len());
+                } else {
+                    fixed_lens.push(prefix.len());
+                }
+            }
+            _ => return None,
+        }
+    }
+    let ctors = if is_array {
+        // the length of an array is fixed, but not known to us
+        match fixed_lens.first() {
+            Some(&len) if fixed_lens.iter().all(|&it| it == len) => {
+                vec![Constructor::FixedLenSlice(len)]
+            }
+            Some(_) => return None,
+            None => vec![Constructor::VarLenSlice { prefix: max_prefix, suffix: max_suffix }],
+        }
+    } else {
+        let longest_fixed = fixed_lens.iter().max().map_or(0, |&len| len + 1);
+        let min_var_len = cmp::max(longest_fixed, max_prefix + max_suffix);
+        (0..min_var_len)
+            .map(Constructor::FixedLenSlice)
+            .chain(iter::once(Constructor::VarLenSlice {
+                prefix: min_var_len - max_suffix,
+                suffix: max_suffix,
+            }))
+            .collect()
+    };
+    Some(ConstructorSet::Finite(ctors))
+}
+
+/// Whether `pat` matches all values built with `ctor`.
+fn covers(pat: &Pattern, ctor: Constructor) -> bool {
+    match pat {
+        Pattern::Wild => true,
+        Pattern::Ctor(pat_ctor, _) => *pat_ctor == ctor,
+        Pattern::Slice { prefix, suffix, has_rest, .. } => {
+            let min_len = prefix.len() + suffix.len();
+            match ctor {
+                Constructor::FixedLenSlice(len) if *has_rest => min_len <= len,
+                Constructor::FixedLenSlice(len) => min_len == len,
+                Constructor::VarLenSlice { prefix: ctor_prefix, suffix: ctor_suffix } => {
+                    *has_rest && prefix.len() <= ctor_prefix && suffix.len() <= ctor_suffix
+                }
+                _ => false,
+            }
+        }
+        Pattern::Opaque => false,
+    }
+}
+
+/// Replaces the first pattern of `row` by its fields, if it matches values
+/// built with `ctor`.
+fn specialize(row: &Row, ctor: Constructor, arity: usize) -> Option<Row> {
+    let head = &row[0];
+    let mut fields = match head {
+        Pattern::Wild => vec![Pattern::Wild; arity],
+        Pattern::Ctor(_, fields) if covers(head, ctor) => fields.clone(),
+        Pattern::Slice { prefix, suffix, .. } if covers(head, ctor) => {
+            let wilds = arity - prefix.len() - suffix.len();
+            let mut fields = prefix.clone();
+            fields.extend(iter::repeat(Pattern::Wild).take(wilds));
+            fields.extend(suffix.iter().cloned());
+            fields
+        }
+        _ => return None,
+    };
+    fields.extend(row[1..].iter().cloned());
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot_matches;
+
+    use crate::mock::MockDatabase;
+
+    #[test]
+    fn missing_match_arms_diagnostics() {
+        let diagnostics = MockDatabase::with_files(
+            r"
+            //- /lib.rs
+            enum Option<T> { Some(T), None }
+            use Option::*;
+            enum E { A, B(bool), C { x: u32, y: bool } }
+
+            fn test(e: E, t: (bool, bool), o: &Option<E>, s: &[u32], n: u32) {
+                match e { E::A => (), E::B(true) => () }
+                match e { E::A => (), E::B(_) => (), E::C { .. } => () }
+                match t { (true, _) => (), (_, false) => () }
+                match o { Some(E::A) => (), None => () }
+                match s { [] => (), [_, ..] => () }
+                match s { [x] => (), [.., 0] => () }
+                match n { 0 => (), 1 => () }
+                match n { 0 => (), _ => () }
+                match e { E::C { y: true, .. } => (), _ if n == 0 => (), E::A | E::B(..) => () }
+            }
+            ",
+        )
+        .diagnostics();
+
+        assert_snapshot_matches!(diagnostics, @r###"
+"e": missing match arms: `E::B(false)`, `E::C { .. }` not covered
+"t": missing match arms: `(false, true)` not covered
+"o": missing match arms: `&Option::Some(E::B(_))`, `&Option::Some(E::C { .. })` not covered
+"s": missing match arms: `&[]`, `&[_, .., _]` not covered
+"n": missing match arms: `_` not covered
+"e": missing match arms: `E::C { y: false, .. }` not covered
+"###
+        );
+    }
+}
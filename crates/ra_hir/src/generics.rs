@@ -5,11 +5,14 @@
 
 use std::sync::Arc;
 
-use ra_syntax::ast::{self, NameOwner, TypeParamsOwner, TypeBoundsOwner, DefaultTypeParamOwner};
+use ra_syntax::ast::{
+    self, AstNode, NameOwner, TypeParamsOwner, TypeBoundsOwner, DefaultTypeParamOwner,
+};
 
 use crate::{
     db::{ HirDatabase, DefDatabase},
-    Name, AsName, Function, Struct, Enum, Trait, TypeAlias, ImplBlock, Container, path::Path, type_ref::TypeRef, AdtDef
+    Name, AsName, Function, Struct, Enum, Trait, TypeAlias, ImplBlock, Container, Either,
+    path::Path, type_ref::TypeRef, AdtDef
 };
 
 /// Data about a generic parameter (to a function, struct, impl, ...).
@@ -77,7 +80,10 @@ impl GenericParams {
                 generics.fill(&*it.source(db).1, start + 1);
             }
             GenericDef::TypeAlias(it) => generics.fill(&*it.source(db).1, start),
-            GenericDef::ImplBlock(it) => generics.fill(&*it.source(db).1, start),
+            GenericDef::ImplBlock(it) => match it.source(db).1 {
+                Either::A(impl_block) => generics.fill(&*impl_block, start),
+                Either::B(derive) => generics.fill_derived(&derive, it.target_trait(db), start),
+            },
         }
 
         Arc::new(generics)
@@ -92,6 +98,25 @@ impl GenericParams {
         }
     }
 
+    /// A derived impl has the generic parameters of the type it is derived
+    /// for, each of them bounded by the derived trait.
+    fn fill_derived(&mut self, derive: &ast::Attr, trait_ref: Option<TypeRef>, start: u32) {
+        let adt = derive.syntax().parent();
+        if let Some(struct_def) = adt.and_then(ast::StructDef::cast) {
+            self.fill(struct_def, start);
+        } else if let Some(enum_def) = adt.and_then(ast::EnumDef::cast) {
+            self.fill(enum_def, start);
+        }
+        let trait_ref = match trait_ref {
+            Some(TypeRef::Path(path)) => path,
+            _ => return,
+        };
+        for param in &self.params {
+            let type_ref = TypeRef::Path(param.name.clone().into());
+            self.where_predicates.push(WherePredicate { type_ref, trait_ref: trait_ref.clone() });
+        }
+    }
+
     fn fill_params(&mut self, params: &ast::TypeParamList, start: u32) {
         for (idx, type_param) in params.type_params().enumerate() {
             let name = type_param.name().map(AsName::as_name).unwrap_or_else(Name::missing);
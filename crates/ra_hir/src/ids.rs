@@ -4,12 +4,13 @@ use std::{
 };
 
 use ra_db::{FileId, salsa};
-use ra_syntax::{TreeArc, AstNode, ast, SyntaxNode};
+use ra_syntax::{TreeArc, AstNode, ast, SyntaxNode, TextUnit};
 use ra_prof::profile;
 use mbe::MacroRules;
 
 use crate::{
-    Module, DefDatabase, AstId, FileAstId,
+    Module, ModuleSource, DefDatabase, AstId, FileAstId, Crate,
+    builtin_macro::{self, BuiltinFnLikeExpander},
 };
 
 /// hir makes heavy use of ids: integer (u32) handlers to various things. You
@@ -24,7 +25,10 @@ use crate::{
 /// Rust are macros: each macro can be thought of as producing a "temporary
 /// file". To assign an id to such a file, we use the id of the macro call that
 /// produced the file. So, a `HirFileId` is either a `FileId` (source code
-/// written by user), or a `MacroCallId` (source code produced by macro).
+/// written by user), or a `MacroCallId` (source code produced by macro). Files
+/// pulled in by `include!` are written by the user, but still get a
+/// `HirFileId` of their own, so that their items are distinct from the items
+/// of the same file used as a module.
 ///
 /// What is a `MacroCallId`? Simplifying, it's a `HirFileId` of a file
 /// containing the call plus the offset of the macro call in the file. Note that
@@ -44,6 +48,16 @@ impl HirFileId {
                 let loc = macro_file.macro_call_id.loc(db);
                 loc.ast_id.file_id().original_file(db)
             }
+            HirFileIdRepr::Include(include_file) => include_file.file_id,
+        }
+    }
+
+    /// For macro-expansion files, returns the crate the macro is defined in.
+    /// This is the crate `$crate` refers to inside of the expansion.
+    pub(crate) fn macro_crate(self, db: &impl DefDatabase) -> Option<Crate> {
+        match self.0 {
+            HirFileIdRepr::File(_) | HirFileIdRepr::Include(_) => None,
+            HirFileIdRepr::Macro(macro_file) => Some(macro_file.macro_call_id.loc(db).def.krate),
         }
     }
 
@@ -53,6 +67,7 @@ impl HirFileId {
     pub fn as_original_file(self) -> FileId {
         match self.0 {
             HirFileIdRepr::File(file_id) => file_id,
+            HirFileIdRepr::Include(include_file) => include_file.file_id,
             HirFileIdRepr::Macro(_r) => panic!("macro generated file: {:?}", self),
         }
     }
@@ -64,6 +79,9 @@ impl HirFileId {
         let _p = profile("parse_or_expand_query");
         match file_id.0 {
             HirFileIdRepr::File(file_id) => Some(db.parse(file_id).syntax().to_owned()),
+            HirFileIdRepr::Include(include_file) => {
+                Some(db.parse(include_file.file_id).syntax().to_owned())
+            }
             HirFileIdRepr::Macro(macro_file) => {
                 let macro_call_id = macro_file.macro_call_id;
                 let tt = db
@@ -96,6 +114,13 @@ impl HirFileId {
 enum HirFileIdRepr {
     File(FileId),
     Macro(MacroFile),
+    Include(IncludeFile),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct IncludeFile {
+    file_id: FileId,
+    macro_call_id: MacroCallId,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -117,10 +142,23 @@ impl From<FileId> for HirFileId {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct MacroDefId(pub(crate) AstId<ast::MacroCall>);
+pub struct MacroDefId {
+    pub(crate) ast_id: AstId<ast::MacroCall>,
+    pub(crate) krate: Crate,
+    pub(crate) kind: MacroDefKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum MacroDefKind {
+    Declarative,
+    BuiltIn(BuiltinFnLikeExpander),
+}
 
 pub(crate) fn macro_def_query(db: &impl DefDatabase, id: MacroDefId) -> Option<Arc<MacroRules>> {
-    let macro_call = id.0.to_node(db);
+    if id.kind != MacroDefKind::Declarative {
+        return None;
+    }
+    let macro_call = id.ast_id.to_node(db);
     let arg = macro_call.token_tree()?;
     let (tt, _) = mbe::ast_to_token_tree(arg).or_else(|| {
         log::warn!("fail on macro_def to token tree: {:#?}", arg);
@@ -148,8 +186,13 @@ pub(crate) fn macro_expand_query(
     let loc = id.loc(db);
     let macro_arg = db.macro_arg(id).ok_or("Fail to args in to tt::TokenTree")?;
 
-    let macro_rules = db.macro_def(loc.def).ok_or("Fail to find macro definition")?;
-    let tt = macro_rules.expand(&macro_arg).map_err(|err| format!("{:?}", err))?;
+    let tt = match loc.def.kind {
+        MacroDefKind::Declarative => {
+            let macro_rules = db.macro_def(loc.def).ok_or("Fail to find macro definition")?;
+            macro_rules.expand(&macro_arg).map_err(|err| format!("{:?}", err))?
+        }
+        MacroDefKind::BuiltIn(expander) => expander.expand(db, id, &macro_arg)?,
+    };
     // Set a hard limit for the expanded tt
     let count = tt.count();
     if count > 65536 {
@@ -181,6 +224,8 @@ impl_intern_key!(MacroCallId);
 pub struct MacroCallLoc {
     pub(crate) def: MacroDefId,
     pub(crate) ast_id: AstId<ast::MacroCall>,
+    /// The crate the macro is called from.
+    pub(crate) krate: Crate,
 }
 
 impl MacroCallId {
@@ -188,10 +233,29 @@ impl MacroCallId {
         db.lookup_intern_macro(self)
     }
 
-    pub(crate) fn as_file(self, kind: MacroFileKind) -> HirFileId {
+    pub(crate) fn as_file(self, db: &impl DefDatabase, kind: MacroFileKind) -> HirFileId {
+        let include = MacroDefKind::BuiltIn(BuiltinFnLikeExpander::Include);
+        if kind == MacroFileKind::Items && self.loc(db).def.kind == include {
+            if let Some(file_id) = builtin_macro::include_file(db, self) {
+                let include_file = IncludeFile { file_id, macro_call_id: self };
+                return HirFileId(HirFileIdRepr::Include(include_file));
+            }
+        }
         let macro_file = MacroFile { macro_call_id: self, macro_file_kind: kind };
         HirFileId(HirFileIdRepr::Macro(macro_file))
     }
+
+    /// Returns the position of the macro call in the file written by the user,
+    /// looking through the calls of the macros the call was produced by.
+    pub(crate) fn call_site(self, db: &impl DefDatabase) -> (FileId, TextUnit) {
+        let loc = self.loc(db);
+        match loc.ast_id.file_id().0 {
+            HirFileIdRepr::Macro(macro_file) => macro_file.macro_call_id.call_site(db),
+            HirFileIdRepr::File(file_id) | HirFileIdRepr::Include(IncludeFile { file_id, .. }) => {
+                (file_id, loc.ast_id.to_node(db).syntax().range().start())
+            }
+        }
+    }
 }
 
 impl MacroCallLoc {
@@ -267,6 +331,32 @@ pub(crate) trait AstItemDef<N: AstNode>: salsa::InternKey + Clone {
         let loc = self.lookup_intern(db);
         loc.module
     }
+    /// The index of the item in the `AstIdMap` of its file, see `from_raw`.
+    fn to_raw(self, db: &impl DefDatabase) -> u32 {
+        self.lookup_intern(db).ast_id.to_raw()
+    }
+    /// Finds the item of `module` which had the index `index` in the file of
+    /// the module, even after the file was edited, see `AstId::relocate`.
+    /// Items expanded from macros are not found.
+    fn from_raw(
+        db: &impl DefDatabase,
+        module: Module,
+        index: u32,
+        is_item: impl Fn(&N) -> bool,
+    ) -> Option<Self> {
+        let (file_id, source) = module.definition_source(db);
+        let module_range = match source {
+            ModuleSource::SourceFile(_) => None,
+            ModuleSource::Module(it) => Some(it.syntax().range()),
+        };
+        // Don't pick up items of inline submodules.
+        let is_item = |it: &N| {
+            let parent_module = it.syntax().ancestors().find_map(ast::Module::cast);
+            parent_module.map(|it| it.syntax().range()) == module_range && is_item(it)
+        };
+        let ast_id = AstId::from_raw(file_id, index).relocate(db, is_item)?;
+        Some(Self::intern(db, ItemLoc { module, ast_id }))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
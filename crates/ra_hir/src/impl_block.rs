@@ -3,38 +3,54 @@ use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
 use ra_syntax::{
-    AstPtr, SourceFile, TreeArc,
-    ast::{self, AstNode}
+    AstPtr, SourceFile, TreeArc, T, SyntaxToken,
+    ast::{self, AstNode, AttrsOwner, NameOwner, TypeParamsOwner}
 };
 
 use crate::{
-    Const, TypeAlias, Function, HirFileId,
+    Const, TypeAlias, Function, HirFileId, Either, Name,
     HirDatabase, DefDatabase, TraitRef,
+    name::AsName,
+    path::{self, Path, PathKind, PathSegment, GenericArgs, GenericArg},
     type_ref::TypeRef,
     ids::LocationCtx,
     resolve::Resolver,
     ty::Ty,
-    generics::HasGenericParams,
     code_model_api::{Module, ModuleSource}
 };
 
+/// Derived impls don't have an impl block in the source, so we point to the
+/// `#[derive]` attribute instead.
+type ImplSourcePtr = Either<AstPtr<ast::ImplBlock>, AstPtr<ast::Attr>>;
+
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct ImplSourceMap {
-    map: ArenaMap<ImplId, AstPtr<ast::ImplBlock>>,
+    map: ArenaMap<ImplId, ImplSourcePtr>,
 }
 
 impl ImplSourceMap {
     fn insert(&mut self, impl_id: ImplId, impl_block: &ast::ImplBlock) {
-        self.map.insert(impl_id, AstPtr::new(impl_block))
+        self.map.insert(impl_id, Either::A(AstPtr::new(impl_block)))
+    }
+
+    fn insert_derive(&mut self, impl_id: ImplId, derive: &ast::Attr) {
+        self.map.insert(impl_id, Either::B(AstPtr::new(derive)))
     }
 
-    pub fn get(&self, source: &ModuleSource, impl_id: ImplId) -> TreeArc<ast::ImplBlock> {
+    pub fn get(
+        &self,
+        source: &ModuleSource,
+        impl_id: ImplId,
+    ) -> Either<TreeArc<ast::ImplBlock>, TreeArc<ast::Attr>> {
         let file = match source {
             ModuleSource::SourceFile(file) => &*file,
             ModuleSource::Module(m) => m.syntax().ancestors().find_map(SourceFile::cast).unwrap(),
         };
 
-        self.map[impl_id].to_node(file.syntax()).to_owned()
+        self.map[impl_id].map(
+            |ptr| ptr.to_node(file.syntax()).to_owned(),
+            |ptr| ptr.to_node(file.syntax()).to_owned(),
+        )
     }
 }
 
@@ -57,8 +73,12 @@ impl ImplBlock {
         ImplBlock { module, impl_id }
     }
 
-    /// Returns the syntax of the impl block
-    pub fn source(&self, db: &impl DefDatabase) -> (HirFileId, TreeArc<ast::ImplBlock>) {
+    /// Returns the syntax of the impl block, or of the `#[derive]` attribute
+    /// if this is a derived impl.
+    pub fn source(
+        &self,
+        db: &impl DefDatabase,
+    ) -> (HirFileId, Either<TreeArc<ast::ImplBlock>, TreeArc<ast::Attr>>) {
         let source_map = db.impls_in_module_source_map(self.module);
         let (file_id, source) = self.module.definition_source(db);
         (file_id, source_map.get(&source, self.impl_id))
@@ -100,8 +120,7 @@ impl ImplBlock {
     pub(crate) fn resolver(&self, db: &impl DefDatabase) -> Resolver {
         let r = self.module().resolver(db);
         // add generic params, if present
-        let p = self.generic_params(db);
-        let r = if !p.params.is_empty() { r.push_generic_params_scope(p) } else { r };
+        let r = r.push_generic_params_scope(db, (*self).into());
         let r = r.push_impl_block_scope(self.clone());
         r
     }
@@ -141,6 +160,37 @@ impl ImplData {
         ImplData { target_trait, target_type, items, negative }
     }
 
+    /// Creates the impl of a built-in derive like `#[derive(Clone)]` on
+    /// `struct Foo<T>`, which is `impl<T: Clone> Clone for Foo<T> {}`. The
+    /// bounds are added by `GenericParams`.
+    fn derived(adt: &(impl TypeParamsOwner + NameOwner), trait_path: Path) -> Self {
+        let args: Vec<_> = adt
+            .type_param_list()
+            .into_iter()
+            .flat_map(|it| it.type_params())
+            .map(|param| {
+                let name = param.name().map(AsName::as_name).unwrap_or_else(Name::missing);
+                GenericArg::Type(TypeRef::Path(name.into()))
+            })
+            .collect();
+        let args_and_bindings = if args.is_empty() {
+            None
+        } else {
+            Some(Arc::new(GenericArgs { args, has_self_type: false, bindings: Vec::new() }))
+        };
+        let name = adt.name().map(AsName::as_name).unwrap_or_else(Name::missing);
+        let target_type = TypeRef::Path(Path {
+            kind: PathKind::Plain,
+            segments: vec![PathSegment { name, args_and_bindings }],
+        });
+        ImplData {
+            target_trait: Some(TypeRef::Path(trait_path)),
+            target_type,
+            items: Vec::new(),
+            negative: false,
+        }
+    }
+
     pub fn target_trait(&self) -> Option<&TypeRef> {
         self.target_trait.as_ref()
     }
@@ -213,8 +263,63 @@ impl ModuleImplBlocks {
             source_map.insert(id, impl_block_ast);
         }
 
+        for item in node.children() {
+            if let Some(struct_def) = ast::StructDef::cast(item) {
+                m.collect_derives(struct_def, source_map);
+            } else if let Some(enum_def) = ast::EnumDef::cast(item) {
+                m.collect_derives(enum_def, source_map);
+            }
+        }
+
         m
     }
+
+    fn collect_derives(
+        &mut self,
+        adt: &(impl TypeParamsOwner + NameOwner + AttrsOwner),
+        source_map: &mut ImplSourceMap,
+    ) {
+        for attr in adt.attrs() {
+            let derives = match attr.as_call() {
+                Some((name, args)) if name == "derive" => args,
+                _ => continue,
+            };
+            // `#[derive(Clone, std::fmt::Debug)]` derives `Clone` and `Debug`;
+            // custom derives are ignored, since we can't know what they expand to.
+            let tokens: Vec<_> = derives
+                .syntax()
+                .children_with_tokens()
+                .filter_map(|it| it.as_token())
+                .filter(|it| !it.kind().is_trivia())
+                .filter(|it| it.kind() != T!['('] && it.kind() != T![')'])
+                .collect();
+            let trait_paths = tokens.split(|it| it.kind() == T![,]).filter_map(builtin_derive);
+            for trait_path in trait_paths {
+                let id = self.impls.alloc(ImplData::derived(adt, trait_path));
+                source_map.insert_derive(id, attr);
+            }
+        }
+    }
+}
+
+/// Returns the trait implemented by the built-in derive written as `tokens`.
+/// Only bare names like `Clone` and paths into `std` or `core`, like
+/// `std::clone::Clone`, are built-in derives, anything else is a custom one.
+fn builtin_derive(tokens: &[SyntaxToken]) -> Option<Path> {
+    let text: String = tokens.iter().map(|it| it.text().as_str()).collect();
+    let file = SourceFile::parse(&format!("type T = {};", text));
+    let path = file.syntax().descendants().find_map(ast::Path::cast)?;
+    let path = Path::from_ast(path)?;
+    let first = path.segments.first()?.name.to_string();
+    let is_builtin = match path.kind {
+        PathKind::Plain if path.segments.len() == 1 => true,
+        PathKind::Plain | PathKind::Abs => first == "std" || first == "core",
+        _ => false,
+    };
+    if !is_builtin {
+        return None;
+    }
+    path::known::std_builtin_derive(&path.segments.last()?.name.to_string())
 }
 
 pub(crate) fn impls_in_module_with_source_map_query(
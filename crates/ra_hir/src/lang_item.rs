@@ -4,7 +4,8 @@ use rustc_hash::FxHashMap;
 use ra_syntax::{SmolStr, ast::AttrsOwner};
 
 use crate::{
-    Crate, DefDatabase, Enum, Function, HirDatabase, ImplBlock, Module, Static, Struct, Trait
+    Crate, DefDatabase, Enum, Function, HirDatabase, ImplBlock, Module, Static, Struct, Trait,
+    ModuleDef, Either,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -79,20 +80,37 @@ impl LangItems {
         let (impl_blocks, source_map) = db.impls_in_module_with_source_map(module.clone());
         let source = module.definition_source(db).1;
         for (impl_id, _) in impl_blocks.impls.iter() {
-            let impl_block = source_map.get(&source, impl_id);
-            let lang_item_name = impl_block
-                .attrs()
-                .filter_map(|a| a.as_key_value())
-                .filter(|(key, _)| key == "lang")
-                .map(|(_, val)| val)
-                .nth(0);
+            let impl_block = match source_map.get(&source, impl_id) {
+                Either::A(impl_block) => impl_block,
+                Either::B(_derive) => continue,
+            };
+            let lang_item_name = lang_item_name(&*impl_block);
             if let Some(lang_item_name) = lang_item_name {
                 let imp = ImplBlock::from_id(*module, impl_id);
                 self.items.entry(lang_item_name).or_insert(LangItemTarget::ImplBlock(imp));
             }
         }
 
-        // FIXME we should look for the other lang item targets (traits, structs, ...)
+        // Look for trait and struct targets
+        for def in module.declarations(db) {
+            match def {
+                ModuleDef::Trait(trait_) => {
+                    let lang_item_name = lang_item_name(&*trait_.source(db).1);
+                    if let Some(lang_item_name) = lang_item_name {
+                        self.items.entry(lang_item_name).or_insert(LangItemTarget::Trait(trait_));
+                    }
+                }
+                ModuleDef::Struct(s) => {
+                    let lang_item_name = lang_item_name(&*s.source(db).1);
+                    if let Some(lang_item_name) = lang_item_name {
+                        self.items.entry(lang_item_name).or_insert(LangItemTarget::Struct(s));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // FIXME we should look for the other lang item targets (enums, functions, ...)
 
         // Look for lang items in the children
         for child in module.children(db) {
@@ -100,3 +118,11 @@ impl LangItems {
         }
     }
 }
+
+fn lang_item_name<T: AttrsOwner>(node: &T) -> Option<SmolStr> {
+    node.attrs()
+        .filter_map(|a| a.as_key_value())
+        .filter(|(key, _)| key == "lang")
+        .map(|(_, val)| val)
+        .nth(0)
+}
@@ -18,6 +18,8 @@ macro_rules! impl_froms {
 }
 
 mod either;
+mod attr;
+mod builtin_macro;
 
 pub mod db;
 #[macro_use]
@@ -63,10 +65,14 @@ pub use self::{
     source_id::{AstIdMap, ErasedFileAstId},
     ids::{HirFileId, MacroDefId, MacroCallId, MacroCallLoc},
     nameres::{PerNs, Namespace, ImportId},
-    ty::{Ty, ApplicationTy, TypeCtor, TraitRef, Substs, display::HirDisplay, CallableDef},
+    ty::{
+        Ty, ApplicationTy, TypeCtor, TraitRef, Substs, display::HirDisplay, CallableDef,
+        ProjectionTy, FnSig,
+    },
     impl_block::{ImplBlock, ImplItem},
+    traits::TraitItem,
     docs::{Docs, Documentation},
-    adt::AdtDef,
+    adt::{AdtDef, VariantDef},
     expr::ExprScopes,
     resolve::Resolution,
     generics::{GenericParams, GenericParam, HasGenericParams},
@@ -76,7 +82,7 @@ pub use self::{
 pub use self::code_model_api::{
     Crate, CrateDependency,
     DefWithBody,
-    Module, ModuleDef, ModuleSource,
+    Module, ModuleDef, ModuleSource, RawModuleDef,
     Struct, Enum, EnumVariant,
     Function, FnSignature,
     StructField, FieldSource,
@@ -10,4 +10,7 @@ test_utils::marks!(
     std_prelude
     match_ergonomics_ref
     trait_resolution_on_fn_type
+    macro_rules_from_other_crates_are_visible_with_macro_use
+    prelude_is_macro_use
+    macro_use_module
 );
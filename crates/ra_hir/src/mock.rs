@@ -1,10 +1,11 @@
-use std::{sync::Arc, panic};
+use std::{sync::Arc, panic, path::Path};
 
 use parking_lot::Mutex;
 use ra_db::{
-    FilePosition, FileId, CrateGraph, SourceRoot, SourceRootId, SourceDatabase, salsa,
-    Edition,
+    FilePosition, FileId, CrateGraph, SourceRoot, SourceRootId, SourceDatabase, salsa, Edition,
+    Env, ExternSource,
 };
+use ra_cfg::CfgOptions;
 use relative_path::RelativePathBuf;
 use test_utils::{parse_fixture, CURSOR_MARKER, extract_offset};
 use rustc_hash::FxHashMap;
@@ -19,6 +20,7 @@ pub struct MockDatabase {
     events: Mutex<Option<Vec<salsa::Event<MockDatabase>>>>,
     runtime: salsa::Runtime<MockDatabase>,
     files: FxHashMap<String, FileId>,
+    source_root_prefixes: FxHashMap<SourceRootId, String>,
 }
 
 impl panic::RefUnwindSafe for MockDatabase {}
@@ -54,12 +56,20 @@ impl MockDatabase {
     pub fn set_crate_graph_from_fixture(&mut self, graph: CrateGraphFixture) {
         let mut ids = FxHashMap::default();
         let mut crate_graph = CrateGraph::default();
-        for (crate_name, (crate_root, edition, _)) in graph.0.iter() {
+        // absolute paths, like the ones in `include!`, resolve to the source
+        // roots of the fixture
+        let mut extern_source = ExternSource::default();
+        for (&source_root_id, prefix) in self.source_root_prefixes.iter() {
+            extern_source.set_extern_path(Path::new(prefix), source_root_id);
+        }
+        for (crate_name, (crate_root, edition, cfg_options, env, _)) in graph.0.iter() {
             let crate_root = self.file_id_of(&crate_root);
-            let crate_id = crate_graph.add_crate_root(crate_root, *edition);
+            let crate_id =
+                crate_graph.add_crate_root(crate_root, *edition, cfg_options.clone(), env.clone());
+            crate_graph.set_extern_source(crate_id, extern_source.clone());
             ids.insert(crate_name, crate_id);
         }
-        for (crate_name, (_, _, deps)) in graph.0.iter() {
+        for (crate_name, (_, _, _, _, deps)) in graph.0.iter() {
             let from = ids[crate_name];
             for dep in deps {
                 let to = ids[dep];
@@ -142,6 +152,7 @@ impl MockDatabase {
         assert!(source_root_prefix.ends_with('/'));
         assert!(path.starts_with(source_root_prefix));
         let rel_path = RelativePathBuf::from_path(&path[source_root_prefix.len()..]).unwrap();
+        self.source_root_prefixes.insert(source_root_id, source_root_prefix.to_string());
 
         let is_crate_root = rel_path == "lib.rs" || rel_path == "/main.rs";
 
@@ -150,13 +161,19 @@ impl MockDatabase {
         assert!(prev.is_none(), "duplicate files in the text fixture");
         let text = Arc::new(text.to_string());
         self.set_file_text(file_id, text);
+        self.set_file_reparse_hint(file_id, None);
         self.set_file_relative_path(file_id, rel_path.clone());
         self.set_file_source_root(file_id, source_root_id);
         source_root.files.insert(rel_path, file_id);
 
         if is_crate_root {
             let mut crate_graph = CrateGraph::default();
-            crate_graph.add_crate_root(file_id, Edition::Edition2018);
+            crate_graph.add_crate_root(
+                file_id,
+                Edition::Edition2018,
+                CfgOptions::default(),
+                Env::default(),
+            );
             self.set_crate_graph(Arc::new(crate_graph));
         }
         file_id
@@ -195,6 +212,7 @@ impl Default for MockDatabase {
             events: Default::default(),
             runtime: salsa::Runtime::default(),
             files: FxHashMap::default(),
+            source_root_prefixes: FxHashMap::default(),
         };
         db.set_crate_graph(Default::default());
         db
@@ -208,6 +226,7 @@ impl salsa::ParallelDatabase for MockDatabase {
             runtime: self.runtime.snapshot(self),
             // only the root database can be used to get file_id by path.
             files: FxHashMap::default(),
+            source_root_prefixes: FxHashMap::default(),
         })
     }
 }
@@ -236,19 +255,57 @@ impl MockDatabase {
 }
 
 #[derive(Default)]
-pub struct CrateGraphFixture(pub Vec<(String, (String, Edition, Vec<String>))>);
+pub struct CrateGraphFixture(pub Vec<(String, (String, Edition, CfgOptions, Env, Vec<String>))>);
 
 #[macro_export]
 macro_rules! crate_graph {
-    ($($crate_name:literal: ($crate_path:literal, $($edition:literal,)? [$($dep:literal),*]),)*) => {{
+    ($(
+        $crate_name:literal: (
+            $crate_path:literal,
+            $($edition:literal,)?
+            [$($dep:literal),*]
+            $(, cfg = {
+                $($key:literal $(= $value:literal)?),*
+                $(,)?
+            })?
+            $(, env = {
+                $($env_key:literal : $env_value:literal),*
+                $(,)?
+            })?
+        ),
+    )*) => {{
         let mut res = $crate::mock::CrateGraphFixture::default();
         $(
             #[allow(unused_mut, unused_assignments)]
             let mut edition = ra_db::Edition::Edition2018;
             $(edition = ra_db::Edition::from_string($edition);)?
+            let cfg_options = {
+                #[allow(unused_mut)]
+                let mut cfg = ::ra_cfg::CfgOptions::default();
+                $(
+                    $(
+                        let value: Option<&str> = None $(.or(Some($value)))?;
+                        cfg = match value {
+                            None => cfg.atom($key.into()),
+                            Some(value) => cfg.key_value($key.into(), value.into()),
+                        };
+                    )*
+                )?
+                cfg
+            };
+            let env = {
+                #[allow(unused_mut)]
+                let mut env = ::ra_db::Env::default();
+                $(
+                    $(
+                        env.set($env_key, $env_value.to_string());
+                    )*
+                )?
+                env
+            };
             res.0.push((
                 $crate_name.to_string(),
-                ($crate_path.to_string(), edition, vec![$($dep.to_string()),*])
+                ($crate_path.to_string(), edition, cfg_options, env, vec![$($dep.to_string()),*])
             ));
         )*
         res
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use ra_syntax::{ast, SmolStr};
+use ra_syntax::{ast, SmolStr, SyntaxToken};
 
 /// `Name` is a wrapper around string, which is used in hir for both references
 /// and declarations. In theory, names should also carry hygiene info, but we are
@@ -46,6 +46,11 @@ impl Name {
         Name::new(idx.to_string().into())
     }
 
+    /// The name of a loop label, including the leading `'`.
+    pub(crate) fn new_lifetime(lt: SyntaxToken) -> Name {
+        Name::new(lt.text().clone())
+    }
+
     // There's should be no way to extract a string out of `Name`: `Name` in the
     // future, `Name` will include hygiene information, and you can't encode
     // hygiene into a String.
@@ -81,6 +86,14 @@ impl Name {
             "Self" => KnownName::SelfType,
             "self" => KnownName::SelfParam,
             "macro_rules" => KnownName::MacroRules,
+            "$crate" => KnownName::DollarCrate,
+            "concat" => KnownName::Concat,
+            "env" => KnownName::Env,
+            "file" => KnownName::File,
+            "format_args" => KnownName::FormatArgs,
+            "include" => KnownName::Include,
+            "line" => KnownName::Line,
+            "stringify" => KnownName::Stringify,
             _ => return None,
         };
         Some(name)
@@ -112,6 +125,12 @@ impl<'a> AsName for ast::FieldKind<'a> {
     }
 }
 
+impl AsName for tt::Ident {
+    fn as_name(&self) -> Name {
+        Name::new(self.text.clone())
+    }
+}
+
 impl AsName for ra_db::Dependency {
     fn as_name(&self) -> Name {
         Name::new(self.name.clone())
@@ -150,4 +169,78 @@ pub(crate) enum KnownName {
     SelfParam,
 
     MacroRules,
+    DollarCrate,
+
+    Concat,
+    Env,
+    File,
+    FormatArgs,
+    Include,
+    Line,
+    Stringify,
+}
+
+/// Names of well-known items of the standard library, which we need to refer
+/// to during type inference.
+pub(crate) mod known {
+    use super::Name;
+
+    pub(crate) fn std() -> Name {
+        Name::new("std".into())
+    }
+
+    pub(crate) fn iter() -> Name {
+        Name::new("iter".into())
+    }
+
+    pub(crate) fn ops() -> Name {
+        Name::new("ops".into())
+    }
+
+    pub(crate) fn result() -> Name {
+        Name::new("result".into())
+    }
+
+    pub(crate) fn result_type() -> Name {
+        Name::new("Result".into())
+    }
+
+    pub(crate) fn into_iterator_trait() -> Name {
+        Name::new("IntoIterator".into())
+    }
+
+    pub(crate) fn try_trait() -> Name {
+        Name::new("Try".into())
+    }
+
+    pub(crate) fn item_type() -> Name {
+        Name::new("Item".into())
+    }
+
+    pub(crate) fn ok_type() -> Name {
+        Name::new("Ok".into())
+    }
+
+    pub(crate) fn target_type() -> Name {
+        Name::new("Target".into())
+    }
+
+    pub(crate) fn output_type() -> Name {
+        Name::new("Output".into())
+    }
+
+    /// Returns the module of `std` declaring the trait of the built-in derive
+    /// `derive`, and the name of the trait.
+    pub(crate) fn builtin_derive(derive: &str) -> Option<(Name, Name)> {
+        let module = match derive {
+            "Clone" => "clone",
+            "Copy" => "marker",
+            "Debug" => "fmt",
+            "Default" => "default",
+            "Hash" => "hash",
+            "PartialEq" | "Eq" | "PartialOrd" | "Ord" => "cmp",
+            _ => return None,
+        };
+        Some((Name::new(module.into()), Name::new(derive.into())))
+    }
 }
@@ -38,11 +38,19 @@
 ///
 /// ## Resolving Macros
 ///
-/// macro_rules from the same crate use a global mutable namespace. We expand
-/// them immediately, when we collect modules.
+/// macro_rules from the same crate use a textual (legacy) scope: a macro is
+/// visible after its definition in the same module and in child modules
+/// declared after it. `#[macro_use]` on a module makes the macros defined in
+/// it visible in the parent module after the module declaration. We expand
+/// such macros immediately, when we collect modules.
 ///
-/// Macros from other crates (including proc-macros) can be used with
-/// `foo::bar!` syntax. We handle them similarly to imports. There's a list of
+/// `#[macro_use] extern crate foo;` (and the implicit one for the crate of the
+/// std prelude) imports all `#[macro_export]`ed macros of `foo` into the
+/// crate-wide `macro_use` prelude, which is consulted after the textual scope.
+///
+/// Macros from other crates (including proc-macros) can also be used with
+/// `foo::bar!` syntax, and `#[macro_export]`ed macros with `$crate::bar!` from
+/// within expansions. We handle them similarly to imports. There's a list of
 /// unexpanded macros. On every iteration, we try to resolve each macro call
 /// path and, upon success, we run macro expansion and "collect module" phase
 /// on the result
@@ -90,7 +98,11 @@ pub struct CrateDefMap {
     extern_prelude: FxHashMap<Name, ModuleDef>,
     root: CrateModuleId,
     modules: Arena<CrateModuleId, ModuleData>,
+    /// `#[macro_export]`ed macros, which are visible at the crate root.
     public_macros: FxHashMap<Name, MacroDefId>,
+    /// Macros imported with `#[macro_use] extern crate`, visible in the whole
+    /// crate.
+    macro_use_prelude: FxHashMap<Name, MacroDefId>,
 
     /// Some macros are not well-behavior, which leads to infinite loop
     /// e.g. macro_rules! foo { ($ty:ty) => { foo!($ty); } }
@@ -104,7 +116,6 @@ pub struct CrateDefMap {
     /// However, do we want to put it as a global variable?
     poison_macros: FxHashSet<MacroDefId>,
 
-    local_macros: FxHashMap<Name, MacroDefId>,
     diagnostics: Vec<DefDiagnostic>,
 }
 
@@ -136,6 +147,9 @@ pub(crate) struct ModuleData {
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct ModuleScope {
     items: FxHashMap<Name, Resolution>,
+    /// `macro_rules` macros which are textually in scope at the end of the
+    /// module.
+    legacy_macros: FxHashMap<Name, MacroDefId>,
 }
 
 impl ModuleScope {
@@ -151,6 +165,9 @@ impl ModuleScope {
             _ => None,
         })
     }
+    fn get_legacy_macro(&self, name: &Name) -> Option<MacroDefId> {
+        self.legacy_macros.get(name).cloned()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -209,8 +226,8 @@ impl CrateDefMap {
                 root,
                 modules,
                 public_macros: FxHashMap::default(),
+                macro_use_prelude: FxHashMap::default(),
                 poison_macros: FxHashSet::default(),
-                local_macros: FxHashMap::default(),
                 diagnostics: Vec::new(),
             }
         };
@@ -230,6 +247,10 @@ impl CrateDefMap {
         Module { krate: self.krate, module_id }
     }
 
+    pub(crate) fn modules<'a>(&'a self) -> impl Iterator<Item = CrateModuleId> + 'a {
+        self.modules.iter().map(|(id, _)| id)
+    }
+
     pub(crate) fn prelude(&self) -> Option<Module> {
         self.prelude
     }
@@ -272,8 +293,40 @@ impl CrateDefMap {
         (res.resolved_def, res.segment_index)
     }
 
-    pub(crate) fn find_macro(&self, name: &Name) -> Option<MacroDefId> {
-        self.public_macros.get(name).or(self.local_macros.get(name)).map(|it| *it)
+    /// Resolves the path of a macro call in `module`.
+    ///
+    /// A single identifier is looked up in the textual `macro_rules` scope of
+    /// the module and then in the `macro_use` prelude. Longer paths, like
+    /// `foo::bar!`, `crate::bar!` or `$crate::bar!`, refer to `#[macro_export]`ed
+    /// macros of a crate.
+    pub(crate) fn resolve_macro(
+        &self,
+        db: &impl DefDatabase,
+        module: CrateModuleId,
+        path: &Path,
+    ) -> Option<MacroDefId> {
+        if let Some(name) = path.as_ident() {
+            return self[module]
+                .scope
+                .get_legacy_macro(name)
+                .or_else(|| self.macro_use_prelude.get(name).cloned());
+        }
+        let (krate, name) = match (path.kind, path.segments.as_slice()) {
+            (PathKind::DollarCrate(krate), [segment]) => (krate, &segment.name),
+            (PathKind::Crate, [segment]) => (self.krate, &segment.name),
+            (PathKind::Plain, [crate_name, segment]) | (PathKind::Abs, [crate_name, segment]) => {
+                match self.resolve_name_in_extern_prelude(&crate_name.name).take_types() {
+                    Some(ModuleDef::Module(m)) => (m.krate, &segment.name),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        if krate == self.krate {
+            self.public_macros.get(name).cloned()
+        } else {
+            db.crate_def_map(krate).public_macros.get(name).cloned()
+        }
     }
 
     // Returns Yes if we are sure that additions to `ItemMap` wouldn't change
@@ -293,6 +346,16 @@ impl CrateDefMap {
             PathKind::Self_ => {
                 PerNs::types(Module { krate: self.krate, module_id: original_module }.into())
             }
+            PathKind::DollarCrate(krate) => {
+                if krate == self.krate {
+                    PerNs::types(Module { krate: self.krate, module_id: self.root }.into())
+                } else {
+                    match krate.root_module(db) {
+                        Some(module) => PerNs::types(module.into()),
+                        None => return ResolvePathResult::empty(ReachedFixedPoint::Yes),
+                    }
+                }
+            }
             // plain import or absolute path in 2015: crate-relative with
             // fallback to extern prelude (with the simplification in
             // rust-lang/rust#57745)
@@ -1,9 +1,9 @@
 use arrayvec::ArrayVec;
 use rustc_hash::FxHashMap;
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
 use test_utils::tested_by;
 use ra_db::FileId;
-use ra_syntax::ast;
+use ra_syntax::{SmolStr, ast};
 
 use crate::{
     Function, Module, Struct, Enum, Const, Static, Trait, TypeAlias,
@@ -15,8 +15,12 @@ use crate::{
         diagnostics::DefDiagnostic,
         raw,
     },
-    ids::{AstItemDef, LocationCtx, MacroCallLoc, MacroCallId, MacroDefId, MacroFileKind},
+    ids::{
+        AstItemDef, LocationCtx, MacroCallLoc, MacroCallId, MacroDefId, MacroDefKind, MacroFileKind,
+    },
+    builtin_macro::BuiltinFnLikeExpander,
     AstId,
+    attr::is_cfg_enabled,
 };
 
 pub(super) fn collect_defs(db: &impl DefDatabase, mut def_map: CrateDefMap) -> CrateDefMap {
@@ -35,13 +39,24 @@ pub(super) fn collect_defs(db: &impl DefDatabase, mut def_map: CrateDefMap) -> C
         }
     }
 
+    // the crate providing the prelude is implicitly `#[macro_use] extern crate`d
+    if let Some(prelude) = def_map.prelude {
+        if prelude.krate != def_map.krate {
+            tested_by!(prelude_is_macro_use);
+            let prelude_def_map = db.crate_def_map(prelude.krate);
+            for (name, &macro_id) in prelude_def_map.public_macros.iter() {
+                def_map.macro_use_prelude.insert(name.clone(), macro_id);
+            }
+        }
+    }
+
     let mut collector = DefCollector {
         db,
         def_map,
         glob_imports: FxHashMap::default(),
         unresolved_imports: Vec::new(),
         unexpanded_macros: Vec::new(),
-        global_macro_scope: FxHashMap::default(),
+        mod_dirs: FxHashMap::default(),
         macro_stack_monitor: MacroStackMonitor::default(),
     };
     collector.collect();
@@ -83,7 +98,7 @@ struct DefCollector<DB> {
     glob_imports: FxHashMap<CrateModuleId, Vec<(CrateModuleId, raw::ImportId)>>,
     unresolved_imports: Vec<(CrateModuleId, raw::ImportId, raw::ImportData)>,
     unexpanded_macros: Vec<(CrateModuleId, AstId<ast::MacroCall>, Path)>,
-    global_macro_scope: FxHashMap<Name, MacroDefId>,
+    mod_dirs: FxHashMap<CrateModuleId, ModDir>,
 
     /// Some macro use `$tt:tt which mean we have to handle the macro perfectly
     /// To prevent stackoverflow, we add a deep counter here for prevent that.
@@ -100,6 +115,8 @@ where
         let raw_items = self.db.raw_items(file_id.into());
         let module_id = self.def_map.root;
         self.def_map.modules[module_id].definition = Some(file_id);
+        let root_path = self.db.file_relative_path(file_id);
+        self.mod_dirs.insert(module_id, ModDir::for_file(&root_path, true));
         ModCollector {
             def_collector: &mut *self,
             module_id,
@@ -128,13 +145,51 @@ where
         }
     }
 
-    fn define_macro(&mut self, name: Name, macro_id: MacroDefId, export: bool) {
+    fn define_macro(
+        &mut self,
+        module_id: CrateModuleId,
+        name: Name,
+        macro_id: MacroDefId,
+        export: bool,
+    ) {
         if export {
             self.def_map.public_macros.insert(name.clone(), macro_id);
-        } else {
-            self.def_map.local_macros.insert(name.clone(), macro_id);
         }
-        self.global_macro_scope.insert(name, macro_id);
+        self.define_legacy_macro(module_id, name, macro_id);
+    }
+
+    /// Makes `macro_id` textually visible in `module_id` from now on.
+    fn define_legacy_macro(&mut self, module_id: CrateModuleId, name: Name, macro_id: MacroDefId) {
+        self.def_map.modules[module_id].scope.legacy_macros.insert(name, macro_id);
+    }
+
+    /// Imports all textually visible macros of `from` into `to`.
+    fn import_all_legacy_macros(&mut self, from: CrateModuleId, to: CrateModuleId) {
+        let macros = self.def_map[from].scope.legacy_macros.clone();
+        for (name, macro_id) in macros {
+            self.define_legacy_macro(to, name, macro_id);
+        }
+    }
+
+    /// Handles `#[macro_use] extern crate foo;` by importing all exported
+    /// macros of `foo` into the `macro_use` prelude.
+    fn import_macros_from_extern_crate(&mut self, import: &raw::ImportData) {
+        log::debug!(
+            "importing macros from extern crate: {:?} ({:?})",
+            import,
+            self.def_map.edition
+        );
+        let res = import
+            .path
+            .as_ident()
+            .map(|name| self.def_map.resolve_name_in_extern_prelude(name).take_types());
+        if let Some(Some(ModuleDef::Module(m))) = res {
+            tested_by!(macro_rules_from_other_crates_are_visible_with_macro_use);
+            let item_map = self.db.crate_def_map(m.krate);
+            for (name, &macro_id) in item_map.public_macros.iter() {
+                self.def_map.macro_use_prelude.insert(name.clone(), macro_id);
+            }
+        }
     }
 
     fn resolve_imports(&mut self) -> ReachedFixedPoint {
@@ -331,26 +386,25 @@ where
         let mut resolved = Vec::new();
         let mut res = ReachedFixedPoint::Yes;
         macros.retain(|(module_id, ast_id, path)| {
-            if path.segments.len() != 2 {
-                return true;
-            }
-            let crate_name = &path.segments[0].name;
-            let krate = match self.def_map.resolve_name_in_extern_prelude(crate_name).take_types() {
-                Some(ModuleDef::Module(m)) => m.krate(self.db),
-                _ => return true,
-            };
-            let krate = match krate {
-                Some(it) => it,
-                _ => return true,
+            // The textual scope was already checked when the call was
+            // collected, only the `macro_use` prelude can change since then.
+            let macro_id = match path.as_ident() {
+                Some(name) => self.def_map.macro_use_prelude.get(name).cloned(),
+                None => self.def_map.resolve_macro(self.db, *module_id, path),
             };
-            res = ReachedFixedPoint::No;
-            let def_map = self.db.crate_def_map(krate);
-            if let Some(macro_id) = def_map.public_macros.get(&path.segments[1].name).cloned() {
-                let call_id = MacroCallLoc { def: macro_id, ast_id: *ast_id }.id(self.db);
-                resolved.push((*module_id, call_id, macro_id));
+            match macro_id {
+                Some(macro_id) => {
+                    res = ReachedFixedPoint::No;
+                    let call_id =
+                        MacroCallLoc { def: macro_id, ast_id: *ast_id, krate: self.def_map.krate }
+                            .id(self.db);
+                    resolved.push((*module_id, call_id, macro_id));
+                    false
+                }
+                None => true,
             }
-            false
         });
+        self.unexpanded_macros = macros;
 
         for (module_id, macro_call_id, macro_def_id) in resolved {
             self.collect_macro_expansion(module_id, macro_call_id, macro_def_id);
@@ -371,7 +425,7 @@ where
         self.macro_stack_monitor.increase(macro_def_id);
 
         if !self.macro_stack_monitor.is_poison(macro_def_id) {
-            let file_id: HirFileId = macro_call_id.as_file(MacroFileKind::Items);
+            let file_id: HirFileId = macro_call_id.as_file(self.db, MacroFileKind::Items);
             let raw_items = self.db.raw_items(file_id);
             ModCollector { def_collector: &mut *self, file_id, module_id, raw_items: &raw_items }
                 .collect(raw_items.items());
@@ -401,26 +455,41 @@ where
     DB: DefDatabase,
 {
     fn collect(&mut self, items: &[raw::RawItem]) {
+        let crate_graph = self.def_collector.db.crate_graph();
+        let cfg_options = crate_graph.cfg_options(self.def_collector.def_map.krate.crate_id());
         for item in items {
-            match *item {
-                raw::RawItem::Module(m) => self.collect_module(&self.raw_items[m]),
-                raw::RawItem::Import(import) => self.def_collector.unresolved_imports.push((
-                    self.module_id,
-                    import,
-                    self.raw_items[import].clone(),
-                )),
-                raw::RawItem::Def(def) => self.define_def(&self.raw_items[def]),
-                raw::RawItem::Macro(mac) => self.collect_macro(&self.raw_items[mac]),
+            if !is_cfg_enabled(item.attrs.as_ref().map(|it| &**it), cfg_options) {
+                continue;
+            }
+            match item.kind {
+                raw::RawItemKind::Module(m) => {
+                    self.collect_module(&self.raw_items[m], raw::is_macro_use(&item.attrs))
+                }
+                raw::RawItemKind::Import(import) => {
+                    let import_data = &self.raw_items[import];
+                    if import_data.is_extern_crate && import_data.is_macro_use {
+                        self.def_collector.import_macros_from_extern_crate(import_data);
+                    }
+                    self.def_collector.unresolved_imports.push((
+                        self.module_id,
+                        import,
+                        import_data.clone(),
+                    ))
+                }
+                raw::RawItemKind::Def(def) => self.define_def(&self.raw_items[def]),
+                raw::RawItemKind::Macro(mac) => self.collect_macro(&self.raw_items[mac]),
             }
         }
     }
 
-    fn collect_module(&mut self, module: &raw::ModuleData) {
+    fn collect_module(&mut self, module: &raw::ModuleData, is_macro_use: bool) {
         match module {
             // inline module, just recurse
-            raw::ModuleData::Definition { name, items, ast_id } => {
+            raw::ModuleData::Definition { name, items, ast_id, attr_path } => {
+                let mod_dir = self.def_collector.mod_dirs[&self.module_id].inline(name, attr_path);
                 let module_id =
                     self.push_child_module(name.clone(), ast_id.with_file_id(self.file_id), None);
+                self.def_collector.mod_dirs.insert(module_id, mod_dir);
                 ModCollector {
                     def_collector: &mut *self.def_collector,
                     module_id,
@@ -428,14 +497,19 @@ where
                     raw_items: self.raw_items,
                 }
                 .collect(&*items);
+                if is_macro_use {
+                    self.import_legacy_macros_from_child(module_id);
+                }
             }
             // out of line module, resovle, parse and recurse
-            raw::ModuleData::Declaration { name, ast_id } => {
+            raw::ModuleData::Declaration { name, ast_id, attr_path } => {
                 let ast_id = ast_id.with_file_id(self.file_id);
-                let is_root = self.def_collector.def_map.modules[self.module_id].parent.is_none();
-                match resolve_submodule(self.def_collector.db, self.file_id, name, is_root) {
-                    Ok(file_id) => {
+                let mod_dir = &self.def_collector.mod_dirs[&self.module_id];
+                let db = self.def_collector.db;
+                match mod_dir.resolve_declaration(db, self.file_id, name, attr_path) {
+                    Ok((file_id, mod_dir)) => {
                         let module_id = self.push_child_module(name.clone(), ast_id, Some(file_id));
+                        self.def_collector.mod_dirs.insert(module_id, mod_dir);
                         let raw_items = self.def_collector.db.raw_items(file_id.into());
                         ModCollector {
                             def_collector: &mut *self.def_collector,
@@ -443,7 +517,10 @@ where
                             file_id: file_id.into(),
                             raw_items: &raw_items,
                         }
-                        .collect(raw_items.items())
+                        .collect(raw_items.items());
+                        if is_macro_use {
+                            self.import_legacy_macros_from_child(module_id);
+                        }
                     }
                     Err(candidate) => self.def_collector.def_map.diagnostics.push(
                         DefDiagnostic::UnresolvedModule {
@@ -469,6 +546,8 @@ where
         modules[res].declaration = Some(declaration);
         modules[res].definition = definition;
         modules[self.module_id].children.insert(name.clone(), res);
+        // macros defined so far in the parent are textually visible in the child
+        self.def_collector.import_all_legacy_macros(self.module_id, res);
         let resolution = Resolution {
             def: PerNs::types(
                 Module { krate: self.def_collector.def_map.krate, module_id: res }.into(),
@@ -506,24 +585,35 @@ where
     }
 
     fn collect_macro(&mut self, mac: &raw::MacroData) {
-        // Case 1: macro rules, define a macro in crate-global mutable scope
+        // Case 1: macro rules, define a macro in the textual scope of the
+        // current module
         if is_macro_rules(&mac.path) {
             if let Some(name) = &mac.name {
-                let macro_id = MacroDefId(mac.ast_id.with_file_id(self.file_id));
-                self.def_collector.define_macro(name.clone(), macro_id, mac.export)
+                let kind = match BuiltinFnLikeExpander::by_name(name) {
+                    Some(expander) if mac.builtin => MacroDefKind::BuiltIn(expander),
+                    _ => MacroDefKind::Declarative,
+                };
+                let macro_id = MacroDefId {
+                    ast_id: mac.ast_id.with_file_id(self.file_id),
+                    krate: self.def_collector.def_map.krate,
+                    kind,
+                };
+                self.def_collector.define_macro(self.module_id, name.clone(), macro_id, mac.export)
             }
             return;
         }
 
         let ast_id = mac.ast_id.with_file_id(self.file_id);
 
-        // Case 2: try to expand macro_rules from this crate, triggering
-        // recursive item collection.
-        if let Some(macro_id) =
-            mac.path.as_ident().and_then(|name| self.def_collector.global_macro_scope.get(&name))
-        {
-            let def = *macro_id;
-            let macro_call_id = MacroCallLoc { def, ast_id }.id(self.def_collector.db);
+        // Case 2: try to expand macro_rules which are textually in scope (or
+        // already resolvable by path), triggering recursive item collection.
+        if let Some(def) = self.def_collector.def_map.resolve_macro(
+            self.def_collector.db,
+            self.module_id,
+            &mac.path,
+        ) {
+            let krate = self.def_collector.def_map.krate;
+            let macro_call_id = MacroCallLoc { def, ast_id, krate }.id(self.def_collector.db);
 
             self.def_collector.collect_macro_expansion(self.module_id, macro_call_id, def);
             return;
@@ -532,43 +622,81 @@ where
         // Case 3: path to a macro from another crate, expand during name resolution
         self.def_collector.unexpanded_macros.push((self.module_id, ast_id, mac.path.clone()))
     }
+
+    /// Makes the macros of a `#[macro_use]` child module visible in the
+    /// current module.
+    fn import_legacy_macros_from_child(&mut self, child: CrateModuleId) {
+        tested_by!(macro_use_module);
+        self.def_collector.import_all_legacy_macros(child, self.module_id);
+    }
 }
 
 fn is_macro_rules(path: &Path) -> bool {
     path.as_ident().and_then(Name::as_known_name) == Some(KnownName::MacroRules)
 }
 
-fn resolve_submodule(
-    db: &impl DefDatabase,
-    file_id: HirFileId,
-    name: &Name,
-    is_root: bool,
-) -> Result<FileId, RelativePathBuf> {
-    // FIXME: handle submodules of inline modules properly
-    let file_id = file_id.original_file(db);
-    let source_root_id = db.file_source_root(file_id);
-    let path = db.file_relative_path(file_id);
-    let root = RelativePathBuf::default();
-    let dir_path = path.parent().unwrap_or(&root);
-    let mod_name = path.file_stem().unwrap_or("unknown");
-    let is_dir_owner = is_root || mod_name == "mod";
-
-    let file_mod = dir_path.join(format!("{}.rs", name));
-    let dir_mod = dir_path.join(format!("{}/mod.rs", name));
-    let file_dir_mod = dir_path.join(format!("{}/{}.rs", mod_name, name));
-    let mut candidates = ArrayVec::<[_; 2]>::new();
-    if is_dir_owner {
-        candidates.push(file_mod.clone());
-        candidates.push(dir_mod);
-    } else {
-        candidates.push(file_dir_mod.clone());
-    };
-    let sr = db.source_root(source_root_id);
-    let mut points_to = candidates.into_iter().filter_map(|path| sr.files.get(&path)).map(|&it| it);
-    // FIXME: handle ambiguity
-    match points_to.next() {
-        Some(file_id) => Ok(file_id),
-        None => Err(if is_dir_owner { file_mod } else { file_dir_mod }),
+/// The directories the files of the out-of-line child modules of a module are
+/// looked up in, relative to the source root.
+#[derive(Debug, Clone)]
+struct ModDir {
+    /// The directory `mod foo;` looks for `foo.rs` and `foo/mod.rs` in.
+    dir: RelativePathBuf,
+    /// The directory `#[path = "..."] mod foo;` is relative to. It is the
+    /// directory of the file itself at the top level of a non-`mod.rs` file,
+    /// and `dir` everywhere else.
+    attr_dir: RelativePathBuf,
+}
+
+impl ModDir {
+    /// The directories of a module defined by a whole file. Crate roots,
+    /// `mod.rs` files and files loaded through `#[path]` own their directory,
+    /// while the children of `foo.rs` live in `foo/`.
+    fn for_file(path: &RelativePath, is_dir_owner: bool) -> ModDir {
+        let root = RelativePathBuf::default();
+        let attr_dir = path.parent().unwrap_or(&root).to_owned();
+        let mod_name = path.file_stem().unwrap_or("unknown");
+        let dir = if is_dir_owner || mod_name == "mod" {
+            attr_dir.clone()
+        } else {
+            attr_dir.join(mod_name)
+        };
+        ModDir { dir, attr_dir }
+    }
+
+    /// The directories of an inline `mod name { ... }` in this module.
+    fn inline(&self, name: &Name, attr_path: &Option<SmolStr>) -> ModDir {
+        let dir = match attr_path {
+            Some(path) => self.attr_dir.join(path.as_str()).normalize(),
+            None => self.dir.join(name.to_string()),
+        };
+        ModDir { dir: dir.clone(), attr_dir: dir }
+    }
+
+    /// Finds the file of a `mod name;` declaration in this module. On failure,
+    /// returns the path a file for the module could be created at.
+    fn resolve_declaration(
+        &self,
+        db: &impl DefDatabase,
+        file_id: HirFileId,
+        name: &Name,
+        attr_path: &Option<SmolStr>,
+    ) -> Result<(FileId, ModDir), RelativePathBuf> {
+        let mut candidates = ArrayVec::<[_; 2]>::new();
+        match attr_path {
+            Some(path) => candidates.push(self.attr_dir.join(path.as_str()).normalize()),
+            None => {
+                candidates.push(self.dir.join(format!("{}.rs", name)));
+                candidates.push(self.dir.join(format!("{}/mod.rs", name)));
+            }
+        }
+        let file_id = file_id.original_file(db);
+        let sr = db.source_root(db.file_source_root(file_id));
+        let mut points_to = candidates.iter().filter_map(|path| Some((*sr.files.get(path)?, path)));
+        // FIXME: handle ambiguity
+        match points_to.next() {
+            Some((file_id, path)) => Ok((file_id, ModDir::for_file(path, attr_path.is_some()))),
+            None => Err(candidates[0].clone()),
+        }
     }
 }
 
@@ -592,7 +720,7 @@ mod tests {
             glob_imports: FxHashMap::default(),
             unresolved_imports: Vec::new(),
             unexpanded_macros: Vec::new(),
-            global_macro_scope: FxHashMap::default(),
+            mod_dirs: FxHashMap::default(),
             macro_stack_monitor: monitor,
         };
         collector.collect();
@@ -616,8 +744,8 @@ mod tests {
                 root,
                 modules,
                 public_macros: FxHashMap::default(),
+                macro_use_prelude: FxHashMap::default(),
                 poison_macros: FxHashSet::default(),
-                local_macros: FxHashMap::default(),
                 diagnostics: Vec::new(),
             }
         };
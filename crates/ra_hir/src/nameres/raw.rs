@@ -6,13 +6,14 @@ use std::{
 use test_utils::tested_by;
 use ra_arena::{Arena, impl_arena_id, RawId, map::ArenaMap};
 use ra_syntax::{
-    AstNode, SourceFile, AstPtr, TreeArc,
+    AstNode, SourceFile, AstPtr, TreeArc, SmolStr,
     ast::{self, NameOwner, AttrsOwner},
 };
 
 use crate::{
     DefDatabase, Name, AsName, Path, HirFileId, ModuleSource,
-    AstIdMap, FileAstId, Either,
+    AstIdMap, FileAstId, Either, Crate,
+    attr::Attr,
 };
 
 /// `RawItems` is a set of top-level items in a file (except for impls).
@@ -74,6 +75,7 @@ impl RawItems {
             raw_items: RawItems::default(),
             source_ast_id_map: db.ast_id_map(file_id.into()),
             source_map: ImportSourceMap::default(),
+            macro_crate: file_id.macro_crate(db),
         };
         if let Some(node) = db.parse_or_expand(file_id) {
             if let Some(source_file) = ast::SourceFile::cast(&node) {
@@ -116,8 +118,14 @@ impl Index<Macro> for RawItems {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(super) struct RawItem {
+    pub(super) attrs: Option<Arc<[Attr]>>,
+    pub(super) kind: RawItemKind,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub(super) enum RawItem {
+pub(super) enum RawItemKind {
     Module(Module),
     Import(ImportId),
     Def(Def),
@@ -130,8 +138,18 @@ impl_arena_id!(Module);
 
 #[derive(Debug, PartialEq, Eq)]
 pub(super) enum ModuleData {
-    Declaration { name: Name, ast_id: FileAstId<ast::Module> },
-    Definition { name: Name, ast_id: FileAstId<ast::Module>, items: Vec<RawItem> },
+    Declaration {
+        name: Name,
+        ast_id: FileAstId<ast::Module>,
+        /// The value of the `#[path = "..."]` attribute, if any.
+        attr_path: Option<SmolStr>,
+    },
+    Definition {
+        name: Name,
+        ast_id: FileAstId<ast::Module>,
+        attr_path: Option<SmolStr>,
+        items: Vec<RawItem>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -145,6 +163,7 @@ pub struct ImportData {
     pub(super) is_glob: bool,
     pub(super) is_prelude: bool,
     pub(super) is_extern_crate: bool,
+    pub(super) is_macro_use: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -178,36 +197,55 @@ pub(super) struct MacroData {
     pub(super) path: Path,
     pub(super) name: Option<Name>,
     pub(super) export: bool,
+    /// Whether this is the declaration of a macro built into the compiler.
+    pub(super) builtin: bool,
 }
 
 struct RawItemsCollector {
     raw_items: RawItems,
     source_ast_id_map: Arc<AstIdMap>,
     source_map: ImportSourceMap,
+    /// For macro expansions, the crate `$crate` refers to.
+    macro_crate: Option<Crate>,
 }
 
 impl RawItemsCollector {
     fn process_module(&mut self, current_module: Option<Module>, body: &impl ast::ModuleItemOwner) {
         for item_or_macro in body.items_with_macros() {
             match item_or_macro {
-                ast::ItemOrMacro::Macro(m) => self.add_macro(current_module, m),
-                ast::ItemOrMacro::Item(item) => self.add_item(current_module, item),
+                ast::ItemOrMacro::Macro(m) => {
+                    let attrs = Attr::from_attrs_owner(m);
+                    self.add_macro(current_module, attrs, m)
+                }
+                ast::ItemOrMacro::Item(item) => {
+                    let attrs = Attr::from_attrs_owner(item);
+                    self.add_item(current_module, attrs, item)
+                }
             }
         }
     }
 
-    fn add_item(&mut self, current_module: Option<Module>, item: &ast::ModuleItem) {
+    fn add_item(
+        &mut self,
+        current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
+        item: &ast::ModuleItem,
+    ) {
         let (kind, name) = match item.kind() {
             ast::ModuleItemKind::Module(module) => {
-                self.add_module(current_module, module);
+                self.add_module(current_module, attrs, module);
                 return;
             }
             ast::ModuleItemKind::UseItem(use_item) => {
-                self.add_use_item(current_module, use_item);
+                self.add_use_item(current_module, attrs, use_item);
                 return;
             }
             ast::ModuleItemKind::ExternCrateItem(extern_crate) => {
-                self.add_extern_crate_item(current_module, extern_crate);
+                self.add_extern_crate_item(current_module, attrs, extern_crate);
+                return;
+            }
+            ast::ModuleItemKind::ExternBlock(extern_block) => {
+                self.add_extern_block(current_module, attrs, extern_block);
                 return;
             }
             ast::ModuleItemKind::ImplBlock(_) => {
@@ -239,19 +277,62 @@ impl RawItemsCollector {
         if let Some(name) = name {
             let name = name.as_name();
             let def = self.raw_items.defs.alloc(DefData { name, kind });
-            self.push_item(current_module, RawItem::Def(def))
+            self.push_item(current_module, attrs, RawItemKind::Def(def))
+        }
+    }
+
+    fn add_extern_block(
+        &mut self,
+        current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
+        extern_block: &ast::ExternBlock,
+    ) {
+        let extern_item_list = match extern_block.extern_item_list() {
+            Some(it) => it,
+            None => return,
+        };
+        for item in extern_item_list.extern_items() {
+            let (kind, name) = match item.kind() {
+                ast::ExternItemKind::FnDef(it) => {
+                    (DefKind::Function(self.source_ast_id_map.ast_id(it)), it.name())
+                }
+                ast::ExternItemKind::StaticDef(it) => {
+                    (DefKind::Static(self.source_ast_id_map.ast_id(it)), it.name())
+                }
+            };
+            let name = match name {
+                Some(it) => it.as_name(),
+                None => continue,
+            };
+            // Attributes of the block, like `#[cfg]`, apply to each of its items.
+            let item_attrs = match (&attrs, Attr::from_attrs_owner(item)) {
+                (Some(block_attrs), Some(item_attrs)) => {
+                    Some(block_attrs.iter().chain(item_attrs.iter()).cloned().collect())
+                }
+                (block_attrs, item_attrs) => item_attrs.or_else(|| block_attrs.clone()),
+            };
+            let def = self.raw_items.defs.alloc(DefData { name, kind });
+            self.push_item(current_module, item_attrs, RawItemKind::Def(def))
         }
     }
 
-    fn add_module(&mut self, current_module: Option<Module>, module: &ast::Module) {
+    fn add_module(
+        &mut self,
+        current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
+        module: &ast::Module,
+    ) {
         let name = match module.name() {
             Some(it) => it.as_name(),
             None => return,
         };
         let ast_id = self.source_ast_id_map.ast_id(module);
+        let attr_path =
+            attrs.iter().flat_map(|attrs| attrs.iter()).find_map(|it| it.as_path()).cloned();
         if module.has_semi() {
-            let item = self.raw_items.modules.alloc(ModuleData::Declaration { name, ast_id });
-            self.push_item(current_module, RawItem::Module(item));
+            let item =
+                self.raw_items.modules.alloc(ModuleData::Declaration { name, ast_id, attr_path });
+            self.push_item(current_module, attrs, RawItemKind::Module(item));
             return;
         }
 
@@ -259,69 +340,108 @@ impl RawItemsCollector {
             let item = self.raw_items.modules.alloc(ModuleData::Definition {
                 name,
                 ast_id,
+                attr_path,
                 items: Vec::new(),
             });
             self.process_module(Some(item), item_list);
-            self.push_item(current_module, RawItem::Module(item));
+            self.push_item(current_module, attrs, RawItemKind::Module(item));
             return;
         }
         tested_by!(name_res_works_for_broken_modules);
     }
 
-    fn add_use_item(&mut self, current_module: Option<Module>, use_item: &ast::UseItem) {
+    fn add_use_item(
+        &mut self,
+        current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
+        use_item: &ast::UseItem,
+    ) {
         let is_prelude = use_item.has_atom_attr("prelude_import");
+        let macro_crate = self.macro_crate;
 
         Path::expand_use_item(use_item, |path, use_tree, is_glob, alias| {
-            let import_data =
-                ImportData { path, alias, is_glob, is_prelude, is_extern_crate: false };
-            self.push_import(current_module, import_data, Either::A(AstPtr::new(use_tree)));
+            let path = path.with_dollar_crate(macro_crate);
+            let import_data = ImportData {
+                path,
+                alias,
+                is_glob,
+                is_prelude,
+                is_extern_crate: false,
+                is_macro_use: false,
+            };
+            self.push_import(
+                current_module,
+                attrs.clone(),
+                import_data,
+                Either::A(AstPtr::new(use_tree)),
+            );
         })
     }
 
     fn add_extern_crate_item(
         &mut self,
         current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
         extern_crate: &ast::ExternCrateItem,
     ) {
         if let Some(name_ref) = extern_crate.name_ref() {
             let path = Path::from_name_ref(name_ref);
             let alias = extern_crate.alias().and_then(|a| a.name()).map(AsName::as_name);
+            let is_macro_use = is_macro_use(&attrs);
             let import_data = ImportData {
                 path,
                 alias,
                 is_glob: false,
                 is_prelude: false,
                 is_extern_crate: true,
+                is_macro_use,
             };
-            self.push_import(current_module, import_data, Either::B(AstPtr::new(extern_crate)));
+            self.push_import(
+                current_module,
+                attrs,
+                import_data,
+                Either::B(AstPtr::new(extern_crate)),
+            );
         }
     }
 
-    fn add_macro(&mut self, current_module: Option<Module>, m: &ast::MacroCall) {
+    fn add_macro(
+        &mut self,
+        current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
+        m: &ast::MacroCall,
+    ) {
         let path = match m.path().and_then(Path::from_ast) {
-            Some(it) => it,
+            Some(it) => it.with_dollar_crate(self.macro_crate),
             _ => return,
         };
 
         let name = m.name().map(|it| it.as_name());
         let ast_id = self.source_ast_id_map.ast_id(m);
         let export = m.has_atom_attr("macro_export");
-        let m = self.raw_items.macros.alloc(MacroData { ast_id, path, name, export });
-        self.push_item(current_module, RawItem::Macro(m));
+        let builtin = m.has_atom_attr("rustc_builtin_macro");
+        let m = self.raw_items.macros.alloc(MacroData { ast_id, path, name, export, builtin });
+        self.push_item(current_module, attrs, RawItemKind::Macro(m));
     }
 
     fn push_import(
         &mut self,
         current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
         data: ImportData,
         source: ImportSourcePtr,
     ) {
         let import = self.raw_items.imports.alloc(data);
         self.source_map.insert(import, source);
-        self.push_item(current_module, RawItem::Import(import))
+        self.push_item(current_module, attrs, RawItemKind::Import(import))
     }
 
-    fn push_item(&mut self, current_module: Option<Module>, item: RawItem) {
+    fn push_item(
+        &mut self,
+        current_module: Option<Module>,
+        attrs: Option<Arc<[Attr]>>,
+        kind: RawItemKind,
+    ) {
         match current_module {
             Some(module) => match &mut self.raw_items.modules[module] {
                 ModuleData::Definition { items, .. } => items,
@@ -329,6 +449,11 @@ impl RawItemsCollector {
             },
             None => &mut self.raw_items.items,
         }
-        .push(item)
+        .push(RawItem { attrs, kind })
     }
 }
+
+/// Checks for a `#[macro_use]` attribute, on both modules and extern crates.
+pub(super) fn is_macro_use(attrs: &Option<Arc<[Attr]>>) -> bool {
+    attrs.iter().flat_map(|attrs| attrs.iter()).any(|it| it.is_simple_atom("macro_use"))
+}
@@ -1,6 +1,7 @@
 mod macros;
 mod globs;
 mod incremental;
+mod mod_resolution;
 
 use std::sync::Arc;
 
@@ -563,3 +564,94 @@ fn unresolved_module_diagnostics() {
 "###
     );
 }
+
+#[test]
+fn cfg_not_test() {
+    let map = def_map_with_crate_graph(
+        r#"
+        //- /main.rs
+        use {Foo, Bar, Baz};
+        //- /lib.rs
+        #[prelude_import]
+        pub use self::prelude::*;
+        mod prelude {
+            #[cfg(test)]
+            pub struct Foo;
+            #[cfg(not(test))]
+            pub struct Bar;
+            #[cfg(all(not(any()), feature = "foo", feature = "bar", opt = "42"))]
+            pub struct Baz;
+        }
+        "#,
+        crate_graph! {
+            "main": ("/main.rs", ["std"]),
+            "std": ("/lib.rs", []),
+        },
+    );
+
+    assert_snapshot_matches!(map, @r###"
+        ⋮crate
+        ⋮Bar: t v
+        ⋮Baz: _
+        ⋮Foo: _
+    "###);
+}
+
+#[test]
+fn cfg_test() {
+    let map = def_map_with_crate_graph(
+        r#"
+        //- /main.rs
+        use {Foo, Bar, Baz};
+        //- /lib.rs
+        #[prelude_import]
+        pub use self::prelude::*;
+        mod prelude {
+            #[cfg(test)]
+            pub struct Foo;
+            #[cfg(not(test))]
+            pub struct Bar;
+            #[cfg(all(not(any()), feature = "foo", feature = "bar", opt = "42"))]
+            pub struct Baz;
+        }
+        "#,
+        crate_graph! {
+            "main": ("/main.rs", ["std"]),
+            "std": ("/lib.rs", [], cfg = {
+                "test",
+                "feature" = "foo",
+                "feature" = "bar",
+                "opt" = "42",
+            }),
+        },
+    );
+
+    assert_snapshot_matches!(map, @r###"
+        ⋮crate
+        ⋮Bar: _
+        ⋮Baz: t v
+        ⋮Foo: t v
+    "###);
+}
+
+#[test]
+fn extern_block_items() {
+    let map = def_map(
+        r#"
+        //- /lib.rs
+        extern "C" {
+            fn foo(x: i32, ...) -> i32;
+            static BAR: u8;
+        }
+        #[cfg(test)]
+        extern {
+            fn baz();
+        }
+        "#,
+    );
+    assert_snapshot_matches!(map, @r###"
+        ⋮crate
+        ⋮BAR: v
+        ⋮foo: v
+    "###);
+}
@@ -92,3 +92,282 @@ fn macro_rules_from_other_crates_are_visible() {
    ⋮bar: t
     "###);
 }
+
+#[test]
+fn macro_rules_are_textually_scoped() {
+    let map = def_map(
+        "
+        //- /lib.rs
+        structs!(Foo);
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+        structs!(Bar);
+        mod m;
+
+        //- /m.rs
+        structs!(Baz);
+        ",
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Bar: t v
+   ⋮m: t
+   ⋮
+   ⋮crate::m
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn macro_rules_are_not_visible_in_modules_declared_before() {
+    let map = def_map(
+        "
+        //- /lib.rs
+        mod m;
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+
+        //- /m.rs
+        structs!(Foo);
+        ",
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮m: t
+   ⋮
+   ⋮crate::m
+    "###);
+}
+
+#[test]
+fn macro_use_module() {
+    covers!(macro_use_module);
+    let map = def_map(
+        "
+        //- /lib.rs
+        #[macro_use]
+        mod m;
+        structs!(Foo);
+
+        //- /m.rs
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+        structs!(Bar);
+        ",
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Foo: t v
+   ⋮m: t
+   ⋮
+   ⋮crate::m
+   ⋮Bar: t v
+    "###);
+}
+
+#[test]
+fn nested_macro_use_modules() {
+    let map = def_map(
+        "
+        //- /lib.rs
+        #[macro_use]
+        mod m;
+        structs!(Foo);
+
+        //- /m/mod.rs
+        #[macro_use]
+        mod n;
+        structs!(Bar);
+
+        //- /m/n.rs
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+        structs!(Baz);
+        ",
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Foo: t v
+   ⋮m: t
+   ⋮
+   ⋮crate::m
+   ⋮Bar: t v
+   ⋮n: t
+   ⋮
+   ⋮crate::m::n
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn macro_rules_from_other_crates_are_visible_with_macro_use() {
+    covers!(macro_rules_from_other_crates_are_visible_with_macro_use);
+    let map = def_map_with_crate_graph(
+        "
+        //- /main.rs
+        structs!(Foo);
+        #[macro_use]
+        extern crate foo;
+        structs!(Bar);
+
+        //- /lib.rs
+        #[macro_export]
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+        ",
+        crate_graph! {
+            "main": ("/main.rs", ["foo"]),
+            "foo": ("/lib.rs", []),
+        },
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Bar: t v
+   ⋮Foo: t v
+   ⋮foo: t
+    "###);
+}
+
+#[test]
+fn prelude_is_macro_use() {
+    covers!(prelude_is_macro_use);
+    let map = def_map_with_crate_graph(
+        "
+        //- /main.rs
+        structs!(Foo);
+
+        //- /lib.rs
+        mod prelude;
+        #[prelude_import]
+        use prelude::*;
+
+        #[macro_export]
+        macro_rules! structs {
+            ($($i:ident),*) => {
+                $(struct $i { field: u32 } )*
+            }
+        }
+
+        //- /prelude.rs
+        pub struct Bar;
+        ",
+        crate_graph! {
+            "main": ("/main.rs", ["foo"]),
+            "foo": ("/lib.rs", []),
+        },
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Foo: t v
+    "###);
+}
+
+#[test]
+fn dollar_crate_paths_resolve_to_the_macro_crate() {
+    let map = def_map_with_crate_graph(
+        "
+        //- /main.rs
+        #[macro_use]
+        extern crate foo;
+        structs!(Foo);
+        reexport!();
+
+        //- /lib.rs
+        pub struct Baz;
+
+        #[macro_export]
+        macro_rules! structs {
+            ($i:ident) => { $crate::inner!($i); }
+        }
+
+        #[macro_export]
+        macro_rules! inner {
+            ($i:ident) => { struct $i; }
+        }
+
+        #[macro_export]
+        macro_rules! reexport {
+            () => { use $crate::Baz; }
+        }
+        ",
+        crate_graph! {
+            "main": ("/main.rs", ["foo"]),
+            "foo": ("/lib.rs", []),
+        },
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Baz: t v
+   ⋮Foo: t v
+   ⋮foo: t
+    "###);
+}
+
+#[test]
+fn include_pulls_in_items_of_the_included_file() {
+    let map = def_map_with_crate_graph(
+        r#"
+        //- /main.rs
+        #[rustc_builtin_macro]
+        macro_rules! include { ($file:expr) => {} }
+        #[rustc_builtin_macro]
+        macro_rules! concat { ($($e:expr),*) => {} }
+
+        include!(concat!("out", "/gen.rs"));
+
+        //- /out/gen.rs
+        pub struct Generated;
+        "#,
+        crate_graph! {
+            "main": ("/main.rs", []),
+        },
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Generated: t v
+    "###);
+}
+
+#[test]
+fn include_from_out_dir_pulls_in_items_of_the_included_file() {
+    let map = def_map_with_crate_graph(
+        r#"
+        //- /main.rs
+        #[rustc_builtin_macro]
+        macro_rules! include { ($file:expr) => {} }
+        #[rustc_builtin_macro]
+        macro_rules! concat { ($($e:expr),*) => {} }
+        #[rustc_builtin_macro]
+        macro_rules! env { ($name:expr) => {} }
+
+        include!(concat!(env!("OUT_DIR"), "/gen.rs"));
+
+        //- root /target/out/
+
+        //- /target/out/gen.rs
+        pub struct Generated;
+        "#,
+        crate_graph! {
+            "main": ("/main.rs", [], env = { "OUT_DIR": "/target/out" }),
+        },
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Generated: t v
+    "###);
+}
@@ -0,0 +1,289 @@
+use super::*;
+
+#[test]
+fn module_resolution_works_for_path_attr() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        #[path = "bar/baz/foo.rs"]
+        mod foo;
+        use self::foo::Bar;
+
+        //- /bar/baz/foo.rs
+        pub struct Bar;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮Bar: t v
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮Bar: t v
+    "###);
+}
+
+#[test]
+fn path_attr_is_relative_to_the_directory_of_a_non_mod_rs_file() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo;
+
+        //- /foo.rs
+        #[path = "bar.rs"]
+        mod bar;
+
+        //- /bar.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn path_attr_is_relative_to_the_directory_of_a_mod_rs_file() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo;
+
+        //- /foo/mod.rs
+        #[path = "baz.rs"]
+        mod bar;
+
+        //- /foo/baz.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn path_attr_can_point_to_the_parent_directory() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo;
+
+        //- /foo/mod.rs
+        #[path = "../common.rs"]
+        mod common;
+
+        //- /common.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮common: t
+   ⋮
+   ⋮crate::foo::common
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn module_loaded_through_path_attr_owns_its_directory() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        #[path = "platform/linux.rs"]
+        mod imp;
+
+        //- /platform/linux.rs
+        mod sys;
+
+        //- /platform/sys.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮imp: t
+   ⋮
+   ⋮crate::imp
+   ⋮sys: t
+   ⋮
+   ⋮crate::imp::sys
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn module_resolution_works_for_submodules_of_inline_modules() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo {
+            mod bar;
+        }
+
+        //- /foo/bar.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn submodules_of_inline_modules_in_a_non_mod_rs_file() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo;
+
+        //- /foo.rs
+        mod bar {
+            mod baz;
+        }
+
+        //- /foo/bar/baz.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮baz: t
+   ⋮
+   ⋮crate::foo::bar::baz
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn path_attr_in_inline_module() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo {
+            #[path = "qux.rs"]
+            mod bar;
+        }
+
+        //- /foo/qux.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn path_attr_in_inline_module_in_a_non_mod_rs_file() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        mod foo;
+
+        //- /foo.rs
+        mod bar {
+            #[path = "qux.rs"]
+            mod baz;
+        }
+
+        //- /foo/bar/qux.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮baz: t
+   ⋮
+   ⋮crate::foo::bar::baz
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn path_attr_on_inline_module() {
+    let map = def_map(
+        r###"
+        //- /lib.rs
+        #[path = "models"]
+        mod foo {
+            mod bar;
+        }
+
+        //- /models/bar.rs
+        pub struct Baz;
+        "###,
+    );
+    assert_snapshot_matches!(map, @r###"
+   ⋮crate
+   ⋮foo: t
+   ⋮
+   ⋮crate::foo
+   ⋮bar: t
+   ⋮
+   ⋮crate::foo::bar
+   ⋮Baz: t v
+    "###);
+}
+
+#[test]
+fn unresolved_path_attr_module_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r#"
+        //- /lib.rs
+        #[path = "missing.rs"]
+        mod foo;
+        "#,
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"#[path = \"missing.rs\"]\nmod foo;": unresolved module
+"###
+    );
+}
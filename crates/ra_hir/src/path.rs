@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
-use ra_syntax::{ast::{self, NameOwner}, AstNode};
+use ra_syntax::{
+    ast::{self, NameOwner, TypeAscriptionOwner},
+    AstNode,
+};
 
-use crate::{Name, AsName, type_ref::TypeRef};
+use crate::{Name, AsName, KnownName, Crate, type_ref::TypeRef};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Path {
@@ -17,12 +20,18 @@ pub struct PathSegment {
 }
 
 /// Generic arguments to a path segment (e.g. the `i32` in `Option<i32>`). This
-/// can (in the future) also include bindings of associated types, like in
-/// `Iterator<Item = Foo>`.
+/// also includes bindings of associated types, like in `Iterator<Item = Foo>`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GenericArgs {
     pub args: Vec<GenericArg>,
-    // someday also bindings
+    /// This specifies whether the args contain a Self type as the first
+    /// element. This is the case for path segments like `<T as Trait>`, where
+    /// `T` is actually a type parameter for the path `Trait` specifying the
+    /// Self type. Otherwise, when we have a path `Trait<X, Y>`, the Self type
+    /// is left out.
+    pub has_self_type: bool,
+    /// Associated type bindings like in `Iterator<Item = T>`.
+    pub bindings: Vec<(Name, TypeRef)>,
 }
 
 /// A single generic argument.
@@ -40,6 +49,8 @@ pub enum PathKind {
     Crate,
     // Absolute path
     Abs,
+    // `$crate` from macro expansion
+    DollarCrate(Crate),
 }
 
 impl Path {
@@ -53,6 +64,21 @@ impl Path {
         }
     }
 
+    /// Constructs a path from the given segment names, without any generic
+    /// arguments.
+    pub(crate) fn from_simple_segments(
+        kind: PathKind,
+        segments: impl IntoIterator<Item = Name>,
+    ) -> Path {
+        Path {
+            kind,
+            segments: segments
+                .into_iter()
+                .map(|name| PathSegment { name, args_and_bindings: None })
+                .collect(),
+        }
+    }
+
     /// Converts an `ast::Path` to `Path`. Works with use trees.
     pub fn from_ast(mut path: &ast::Path) -> Option<Path> {
         let mut kind = PathKind::Plain;
@@ -66,11 +92,31 @@ impl Path {
 
             match segment.kind()? {
                 ast::PathSegmentKind::Name(name) => {
-                    let args =
-                        segment.type_arg_list().and_then(GenericArgs::from_ast).map(Arc::new);
+                    let args = segment
+                        .type_arg_list()
+                        .and_then(GenericArgs::from_ast)
+                        .or_else(|| GenericArgs::from_fn_like_path_ast(segment.param_list()))
+                        .map(Arc::new);
                     let segment = PathSegment { name: name.as_name(), args_and_bindings: args };
                     segments.push(segment);
                 }
+                ast::PathSegmentKind::Type { type_ref, trait_ref } => {
+                    // this can only occur at the first segment
+                    let self_type = TypeRef::from_ast(type_ref?);
+                    // FIXME: handle `<T>::foo` paths without a trait
+                    let trait_path = Path::from_ast(trait_ref?.path()?)?;
+                    // `<T as Trait<A>>::Foo` desugars to `Trait<Self=T, A>::Foo`
+                    kind = trait_path.kind;
+                    segments.extend(trait_path.segments.into_iter().rev());
+                    let last_segment = segments.last_mut()?;
+                    let args = last_segment
+                        .args_and_bindings
+                        .get_or_insert_with(|| Arc::new(GenericArgs::empty()));
+                    let args = Arc::make_mut(args);
+                    args.has_self_type = true;
+                    args.args.insert(0, GenericArg::Type(self_type));
+                    break;
+                }
                 ast::PathSegmentKind::CrateKw => {
                     kind = PathKind::Crate;
                     break;
@@ -130,6 +176,24 @@ impl Path {
     pub fn expand_macro_expr(&self) -> Option<Name> {
         self.as_ident().and_then(|name| Some(name.clone()))
     }
+
+    /// Replaces a leading `$crate` segment, which comes from a `macro_rules`
+    /// expansion, with a reference to `krate`, the crate the macro is defined
+    /// in. `krate` is `None` for paths outside of macro expansions.
+    pub(crate) fn with_dollar_crate(mut self, krate: Option<Crate>) -> Path {
+        let krate = match krate {
+            Some(it) => it,
+            None => return self,
+        };
+        let is_dollar_crate = self.kind == PathKind::Plain
+            && self.segments.first().and_then(|it| it.name.as_known_name())
+                == Some(KnownName::DollarCrate);
+        if is_dollar_crate {
+            self.segments.remove(0);
+            self.kind = PathKind::DollarCrate(krate);
+        }
+        self
+    }
 }
 
 impl GenericArgs {
@@ -139,13 +203,36 @@ impl GenericArgs {
             let type_ref = TypeRef::from_ast_opt(type_arg.type_ref());
             args.push(GenericArg::Type(type_ref));
         }
-        // lifetimes and assoc type args ignored for now
-        if args.len() > 0 {
-            Some(GenericArgs { args })
+        // lifetimes ignored for now
+        let mut bindings = Vec::new();
+        for assoc_type_arg in node.assoc_type_args() {
+            if let Some(name_ref) = assoc_type_arg.name_ref() {
+                let name = name_ref.as_name();
+                let type_ref = TypeRef::from_ast_opt(assoc_type_arg.type_ref());
+                bindings.push((name, type_ref));
+            }
+        }
+        if args.len() > 0 || bindings.len() > 0 {
+            Some(GenericArgs { args, has_self_type: false, bindings })
         } else {
             None
         }
     }
+
+    /// Collects the arguments of the `Fn(A, B) -> C` sugar of the `Fn` traits
+    /// as a single tuple type argument, like in `Fn<(A, B)>`.
+    // FIXME: lower the return type as well, once we have associated type bindings
+    pub(crate) fn from_fn_like_path_ast(params: Option<&ast::ParamList>) -> Option<GenericArgs> {
+        let params = params?;
+        let param_types =
+            params.params().map(|p| TypeRef::from_ast_opt(p.ascribed_type())).collect();
+        let args = vec![GenericArg::Type(TypeRef::Tuple(param_types))];
+        Some(GenericArgs { args, has_self_type: false, bindings: Vec::new() })
+    }
+
+    pub(crate) fn empty() -> GenericArgs {
+        GenericArgs { args: Vec::new(), has_self_type: false, bindings: Vec::new() }
+    }
 }
 
 impl From<Name> for Path {
@@ -233,6 +320,44 @@ fn convert_path(prefix: Option<Path>, path: &ast::Path) -> Option<Path> {
             }
             Path { kind: PathKind::Super, segments: Vec::new() }
         }
+        ast::PathSegmentKind::Type { .. } => {
+            // not allowed in imports
+            return None;
+        }
     };
     Some(res)
 }
+
+pub(crate) mod known {
+    use crate::name::known;
+    use super::{Path, PathKind};
+
+    pub(crate) fn std_iter_into_iterator() -> Path {
+        Path::from_simple_segments(
+            PathKind::Abs,
+            vec![known::std(), known::iter(), known::into_iterator_trait()],
+        )
+    }
+
+    pub(crate) fn std_result_result() -> Path {
+        Path::from_simple_segments(
+            PathKind::Abs,
+            vec![known::std(), known::result(), known::result_type()],
+        )
+    }
+
+    pub(crate) fn std_ops_try() -> Path {
+        Path::from_simple_segments(
+            PathKind::Abs,
+            vec![known::std(), known::ops(), known::try_trait()],
+        )
+    }
+
+    /// The trait implemented by a built-in derive, e.g. `::std::clone::Clone`
+    /// for `#[derive(Clone)]`.
+    // FIXME: this doesn't resolve in `#![no_std]` crates
+    pub(crate) fn std_builtin_derive(derive: &str) -> Option<Path> {
+        let (module, trait_) = known::builtin_derive(derive)?;
+        Some(Path::from_simple_segments(PathKind::Abs, vec![known::std(), module, trait_]))
+    }
+}
@@ -7,10 +7,10 @@ use crate::{
     ModuleDef, Trait,
     code_model_api::Crate,
     MacroDefId,
-    db::HirDatabase,
+    db::{HirDatabase, DefDatabase},
     name::{Name, KnownName},
     nameres::{PerNs, CrateDefMap, CrateModuleId},
-    generics::GenericParams,
+    generics::{GenericParams, GenericDef, WherePredicate},
     expr::{scope::{ExprScopes, ScopeId}, PatId},
     impl_block::ImplBlock,
     path::Path,
@@ -100,7 +100,7 @@ pub(crate) enum Scope {
     /// All the items and imported names of a module
     ModuleScope(ModuleItemMap),
     /// Brings the generic parameters of an item into scope
-    GenericParams(Arc<GenericParams>),
+    GenericParams { def: GenericDef, params: Arc<GenericParams> },
     /// Brings `Self` into scope
     ImplBlockScope(ImplBlock),
     /// Local bindings
@@ -130,9 +130,13 @@ impl Resolver {
         resolution
     }
 
-    pub(crate) fn resolve_macro_call(&self, path: Option<Path>) -> Option<MacroDefId> {
-        let name = path.and_then(|path| path.expand_macro_expr()).unwrap_or_else(Name::missing);
-        self.module()?.0.find_macro(&name)
+    pub(crate) fn resolve_macro_call(
+        &self,
+        db: &impl HirDatabase,
+        path: Option<Path>,
+    ) -> Option<MacroDefId> {
+        let (crate_def_map, module_id) = self.module()?;
+        crate_def_map.resolve_macro(db, module_id, &path?)
     }
 
     /// Returns the resolved path segments
@@ -207,6 +211,29 @@ impl Resolver {
     pub(crate) fn krate(&self) -> Option<Crate> {
         self.module().map(|t| t.0.krate())
     }
+
+    pub(crate) fn where_predicates_in_scope<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = &'a WherePredicate> + 'a {
+        self.scopes
+            .iter()
+            .filter_map(|scope| match scope {
+                Scope::GenericParams { params, .. } => Some(params),
+                _ => None,
+            })
+            .flat_map(|params| params.where_predicates.iter())
+    }
+
+    /// The item whose generic parameter with the given index is in scope, if
+    /// any. E.g. for the `Self` type parameter of a trait, this is the trait.
+    pub(crate) fn generic_def_of_param(&self, idx: u32) -> Option<GenericDef> {
+        self.scopes.iter().rev().find_map(|scope| match scope {
+            Scope::GenericParams { def, params } if params.params.iter().any(|p| p.idx == idx) => {
+                Some(*def)
+            }
+            _ => None,
+        })
+    }
 }
 
 impl Resolver {
@@ -215,8 +242,18 @@ impl Resolver {
         self
     }
 
-    pub(crate) fn push_generic_params_scope(self, params: Arc<GenericParams>) -> Resolver {
-        self.push_scope(Scope::GenericParams(params))
+    /// Adds the generic parameters of `def` to the scope, if there are any.
+    pub(crate) fn push_generic_params_scope(
+        self,
+        db: &impl DefDatabase,
+        def: GenericDef,
+    ) -> Resolver {
+        let params = db.generic_params(def);
+        if params.params.is_empty() {
+            self
+        } else {
+            self.push_scope(Scope::GenericParams { def, params })
+        }
     }
 
     pub(crate) fn push_impl_block_scope(self, impl_block: ImplBlock) -> Resolver {
@@ -252,7 +289,7 @@ impl Scope {
                         .map(Resolution::Def)
                 }
             }
-            Scope::GenericParams(gp) => match gp.find_by_name(name) {
+            Scope::GenericParams { params, .. } => match params.find_by_name(name) {
                 Some(gp) => PerNs::types(Resolution::GenericParam(gp.idx)),
                 None => PerNs::none(),
             },
@@ -297,8 +334,8 @@ impl Scope {
                     });
                 }
             }
-            Scope::GenericParams(gp) => {
-                for param in &gp.params {
+            Scope::GenericParams { params, .. } => {
+                for param in &params.params {
                     f(param.name.clone(), PerNs::types(Resolution::GenericParam(param.idx)))
                 }
             }
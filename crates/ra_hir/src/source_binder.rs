@@ -17,12 +17,13 @@ use ra_syntax::{
 };
 
 use crate::{
-    HirDatabase, Function, Struct, Enum, Const, Static, Either, DefWithBody, PerNs, Name,
-    AsName, Module, HirFileId, Crate, Trait, Resolver, Ty,Path,
+    HirDatabase, Function, Struct, Enum, Const, Static, TypeAlias, Either, DefWithBody, PerNs,
+    Name, AsName, Module, HirFileId, Crate, Trait, Resolver, Ty,Path,
     expr::{BodySourceMap, scope::{ScopeId, ExprScopes}},
-    ids::{LocationCtx, MacroDefId},
+    ids::{AstItemDef, LocationCtx, MacroDefId},
     docs::{docs_from_ast,Documentation},
     expr, AstId,
+    attr::Attr,
 };
 
 /// Locates the module by `FileId`. Picks topmost module in the file.
@@ -122,6 +123,81 @@ pub fn trait_from_module(
     Trait { id: ctx.to_def(trait_def) }
 }
 
+pub fn function_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    fn_def: &ast::FnDef,
+) -> Function {
+    Function { id: item_from_module(db, module, fn_def) }
+}
+
+pub fn const_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    const_def: &ast::ConstDef,
+) -> Const {
+    Const { id: item_from_module(db, module, const_def) }
+}
+
+pub fn static_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    static_def: &ast::StaticDef,
+) -> Static {
+    Static { id: item_from_module(db, module, static_def) }
+}
+
+pub fn type_alias_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    type_alias_def: &ast::TypeAliasDef,
+) -> TypeAlias {
+    TypeAlias { id: item_from_module(db, module, type_alias_def) }
+}
+
+fn item_from_module<N: AstNode, DEF: AstItemDef<N>>(
+    db: &impl HirDatabase,
+    module: Module,
+    node: &N,
+) -> DEF {
+    let (file_id, _) = module.definition_source(db);
+    let ctx = LocationCtx::new(db, module, file_id.into());
+    ctx.to_def(node)
+}
+
+/// Returns the ranges of the items, statements, match arms and fields of the
+/// file which are disabled by `#[cfg(..)]` attributes in the crate of the file.
+pub fn inactive_code(db: &impl HirDatabase, file_id: FileId) -> Vec<TextRange> {
+    let krate = match module_from_file_id(db, file_id).and_then(|m| m.krate(db)) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let crate_graph = db.crate_graph();
+    let cfg_options = crate_graph.cfg_options(krate.crate_id());
+    let file = db.parse(file_id);
+    let mut res: Vec<TextRange> = Vec::new();
+    for attr in file.syntax().descendants().filter_map(ast::Attr::cast) {
+        if attr.is_inner() {
+            continue;
+        }
+        let is_enabled = Attr::from_src(attr).and_then(|it| it.is_cfg_enabled(cfg_options));
+        if is_enabled != Some(false) {
+            continue;
+        }
+        let range = match attr.syntax().parent() {
+            Some(it) => it.range(),
+            None => continue,
+        };
+        // Nested attributes are visited after the outer ones, so skipping
+        // ranges covered by the last one is enough.
+        match res.last() {
+            Some(last) if range.is_subrange(last) => continue,
+            _ => res.push(range),
+        }
+    }
+    res
+}
+
 fn try_get_resolver_for_node(
     db: &impl HirDatabase,
     file_id: FileId,
@@ -196,7 +272,7 @@ pub struct MacroByExampleDef {
 
 impl MacroByExampleDef {
     pub fn source(&self, db: &impl HirDatabase) -> (HirFileId, TreeArc<ast::MacroCall>) {
-        (self.id.0.file_id(), self.id.0.to_node(db))
+        (self.id.ast_id.file_id(), self.id.ast_id.to_node(db))
     }
 }
 
@@ -273,6 +349,14 @@ impl SourceAnalyzer {
         Some(self.infer.as_ref()?[pat_id].clone())
     }
 
+    /// Returns the type of a local binding, as returned by `all_names`.
+    pub fn type_of_local(&self, resolution: &crate::Resolution) -> Option<crate::Ty> {
+        match resolution {
+            crate::Resolution::LocalBinding(pat_id) => Some(self.infer.as_ref()?[*pat_id].clone()),
+            _ => None,
+        }
+    }
+
     pub fn resolve_method_call(&self, call: &ast::MethodCallExpr) -> Option<Function> {
         let expr_id = self.body_source_map.as_ref()?.node_expr(call.into())?;
         self.infer.as_ref()?.method_resolution(expr_id)
@@ -283,8 +367,13 @@ impl SourceAnalyzer {
         self.infer.as_ref()?.field_resolution(expr_id)
     }
 
-    pub fn resolve_macro_call(&self, macro_call: &ast::MacroCall) -> Option<MacroByExampleDef> {
-        let id = self.resolver.resolve_macro_call(macro_call.path().and_then(Path::from_ast))?;
+    pub fn resolve_macro_call(
+        &self,
+        db: &impl HirDatabase,
+        macro_call: &ast::MacroCall,
+    ) -> Option<MacroByExampleDef> {
+        let id =
+            self.resolver.resolve_macro_call(db, macro_call.path().and_then(Path::from_ast))?;
         Some(MacroByExampleDef { id })
     }
 
@@ -382,10 +471,45 @@ impl SourceAnalyzer {
             db,
             &self.resolver,
             name,
+            crate::ty::method_resolution::LookupMode::MethodCall,
             callback,
         )
     }
 
+    /// Like `iterate_method_candidates`, but for paths like `Foo::foo`: also
+    /// returns functions without a `self` parameter, and doesn't autoderef.
+    pub fn iterate_path_candidates<T>(
+        &self,
+        db: &impl HirDatabase,
+        ty: Ty,
+        name: Option<&Name>,
+        callback: impl FnMut(&Ty, Function) -> Option<T>,
+    ) -> Option<T> {
+        // There should be no inference vars in types passed here
+        // FIXME check that?
+        let canonical = crate::ty::Canonical { value: ty, num_vars: 0 };
+        crate::ty::method_resolution::iterate_method_candidates(
+            &canonical,
+            db,
+            &self.resolver,
+            name,
+            crate::ty::method_resolution::LookupMode::Path,
+            callback,
+        )
+    }
+
+    pub fn autoderef<'a>(
+        &'a self,
+        db: &'a impl HirDatabase,
+        ty: Ty,
+    ) -> impl Iterator<Item = Ty> + 'a {
+        // There should be no inference vars in types passed here
+        // FIXME check that?
+        let canonical = crate::ty::Canonical { value: ty, num_vars: 0 };
+        crate::ty::autoderef::autoderef(db, &self.resolver, canonical)
+            .map(|canonical| canonical.value)
+    }
+
     #[cfg(test)]
     pub(crate) fn body_source_map(&self) -> Arc<BodySourceMap> {
         self.body_source_map.clone().unwrap()
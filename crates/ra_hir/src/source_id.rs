@@ -38,10 +38,46 @@ impl<N: AstNode> AstId<N> {
         self.file_id
     }
 
+    /// Creates an id from an index which may come from outside of the
+    /// analysis. It may point to no item at all, so it has to be checked with
+    /// `relocate` before it is used.
+    pub(crate) fn from_raw(file_id: HirFileId, index: u32) -> AstId<N> {
+        let raw = ErasedFileAstId(RawId::from(index));
+        FileAstId { raw, _ty: PhantomData }.with_file_id(file_id)
+    }
+
+    pub(crate) fn to_raw(&self) -> u32 {
+        self.file_ast_id.raw.0.into()
+    }
+
     pub(crate) fn to_node(&self, db: &impl DefDatabase) -> TreeArc<N> {
         let syntax_node = db.ast_id_to_node(self.file_id, self.file_ast_id.raw);
         N::cast(&syntax_node).unwrap().to_owned()
     }
+
+    /// Finds the item again after its file was edited. Ids are assigned by the
+    /// position of the items in the file, so after an edit, the id might point
+    /// to another item, or past the last one. The item is then looked up among
+    /// the `N`s of the file, and found if `is_item` holds for exactly one.
+    pub(crate) fn relocate(
+        &self,
+        db: &impl DefDatabase,
+        is_item: impl Fn(&N) -> bool,
+    ) -> Option<AstId<N>> {
+        let root = db.parse_or_expand(self.file_id)?;
+        let map = db.ast_id_map(self.file_id);
+        let matches =
+            |ptr: &SyntaxNodePtr| N::cast(ptr.to_node(&root)).map_or(false, |it| is_item(it));
+        if map.arena.iter().any(|(id, ptr)| id == self.file_ast_id.raw && matches(ptr)) {
+            return Some(*self);
+        }
+        let mut items = map.arena.iter().filter(|(_, ptr)| matches(ptr)).map(|(id, _)| id);
+        let raw = items.next()?;
+        if items.next().is_some() {
+            return None;
+        }
+        Some(FileAstId { raw, _ty: PhantomData }.with_file_id(self.file_id))
+    }
 }
 
 /// `AstId` points to an AST node in a specific file.
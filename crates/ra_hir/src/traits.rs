@@ -47,6 +47,17 @@ impl TraitData {
     pub(crate) fn is_auto(&self) -> bool {
         self.auto
     }
+
+    pub(crate) fn associated_type_by_name(
+        &self,
+        db: &impl DefDatabase,
+        name: &Name,
+    ) -> Option<TypeAlias> {
+        self.items.iter().find_map(|item| match item {
+            TraitItem::TypeAlias(t) if t.name(db) == *name => Some(*t),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -1,7 +1,7 @@
 //! The type system. We currently use this to infer types for completion, hover
 //! information and various assists.
 
-mod autoderef;
+pub(crate) mod autoderef;
 pub(crate) mod primitive;
 #[cfg(test)]
 mod tests;
@@ -16,8 +16,13 @@ use std::sync::Arc;
 use std::ops::Deref;
 use std::{fmt, mem};
 
-use crate::{Name, AdtDef, type_ref::Mutability, db::HirDatabase, Trait, GenericParams};
+use crate::{
+    Name, AdtDef, type_ref::Mutability, db::HirDatabase, Trait, GenericParams, DefWithBody,
+    TypeAlias,
+    expr::ExprId,
+};
 use display::{HirDisplay, HirFormatter};
+use traits::ProjectionPredicate;
 
 pub(crate) use lower::{TypableDef, type_for_def, type_for_field, callable_item_sig, generic_predicates, generic_defaults};
 pub(crate) use infer::{infer_query, InferenceResult, InferTy};
@@ -89,6 +94,12 @@ pub enum TypeCtor {
 
     /// A tuple type.  For example, `(i32, bool)`.
     Tuple { cardinality: u16 },
+
+    /// The type of a specific closure.
+    ///
+    /// The closure signature is stored in a `FnPtr` type in the first type
+    /// parameter.
+    Closure { def: DefWithBody, expr: ExprId },
 }
 
 /// A nominal type with (maybe 0) type parameters. This might be a primitive
@@ -100,6 +111,39 @@ pub struct ApplicationTy {
     pub parameters: Substs,
 }
 
+/// A "projection" type corresponds to an (unnormalized) projection like
+/// `<P0 as Trait<P1..Pn>>::Foo`. Note that the trait and all its parameters
+/// are fully known.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ProjectionTy {
+    pub associated_ty: TypeAlias,
+    pub parameters: Substs,
+}
+
+impl ProjectionTy {
+    pub fn trait_ref(&self, db: &impl HirDatabase) -> TraitRef {
+        TraitRef { trait_: self.trait_(db), substs: self.parameters.clone() }
+    }
+
+    fn trait_(&self, db: &impl HirDatabase) -> Trait {
+        self.associated_ty.parent_trait(db).expect("projection ty without parent trait")
+    }
+
+    pub fn walk(&self, f: &mut impl FnMut(&Ty)) {
+        for t in self.parameters.iter() {
+            t.walk(f);
+        }
+    }
+
+    pub fn walk_mut(&mut self, f: &mut impl FnMut(&mut Ty)) {
+        self.walk_mut_binders(&mut |ty, _binders| f(ty), 0);
+    }
+
+    fn walk_mut_binders(&mut self, f: &mut impl FnMut(&mut Ty, usize), binders: usize) {
+        self.parameters.walk_mut_binders(f, binders);
+    }
+}
+
 /// A type.
 ///
 /// See also the `TyKind` enum in rustc (librustc/ty/sty.rs), which represents
@@ -113,6 +157,11 @@ pub enum Ty {
     /// several other things.
     Apply(ApplicationTy),
 
+    /// A projection of an associated type, like `<T as Iterator>::Item`. These
+    /// are normalized to the actual type using the trait solver during type
+    /// inference, if possible.
+    Projection(ProjectionTy),
+
     /// A type parameter; for example, `T` in `fn f<T>(x: T) {}
     Param {
         /// The index of the parameter (starting with parameters from the
@@ -123,6 +172,21 @@ pub enum Ty {
         name: Name,
     },
 
+    /// A trait object (`dyn Trait`).
+    ///
+    /// The predicates are quantified over the `Self` type, i.e. `Ty::Bound(0)`
+    /// represents the `Self` type inside the bounds. This is currently
+    /// implicit; Chalk has the `Binders` struct to make it explicit, but it
+    /// didn't seem worth the overhead yet.
+    Dyn(Arc<[GenericPredicate]>),
+
+    /// An opaque type (`impl Trait`), either in return position or as the
+    /// placeholder for an argument-position `impl Trait`.
+    ///
+    /// The predicates are quantified over the `Self` type; see `Ty::Dyn` for
+    /// more.
+    Opaque(Arc<[GenericPredicate]>),
+
     /// A bound type variable. Only used during trait resolution to represent
     /// Chalk variables.
     Bound(u32),
@@ -157,10 +221,14 @@ impl Substs {
     }
 
     pub fn walk_mut(&mut self, f: &mut impl FnMut(&mut Ty)) {
+        self.walk_mut_binders(&mut |ty, _binders| f(ty), 0);
+    }
+
+    fn walk_mut_binders(&mut self, f: &mut impl FnMut(&mut Ty, usize), binders: usize) {
         // Without an Arc::make_mut_slice, we can't avoid the clone here:
         let mut v: Vec<_> = self.0.iter().cloned().collect();
         for t in &mut v {
-            t.walk_mut(f);
+            t.walk_mut_binders(f, binders);
         }
         self.0 = v.into();
     }
@@ -221,6 +289,10 @@ pub struct TraitRef {
 }
 
 impl TraitRef {
+    pub fn trait_(&self) -> Trait {
+        self.trait_
+    }
+
     pub fn self_ty(&self) -> &Ty {
         &self.substs[0]
     }
@@ -232,6 +304,16 @@ impl TraitRef {
         });
         self
     }
+
+    pub fn walk(&self, f: &mut impl FnMut(&Ty)) {
+        for t in self.substs.iter() {
+            t.walk(f);
+        }
+    }
+
+    fn walk_mut_binders(&mut self, f: &mut impl FnMut(&mut Ty, usize), binders: usize) {
+        self.substs.walk_mut_binders(f, binders);
+    }
 }
 
 /// Like `generics::WherePredicate`, but with resolved types: A condition on the
@@ -240,6 +322,8 @@ impl TraitRef {
 pub enum GenericPredicate {
     /// The given trait needs to be implemented for its type parameters.
     Implemented(TraitRef),
+    /// An associated type binding like in `Iterator<Item = T>`.
+    Projection(ProjectionPredicate),
     /// We couldn't resolve the trait reference. (If some type parameters can't
     /// be resolved, they will just be Unknown).
     Error,
@@ -258,9 +342,30 @@ impl GenericPredicate {
             GenericPredicate::Implemented(trait_ref) => {
                 GenericPredicate::Implemented(trait_ref.subst(substs))
             }
+            GenericPredicate::Projection(projection_predicate) => {
+                GenericPredicate::Projection(projection_predicate.subst(substs))
+            }
             GenericPredicate::Error => self,
         }
     }
+
+    pub fn walk(&self, f: &mut impl FnMut(&Ty)) {
+        match self {
+            GenericPredicate::Implemented(trait_ref) => trait_ref.walk(f),
+            GenericPredicate::Projection(projection_pred) => projection_pred.walk(f),
+            GenericPredicate::Error => {}
+        }
+    }
+
+    fn walk_mut_binders(&mut self, f: &mut impl FnMut(&mut Ty, usize), binders: usize) {
+        match self {
+            GenericPredicate::Implemented(trait_ref) => trait_ref.walk_mut_binders(f, binders),
+            GenericPredicate::Projection(projection_pred) => {
+                projection_pred.walk_mut_binders(f, binders)
+            }
+            GenericPredicate::Error => {}
+        }
+    }
 }
 
 /// Basically a claim (currently not validated / checked) that the contained
@@ -279,16 +384,19 @@ pub struct Canonical<T> {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct FnSig {
     params_and_return: Arc<[Ty]>,
+    /// True for C-variadic functions, which accept more arguments than they
+    /// have parameters.
+    is_variadic: bool,
 }
 
 impl FnSig {
-    pub fn from_params_and_return(mut params: Vec<Ty>, ret: Ty) -> FnSig {
+    pub fn from_params_and_return(mut params: Vec<Ty>, ret: Ty, is_variadic: bool) -> FnSig {
         params.push(ret);
-        FnSig { params_and_return: params.into() }
+        FnSig { params_and_return: params.into(), is_variadic }
     }
 
     pub fn from_fn_ptr_substs(substs: &Substs) -> FnSig {
-        FnSig { params_and_return: Arc::clone(&substs.0) }
+        FnSig { params_and_return: Arc::clone(&substs.0), is_variadic: false }
     }
 
     pub fn params(&self) -> &[Ty] {
@@ -299,12 +407,16 @@ impl FnSig {
         &self.params_and_return[self.params_and_return.len() - 1]
     }
 
+    pub fn is_variadic(&self) -> bool {
+        self.is_variadic
+    }
+
     /// Applies the given substitutions to all types in this signature and
     /// returns the result.
     pub fn subst(&self, substs: &Substs) -> FnSig {
         let result: Vec<_> =
             self.params_and_return.iter().map(|ty| ty.clone().subst(substs)).collect();
-        FnSig { params_and_return: result.into() }
+        FnSig { params_and_return: result.into(), is_variadic: self.is_variadic }
     }
 
     pub fn walk_mut(&mut self, f: &mut impl FnMut(&mut Ty)) {
@@ -338,26 +450,55 @@ impl Ty {
                     t.walk(f);
                 }
             }
+            Ty::Projection(p_ty) => p_ty.walk(f),
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => {
+                for p in predicates.iter() {
+                    p.walk(f);
+                }
+            }
             Ty::Param { .. } | Ty::Bound(_) | Ty::Infer(_) | Ty::Unknown => {}
         }
         f(self);
     }
 
     fn walk_mut(&mut self, f: &mut impl FnMut(&mut Ty)) {
+        self.walk_mut_binders(&mut |ty_mut, _binders| f(ty_mut), 0);
+    }
+
+    /// Walks the type like `walk_mut`, but additionally passes the number of
+    /// binders (i.e. `dyn Trait` / `impl Trait` types) we're currently inside
+    /// of to `f`. Inside `n` binders, `Ty::Bound(i)` with `i < n` refers to
+    /// the `Self` types of these binders and not to a variable from outside.
+    fn walk_mut_binders(&mut self, f: &mut impl FnMut(&mut Ty, usize), binders: usize) {
         match self {
             Ty::Apply(a_ty) => {
-                a_ty.parameters.walk_mut(f);
+                a_ty.parameters.walk_mut_binders(f, binders);
+            }
+            Ty::Projection(p_ty) => p_ty.walk_mut_binders(f, binders),
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => {
+                let mut v: Vec<_> = predicates.iter().cloned().collect();
+                for p in &mut v {
+                    p.walk_mut_binders(f, binders + 1);
+                }
+                *predicates = v.into();
             }
             Ty::Param { .. } | Ty::Bound(_) | Ty::Infer(_) | Ty::Unknown => {}
         }
-        f(self);
+        f(self, binders);
     }
 
-    fn fold(mut self, f: &mut impl FnMut(Ty) -> Ty) -> Ty {
-        self.walk_mut(&mut |ty_mut| {
-            let ty = mem::replace(ty_mut, Ty::Unknown);
-            *ty_mut = f(ty);
-        });
+    fn fold(self, f: &mut impl FnMut(Ty) -> Ty) -> Ty {
+        self.fold_binders(&mut |ty, _binders| f(ty), 0)
+    }
+
+    fn fold_binders(mut self, f: &mut impl FnMut(Ty, usize) -> Ty, binders: usize) -> Ty {
+        self.walk_mut_binders(
+            &mut |ty_mut, binders| {
+                let ty = mem::replace(ty_mut, Ty::Unknown);
+                *ty_mut = f(ty, binders);
+            },
+            binders,
+        );
         self
     }
 
@@ -397,6 +538,17 @@ impl Ty {
         }
     }
 
+    /// Whether `Ty::Unknown` occurs anywhere in this type.
+    pub fn contains_unknown(&self) -> bool {
+        let mut contains_unknown = false;
+        self.walk(&mut |ty| {
+            if *ty == Ty::Unknown {
+                contains_unknown = true;
+            }
+        });
+        contains_unknown
+    }
+
     fn builtin_deref(&self) -> Option<Ty> {
         match self {
             Ty::Apply(a_ty) => match a_ty.ctor {
@@ -408,7 +560,8 @@ impl Ty {
         }
     }
 
-    fn callable_sig(&self, db: &impl HirDatabase) -> Option<FnSig> {
+    /// The signature of a function, function pointer or closure type.
+    pub fn callable_sig(&self, db: &impl HirDatabase) -> Option<FnSig> {
         match self {
             Ty::Apply(a_ty) => match a_ty.ctor {
                 TypeCtor::FnPtr { .. } => Some(FnSig::from_fn_ptr_substs(&a_ty.parameters)),
@@ -416,6 +569,10 @@ impl Ty {
                     let sig = db.callable_item_signature(def);
                     Some(sig.subst(&a_ty.parameters))
                 }
+                TypeCtor::Closure { .. } => {
+                    let sig_param = &a_ty.parameters[0];
+                    sig_param.callable_sig(db)
+                }
                 _ => None,
             },
             _ => None,
@@ -450,9 +607,80 @@ impl Ty {
 
     /// Substitutes `Ty::Bound` vars (as opposed to type parameters).
     pub fn subst_bound_vars(self, substs: &Substs) -> Ty {
-        self.fold(&mut |ty| match ty {
-            Ty::Bound(idx) => substs.get(idx as usize).cloned().unwrap_or(Ty::Bound(idx)),
-            ty => ty,
+        self.fold_binders(
+            &mut |ty, binders| match ty {
+                Ty::Bound(idx) if idx as usize >= binders => substs
+                    .get(idx as usize - binders)
+                    .map(|ty| ty.clone().shift_bound_vars(binders as u32))
+                    .unwrap_or(Ty::Bound(idx)),
+                ty => ty,
+            },
+            0,
+        )
+    }
+
+    /// Shifts up `Ty::Bound` vars by `n`.
+    pub fn shift_bound_vars(self, n: u32) -> Ty {
+        self.fold_binders(
+            &mut |ty, binders| match ty {
+                Ty::Bound(idx) if idx as usize >= binders => Ty::Bound(idx + n),
+                ty => ty,
+            },
+            0,
+        )
+    }
+
+    /// If this is a `dyn Trait` or `impl Trait` type, returns the traits from
+    /// its bounds. Methods of these traits can be called on the type even if
+    /// the traits aren't in scope.
+    pub fn inherent_traits(&self) -> Vec<Trait> {
+        match self {
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => predicates
+                .iter()
+                .filter_map(|pred| match pred {
+                    GenericPredicate::Implemented(trait_ref) => Some(trait_ref.trait_),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// If this is a `dyn Trait` or `impl Trait` type which has `trait_` in
+    /// its bounds, returns the trait ref from that bound, with `self` as the
+    /// `Self` type.
+    pub(crate) fn inherent_trait_ref(&self, trait_: Trait) -> Option<TraitRef> {
+        let predicates = match self {
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => predicates,
+            _ => return None,
+        };
+        predicates.iter().find_map(|pred| match pred {
+            GenericPredicate::Implemented(trait_ref) if trait_ref.trait_ == trait_ => {
+                let self_ty = Substs::single(self.clone());
+                let substs: Vec<_> =
+                    trait_ref.substs.iter().map(|t| t.clone().subst_bound_vars(&self_ty)).collect();
+                Some(TraitRef { trait_, substs: substs.into() })
+            }
+            _ => None,
+        })
+    }
+
+    /// If this is a `dyn Trait` or `impl Trait` type with a binding for the
+    /// associated type of `projection` in its bounds (like the `Item = Foo`
+    /// in `impl Iterator<Item = Foo>`), returns the bound type.
+    pub(crate) fn inherent_projection(&self, projection: &ProjectionTy) -> Option<Ty> {
+        let predicates = match self {
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => predicates,
+            _ => return None,
+        };
+        predicates.iter().find_map(|pred| match pred {
+            GenericPredicate::Projection(projection_pred)
+                if projection_pred.projection_ty.associated_ty == projection.associated_ty =>
+            {
+                let self_ty = Substs::single(self.clone());
+                Some(projection_pred.ty.clone().subst_bound_vars(&self_ty))
+            }
+            _ => None,
         })
     }
 
@@ -533,6 +761,14 @@ impl HirDisplay for ApplicationTy {
                 f.write_joined(sig.params(), ", ")?;
                 write!(f, ") -> {}", sig.ret().display(f.db))?;
             }
+            TypeCtor::Closure { .. } => {
+                let sig = self.parameters[0]
+                    .callable_sig(f.db)
+                    .expect("first closure parameter should contain signature");
+                write!(f, "|")?;
+                f.write_joined(sig.params(), ", ")?;
+                write!(f, "| -> {}", sig.ret().display(f.db))?;
+            }
             TypeCtor::Adt(def_id) => {
                 let name = match def_id {
                     AdtDef::Struct(s) => s.name(f.db),
@@ -551,11 +787,65 @@ impl HirDisplay for ApplicationTy {
     }
 }
 
+impl HirDisplay for ProjectionTy {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        let trait_name = self.trait_(f.db).name(f.db).unwrap_or_else(Name::missing);
+        write!(f, "<{} as {}", self.parameters[0].display(f.db), trait_name)?;
+        if self.parameters.len() > 1 {
+            write!(f, "<")?;
+            f.write_joined(&self.parameters[1..], ", ")?;
+            write!(f, ">")?;
+        }
+        write!(f, ">::{}", self.associated_ty.name(f.db))
+    }
+}
+
 impl HirDisplay for Ty {
     fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
         match self {
             Ty::Apply(a_ty) => a_ty.hir_fmt(f)?,
+            Ty::Projection(p_ty) => p_ty.hir_fmt(f)?,
             Ty::Param { name, .. } => write!(f, "{}", name)?,
+            Ty::Dyn(predicates) | Ty::Opaque(predicates) => {
+                match self {
+                    Ty::Dyn(_) => write!(f, "dyn ")?,
+                    _ => write!(f, "impl ")?,
+                }
+                // Associated type bindings directly follow the bound of
+                // their trait, so they are printed in its angle brackets,
+                // like `impl Iterator<Item = Foo>`.
+                let mut angle_open = false;
+                for (i, pred) in predicates.iter().enumerate() {
+                    match pred {
+                        GenericPredicate::Projection(projection_pred) => {
+                            write!(f, "{}", if angle_open { ", " } else { "<" })?;
+                            angle_open = true;
+                            let name = projection_pred.projection_ty.associated_ty.name(f.db);
+                            write!(f, "{} = {}", name, projection_pred.ty.display(f.db))?;
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    if angle_open {
+                        write!(f, ">")?;
+                        angle_open = false;
+                    }
+                    if i > 0 {
+                        write!(f, " + ")?;
+                    }
+                    match pred {
+                        // the self type is implied, so we don't print it
+                        GenericPredicate::Implemented(trait_ref) => {
+                            trait_ref.hir_fmt_ext(f, false)?
+                        }
+                        GenericPredicate::Projection(_) => unreachable!(),
+                        GenericPredicate::Error => write!(f, "{{error}}")?,
+                    }
+                }
+                if angle_open {
+                    write!(f, ">")?;
+                }
+            }
             Ty::Bound(idx) => write!(f, "?{}", idx)?,
             Ty::Unknown => write!(f, "{{unknown}}")?,
             Ty::Infer(..) => write!(f, "_")?,
@@ -564,14 +854,16 @@ impl HirDisplay for Ty {
     }
 }
 
-impl HirDisplay for TraitRef {
-    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
-        write!(
-            f,
-            "{}: {}",
-            self.substs[0].display(f.db),
-            self.trait_.name(f.db).unwrap_or_else(Name::missing)
-        )?;
+impl TraitRef {
+    fn hir_fmt_ext(
+        &self,
+        f: &mut HirFormatter<impl HirDatabase>,
+        with_self_ty: bool,
+    ) -> fmt::Result {
+        if with_self_ty {
+            write!(f, "{}: ", self.substs[0].display(f.db))?;
+        }
+        write!(f, "{}", self.trait_.name(f.db).unwrap_or_else(Name::missing))?;
         if self.substs.len() > 1 {
             write!(f, "<")?;
             f.write_joined(&self.substs[1..], ", ")?;
@@ -580,3 +872,9 @@ impl HirDisplay for TraitRef {
         Ok(())
     }
 }
+
+impl HirDisplay for TraitRef {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        self.hir_fmt_ext(f, true)
+    }
+}
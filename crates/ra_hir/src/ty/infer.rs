@@ -27,26 +27,29 @@ use ra_prof::profile;
 use test_utils::tested_by;
 
 use crate::{
-    Function, StructField, Path, Name,
+    Function, StructField, Path, Name, Struct, Enum, Trait, TypeAlias,
     FnSignature, AdtDef,ConstSignature,
     HirDatabase,
     DefWithBody,
     ImplItem,
+    name, path,
     type_ref::{TypeRef, Mutability},
-    expr::{Body, Expr, BindingAnnotation, Literal, ExprId, Pat, PatId, UnaryOp, BinaryOp, Statement, FieldPat,Array, self},
+    expr::{Body, Expr, BindingAnnotation, Literal, ExprId, Pat, PatId, UnaryOp, BinaryOp, RangeOp, Statement, FieldPat,Array, self},
     generics::{GenericParams, HasGenericParams},
     path::{GenericArgs, GenericArg},
     ModuleDef,
     adt::VariantDef,
     resolve::{Resolver, Resolution},
     nameres::Namespace,
+    lang_item::LangItemTarget,
     ty::infer::diagnostics::InferenceDiagnostic,
     diagnostics::DiagnosticSink,
 };
 use super::{
     Ty, TypableDef, Substs, primitive, op, ApplicationTy, TypeCtor, CallableDef, TraitRef,
-    traits::{Solution, Obligation, Guidance},
-    method_resolution,
+    GenericPredicate, ProjectionTy, Canonical,
+    traits::{Solution, Obligation, Guidance, ProjectionPredicate},
+    method_resolution, autoderef,
 };
 
 mod unify;
@@ -171,6 +174,21 @@ struct InferenceContext<'a, D: HirDatabase> {
     diagnostics: Vec<InferenceDiagnostic>,
     /// The return type of the function being inferred.
     return_ty: Ty,
+    /// The loops enclosing the expression being inferred, innermost last.
+    breakables: Vec<BreakableContext>,
+}
+
+/// A loop or labeled block that can be left with a `break`.
+#[derive(Clone, Debug)]
+struct BreakableContext {
+    expr: ExprId,
+    /// Whether this is a labeled block, which only a labeled `break` leaves.
+    is_block: bool,
+    /// Whether the loop is left by some `break`; a `loop` which is not has
+    /// type `!`.
+    may_break: bool,
+    /// The type of the values passed to `break`.
+    break_ty: Ty,
 }
 
 impl<'a, D: HirDatabase> InferenceContext<'a, D> {
@@ -185,6 +203,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             var_unification_table: InPlaceUnificationTable::new(),
             obligations: Vec::default(),
             return_ty: Ty::Unknown, // set in collect_fn_signature
+            breakables: Vec::new(),
             db,
             body,
             resolver,
@@ -204,13 +223,19 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             let resolved = self.resolve_ty_completely(&mut tv_stack, mem::replace(ty, Ty::Unknown));
             *ty = resolved;
         }
+        let mut diagnostics = mem::replace(&mut self.diagnostics, Vec::new());
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.walk_types_mut(&mut |ty| {
+                *ty = self.resolve_ty_completely(&mut tv_stack, mem::replace(ty, Ty::Unknown));
+            });
+        }
         InferenceResult {
             method_resolutions: self.method_resolutions,
             field_resolutions: self.field_resolutions,
             assoc_resolutions: self.assoc_resolutions,
             type_of_expr: expr_types,
             type_of_pat: pat_types,
-            diagnostics: self.diagnostics,
+            diagnostics,
         }
     }
 
@@ -242,7 +267,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             type_ref,
         );
         let ty = self.insert_type_vars(ty);
-        ty
+        self.normalize_associated_types_in(ty)
     }
 
     fn unify_substs(&mut self, substs1: &Substs, substs2: &Substs, depth: usize) -> bool {
@@ -323,19 +348,49 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         ty.fold(&mut |ty| self.insert_type_vars_shallow(ty))
     }
 
+    fn normalize_associated_types_in(&mut self, ty: Ty) -> Ty {
+        ty.fold(&mut |ty| match ty {
+            Ty::Projection(proj_ty) => self.normalize_projection_ty(proj_ty),
+            _ => ty,
+        })
+    }
+
+    fn normalize_associated_types_in_all(&mut self, tys: Vec<Ty>) -> Vec<Ty> {
+        tys.into_iter().map(|ty| self.normalize_associated_types_in(ty)).collect()
+    }
+
+    /// Replaces the projection type by a new type variable and registers an
+    /// obligation that the projection normalizes to it. Projections on `dyn
+    /// Trait` and `impl Trait` types are normalized directly using the
+    /// associated type bindings in their bounds, since Chalk doesn't know
+    /// about these types.
+    fn normalize_projection_ty(&mut self, proj_ty: ProjectionTy) -> Ty {
+        let self_ty = self.resolve_ty_shallow(&proj_ty.parameters[0]).into_owned();
+        if let Some(ty) = self_ty.inherent_projection(&proj_ty) {
+            return ty;
+        }
+        let var = self.new_type_var();
+        let predicate = ProjectionPredicate { projection_ty: proj_ty, ty: var.clone() };
+        let obligation = Obligation::Projection(predicate);
+        self.obligations.push(obligation);
+        var
+    }
+
     fn resolve_obligations_as_possible(&mut self) {
+        let krate = match self.resolver.krate() {
+            Some(krate) => krate,
+            None => return,
+        };
         let obligations = mem::replace(&mut self.obligations, Vec::new());
         for obligation in obligations {
             let (solution, canonicalized) = match &obligation {
                 Obligation::Trait(tr) => {
                     let canonicalized = self.canonicalizer().canonicalize_trait_ref(tr.clone());
-                    (
-                        self.db.implements(
-                            self.resolver.krate().unwrap(),
-                            canonicalized.value.clone(),
-                        ),
-                        canonicalized,
-                    )
+                    (self.db.implements(krate, canonicalized.value.clone()), canonicalized)
+                }
+                Obligation::Projection(pr) => {
+                    let canonicalized = self.canonicalizer().canonicalize_projection(pr.clone());
+                    (self.db.normalize(krate, canonicalized.value.clone()), canonicalized)
                 }
             };
             match solution {
@@ -507,7 +562,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
 
             actual_def_ty = Some(ty.clone());
 
-            let item: crate::ModuleDef = ty.iterate_impl_items(self.db, krate, |item| {
+            let inherent_item = ty.iterate_impl_items(self.db, krate, |item| {
                 let matching_def: Option<crate::ModuleDef> = match item {
                     crate::ImplItem::Method(func) => {
                         let sig = func.signature(self.db);
@@ -537,7 +592,26 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     }
                     None => None,
                 }
-            })?;
+            });
+            let item: crate::ModuleDef = match inherent_item {
+                Some(item) => item,
+                None => {
+                    // Look for functions of traits implemented by the type, like
+                    // `Default::default` for `Foo::default()`.
+                    // FIXME: resolve associated consts and types of traits as well
+                    let canonical = Canonical { value: actual_def_ty.clone()?, num_vars: 0 };
+                    let func = method_resolution::iterate_method_candidates(
+                        &canonical,
+                        self.db,
+                        resolver,
+                        Some(&segment.name),
+                        method_resolution::LookupMode::Path,
+                        |_ty, func| Some(func),
+                    )?;
+                    self.write_assoc_resolution(id, func.into());
+                    func.into()
+                }
+            };
 
             resolved = Resolution::Def(item.into());
         }
@@ -582,6 +656,14 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 return None;
             }
 
+            if func.parent_trait(self.db).is_some() {
+                // The first parameter of a trait is `Self`, which is the type
+                // the function was looked up on.
+                let mut new_substs = vec![Ty::Unknown; gen.count_parent_params()];
+                new_substs[0] = actual_def_ty;
+                return Some(Substs(new_substs.into()));
+            }
+
             let impl_block = func.impl_block(self.db)?.target_ty(self.db);
             let impl_block_substs = impl_block.substs()?;
             let actual_substs = actual_def_ty.substs()?;
@@ -656,6 +738,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         &mut self,
         path: Option<&Path>,
         subpats: &[PatId],
+        ellipsis: Option<usize>,
         expected: &Ty,
         default_bm: BindingMode,
     ) -> Ty {
@@ -664,8 +747,15 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         self.unify(&ty, expected);
 
         let substs = ty.substs().unwrap_or_else(Substs::empty);
+        let field_count = def.map_or(subpats.len(), |d| d.fields(self.db).len());
 
         for (i, &subpat) in subpats.iter().enumerate() {
+            let i = match ellipsis {
+                Some(ellipsis) if field_count >= subpats.len() => {
+                    expr::field_index(i, ellipsis, subpats.len(), field_count)
+                }
+                _ => i,
+            };
             let expected_ty = def
                 .and_then(|d| d.field(self.db, &Name::tuple_field_name(i)))
                 .map_or(Ty::Unknown, |field| field.ty(self.db))
@@ -703,13 +793,19 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         let body = Arc::clone(&self.body); // avoid borrow checker problem
 
         let is_non_ref_pat = match &body[pat] {
-            Pat::Tuple(..)
+            Pat::Tuple { .. }
             | Pat::TupleStruct { .. }
             | Pat::Struct { .. }
             | Pat::Range { .. }
             | Pat::Slice { .. } => true,
-            // FIXME: Path/Lit might actually evaluate to ref, but inference is unimplemented.
-            Pat::Path(..) | Pat::Lit(..) => true,
+            // string literals are matched against references to `str`
+            Pat::Lit(expr) => match &body[*expr] {
+                Expr::Literal(Literal::String(..)) => false,
+                Expr::Literal(Literal::ByteString(..)) => false,
+                _ => true,
+            },
+            // FIXME: Path might actually evaluate to ref, but inference is unimplemented.
+            Pat::Path(..) => true,
             Pat::Wild | Pat::Bind { .. } | Pat::Ref { .. } | Pat::Missing => false,
         };
         if is_non_ref_pat {
@@ -733,21 +829,35 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         let expected = expected;
 
         let ty = match &body[pat] {
-            Pat::Tuple(ref args) => {
+            Pat::Tuple { ref args, ellipsis } => {
                 let expectations = match expected.as_tuple() {
                     Some(parameters) => &*parameters.0,
                     _ => &[],
                 };
-                let expectations_iter = expectations.iter().chain(repeat(&Ty::Unknown));
-
-                let inner_tys: Substs = args
-                    .iter()
-                    .zip(expectations_iter)
-                    .map(|(&pat, ty)| self.infer_pat(pat, ty, default_bm))
-                    .collect::<Vec<_>>()
-                    .into();
-
-                Ty::apply(TypeCtor::Tuple { cardinality: inner_tys.len() as u16 }, inner_tys)
+                match ellipsis {
+                    Some(ellipsis) if expectations.len() >= args.len() => {
+                        // the `..` stands for the fields we don't have patterns for
+                        let (pats, fields) = (args.len(), expectations.len());
+                        for (i, &pat) in args.iter().enumerate() {
+                            let field = expr::field_index(i, *ellipsis, pats, fields);
+                            self.infer_pat(pat, &expectations[field], default_bm);
+                        }
+                        expected.clone()
+                    }
+                    _ => {
+                        let expectations_iter = expectations.iter().chain(repeat(&Ty::Unknown));
+
+                        let inner_tys: Substs = args
+                            .iter()
+                            .zip(expectations_iter)
+                            .map(|(&pat, ty)| self.infer_pat(pat, ty, default_bm))
+                            .collect::<Vec<_>>()
+                            .into();
+
+                        let cardinality = inner_tys.len() as u16;
+                        Ty::apply(TypeCtor::Tuple { cardinality }, inner_tys)
+                    }
+                }
             }
             Pat::Ref { pat, mutability } => {
                 let expectation = match expected.as_reference() {
@@ -762,8 +872,8 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 let subty = self.infer_pat(*pat, expectation, default_bm);
                 Ty::apply_one(TypeCtor::Ref(*mutability), subty.into())
             }
-            Pat::TupleStruct { path: ref p, args: ref subpats } => {
-                self.infer_tuple_struct_pat(p.as_ref(), subpats, expected, default_bm)
+            Pat::TupleStruct { path: ref p, args: ref subpats, ellipsis } => {
+                self.infer_tuple_struct_pat(p.as_ref(), subpats, *ellipsis, expected, default_bm)
             }
             Pat::Struct { path: ref p, args: ref fields } => {
                 self.infer_struct_pat(p.as_ref(), fields, expected, default_bm)
@@ -773,6 +883,23 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 let resolver = self.resolver.clone();
                 self.infer_path_expr(&resolver, &path, pat.into()).unwrap_or(Ty::Unknown)
             }
+            Pat::Slice { prefix, rest, suffix } => {
+                let elem_ty = match expected {
+                    Ty::Apply(a_ty) => match a_ty.ctor {
+                        TypeCtor::Slice | TypeCtor::Array => a_ty.parameters.as_single().clone(),
+                        _ => Ty::Unknown,
+                    },
+                    _ => Ty::Unknown,
+                };
+                for &pat in prefix.iter().chain(suffix) {
+                    self.infer_pat(pat, &elem_ty, default_bm);
+                }
+                if let Some(rest) = rest {
+                    self.infer_pat(*rest, expected, default_bm);
+                }
+                expected.clone()
+            }
+            Pat::Lit(expr) => self.infer_expr(*expr, &Expectation::has_type(expected.clone())),
             Pat::Bind { mode, name: _name, subpat } => {
                 let mode = if mode == &BindingAnnotation::Unannotated {
                     default_bm
@@ -850,7 +977,6 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             Ty::Apply(a_ty) => match a_ty.ctor {
                 TypeCtor::FnDef(def) => {
                     // add obligation for trait implementation, if this is a trait method
-                    // FIXME also register obligations from where clauses from the trait or impl and method
                     match def {
                         CallableDef::Function(f) => {
                             if let Some(trait_) = f.parent_trait(self.db) {
@@ -861,6 +987,15 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                                 self.obligations
                                     .push(Obligation::Trait(TraitRef { trait_, substs }));
                             }
+                            // add obligations from the where clauses of the function
+                            // FIXME also do this for structs and enum variants
+                            let predicates = self.db.generic_predicates(f.into());
+                            for predicate in predicates.iter() {
+                                let predicate = predicate.clone().subst(&a_ty.parameters);
+                                if let GenericPredicate::Implemented(trait_ref) = predicate {
+                                    self.obligations.push(Obligation::Trait(trait_ref));
+                                }
+                            }
                         }
                         CallableDef::Struct(_) | CallableDef::EnumVariant(_) => {}
                     }
@@ -887,7 +1022,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             method_name,
             &self.resolver,
         );
-        let (derefed_receiver_ty, method_ty, def_generics) = match resolved {
+        let (derefed_receiver_ty, method_ty, def_generics, method_trait) = match resolved {
             Some((ty, func)) => {
                 let ty = canonicalized_receiver.decanonicalize_ty(ty);
                 self.write_method_resolution(tgt_expr, func);
@@ -895,18 +1030,39 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     ty,
                     self.db.type_for_def(func.into(), Namespace::Values),
                     Some(func.generic_params(self.db)),
+                    func.parent_trait(self.db),
                 )
             }
-            None => (receiver_ty, Ty::Unknown, None),
+            None => {
+                self.diagnostics.push(InferenceDiagnostic::UnresolvedMethodCall {
+                    expr: tgt_expr,
+                    method_name: method_name.clone(),
+                    receiver_ty: receiver_ty.clone(),
+                });
+                (receiver_ty, Ty::Unknown, None, None)
+            }
         };
-        let substs =
+        let mut substs =
             self.substs_for_method_call(def_generics.clone(), generic_args, &derefed_receiver_ty);
+        if let Some(trait_ref) =
+            method_trait.and_then(|t| derefed_receiver_ty.inherent_trait_ref(t))
+        {
+            // For methods of the traits of a `dyn Trait` / `impl Trait`
+            // receiver, the trait parameters are known from the bounds
+            let mut substs_vec = substs.0.to_vec();
+            if trait_ref.substs.len() <= substs_vec.len() {
+                substs_vec[..trait_ref.substs.len()].clone_from_slice(&trait_ref.substs);
+            }
+            substs = substs_vec.into();
+        }
         let method_ty = method_ty.apply_substs(substs);
         let method_ty = self.insert_type_vars(method_ty);
         self.register_obligations_for_call(&method_ty);
         let (expected_receiver_ty, param_tys, ret_ty) = match method_ty.callable_sig(self.db) {
             Some(sig) => {
                 if !sig.params().is_empty() {
+                    let expected = sig.params().len() - 1;
+                    self.check_arg_count(tgt_expr, expected, args.len(), sig.is_variadic());
                     (sig.params()[0].clone(), sig.params()[1..].to_vec(), sig.ret().clone())
                 } else {
                     (Ty::Unknown, Vec::new(), sig.ret().clone())
@@ -914,6 +1070,9 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             }
             None => (Ty::Unknown, Vec::new(), Ty::Unknown),
         };
+        let expected_receiver_ty = self.normalize_associated_types_in(expected_receiver_ty);
+        let param_tys = self.normalize_associated_types_in_all(param_tys);
+        let ret_ty = self.normalize_associated_types_in(ret_ty);
         // Apply autoref so the below unification works correctly
         // FIXME: return correct autorefs from lookup_method
         let actual_receiver_ty = match expected_receiver_ty.as_reference() {
@@ -948,27 +1107,76 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 };
                 then_ty
             }
-            Expr::Block { statements, tail } => self.infer_block(statements, *tail, expected),
-            Expr::Loop { body } => {
-                self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
-                // FIXME handle break with value
-                Ty::simple(TypeCtor::Never)
+            Expr::Block { statements, tail, label: None } => {
+                self.infer_block(statements, *tail, expected)
             }
-            Expr::While { condition, body } => {
+            Expr::Block { statements, tail, label: Some(_) } => {
+                let break_ty = self.new_type_var();
+                self.breakables.push(BreakableContext {
+                    expr: tgt_expr,
+                    is_block: true,
+                    may_break: false,
+                    break_ty: break_ty.clone(),
+                });
+                let ty = self.infer_block(statements, *tail, &Expectation::has_type(break_ty));
+                let ctxt = self.breakables.pop().expect("breakable stack is balanced");
+                if ctxt.may_break {
+                    ctxt.break_ty
+                } else {
+                    ty
+                }
+            }
+            Expr::TryBlock { body } => {
+                let expected_ty = self.resolve_ty_shallow(&expected.ty).into_owned();
+                match expected_ty {
+                    Ty::Unknown | Ty::Infer(_) => {
+                        // without an expected type, assume the block is a `Result`
+                        let ok_ty = self.infer_expr(*body, &Expectation::none());
+                        match self.resolve_known_enum(&path::known::std_result_result()) {
+                            Some(result) => {
+                                let error_ty = self.new_type_var();
+                                let parameters = Substs(vec![ok_ty, error_ty].into());
+                                Ty::apply(TypeCtor::Adt(AdtDef::Enum(result)), parameters)
+                            }
+                            None => Ty::Unknown,
+                        }
+                    }
+                    _ => {
+                        let ops_try_ok = self.resolve_ops_try_ok();
+                        let ok_ty = self.resolve_associated_type(expected_ty.clone(), ops_try_ok);
+                        self.infer_expr(*body, &Expectation::has_type(ok_ty));
+                        expected_ty
+                    }
+                }
+            }
+            Expr::Loop { body, .. } => {
+                let break_ty = self.new_type_var();
+                let ctxt = self.infer_loop_body(tgt_expr, *body, break_ty);
+                if ctxt.may_break {
+                    ctxt.break_ty
+                } else {
+                    Ty::simple(TypeCtor::Never)
+                }
+            }
+            Expr::While { condition, body, .. } => {
                 // while let is desugared to a match loop, so this is always simple while
                 self.infer_expr(*condition, &Expectation::has_type(Ty::simple(TypeCtor::Bool)));
-                self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
+                self.infer_loop_body(tgt_expr, *body, Ty::unit());
                 Ty::unit()
             }
-            Expr::For { iterable, body, pat } => {
-                let _iterable_ty = self.infer_expr(*iterable, &Expectation::none());
-                self.infer_pat(*pat, &Ty::Unknown, BindingMode::default());
-                self.infer_expr(*body, &Expectation::has_type(Ty::unit()));
+            Expr::For { iterable, body, pat, .. } => {
+                let iterable_ty = self.infer_expr(*iterable, &Expectation::none());
+                let into_iter_item = self.resolve_into_iter_item();
+                let pat_ty = self.resolve_associated_type(iterable_ty, into_iter_item);
+                self.infer_pat(*pat, &pat_ty, BindingMode::default());
+                self.infer_loop_body(tgt_expr, *body, Ty::unit());
                 Ty::unit()
             }
             Expr::Lambda { body, args, arg_types } => {
                 assert_eq!(args.len(), arg_types.len());
 
+                let mut sig_tys = Vec::new();
+
                 for (arg_pat, arg_type) in args.iter().zip(arg_types.iter()) {
                     let expected = if let Some(type_ref) = arg_type {
                         let ty = self.make_ty(type_ref);
@@ -976,24 +1184,62 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     } else {
                         Ty::Unknown
                     };
-                    self.infer_pat(*arg_pat, &expected, BindingMode::default());
+                    let arg_ty = self.infer_pat(*arg_pat, &expected, BindingMode::default());
+                    sig_tys.push(arg_ty);
                 }
 
-                // FIXME: infer lambda type etc.
-                let _body_ty = self.infer_expr(*body, &Expectation::none());
-                Ty::Unknown
+                // add return type
+                let ret_ty = self.new_type_var();
+                sig_tys.push(ret_ty.clone());
+                let sig_ty = Ty::apply(
+                    TypeCtor::FnPtr { num_args: sig_tys.len() as u16 - 1 },
+                    sig_tys.into(),
+                );
+                let closure_ty = Ty::apply_one(
+                    TypeCtor::Closure { def: self.body.owner(), expr: tgt_expr },
+                    sig_ty.clone(),
+                );
+
+                // Eagerly try to relate the closure type with the expected
+                // type, otherwise we often won't have enough information to
+                // infer the body. The parameter types are then inferred from
+                // the `Fn` trait obligations on the expected type.
+                let expected_ty = self.resolve_ty_shallow(&expected.ty).into_owned();
+                match &expected_ty {
+                    // the closure is coerced to a function pointer
+                    Ty::Apply(ApplicationTy { ctor: TypeCtor::FnPtr { .. }, .. }) => {
+                        self.unify(&sig_ty, &expected_ty)
+                    }
+                    _ => self.unify(&closure_ty, &expected_ty),
+                };
+
+                // `return` inside of the closure refers to the closure, and
+                // `break` can't leave it
+                let prev_ret_ty = mem::replace(&mut self.return_ty, ret_ty.clone());
+                let prev_breakables = mem::replace(&mut self.breakables, Vec::new());
+                self.infer_expr(*body, &Expectation::has_type(ret_ty));
+                self.return_ty = prev_ret_ty;
+                self.breakables = prev_breakables;
+
+                closure_ty
             }
             Expr::Call { callee, args } => {
                 let callee_ty = self.infer_expr(*callee, &Expectation::none());
+                self.register_obligations_for_call(&callee_ty);
                 let (param_tys, ret_ty) = match callee_ty.callable_sig(self.db) {
-                    Some(sig) => (sig.params().to_vec(), sig.ret().clone()),
+                    Some(sig) => {
+                        let expected = sig.params().len();
+                        self.check_arg_count(tgt_expr, expected, args.len(), sig.is_variadic());
+                        (sig.params().to_vec(), sig.ret().clone())
+                    }
                     None => {
                         // Not callable
                         // FIXME: report an error
                         (Vec::new(), Ty::Unknown)
                     }
                 };
-                // FIXME register obligations from where clauses from the function
+                let param_tys = self.normalize_associated_types_in_all(param_tys);
+                let ret_ty = self.normalize_associated_types_in(ret_ty);
                 let param_iter = param_tys.into_iter().chain(repeat(Ty::Unknown));
                 for (arg, param) in args.iter().zip(param_iter) {
                     self.infer_expr(*arg, &Expectation::has_type(param));
@@ -1030,11 +1276,23 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 let resolver = expr::resolver_for_expr(self.body.clone(), self.db, tgt_expr);
                 self.infer_path_expr(&resolver, p, tgt_expr.into()).unwrap_or(Ty::Unknown)
             }
-            Expr::Continue => Ty::simple(TypeCtor::Never),
-            Expr::Break { expr } => {
-                if let Some(expr) = expr {
-                    // FIXME handle break with value
-                    self.infer_expr(*expr, &Expectation::none());
+            Expr::Continue { .. } => Ty::simple(TypeCtor::Never),
+            Expr::Break { expr, label } => {
+                let target = self.find_breakable(tgt_expr, label.as_ref());
+                let break_ty = match target {
+                    Some(idx) => self.breakables[idx].break_ty.clone(),
+                    None => Ty::Unknown,
+                };
+                match expr {
+                    Some(expr) => {
+                        self.infer_expr(*expr, &Expectation::has_type(break_ty));
+                    }
+                    None => {
+                        self.unify(&break_ty, &Ty::unit());
+                    }
+                }
+                if let Some(idx) = target {
+                    self.breakables[idx].may_break = true;
                 }
                 Ty::simple(TypeCtor::Never)
             }
@@ -1070,30 +1328,39 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             }
             Expr::Field { expr, name } => {
                 let receiver_ty = self.infer_expr(*expr, &Expectation::none());
-                let ty = receiver_ty
-                    .autoderef(self.db)
-                    .find_map(|derefed_ty| match derefed_ty {
-                        Ty::Apply(a_ty) => match a_ty.ctor {
-                            TypeCtor::Tuple { .. } => {
-                                let i = name.to_string().parse::<usize>().ok();
-                                i.and_then(|i| a_ty.parameters.0.get(i).cloned())
-                            }
-                            TypeCtor::Adt(AdtDef::Struct(s)) => {
-                                s.field(self.db, name).map(|field| {
-                                    self.write_field_resolution(tgt_expr, field);
-                                    field.ty(self.db).subst(&a_ty.parameters)
-                                })
-                            }
-                            _ => None,
-                        },
+                let canonicalized = self.canonicalizer().canonicalize_ty(receiver_ty);
+                let ty = autoderef::autoderef(
+                    self.db,
+                    &self.resolver.clone(),
+                    canonicalized.value.clone(),
+                )
+                .find_map(|derefed_ty| match canonicalized.decanonicalize_ty(derefed_ty.value) {
+                    Ty::Apply(a_ty) => match a_ty.ctor {
+                        TypeCtor::Tuple { .. } => {
+                            let i = name.to_string().parse::<usize>().ok();
+                            i.and_then(|i| a_ty.parameters.0.get(i).cloned())
+                        }
+                        TypeCtor::Adt(AdtDef::Struct(s)) => s.field(self.db, name).map(|field| {
+                            self.write_field_resolution(tgt_expr, field);
+                            field.ty(self.db).subst(&a_ty.parameters)
+                        }),
                         _ => None,
-                    })
-                    .unwrap_or(Ty::Unknown);
-                self.insert_type_vars(ty)
+                    },
+                    _ => None,
+                })
+                .unwrap_or(Ty::Unknown);
+                let ty = self.insert_type_vars(ty);
+                self.normalize_associated_types_in(ty)
             }
             Expr::Try { expr } => {
-                let _inner_ty = self.infer_expr(*expr, &Expectation::none());
-                Ty::Unknown
+                let inner_ty = self.infer_expr(*expr, &Expectation::none());
+                let ops_try_ok = self.resolve_ops_try_ok();
+                self.resolve_associated_type(inner_ty, ops_try_ok)
+            }
+            Expr::Await { expr } => {
+                let inner_ty = self.infer_expr(*expr, &Expectation::none());
+                let future_output = self.resolve_future_output();
+                self.resolve_associated_type(inner_ty, future_output)
             }
             Expr::Cast { expr, type_ref } => {
                 let _inner_ty = self.infer_expr(*expr, &Expectation::none());
@@ -1116,48 +1383,54 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 let inner_ty = self.infer_expr(*expr, &expectation);
                 Ty::apply_one(TypeCtor::Ref(*mutability), inner_ty)
             }
+            Expr::Box { expr } => {
+                let inner_ty = self.infer_expr(*expr, &Expectation::none());
+                match self.resolve_lang_struct("owned_box") {
+                    Some(box_) => Ty::apply_one(TypeCtor::Adt(AdtDef::Struct(box_)), inner_ty),
+                    None => Ty::Unknown,
+                }
+            }
             Expr::UnaryOp { expr, op } => {
                 let inner_ty = self.infer_expr(*expr, &Expectation::none());
                 match op {
                     UnaryOp::Deref => {
-                        if let Some(derefed_ty) = inner_ty.builtin_deref() {
-                            derefed_ty
+                        let canonicalized = self.canonicalizer().canonicalize_ty(inner_ty);
+                        if let Some(derefed_ty) =
+                            autoderef::deref(self.db, &self.resolver, &canonicalized.value)
+                        {
+                            canonicalized.decanonicalize_ty(derefed_ty.value)
                         } else {
-                            // FIXME Deref::deref
                             Ty::Unknown
                         }
                     }
-                    UnaryOp::Neg => {
-                        match &inner_ty {
-                            Ty::Apply(a_ty) => match a_ty.ctor {
-                                TypeCtor::Int(primitive::UncertainIntTy::Unknown)
-                                | TypeCtor::Int(primitive::UncertainIntTy::Known(
-                                    primitive::IntTy {
-                                        signedness: primitive::Signedness::Signed,
-                                        ..
-                                    },
-                                ))
-                                | TypeCtor::Float(..) => inner_ty,
-                                _ => Ty::Unknown,
-                            },
-                            Ty::Infer(InferTy::IntVar(..)) | Ty::Infer(InferTy::FloatVar(..)) => {
-                                inner_ty
-                            }
-                            // FIXME: resolve ops::Neg trait
-                            _ => Ty::Unknown,
-                        }
+                    UnaryOp::Neg | UnaryOp::Not if op::is_overloaded_operand(&inner_ty) => {
+                        let output = op::unary_op_lang_item(*op)
+                            .and_then(|lang_item| self.resolve_lang_trait_output(lang_item));
+                        self.resolve_associated_type(inner_ty, output)
                     }
-                    UnaryOp::Not => {
-                        match &inner_ty {
-                            Ty::Apply(a_ty) => match a_ty.ctor {
-                                TypeCtor::Bool | TypeCtor::Int(_) => inner_ty,
-                                _ => Ty::Unknown,
-                            },
-                            Ty::Infer(InferTy::IntVar(..)) => inner_ty,
-                            // FIXME: resolve ops::Not trait for inner_ty
+                    UnaryOp::Neg => match &inner_ty {
+                        Ty::Apply(a_ty) => match a_ty.ctor {
+                            TypeCtor::Int(primitive::UncertainIntTy::Unknown)
+                            | TypeCtor::Int(primitive::UncertainIntTy::Known(primitive::IntTy {
+                                signedness: primitive::Signedness::Signed,
+                                ..
+                            }))
+                            | TypeCtor::Float(..) => inner_ty,
                             _ => Ty::Unknown,
+                        },
+                        Ty::Infer(InferTy::IntVar(..)) | Ty::Infer(InferTy::FloatVar(..)) => {
+                            inner_ty
                         }
-                    }
+                        _ => Ty::Unknown,
+                    },
+                    UnaryOp::Not => match &inner_ty {
+                        Ty::Apply(a_ty) => match a_ty.ctor {
+                            TypeCtor::Bool | TypeCtor::Int(_) => inner_ty,
+                            _ => Ty::Unknown,
+                        },
+                        Ty::Infer(InferTy::IntVar(..)) => inner_ty,
+                        _ => Ty::Unknown,
+                    },
                 }
             }
             Expr::BinaryOp { lhs, rhs, op } => match op {
@@ -1169,16 +1442,57 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                         _ => Expectation::none(),
                     };
                     let lhs_ty = self.infer_expr(*lhs, &lhs_expectation);
-                    // FIXME: find implementation of trait corresponding to operation
-                    // symbol and resolve associated `Output` type
-                    let rhs_expectation = op::binary_op_rhs_expectation(*op, lhs_ty);
+                    let rhs_expectation = op::binary_op_rhs_expectation(*op, lhs_ty.clone());
                     let rhs_ty = self.infer_expr(*rhs, &Expectation::has_type(rhs_expectation));
 
-                    // FIXME: similar as above, return ty is often associated trait type
-                    op::binary_op_return_ty(*op, rhs_ty)
+                    match op::binary_op_lang_item(*op) {
+                        Some(lang_item) if op::is_overloaded_operand(&lhs_ty) => {
+                            self.infer_overloaded_binop(*op, lang_item, lhs_ty, rhs_ty)
+                        }
+                        _ => {
+                            let operand_ty = op::deref_operand(&lhs_ty)
+                                .or_else(|| op::deref_operand(&rhs_ty))
+                                .unwrap_or(rhs_ty);
+                            op::binary_op_return_ty(*op, operand_ty)
+                        }
+                    }
                 }
                 _ => Ty::Unknown,
             },
+            Expr::Index { base, index } => {
+                let base_ty = self.infer_expr(*base, &Expectation::none());
+                let index_ty = self.infer_expr(*index, &Expectation::none());
+                self.infer_index(base_ty, index_ty)
+            }
+            Expr::Range { lhs, rhs, range_type } => {
+                let lhs_ty = lhs.map(|e| self.infer_expr(e, &Expectation::none()));
+                let rhs_expect = lhs_ty
+                    .as_ref()
+                    .map_or_else(Expectation::none, |ty| Expectation::has_type(ty.clone()));
+                let rhs_ty = rhs.map(|e| self.infer_expr(e, &rhs_expect));
+                let (lang_item, elem_ty) = match (*range_type, lhs_ty, rhs_ty) {
+                    (RangeOp::Exclusive, None, None) => (Some("RangeFull"), None),
+                    (RangeOp::Exclusive, None, Some(ty)) => (Some("RangeTo"), Some(ty)),
+                    (RangeOp::Exclusive, Some(ty), None) => (Some("RangeFrom"), Some(ty)),
+                    (RangeOp::Exclusive, Some(_), Some(ty)) => (Some("Range"), Some(ty)),
+                    (RangeOp::Inclusive, None, Some(ty)) => (Some("RangeToInclusive"), Some(ty)),
+                    (RangeOp::Inclusive, Some(_), Some(ty)) => {
+                        (Some("RangeInclusiveStruct"), Some(ty))
+                    }
+                    // `a..=` is a syntax error
+                    (RangeOp::Inclusive, _, None) => (None, None),
+                };
+                match lang_item.and_then(|it| self.resolve_lang_struct(it)) {
+                    Some(range) => {
+                        let ctor = TypeCtor::Adt(AdtDef::Struct(range));
+                        match elem_ty {
+                            Some(ty) => Ty::apply_one(ctor, ty),
+                            None => Ty::simple(ctor),
+                        }
+                    }
+                    None => Ty::Unknown,
+                }
+            }
             Expr::Tuple { exprs } => {
                 let mut ty_vec = Vec::with_capacity(exprs.len());
                 for arg in exprs.iter() {
@@ -1239,7 +1553,15 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         };
         // use a new type variable if we got Ty::Unknown here
         let ty = self.insert_type_vars_shallow(ty);
-        self.unify(&ty, &expected.ty);
+        let could_unify = self.unify(&ty, &expected.ty);
+        // the expectation of blocks and `if`s is already checked on their tails
+        let checked_inside = match &body[tgt_expr] {
+            Expr::Block { tail: Some(_), .. } | Expr::If { .. } => true,
+            _ => false,
+        };
+        if !could_unify && !checked_inside {
+            self.record_type_mismatch(tgt_expr, expected.ty.clone(), ty.clone());
+        }
         let ty = self.resolve_ty_as_possible(&mut vec![], ty);
         self.write_expr_ty(tgt_expr, ty.clone());
         ty
@@ -1251,8 +1573,9 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         tail: Option<ExprId>,
         expected: &Expectation,
     ) -> Ty {
+        let mut diverges = false;
         for stmt in statements {
-            match stmt {
+            let stmt_ty = match stmt {
                 Statement::Let { pat, type_ref, initializer } => {
                     let decl_ty =
                         type_ref.as_ref().map(|tr| self.make_ty(tr)).unwrap_or(Ty::Unknown);
@@ -1265,16 +1588,168 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     };
 
                     self.infer_pat(*pat, &ty, BindingMode::default());
+                    ty
                 }
-                Statement::Expr(expr) => {
-                    self.infer_expr(*expr, &Expectation::none());
-                }
-            }
+                Statement::Expr(expr) => self.infer_expr(*expr, &Expectation::none()),
+            };
+            diverges |= stmt_ty == Ty::simple(TypeCtor::Never);
         }
-        let ty = if let Some(expr) = tail { self.infer_expr(expr, expected) } else { Ty::unit() };
+        let ty = match tail {
+            Some(expr) => self.infer_expr(expr, expected),
+            // a block ending in e.g. `return x;` can have any type
+            None if diverges => Ty::simple(TypeCtor::Never),
+            None => Ty::unit(),
+        };
         ty
     }
 
+    /// Records that `expr` has type `actual` where `expected` is required,
+    /// unless `actual` may coerce to `expected`.
+    fn record_type_mismatch(&mut self, expr: ExprId, expected: Ty, actual: Ty) {
+        let expected = self.resolve_ty_as_possible(&mut vec![], expected);
+        let actual = self.resolve_ty_as_possible(&mut vec![], actual);
+        if may_coerce(&actual, &expected) {
+            return;
+        }
+        self.diagnostics.push(InferenceDiagnostic::TypeMismatch { expr, expected, actual });
+    }
+
+    /// Variadic functions accept any number of arguments beyond the expected
+    /// ones.
+    fn check_arg_count(
+        &mut self,
+        call_expr: ExprId,
+        expected: usize,
+        found: usize,
+        is_variadic: bool,
+    ) {
+        if found < expected || (found > expected && !is_variadic) {
+            self.diagnostics.push(InferenceDiagnostic::MismatchedArgCount {
+                expr: call_expr,
+                expected,
+                found,
+            });
+        }
+    }
+
+    /// Infers the type of `lhs_ty op rhs_ty` for an operator overloaded by the
+    /// `std::ops` trait with the lang item `lang_item`.
+    fn infer_overloaded_binop(
+        &mut self,
+        op: BinaryOp,
+        lang_item: &str,
+        lhs_ty: Ty,
+        rhs_ty: Ty,
+    ) -> Ty {
+        if op.is_assignment() {
+            // `AddAssign` and friends have no associated type, compound
+            // assignments always evaluate to `()`
+            if let Some(trait_) = self.resolve_lang_trait(lang_item) {
+                let substs = Substs(vec![lhs_ty, rhs_ty].into());
+                self.obligations.push(Obligation::Trait(TraitRef { trait_, substs }));
+            }
+            Ty::unit()
+        } else {
+            let output = self.resolve_lang_trait_output(lang_item);
+            self.resolve_associated_type_with_params(lhs_ty, &[rhs_ty], output)
+        }
+    }
+
+    /// Infers the type of `base[index]`: `base` is autoderefed until it is an
+    /// array or slice indexed by an integer, or a type implementing `Index`.
+    fn infer_index(&mut self, base_ty: Ty, index_ty: Ty) -> Ty {
+        let canonicalized = self.canonicalizer().canonicalize_ty(base_ty);
+        let derefed_tys: Vec<Ty> =
+            autoderef::autoderef(self.db, &self.resolver.clone(), canonicalized.value.clone())
+                .map(|derefed_ty| canonicalized.decanonicalize_ty(derefed_ty.value))
+                .collect();
+        let index_trait = self.resolve_lang_trait("index");
+        for derefed_ty in derefed_tys {
+            if let Some(elem_ty) = self.builtin_index(&derefed_ty, &index_ty) {
+                return elem_ty;
+            }
+            let index_trait = match index_trait {
+                Some(it) => it,
+                None => continue,
+            };
+            let substs = Substs(vec![derefed_ty.clone(), index_ty.clone()].into());
+            let trait_ref = TraitRef { trait_: index_trait, substs };
+            if self.implements(trait_ref) {
+                let index_output = self.resolve_ops_index_output();
+                return self.resolve_associated_type_with_params(
+                    derefed_ty,
+                    &[index_ty],
+                    index_output,
+                );
+            }
+        }
+        Ty::Unknown
+    }
+
+    /// Returns the element type if `base_ty[index_ty]` is built-in indexing of
+    /// an array or slice by `usize`.
+    fn builtin_index(&mut self, base_ty: &Ty, index_ty: &Ty) -> Option<Ty> {
+        let elem_ty = match base_ty {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Array, parameters })
+            | Ty::Apply(ApplicationTy { ctor: TypeCtor::Slice, parameters }) => {
+                parameters.as_single().clone()
+            }
+            _ => return None,
+        };
+        let usize_ty =
+            Ty::simple(TypeCtor::Int(primitive::UncertainIntTy::Known(primitive::IntTy::usize())));
+        match index_ty {
+            Ty::Infer(InferTy::IntVar(..)) => {
+                self.unify(index_ty, &usize_ty);
+                Some(elem_ty)
+            }
+            _ if *index_ty == usize_ty => Some(elem_ty),
+            _ => None,
+        }
+    }
+
+    /// Checks whether `trait_ref` may hold, i.e. the trait solver doesn't rule
+    /// it out.
+    fn implements(&mut self, trait_ref: TraitRef) -> bool {
+        let krate = match self.resolver.krate() {
+            Some(krate) => krate,
+            None => return false,
+        };
+        let canonicalized = self.canonicalizer().canonicalize_trait_ref(trait_ref);
+        self.db.implements(krate, canonicalized.value).is_some()
+    }
+
+    /// Infers the body of the loop `loop_expr`, in which a `break` passes a
+    /// value of `break_ty`.
+    fn infer_loop_body(
+        &mut self,
+        loop_expr: ExprId,
+        body: ExprId,
+        break_ty: Ty,
+    ) -> BreakableContext {
+        self.breakables.push(BreakableContext {
+            expr: loop_expr,
+            is_block: false,
+            may_break: false,
+            break_ty,
+        });
+        self.infer_expr(body, &Expectation::has_type(Ty::unit()));
+        self.breakables.pop().expect("breakable stack is balanced")
+    }
+
+    /// Finds the index of the loop or labeled block in `breakables` which
+    /// `break_expr` leaves.
+    fn find_breakable(&self, break_expr: ExprId, label: Option<&Name>) -> Option<usize> {
+        match label {
+            None => self.breakables.iter().rposition(|ctxt| !ctxt.is_block),
+            Some(label) => {
+                let scopes = self.db.expr_scopes(self.body.owner());
+                let loop_expr = scopes.resolve_label(scopes.scope_for(break_expr), label)?;
+                self.breakables.iter().rposition(|ctxt| ctxt.expr == loop_expr)
+            }
+        }
+    }
+
     fn collect_const_signature(&mut self, signature: &ConstSignature) {
         self.return_ty = self.make_ty(signature.type_ref());
     }
@@ -1292,6 +1767,115 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
     fn infer_body(&mut self) {
         self.infer_expr(self.body.body_expr(), &Expectation::has_type(self.return_ty.clone()));
     }
+
+    /// Returns the type of the associated type `assoc_ty` for `inner_ty` as
+    /// far as it is currently known, e.g. `<Vec<u32> as IntoIterator>::Item`.
+    fn resolve_associated_type(&mut self, inner_ty: Ty, assoc_ty: Option<TypeAlias>) -> Ty {
+        self.resolve_associated_type_with_params(inner_ty, &[], assoc_ty)
+    }
+
+    /// Like `resolve_associated_type`, for traits with type parameters, e.g.
+    /// `<Vec<u32> as Index<usize>>::Output`.
+    fn resolve_associated_type_with_params(
+        &mut self,
+        inner_ty: Ty,
+        params: &[Ty],
+        assoc_ty: Option<TypeAlias>,
+    ) -> Ty {
+        match assoc_ty {
+            Some(assoc_ty) => {
+                let mut parameters = vec![inner_ty];
+                parameters.extend(params.iter().cloned());
+                let parameters = Substs(parameters.into());
+                let projection_ty = ProjectionTy { associated_ty: assoc_ty, parameters };
+                let ty = self.normalize_projection_ty(projection_ty);
+                self.resolve_ty_as_possible(&mut vec![], ty)
+            }
+            None => Ty::Unknown,
+        }
+    }
+
+    fn resolve_into_iter_item(&self) -> Option<TypeAlias> {
+        let trait_ = self.resolve_known_trait(&path::known::std_iter_into_iterator())?;
+        trait_.associated_type_by_name(self.db, &name::known::item_type())
+    }
+
+    fn resolve_ops_try_ok(&self) -> Option<TypeAlias> {
+        let trait_ = self.resolve_known_trait(&path::known::std_ops_try())?;
+        trait_.associated_type_by_name(self.db, &name::known::ok_type())
+    }
+
+    fn resolve_ops_index_output(&self) -> Option<TypeAlias> {
+        self.resolve_lang_trait_output("index")
+    }
+
+    fn resolve_future_output(&self) -> Option<TypeAlias> {
+        self.resolve_lang_trait_output("future_trait")
+    }
+
+    /// Resolves the `Output` associated type of the lang item trait `name`.
+    fn resolve_lang_trait_output(&self, name: &str) -> Option<TypeAlias> {
+        let trait_ = self.resolve_lang_trait(name)?;
+        trait_.associated_type_by_name(self.db, &name::known::output_type())
+    }
+
+    fn resolve_lang_trait(&self, name: &str) -> Option<Trait> {
+        match self.db.lang_item(self.resolver.krate()?, name.into())? {
+            LangItemTarget::Trait(trait_) => Some(trait_),
+            _ => None,
+        }
+    }
+
+    fn resolve_lang_struct(&self, name: &str) -> Option<Struct> {
+        match self.db.lang_item(self.resolver.krate()?, name.into())? {
+            LangItemTarget::Struct(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn resolve_known_trait(&self, path: &Path) -> Option<Trait> {
+        match self.resolver.resolve_path(self.db, path).take_types()? {
+            Resolution::Def(ModuleDef::Trait(trait_)) => Some(trait_),
+            _ => None,
+        }
+    }
+
+    fn resolve_known_enum(&self, path: &Path) -> Option<Enum> {
+        match self.resolver.resolve_path(self.db, path).take_types()? {
+            Resolution::Def(ModuleDef::Enum(e)) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Checks whether `actual` may coerce to `expected`. Coercions are not
+/// implemented yet, so we can't tell whether there is an actual type mismatch
+/// between such types.
+fn may_coerce(actual: &Ty, expected: &Ty) -> bool {
+    let (actual_ctor, expected_ctor) = match (actual, expected) {
+        (Ty::Apply(actual), Ty::Apply(expected)) => (actual.ctor, expected.ctor),
+        // type parameters and projections which we failed to normalize
+        _ => return true,
+    };
+    match (actual_ctor, expected_ctor) {
+        (TypeCtor::Never, _) | (_, TypeCtor::Never) => true,
+        (TypeCtor::Ref(_), TypeCtor::Ref(_))
+        | (TypeCtor::Ref(_), TypeCtor::RawPtr(_))
+        | (TypeCtor::RawPtr(_), TypeCtor::RawPtr(_)) => true,
+        (TypeCtor::FnDef(_), TypeCtor::FnPtr { .. })
+        | (TypeCtor::Closure { .. }, TypeCtor::FnPtr { .. }) => true,
+        // unsizing, e.g. `Box<T>` to `Box<dyn Trait>`
+        (TypeCtor::Adt(_), TypeCtor::Adt(_)) => {
+            let mut contains_dyn = false;
+            expected.walk(&mut |ty| {
+                if let Ty::Dyn(_) = ty {
+                    contains_dyn = true;
+                }
+            });
+            contains_dyn
+        }
+        _ => false,
+    }
 }
 
 /// The ID of a type variable.
@@ -1407,26 +1991,72 @@ impl Expectation {
 }
 
 mod diagnostics {
-    use crate::{expr::ExprId, diagnostics::{DiagnosticSink, NoSuchField}, HirDatabase, Function};
+    use crate::{
+        expr::ExprId,
+        diagnostics::{
+            DiagnosticSink, MismatchedArgCount, NoSuchField, TypeMismatch, UnresolvedMethodCall,
+        },
+        HirDatabase, Function, Name,
+        ty::Ty,
+    };
 
     #[derive(Debug, PartialEq, Eq, Clone)]
     pub(super) enum InferenceDiagnostic {
         NoSuchField { expr: ExprId, field: usize },
+        TypeMismatch { expr: ExprId, expected: Ty, actual: Ty },
+        MismatchedArgCount { expr: ExprId, expected: usize, found: usize },
+        UnresolvedMethodCall { expr: ExprId, method_name: Name, receiver_ty: Ty },
     }
 
     impl InferenceDiagnostic {
+        pub(super) fn walk_types_mut(&mut self, f: &mut impl FnMut(&mut Ty)) {
+            match self {
+                InferenceDiagnostic::TypeMismatch { expected, actual, .. } => {
+                    f(expected);
+                    f(actual);
+                }
+                InferenceDiagnostic::UnresolvedMethodCall { receiver_ty, .. } => f(receiver_ty),
+                InferenceDiagnostic::NoSuchField { .. }
+                | InferenceDiagnostic::MismatchedArgCount { .. } => {}
+            }
+        }
+
         pub(super) fn add_to(
             &self,
             db: &impl HirDatabase,
             owner: Function,
             sink: &mut DiagnosticSink,
         ) {
+            let (file, _) = owner.source(db);
+            let source_map = owner.body_source_map(db);
             match self {
                 InferenceDiagnostic::NoSuchField { expr, field } => {
-                    let (file, _) = owner.source(db);
-                    let field = owner.body_source_map(db).field_syntax(*expr, *field);
+                    let field = source_map.field_syntax(*expr, *field);
                     sink.push(NoSuchField { file, field })
                 }
+                InferenceDiagnostic::TypeMismatch { expr, expected, actual } => {
+                    // expressions from macro expansions have no source
+                    if let Some(expr) = source_map.expr_syntax(*expr) {
+                        let (expected, actual) = (expected.clone(), actual.clone());
+                        sink.push(TypeMismatch { file, expr, expected, actual })
+                    }
+                }
+                InferenceDiagnostic::MismatchedArgCount { expr, expected, found } => {
+                    if let Some(call_expr) = source_map.expr_syntax(*expr) {
+                        let (expected, found) = (*expected, *found);
+                        sink.push(MismatchedArgCount { file, call_expr, expected, found })
+                    }
+                }
+                InferenceDiagnostic::UnresolvedMethodCall { expr, method_name, receiver_ty } => {
+                    if let Some(call_expr) = source_map.expr_syntax(*expr) {
+                        sink.push(UnresolvedMethodCall {
+                            file,
+                            call_expr,
+                            method_name: method_name.clone(),
+                            receiver_ty: receiver_ty.clone(),
+                        })
+                    }
+                }
             }
         }
     }
@@ -1,7 +1,7 @@
 //! Unification and canonicalization logic.
 
 use crate::db::HirDatabase;
-use crate::ty::{Ty, Canonical, TraitRef, InferTy};
+use crate::ty::{Ty, Canonical, TraitRef, InferTy, ProjectionTy, traits::ProjectionPredicate};
 use super::InferenceContext;
 
 impl<'a, D: HirDatabase> InferenceContext<'a, D> {
@@ -43,31 +43,36 @@ where
     }
 
     fn do_canonicalize_ty(&mut self, ty: Ty) -> Ty {
-        ty.fold(&mut |ty| match ty {
-            Ty::Infer(tv) => {
-                let inner = tv.to_inner();
-                if self.var_stack.contains(&inner) {
-                    // recursive type
-                    return tv.fallback_value();
+        ty.fold_binders(
+            &mut |ty, binders| match ty {
+                Ty::Infer(tv) => {
+                    let inner = tv.to_inner();
+                    if self.var_stack.contains(&inner) {
+                        // recursive type
+                        return tv.fallback_value();
+                    }
+                    if let Some(known_ty) =
+                        self.ctx.var_unification_table.probe_value(inner).known()
+                    {
+                        self.var_stack.push(inner);
+                        let result = self.do_canonicalize_ty(known_ty.clone());
+                        self.var_stack.pop();
+                        result.shift_bound_vars(binders as u32)
+                    } else {
+                        let root = self.ctx.var_unification_table.find(inner);
+                        let free_var = match tv {
+                            InferTy::TypeVar(_) => InferTy::TypeVar(root),
+                            InferTy::IntVar(_) => InferTy::IntVar(root),
+                            InferTy::FloatVar(_) => InferTy::FloatVar(root),
+                        };
+                        let position = self.add(free_var);
+                        Ty::Bound((position + binders) as u32)
+                    }
                 }
-                if let Some(known_ty) = self.ctx.var_unification_table.probe_value(inner).known() {
-                    self.var_stack.push(inner);
-                    let result = self.do_canonicalize_ty(known_ty.clone());
-                    self.var_stack.pop();
-                    result
-                } else {
-                    let root = self.ctx.var_unification_table.find(inner);
-                    let free_var = match tv {
-                        InferTy::TypeVar(_) => InferTy::TypeVar(root),
-                        InferTy::IntVar(_) => InferTy::IntVar(root),
-                        InferTy::FloatVar(_) => InferTy::FloatVar(root),
-                    };
-                    let position = self.add(free_var);
-                    Ty::Bound(position as u32)
-                }
-            }
-            _ => ty,
-        })
+                _ => ty,
+            },
+            0,
+        )
     }
 
     fn do_canonicalize_trait_ref(&mut self, trait_ref: TraitRef) -> TraitRef {
@@ -79,6 +84,25 @@ where
         TraitRef { trait_: trait_ref.trait_, substs: substs.into() }
     }
 
+    fn do_canonicalize_projection_ty(&mut self, projection_ty: ProjectionTy) -> ProjectionTy {
+        let params = projection_ty
+            .parameters
+            .iter()
+            .map(|ty| self.do_canonicalize_ty(ty.clone()))
+            .collect::<Vec<_>>();
+        ProjectionTy { associated_ty: projection_ty.associated_ty, parameters: params.into() }
+    }
+
+    fn do_canonicalize_projection_predicate(
+        &mut self,
+        projection: ProjectionPredicate,
+    ) -> ProjectionPredicate {
+        let ty = self.do_canonicalize_ty(projection.ty);
+        let projection_ty = self.do_canonicalize_projection_ty(projection.projection_ty);
+
+        ProjectionPredicate { ty, projection_ty }
+    }
+
     fn into_canonicalized<T>(self, result: T) -> Canonicalized<T> {
         Canonicalized {
             value: Canonical { value: result, num_vars: self.free_vars.len() },
@@ -95,20 +119,31 @@ where
         let result = self.do_canonicalize_trait_ref(trait_ref);
         self.into_canonicalized(result)
     }
+
+    pub fn canonicalize_projection(
+        mut self,
+        projection: ProjectionPredicate,
+    ) -> Canonicalized<ProjectionPredicate> {
+        let result = self.do_canonicalize_projection_predicate(projection);
+        self.into_canonicalized(result)
+    }
 }
 
 impl<T> Canonicalized<T> {
     pub fn decanonicalize_ty(&self, ty: Ty) -> Ty {
-        ty.fold(&mut |ty| match ty {
-            Ty::Bound(idx) => {
-                if (idx as usize) < self.free_vars.len() {
-                    Ty::Infer(self.free_vars[idx as usize].clone())
-                } else {
-                    Ty::Bound(idx)
+        ty.fold_binders(
+            &mut |ty, binders| match ty {
+                Ty::Bound(idx) => {
+                    if idx as usize >= binders && (idx as usize - binders) < self.free_vars.len() {
+                        Ty::Infer(self.free_vars[idx as usize - binders].clone())
+                    } else {
+                        Ty::Bound(idx)
+                    }
                 }
-            }
-            ty => ty,
-        })
+                ty => ty,
+            },
+            0,
+        )
     }
 
     pub fn apply_solution(
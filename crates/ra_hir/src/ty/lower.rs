@@ -10,19 +10,22 @@ use std::iter;
 
 use crate::{
     Function, Struct, StructField, Enum, EnumVariant, Path, ModuleDef, TypeAlias, Const, Static,
-    HirDatabase,
-    type_ref::TypeRef,
+    HirDatabase, Name,
+    type_ref::{TypeRef, TypeBound},
     name::KnownName,
     nameres::Namespace,
     resolve::{Resolver, Resolution},
-    path::{PathSegment, GenericArg},
+    path::{PathSegment, GenericArg, PathKind},
     generics::{HasGenericParams},
     adt::VariantDef,
     Trait,
-    generics::{WherePredicate, GenericDef},
+    generics::GenericDef,
     ty::AdtDef,
 };
-use super::{Ty, primitive, FnSig, Substs, TypeCtor, TraitRef, GenericPredicate};
+use super::{
+    Ty, primitive, FnSig, Substs, TypeCtor, TraitRef, GenericPredicate, ProjectionTy,
+    traits::ProjectionPredicate,
+};
 
 impl Ty {
     pub(crate) fn from_hir(db: &impl HirDatabase, resolver: &Resolver, type_ref: &TypeRef) -> Self {
@@ -60,6 +63,14 @@ impl Ty {
                 let sig = Substs(inner_tys.into());
                 Ty::apply(TypeCtor::FnPtr { num_args: sig.len() as u16 - 1 }, sig)
             }
+            TypeRef::DynTrait(bounds) => {
+                let predicates = GenericPredicate::from_type_bounds(db, resolver, bounds);
+                Ty::Dyn(predicates)
+            }
+            TypeRef::ImplTrait(bounds) => {
+                let predicates = GenericPredicate::from_type_bounds(db, resolver, bounds);
+                Ty::Opaque(predicates)
+            }
             TypeRef::Error => Ty::Unknown,
         }
     }
@@ -81,8 +92,35 @@ impl Ty {
             }
         }
 
+        if let Some(ty) = Ty::from_type_relative_path(db, resolver, path) {
+            return ty;
+        }
+
         // Resolve the path (in type namespace)
-        let resolution = resolver.resolve_path(db, path).take_types();
+        let (resolution, remaining_index) = resolver.resolve_path_segments(db, path).into_inner();
+        let resolution = resolution.take_types();
+
+        if let Some(remaining_index) = remaining_index {
+            // The only partially resolved type paths we support are associated
+            // types of traits, i.e. `Trait::Item` or `<T as Trait>::Item`
+            return match resolution {
+                Some(Resolution::Def(ModuleDef::Trait(trait_)))
+                    if remaining_index == path.segments.len() - 1 =>
+                {
+                    let trait_segment = &path.segments[remaining_index - 1];
+                    let substs = substs_from_path_segment(
+                        db,
+                        resolver,
+                        trait_segment,
+                        Some(trait_.into()),
+                        true,
+                    );
+                    let trait_ref = TraitRef { trait_, substs };
+                    Ty::projection(db, trait_ref, &path.segments[remaining_index].name)
+                }
+                _ => Ty::Unknown,
+            };
+        }
 
         let def = match resolution {
             Some(Resolution::Def(def)) => def,
@@ -115,6 +153,43 @@ impl Ty {
         ty.subst(&substs)
     }
 
+    /// Lowers a path like `T::Item` or `Self::Item`, where `T` is a type
+    /// parameter (or `Self`), to the projection `<T as Trait>::Item` for the
+    /// trait bound of `T` that has an associated type with that name.
+    fn from_type_relative_path(
+        db: &impl HirDatabase,
+        resolver: &Resolver,
+        path: &Path,
+    ) -> Option<Ty> {
+        if path.kind != PathKind::Plain || path.segments.len() != 2 {
+            return None;
+        }
+        let (param_segment, assoc_segment) = (&path.segments[0], &path.segments[1]);
+        let trait_ref = match resolver.resolve_name(db, &param_segment.name).take_types()? {
+            Resolution::GenericParam(idx) => {
+                let self_ty = Ty::Param { idx, name: param_segment.name.clone() };
+                trait_bound_with_assoc_ty(db, resolver, self_ty, &assoc_segment.name)
+            }
+            Resolution::SelfType(impl_block) => impl_block.target_trait_ref(db),
+            Resolution::Def(_) | Resolution::LocalBinding(_) => return None,
+        };
+        Some(match trait_ref {
+            Some(trait_ref) => Ty::projection(db, trait_ref, &assoc_segment.name),
+            None => Ty::Unknown,
+        })
+    }
+
+    /// Builds the projection type for the associated type `name` of the trait
+    /// in `trait_ref`.
+    fn projection(db: &impl HirDatabase, trait_ref: TraitRef, name: &Name) -> Ty {
+        match trait_ref.trait_.associated_type_by_name(db, name) {
+            Some(associated_ty) => {
+                Ty::Projection(ProjectionTy { associated_ty, parameters: trait_ref.substs })
+            }
+            None => Ty::Unknown,
+        }
+    }
+
     pub(super) fn substs_from_path_segment(
         db: &impl HirDatabase,
         resolver: &Resolver,
@@ -169,6 +244,40 @@ impl Ty {
     }
 }
 
+/// Finds the trait bound of the type parameter `self_ty` for a trait that has
+/// an associated type called `name`.
+fn trait_bound_with_assoc_ty(
+    db: &impl HirDatabase,
+    resolver: &Resolver,
+    self_ty: Ty,
+    name: &Name,
+) -> Option<TraitRef> {
+    let (idx, param_name) = match &self_ty {
+        Ty::Param { idx, name } => (*idx, name),
+        _ => return None,
+    };
+    // the `Self` parameter of a trait implicitly implements the trait
+    if let Some(GenericDef::Trait(trait_)) = resolver.generic_def_of_param(idx) {
+        if param_name.as_known_name() == Some(KnownName::SelfType) {
+            return Some(TraitRef::for_trait(db, trait_));
+        }
+    }
+    resolver.where_predicates_in_scope().find_map(|pred| {
+        match &pred.type_ref {
+            TypeRef::Path(path) if path.as_ident() == Some(param_name) => {}
+            _ => return None,
+        }
+        // check the associated type before lowering the whole trait ref, to
+        // avoid lowering unrelated bounds (which may refer to `T::Item` again)
+        let trait_ = match resolver.resolve_path(db, &pred.trait_ref).take_types()? {
+            Resolution::Def(ModuleDef::Trait(trait_)) => trait_,
+            _ => return None,
+        };
+        trait_.associated_type_by_name(db, name)?;
+        TraitRef::from_path(db, resolver, &pred.trait_ref, Some(self_ty.clone()))
+    })
+}
+
 pub(super) fn substs_from_path_segment(
     db: &impl HirDatabase,
     resolver: &Resolver,
@@ -181,18 +290,27 @@ pub(super) fn substs_from_path_segment(
 
     let parent_param_count = def_generics.count_parent_params();
     substs.extend(iter::repeat(Ty::Unknown).take(parent_param_count));
+    let explicit_self_arg = segment
+        .args_and_bindings
+        .as_ref()
+        .filter(|generic_args| generic_args.has_self_type)
+        .and_then(|generic_args| generic_args.args.first());
     if add_self_param {
         // FIXME this add_self_param argument is kind of a hack: Traits have the
-        // Self type as an implicit first type parameter, but it can't be
-        // actually provided in the type arguments
-        // (well, actually sometimes it can, in the form of type-relative paths: `<Foo as Default>::default()`)
-        substs.push(Ty::Unknown);
+        // Self type as an implicit first type parameter, but it can only be
+        // provided in type-relative paths like `<Foo as Default>::default()`
+        let self_ty = match explicit_self_arg {
+            Some(GenericArg::Type(type_ref)) => Ty::from_hir(db, resolver, type_ref),
+            None => Ty::Unknown,
+        };
+        substs.push(self_ty);
     }
     if let Some(generic_args) = &segment.args_and_bindings {
         // if args are provided, it should be all of them, but we can't rely on that
         let self_param_correction = if add_self_param { 1 } else { 0 };
         let param_count = def_generics.params.len() - self_param_correction;
-        for arg in generic_args.args.iter().take(param_count) {
+        let skip = if generic_args.has_self_type { 1 } else { 0 };
+        for arg in generic_args.args.iter().skip(skip).take(param_count) {
             match arg {
                 GenericArg::Type(type_ref) => {
                     let ty = Ty::from_hir(db, resolver, type_ref);
@@ -271,14 +389,59 @@ impl TraitRef {
         let substs = Substs::identity(&trait_.generic_params(db));
         TraitRef { trait_, substs }
     }
+}
+
+impl GenericPredicate {
+    /// Lowers the bounds of a `dyn Trait` or `impl Trait` type. The `Self`
+    /// type of the predicates is `Ty::Bound(0)`.
+    pub(crate) fn from_type_bounds(
+        db: &impl HirDatabase,
+        resolver: &Resolver,
+        bounds: &[TypeBound],
+    ) -> Arc<[GenericPredicate]> {
+        let self_ty = Ty::Bound(0);
+        bounds
+            .iter()
+            .flat_map(|bound| match bound.as_path() {
+                Some(path) => {
+                    GenericPredicate::from_bound_path(db, resolver, path, self_ty.clone())
+                }
+                None => vec![GenericPredicate::Error],
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
 
-    pub(crate) fn for_where_predicate(
+    /// Lowers a bound like `Iterator<Item = Foo>` on `self_ty`: the trait
+    /// bound itself and a projection predicate for each associated type
+    /// binding.
+    fn from_bound_path(
         db: &impl HirDatabase,
         resolver: &Resolver,
-        pred: &WherePredicate,
-    ) -> Option<TraitRef> {
-        let self_ty = Ty::from_hir(db, resolver, &pred.type_ref);
-        TraitRef::from_path(db, resolver, &pred.trait_ref, Some(self_ty))
+        path: &Path,
+        self_ty: Ty,
+    ) -> Vec<GenericPredicate> {
+        let trait_ref = match TraitRef::from_path(db, resolver, path, Some(self_ty)) {
+            Some(trait_ref) => trait_ref,
+            None => return vec![GenericPredicate::Error],
+        };
+        let bindings = path
+            .segments
+            .last()
+            .and_then(|segment| segment.args_and_bindings.as_ref())
+            .into_iter()
+            .flat_map(|args| args.bindings.iter())
+            .filter_map(|(name, type_ref)| {
+                let associated_ty = trait_ref.trait_.associated_type_by_name(db, name)?;
+                let projection_ty =
+                    ProjectionTy { associated_ty, parameters: trait_ref.substs.clone() };
+                let ty = Ty::from_hir(db, resolver, type_ref);
+                Some(GenericPredicate::Projection(ProjectionPredicate { projection_ty, ty }))
+            })
+            .collect::<Vec<_>>();
+        let mut predicates = vec![GenericPredicate::Implemented(trait_ref)];
+        predicates.extend(bindings);
+        predicates
     }
 }
 
@@ -338,9 +501,9 @@ pub(crate) fn generic_predicates(
     let predicates = generic_params
         .where_predicates
         .iter()
-        .map(|pred| {
-            TraitRef::for_where_predicate(db, &resolver, pred)
-                .map_or(GenericPredicate::Error, GenericPredicate::Implemented)
+        .flat_map(|pred| {
+            let self_ty = Ty::from_hir(db, &resolver, &pred.type_ref);
+            GenericPredicate::from_bound_path(db, &resolver, &pred.trait_ref, self_ty)
         })
         .collect::<Vec<_>>();
     predicates.into()
@@ -368,7 +531,7 @@ fn fn_sig_for_fn(db: &impl HirDatabase, def: Function) -> FnSig {
     let params =
         signature.params().iter().map(|tr| Ty::from_hir(db, &resolver, tr)).collect::<Vec<_>>();
     let ret = Ty::from_hir(db, &resolver, signature.ret_type());
-    FnSig::from_params_and_return(params, ret)
+    FnSig::from_params_and_return(params, ret, signature.is_variadic())
 }
 
 /// Build the declared type of a function. This should not need to look at the
@@ -407,7 +570,7 @@ fn fn_sig_for_struct_constructor(db: &impl HirDatabase, def: Struct) -> FnSig {
         .map(|(_, field)| Ty::from_hir(db, &resolver, &field.type_ref))
         .collect::<Vec<_>>();
     let ret = type_for_adt(db, def);
-    FnSig::from_params_and_return(params, ret)
+    FnSig::from_params_and_return(params, ret, false)
 }
 
 /// Build the type of a tuple struct constructor.
@@ -435,7 +598,7 @@ fn fn_sig_for_enum_variant_constructor(db: &impl HirDatabase, def: EnumVariant)
     let generics = def.parent_enum(db).generic_params(db);
     let substs = Substs::identity(&generics);
     let ret = type_for_adt(db, def.parent_enum(db)).subst(&substs);
-    FnSig::from_params_and_return(params, ret)
+    FnSig::from_params_and_return(params, ret, false)
 }
 
 /// Build the type of a tuple enum variant constructor.
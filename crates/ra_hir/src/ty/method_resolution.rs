@@ -16,7 +16,7 @@ use crate::{
     generics::HasGenericParams,
     ty::primitive::{UncertainIntTy, UncertainFloatTy}
 };
-use super::{TraitRef, Canonical};
+use super::{TraitRef, Canonical, autoderef};
 
 /// This is used as a key for indexing impls.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -140,7 +140,17 @@ pub(crate) fn lookup_method(
     name: &Name,
     resolver: &Resolver,
 ) -> Option<(Ty, Function)> {
-    iterate_method_candidates(ty, db, resolver, Some(name), |ty, f| Some((ty.clone(), f)))
+    iterate_method_candidates(ty, db, resolver, Some(name), LookupMode::MethodCall, |ty, f| {
+        Some((ty.clone(), f))
+    })
+}
+
+/// Whether we're looking up a method for a method call `x.foo()`, or any
+/// associated function for a path like `Foo::foo`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LookupMode {
+    MethodCall,
+    Path,
 }
 
 // This would be nicer if it just returned an iterator, but that runs into
@@ -150,8 +160,18 @@ pub(crate) fn iterate_method_candidates<T>(
     db: &impl HirDatabase,
     resolver: &Resolver,
     name: Option<&Name>,
+    mode: LookupMode,
     mut callback: impl FnMut(&Ty, Function) -> Option<T>,
 ) -> Option<T> {
+    let krate = resolver.krate()?;
+    if mode == LookupMode::Path {
+        // There's no autoderef for paths: `Foo::foo` only finds functions of `Foo`.
+        if let Some(result) = iterate_inherent_methods(ty, db, name, mode, krate, &mut callback) {
+            return Some(result);
+        }
+        return iterate_trait_method_candidates(ty, db, resolver, name, mode, &mut callback);
+    }
+
     // For method calls, rust first does any number of autoderef, and then one
     // autoref (i.e. when the method takes &self or &mut self). We just ignore
     // the autoref currently -- when we find a method matching the given name,
@@ -161,15 +181,14 @@ pub(crate) fn iterate_method_candidates<T>(
     // find in the end takes &self, we still do the autoderef step (just as
     // rustc does an autoderef and then autoref again).
 
-    let krate = resolver.krate()?;
-    for derefed_ty in ty.value.clone().autoderef(db) {
-        let derefed_ty = Canonical { value: derefed_ty, num_vars: ty.num_vars };
-        if let Some(result) = iterate_inherent_methods(&derefed_ty, db, name, krate, &mut callback)
+    for derefed_ty in autoderef::autoderef(db, resolver, ty.clone()) {
+        if let Some(result) =
+            iterate_inherent_methods(&derefed_ty, db, name, mode, krate, &mut callback)
         {
             return Some(result);
         }
         if let Some(result) =
-            iterate_trait_method_candidates(&derefed_ty, db, resolver, name, &mut callback)
+            iterate_trait_method_candidates(&derefed_ty, db, resolver, name, mode, &mut callback)
         {
             return Some(result);
         }
@@ -182,20 +201,29 @@ fn iterate_trait_method_candidates<T>(
     db: &impl HirDatabase,
     resolver: &Resolver,
     name: Option<&Name>,
+    mode: LookupMode,
     mut callback: impl FnMut(&Ty, Function) -> Option<T>,
 ) -> Option<T> {
     let krate = resolver.krate()?;
-    'traits: for t in resolver.traits_in_scope(db) {
+    // The traits from the bounds of `dyn Trait` / `impl Trait` types are
+    // always implemented and don't need to be in scope.
+    let inherent_traits = ty.value.inherent_traits();
+    let in_scope =
+        resolver.traits_in_scope(db).into_iter().filter(|t| !inherent_traits.contains(t));
+    let traits = inherent_traits.iter().map(|&t| (t, true)).chain(in_scope.map(|t| (t, false)));
+    'traits: for (t, is_inherent) in traits {
         let data = t.trait_data(db);
         // we'll be lazy about checking whether the type implements the
         // trait, but if we find out it doesn't, we'll skip the rest of the
         // iteration
-        let mut known_implemented = false;
+        let mut known_implemented = is_inherent;
         for item in data.items() {
             match item {
                 &TraitItem::Function(m) => {
                     let sig = m.signature(db);
-                    if name.map_or(true, |name| sig.name() == name) && sig.has_self_param() {
+                    if name.map_or(true, |name| sig.name() == name)
+                        && (sig.has_self_param() || mode == LookupMode::Path)
+                    {
                         if !known_implemented {
                             let trait_ref = canonical_trait_ref(db, t, ty.clone());
                             if db.implements(krate, trait_ref).is_none() {
@@ -219,6 +247,7 @@ fn iterate_inherent_methods<T>(
     ty: &Canonical<Ty>,
     db: &impl HirDatabase,
     name: Option<&Name>,
+    mode: LookupMode,
     krate: Crate,
     mut callback: impl FnMut(&Ty, Function) -> Option<T>,
 ) -> Option<T> {
@@ -233,7 +262,9 @@ fn iterate_inherent_methods<T>(
             match item {
                 ImplItem::Method(f) => {
                     let sig = f.signature(db);
-                    if name.map_or(true, |name| sig.name() == name) && sig.has_self_param() {
+                    if name.map_or(true, |name| sig.name() == name)
+                        && (sig.has_self_param() || mode == LookupMode::Path)
+                    {
                         if let Some(result) = callback(&ty.value, f) {
                             return Some(result);
                         }
@@ -1,4 +1,4 @@
-use crate::{ ty::ApplicationTy, expr::BinaryOp};
+use crate::{ ty::ApplicationTy, expr::{BinaryOp, UnaryOp}};
 use super::{Ty, TypeCtor, InferTy};
 
 pub(super) fn binary_op_return_ty(op: BinaryOp, rhs_ty: Ty) -> Ty {
@@ -92,3 +92,70 @@ pub(super) fn binary_op_rhs_expectation(op: BinaryOp, lhs_ty: Ty) -> Ty {
         _ => Ty::Unknown,
     }
 }
+
+/// Whether an operator applied to `ty` is resolved through the `std::ops`
+/// traits, rather than being one of the builtin operators.
+pub(super) fn is_overloaded_operand(ty: &Ty) -> bool {
+    match ty {
+        Ty::Apply(ApplicationTy { ctor, parameters }) => match ctor {
+            TypeCtor::Int(..) | TypeCtor::Float(..) | TypeCtor::Bool => false,
+            TypeCtor::Ref(_) => is_overloaded_operand(parameters.as_single()),
+            _ => true,
+        },
+        // the builtin rules still make a guess based on the other operand
+        Ty::Infer(..) | Ty::Unknown => false,
+        _ => true,
+    }
+}
+
+/// The primitive `ty` refers to, if it is a reference operand of a builtin
+/// operator: these operators are forwarded to the primitives, e.g. `&i32 +
+/// &i32` is an `i32`.
+pub(super) fn deref_operand(ty: &Ty) -> Option<Ty> {
+    match ty {
+        Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(_), parameters }) => {
+            Some(parameters.as_single().clone())
+        }
+        _ => None,
+    }
+}
+
+/// The lang item of the `std::ops` trait behind a binary operator, like `add`
+/// for `Add` or `add_assign` for `AddAssign`. Comparisons and the lazy boolean
+/// operators have no such trait here.
+pub(super) fn binary_op_lang_item(op: BinaryOp) -> Option<&'static str> {
+    let lang_item = match op {
+        BinaryOp::Addition => "add",
+        BinaryOp::Subtraction => "sub",
+        BinaryOp::Multiplication => "mul",
+        BinaryOp::Division => "div",
+        BinaryOp::Remainder => "rem",
+        BinaryOp::LeftShift => "shl",
+        BinaryOp::RightShift => "shr",
+        BinaryOp::BitwiseAnd => "bitand",
+        BinaryOp::BitwiseOr => "bitor",
+        BinaryOp::BitwiseXor => "bitxor",
+        BinaryOp::AddAssign => "add_assign",
+        BinaryOp::SubAssign => "sub_assign",
+        BinaryOp::MulAssign => "mul_assign",
+        BinaryOp::DivAssign => "div_assign",
+        BinaryOp::RemAssign => "rem_assign",
+        BinaryOp::ShlAssign => "shl_assign",
+        BinaryOp::ShrAssign => "shr_assign",
+        BinaryOp::BitAndAssign => "bitand_assign",
+        BinaryOp::BitOrAssign => "bitor_assign",
+        BinaryOp::BitXorAssign => "bitxor_assign",
+        _ => return None,
+    };
+    Some(lang_item)
+}
+
+/// The lang item of `Neg` or `Not` for `-` and `!`.
+pub(super) fn unary_op_lang_item(op: UnaryOp) -> Option<&'static str> {
+    match op {
+        UnaryOp::Neg => Some("neg"),
+        UnaryOp::Not => Some("not"),
+        // overloaded through `Deref`, which autoderef handles
+        UnaryOp::Deref => None,
+    }
+}
@@ -829,12 +829,13 @@ fn test(x: &i32) {
 [144; 145) 'e': {unknown}
 [158; 205) 'if let...     }': ()
 [165; 170) '[val]': {unknown}
+[166; 169) 'val': {unknown}
 [173; 176) 'opt': {unknown}
 [177; 205) '{     ...     }': ()
 [191; 192) 'h': {unknown}
 [195; 198) 'val': {unknown}
-[215; 221) 'lambda': {unknown}
-[224; 256) '|a: u6...b; c }': {unknown}
+[215; 221) 'lambda': |u64, u64, i32| -> i32
+[224; 256) '|a: u6...b; c }': |u64, u64, i32| -> i32
 [225; 226) 'a': u64
 [233; 234) 'b': u64
 [236; 237) 'c': i32
@@ -1955,6 +1956,30 @@ mod foo {
     assert_eq!("i128", type_at_pos(&db, pos));
 }
 
+#[test]
+fn infer_cfg_attributes_in_body() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+struct S {
+    #[cfg(feature = "foo")] a: u8,
+    #[cfg(not(feature = "foo"))] a: u32,
+}
+
+fn test() {
+    #[cfg(test)] let x = 1u16;
+    #[cfg(not(test))] let x = 1u64;
+    let s = S { #[cfg(test)] a: 1, #[cfg(not(test))] a: 2 };
+    (x, s.a)<|>;
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", [], cfg = { "test", "feature" = "foo" }),
+    });
+    assert_eq!("(u16, u8)", type_at_pos(&db, pos));
+}
+
 #[test]
 fn infer_const() {
     assert_snapshot_matches!(
@@ -2429,11 +2454,11 @@ fn test() -> u64 {
 [54; 55) 'a': S
 [58; 59) 'S': S(fn(u32) -> u64) -> S
 [58; 68) 'S(|i| 2*i)': S
-[60; 67) '|i| 2*i': fn(u32) -> u64
-[61; 62) 'i': i32
-[64; 65) '2': i32
-[64; 67) '2*i': i32
-[66; 67) 'i': i32
+[60; 67) '|i| 2*i': |u32| -> u64
+[61; 62) 'i': u32
+[64; 65) '2': u32
+[64; 67) '2*i': u32
+[66; 67) 'i': u32
 [78; 79) 'b': u64
 [82; 83) 'a': S
 [82; 85) 'a.0': fn(u32) -> u64
@@ -2470,6 +2495,130 @@ fn main() {
     );
 }
 
+#[test]
+fn infer_builtin_macros() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+#[rustc_builtin_macro]
+macro_rules! line { () => {} }
+#[rustc_builtin_macro]
+macro_rules! file { () => {} }
+#[rustc_builtin_macro]
+macro_rules! stringify { ($($t:tt)*) => {} }
+#[rustc_builtin_macro]
+macro_rules! concat { ($($e:expr),*) => {} }
+#[rustc_builtin_macro]
+macro_rules! env { ($name:expr) => {} }
+
+fn test() {
+    let l = line!();
+    let f = file!();
+    let s = stringify!(a + b);
+    let c = concat!("a", 1, env!("CARGO_PKG_NAME"));
+    (l, f, s, c)<|>;
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", [], env = { "CARGO_PKG_NAME": "main" }),
+    });
+    assert_eq!("(u32, &str, &str, &str)", type_at_pos(&db, pos));
+}
+
+#[test]
+fn infer_foreign_items() {
+    let t = type_at(
+        r#"
+//- /main.rs
+mod ffi {
+    extern "C" {
+        pub fn printf(format: *const u8, ...) -> i32;
+        pub static errno: u64;
+    }
+}
+
+fn test() {
+    let n = unsafe { ffi::printf(0 as *const u8, 1, 2.0) };
+    (n, unsafe { ffi::errno })<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(i32, u64)");
+}
+
+#[test]
+fn infer_include_in_expression_position() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+#[rustc_builtin_macro]
+macro_rules! include { ($file:expr) => {} }
+
+fn test() {
+    let t = include!("data.rs");
+    t<|>;
+}
+
+//- /data.rs
+(1u8, "a")
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", []),
+    });
+    assert_eq!("(u8, &str)", type_at_pos(&db, pos));
+}
+
+#[test]
+fn infer_format_args() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+#[rustc_builtin_macro]
+macro_rules! format_args { ($fmt:expr, $($args:tt)*) => {} }
+
+fn test() {
+    let a = format_args!("{} {x}", 1, x = 2);
+    a<|>;
+}
+
+//- /std.rs
+pub mod fmt {
+    pub struct Formatter;
+    pub struct Error;
+    pub type Result = crate::result::Result<(), Error>;
+
+    pub trait Display {
+        fn fmt(&self, f: &mut Formatter) -> Result;
+    }
+
+    pub struct ArgumentV1;
+    impl ArgumentV1 {
+        pub fn new<T>(x: &T, f: fn(&T, &mut Formatter) -> Result) -> ArgumentV1 {}
+    }
+
+    pub struct Arguments;
+    impl Arguments {
+        pub fn new_v1(pieces: &[&str], args: &[ArgumentV1]) -> Arguments {}
+    }
+}
+
+pub mod result {
+    pub enum Result<T, E> {
+        Ok(T),
+        Err(E),
+    }
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+    assert_eq!("Arguments", type_at_pos(&db, pos));
+}
+
 #[ignore]
 #[test]
 fn method_resolution_trait_before_autoref() {
@@ -2701,132 +2850,864 @@ fn test() { (S {}).method()<|>; }
     assert_eq!(t, "{unknown}");
 }
 
-fn type_at_pos(db: &MockDatabase, pos: FilePosition) -> String {
-    let file = db.parse(pos.file_id);
-    let expr = algo::find_node_at_offset::<ast::Expr>(file.syntax(), pos.offset).unwrap();
-    let analyzer = SourceAnalyzer::new(db, pos.file_id, expr.syntax(), Some(pos.offset));
-    let ty = analyzer.type_of(db, expr).unwrap();
-    ty.display(db).to_string()
+#[test]
+fn infer_closure() {
+    assert_snapshot_matches!(
+        infer(r#"
+fn test() {
+    let f = |x: u32| x;
+    f(1);
+}
+"#),
+        @r###"
+[11; 48) '{     ...(1); }': ()
+[21; 22) 'f': |u32| -> u32
+[25; 35) '|x: u32| x': |u32| -> u32
+[26; 27) 'x': u32
+[34; 35) 'x': u32
+[41; 42) 'f': |u32| -> u32
+[41; 45) 'f(1)': u32
+[43; 44) '1': u32
+"###
+    );
 }
 
-fn type_at(content: &str) -> String {
-    let (db, file_pos) = MockDatabase::with_position(content);
-    type_at_pos(&db, file_pos)
+#[test]
+fn closure_call_infers_param_type() {
+    let t = type_at(
+        r#"
+//- /main.rs
+fn test() {
+    let f = |x| x;
+    f(1u64)<|>;
+}
+"#,
+    );
+    assert_eq!(t, "u64");
 }
 
-fn infer(content: &str) -> String {
-    let (db, _, file_id) = MockDatabase::with_single_file(content);
-    let source_file = db.parse(file_id);
+#[test]
+fn closure_param_type_from_fn_trait_bound() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "fn_once"]
+trait FnOnce<Args> {}
 
-    let mut acc = String::new();
-    acc.push_str("\n");
+struct S;
+impl S { fn foo(&self) -> u128 { 0 } }
 
-    let mut infer_def = |inference_result: Arc<InferenceResult>,
-                         body_source_map: Arc<BodySourceMap>| {
-        let mut types = Vec::new();
+fn call<F: FnOnce(S)>(f: F) {}
 
-        for (pat, ty) in inference_result.type_of_pat.iter() {
-            let syntax_ptr = match body_source_map.pat_syntax(pat) {
-                Some(sp) => sp.either(|it| it.syntax_node_ptr(), |it| it.syntax_node_ptr()),
-                None => continue,
-            };
-            types.push((syntax_ptr, ty));
-        }
+fn test() {
+    call(|x| { x.foo()<|>; });
+}
+"#,
+    );
+    assert_eq!(t, "u128");
+}
 
-        for (expr, ty) in inference_result.type_of_expr.iter() {
-            let syntax_ptr = match body_source_map.expr_syntax(expr) {
-                Some(sp) => sp,
-                None => continue,
-            };
-            types.push((syntax_ptr, ty));
-        }
+#[test]
+fn closure_param_type_from_fn_trait_bound_in_method_call() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "fn_mut"]
+trait FnMut<Args> {}
 
-        // sort ranges for consistency
-        types.sort_by_key(|(ptr, _)| (ptr.range().start(), ptr.range().end()));
-        for (syntax_ptr, ty) in &types {
-            let node = syntax_ptr.to_node(source_file.syntax());
-            let (range, text) = if let Some(self_param) = ast::SelfParam::cast(node) {
-                (self_param.self_kw_token().range(), "self".to_string())
-            } else {
-                (syntax_ptr.range(), node.text().to_string().replace("\n", " "))
-            };
-            write!(acc, "{} '{}': {}\n", range, ellipsize(text, 15), ty.display(&db)).unwrap();
-        }
-    };
+struct S;
+impl S { fn foo(&self) -> u128 { 0 } }
 
-    for node in source_file.syntax().descendants() {
-        if node.kind() == FN_DEF || node.kind() == CONST_DEF || node.kind() == STATIC_DEF {
-            let analyzer = SourceAnalyzer::new(&db, file_id, node, None);
-            infer_def(analyzer.inference_result(), analyzer.body_source_map());
-        }
-    }
+struct Iter;
+impl Iter { fn for_each<F>(self, f: F) where F: FnMut(&S) {} }
 
-    acc.truncate(acc.trim_end().len());
-    acc
+fn test() {
+    Iter.for_each(|x| { x.foo()<|>; });
 }
-
-fn ellipsize(mut text: String, max_len: usize) -> String {
-    if text.len() <= max_len {
-        return text;
-    }
-    let ellipsis = "...";
-    let e_len = ellipsis.len();
-    let mut prefix_len = (max_len - e_len) / 2;
-    while !text.is_char_boundary(prefix_len) {
-        prefix_len += 1;
-    }
-    let mut suffix_len = max_len - e_len - prefix_len;
-    while !text.is_char_boundary(text.len() - suffix_len) {
-        suffix_len += 1;
-    }
-    text.replace_range(prefix_len..text.len() - suffix_len, ellipsis);
-    text
+"#,
+    );
+    assert_eq!(t, "u128");
 }
 
 #[test]
-fn typing_whitespace_inside_a_function_should_not_invalidate_types() {
-    let (mut db, pos) = MockDatabase::with_position(
-        "
-        //- /lib.rs
-        fn foo() -> i32 {
-            <|>1 + 1
-        }
-    ",
+fn infer_project_associated_type() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Iterable {
+    type Item;
+}
+struct S;
+impl Iterable for S { type Item = u32; }
+fn test() {
+    let x: <S as Iterable>::Item;
+    x<|>;
+}
+"#,
     );
-    {
-        let file = db.parse(pos.file_id);
-        let node =
-            algo::find_token_at_offset(file.syntax(), pos.offset).right_biased().unwrap().parent();
-        let events = db.log_executed(|| {
-            SourceAnalyzer::new(&db, pos.file_id, node, None);
-        });
-        assert!(format!("{:?}", events).contains("infer"))
-    }
-
-    let new_text = "
-        fn foo() -> i32 {
-            1
-            +
-            1
-        }
-    "
-    .to_string();
+    assert_eq!(t, "u32");
+}
 
-    db.query_mut(ra_db::FileTextQuery).set(pos.file_id, Arc::new(new_text));
+#[test]
+fn infer_associated_type_in_fn_signature() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Iterable {
+    type Item;
+}
+struct S;
+impl Iterable for S { type Item = u32; }
+fn foo<T: Iterable>(t: T) -> T::Item {}
+fn test() {
+    foo(S)<|>;
+}
+"#,
+    );
+    assert_eq!(t, "u32");
+}
 
-    {
-        let file = db.parse(pos.file_id);
-        let node =
-            algo::find_token_at_offset(file.syntax(), pos.offset).right_biased().unwrap().parent();
-        let events = db.log_executed(|| {
-            SourceAnalyzer::new(&db, pos.file_id, node, None);
-        });
-        assert!(!format!("{:?}", events).contains("infer"), "{:#?}", events)
-    }
+#[test]
+fn infer_associated_type_of_generic_struct() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Iterable {
+    type Item;
+}
+struct Wrapper<T>(T);
+impl<T> Iterable for Wrapper<T> { type Item = T; }
+fn first<I: Iterable>(i: I) -> <I as Iterable>::Item {}
+fn test() {
+    first(Wrapper(1u8))<|>;
+}
+"#,
+    );
+    assert_eq!(t, "u8");
 }
 
 #[test]
-fn no_such_field_diagnostics() {
+fn infer_for_loop() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+use std::collections::Vec;
+
+fn test() {
+    let v = Vec::new();
+    v.push("foo");
+    for x in v {
+        x<|>;
+    }
+}
+
+//- /std.rs
+mod iter {
+    trait IntoIterator {
+        type Item;
+    }
+}
+mod collections {
+    struct Vec<T> {}
+    impl<T> Vec<T> {
+        fn new() -> Self { Vec {} }
+        fn push(&mut self, t: T) { }
+    }
+
+    impl<T> crate::iter::IntoIterator for Vec<T> {
+        type Item = T;
+    }
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+    assert_eq!("&str", type_at_pos(&db, pos));
+}
+
+#[test]
+fn infer_try() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+fn test() {
+    let r: Result<i32, u64> = Result::Ok(1);
+    let v = r?;
+    v<|>;
+}
+
+//- /std.rs
+#[prelude_import] use ops::*;
+mod ops {
+    trait Try {
+        type Ok;
+        type Error;
+    }
+}
+
+#[prelude_import] use result::*;
+mod result {
+    enum Result<O, E> {
+        Ok(O),
+        Err(E)
+    }
+
+    impl<O, E> crate::ops::Try for Result<O, E> {
+        type Ok = O;
+        type Error = E;
+    }
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+    assert_eq!("i32", type_at_pos(&db, pos));
+}
+
+#[test]
+fn infer_ops_index() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "index"]
+trait Index<Idx> {
+    type Output;
+}
+
+struct Bar;
+struct Foo;
+
+impl Index<u32> for Bar {
+    type Output = Foo;
+}
+
+fn test() {
+    let a = Bar;
+    let b = a[1u32];
+    b<|>;
+}
+"#,
+    );
+    assert_eq!(t, "Foo");
+}
+
+#[test]
+fn infer_builtin_index() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "deref"]
+trait Deref {
+    type Target;
+}
+
+struct Chars;
+impl Deref for Chars {
+    type Target = [char];
+}
+
+fn test(a: [u8; 2], s: &[u16], c: Chars) {
+    let t = (a[0], s[1usize], c[2], (&&a)[0]);
+    t<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(u8, u16, char, u8)");
+}
+
+#[test]
+fn infer_ops_overloaded() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "add"]
+trait Add<Rhs> {
+    type Output;
+}
+#[lang = "mul"]
+trait Mul<Rhs> {
+    type Output;
+}
+#[lang = "neg"]
+trait Neg {
+    type Output;
+}
+#[lang = "add_assign"]
+trait AddAssign<Rhs> {}
+
+struct Meters;
+struct Area;
+
+impl Add<Meters> for Meters {
+    type Output = Meters;
+}
+impl Mul<Meters> for Meters {
+    type Output = Area;
+}
+impl Neg for Meters {
+    type Output = Meters;
+}
+impl AddAssign<Meters> for Meters {}
+
+fn test(a: Meters, b: Meters, mut c: Meters) {
+    let t = (a + b, a * b, -a, c += b, 1 + 2u8);
+    t<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(Meters, Area, Meters, (), u8)");
+}
+
+#[test]
+fn infer_ops_on_references_to_primitives() {
+    let t = type_at(
+        r#"
+//- /main.rs
+fn test(a: i32, b: &i32, c: &f64) {
+    let t = (&a + &a, b * 2, a - b, c / c, b < &a);
+    t<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(i32, i32, i32, f64, bool)");
+}
+
+#[test]
+fn infer_ranges() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "RangeFull"]
+struct RangeFull;
+#[lang = "Range"]
+struct Range<Idx> { start: Idx, end: Idx }
+#[lang = "RangeFrom"]
+struct RangeFrom<Idx> { start: Idx }
+#[lang = "RangeTo"]
+struct RangeTo<Idx> { end: Idx }
+#[lang = "RangeInclusiveStruct"]
+struct RangeInclusive<Idx> { start: Idx, end: Idx }
+#[lang = "RangeToInclusive"]
+struct RangeToInclusive<Idx> { end: Idx }
+
+fn test() {
+    let a = ..;
+    let b = 1..;
+    let c = ..2u32;
+    let d = 1..2usize;
+    let e = ..=10;
+    let f = 'a'..='z';
+    let t = (a, b, c, d, e, f);
+    t<|>;
+}
+"#,
+    );
+    assert_eq!(
+        t,
+        "(RangeFull, RangeFrom<i32>, RangeTo<u32>, Range<usize>, RangeToInclusive<i32>, \
+         RangeInclusive<char>)"
+    );
+}
+
+#[test]
+fn infer_await() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "future_trait"]
+trait Future {
+    type Output;
+}
+
+struct IntFuture;
+impl Future for IntFuture {
+    type Output = u64;
+}
+
+fn test() {
+    let r = IntFuture;
+    let v = r.await;
+    v<|>;
+}
+"#,
+    );
+    assert_eq!(t, "u64");
+}
+
+#[test]
+fn infer_box() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "owned_box"]
+struct Box<T>;
+
+fn test() {
+    let x = box 1u8;
+    x<|>;
+}
+"#,
+    );
+    assert_eq!(t, "Box<u8>");
+}
+
+#[test]
+fn infer_loop_break_with_value() {
+    let t = type_at(
+        r#"
+//- /main.rs
+fn test() {
+    let x = loop { break 1u8; };
+    let y = 'outer: loop {
+        loop { break 'outer 2u16; }
+    };
+    let z = loop {
+        let f = || { loop { break; } };
+        break;
+    };
+    (x, y, z)<|>
+}
+"#,
+    );
+    assert_eq!(t, "(u8, u16, ())");
+}
+
+#[test]
+fn infer_labeled_block_break_with_value() {
+    let t = type_at(
+        r#"
+//- /main.rs
+fn test(c: bool) {
+    let x = 'a: {
+        if c { break 'a 1u8; }
+        2
+    };
+    let y = loop {
+        'b: { break; }
+    };
+    (x, y)<|>
+}
+"#,
+    );
+    assert_eq!(t, "(u8, ())");
+}
+
+#[test]
+fn infer_try_block() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+fn test() {
+    let a = try { 1u8 };
+    let b: Option<u16> = try { 2 };
+    (a, b)<|>;
+}
+
+//- /std.rs
+#[prelude_import] use ops::*;
+mod ops {
+    trait Try {
+        type Ok;
+        type Error;
+    }
+}
+
+#[prelude_import] use result::*;
+mod result {
+    enum Result<O, E> {
+        Ok(O),
+        Err(E)
+    }
+
+    impl<O, E> crate::ops::Try for Result<O, E> {
+        type Ok = O;
+        type Error = E;
+    }
+}
+
+#[prelude_import] use option::*;
+mod option {
+    enum Option<T> {
+        None,
+        Some(T)
+    }
+
+    impl<T> crate::ops::Try for Option<T> {
+        type Ok = T;
+        type Error = ();
+    }
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+    assert_eq!("(Result<u8, {unknown}>, Option<u16>)", type_at_pos(&db, pos));
+}
+
+#[test]
+fn deref_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "deref"]
+trait Deref {
+    type Target;
+}
+
+struct Arc<T>;
+impl<T> Deref for Arc<T> {
+    type Target = T;
+}
+
+struct S;
+impl S {
+    fn foo(&self) -> u128 {}
+}
+
+fn test(s: Arc<S>) {
+    (*s, s.foo())<|>
+}
+"#,
+    );
+    assert_eq!(t, "(S, u128)");
+}
+
+#[test]
+fn deref_trait_field_access() {
+    let t = type_at(
+        r#"
+//- /main.rs
+#[lang = "deref"]
+trait Deref {
+    type Target;
+}
+
+struct Box<T>;
+impl<T> Deref for Box<T> {
+    type Target = T;
+}
+
+struct S { field: u64 }
+
+fn test(s: Box<S>) {
+    s.field<|>
+}
+"#,
+    );
+    assert_eq!(t, "u64");
+}
+
+#[test]
+fn dyn_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait<T> {
+    fn foo(&self) -> T;
+    fn foo2(&self) -> i64;
+}
+
+fn test(x: &dyn Trait<u64>) {
+    (x.foo(), x.foo2())<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(u64, i64)");
+}
+
+#[test]
+fn impl_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait<T> {
+    fn foo(&self) -> T;
+}
+
+fn bar() -> impl Trait<u64> {}
+
+fn test(x: impl Trait<u64>) {
+    let y = bar();
+    (x.foo(), y.foo())<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(u64, u64)");
+}
+
+#[test]
+fn dyn_trait_method_trait_not_in_scope() {
+    let t = type_at(
+        r#"
+//- /main.rs
+mod foo {
+    pub trait Trait {
+        fn foo(&self) -> u32;
+    }
+}
+
+fn test(x: &dyn foo::Trait) {
+    x.foo()<|>;
+}
+"#,
+    );
+    assert_eq!(t, "u32");
+}
+
+#[test]
+fn display_dyn_and_impl_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Trait<T> {}
+trait Clone {}
+
+fn bar() -> impl Trait<u64> + Clone {}
+
+fn test(x: &dyn Trait<u32>) {
+    (x, bar())<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(&dyn Trait<u32>, impl Trait<u64> + Clone)");
+}
+
+#[test]
+fn impl_trait_assoc_type_binding() {
+    let t = type_at(
+        r#"
+//- /main.rs
+enum Option<T> { Some(T), None }
+trait Iterator {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+}
+struct Foo;
+
+fn bar() -> impl Iterator<Item = Foo> {}
+
+fn test(x: &mut dyn Iterator<Item = u32>) {
+    let mut y = bar();
+    (y.next(), x.next())<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(Option<Foo>, Option<u32>)");
+}
+
+#[test]
+fn display_assoc_type_binding_and_lifetime_bound() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Iterator { type Item; }
+trait Clone {}
+struct Foo;
+
+fn bar() -> impl Iterator<Item = Foo> + Clone + 'static {}
+
+fn test(x: &dyn Iterator<Item = u32>) {
+    (x, bar())<|>;
+}
+"#,
+    );
+    assert_eq!(t, "(&dyn Iterator<Item = u32>, impl Iterator<Item = Foo> + Clone)");
+}
+
+#[test]
+fn infer_derive_clone() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+#[derive(Clone)]
+struct S<T>(T);
+#[derive(Clone, Debug)]
+struct A;
+struct NotClone;
+
+fn test() {
+    (S(A).clone(), S(NotClone).clone())<|>;
+}
+
+//- /std.rs
+#[prelude_import] use clone::*;
+mod clone {
+    trait Clone {
+        fn clone(&self) -> Self;
+    }
+}
+mod fmt {
+    trait Debug {}
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+    assert_eq!("(S<A>, {unknown})", type_at_pos(&db, pos));
+}
+
+#[test]
+fn infer_derive_default_path() {
+    let (mut db, pos) = MockDatabase::with_position(
+        r#"
+//- /main.rs
+#[derive(Default)]
+struct S;
+
+fn test() {
+    S::default()<|>;
+}
+
+//- /std.rs
+#[prelude_import] use default::*;
+mod default {
+    trait Default {
+        fn default() -> Self;
+    }
+}
+"#,
+    );
+    db.set_crate_graph_from_fixture(crate_graph! {
+        "main": ("/main.rs", ["std"]),
+        "std": ("/std.rs", []),
+    });
+    assert_eq!("S", type_at_pos(&db, pos));
+}
+
+fn type_at_pos(db: &MockDatabase, pos: FilePosition) -> String {
+    let file = db.parse(pos.file_id);
+    let expr = algo::find_node_at_offset::<ast::Expr>(file.syntax(), pos.offset).unwrap();
+    let analyzer = SourceAnalyzer::new(db, pos.file_id, expr.syntax(), Some(pos.offset));
+    let ty = analyzer.type_of(db, expr).unwrap();
+    ty.display(db).to_string()
+}
+
+fn type_at(content: &str) -> String {
+    let (db, file_pos) = MockDatabase::with_position(content);
+    type_at_pos(&db, file_pos)
+}
+
+fn infer(content: &str) -> String {
+    let (db, _, file_id) = MockDatabase::with_single_file(content);
+    let source_file = db.parse(file_id);
+
+    let mut acc = String::new();
+    acc.push_str("\n");
+
+    let mut infer_def = |inference_result: Arc<InferenceResult>,
+                         body_source_map: Arc<BodySourceMap>| {
+        let mut types = Vec::new();
+
+        for (pat, ty) in inference_result.type_of_pat.iter() {
+            let syntax_ptr = match body_source_map.pat_syntax(pat) {
+                Some(sp) => sp.either(|it| it.syntax_node_ptr(), |it| it.syntax_node_ptr()),
+                None => continue,
+            };
+            types.push((syntax_ptr, ty));
+        }
+
+        for (expr, ty) in inference_result.type_of_expr.iter() {
+            let syntax_ptr = match body_source_map.expr_syntax(expr) {
+                Some(sp) => sp,
+                None => continue,
+            };
+            types.push((syntax_ptr, ty));
+        }
+
+        // sort ranges for consistency
+        types.sort_by_key(|(ptr, _)| (ptr.range().start(), ptr.range().end()));
+        for (syntax_ptr, ty) in &types {
+            let node = syntax_ptr.to_node(source_file.syntax());
+            let (range, text) = if let Some(self_param) = ast::SelfParam::cast(node) {
+                (self_param.self_kw_token().range(), "self".to_string())
+            } else {
+                (syntax_ptr.range(), node.text().to_string().replace("\n", " "))
+            };
+            write!(acc, "{} '{}': {}\n", range, ellipsize(text, 15), ty.display(&db)).unwrap();
+        }
+    };
+
+    for node in source_file.syntax().descendants() {
+        if node.kind() == FN_DEF || node.kind() == CONST_DEF || node.kind() == STATIC_DEF {
+            let analyzer = SourceAnalyzer::new(&db, file_id, node, None);
+            infer_def(analyzer.inference_result(), analyzer.body_source_map());
+        }
+    }
+
+    acc.truncate(acc.trim_end().len());
+    acc
+}
+
+fn ellipsize(mut text: String, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text;
+    }
+    let ellipsis = "...";
+    let e_len = ellipsis.len();
+    let mut prefix_len = (max_len - e_len) / 2;
+    while !text.is_char_boundary(prefix_len) {
+        prefix_len += 1;
+    }
+    let mut suffix_len = max_len - e_len - prefix_len;
+    while !text.is_char_boundary(text.len() - suffix_len) {
+        suffix_len += 1;
+    }
+    text.replace_range(prefix_len..text.len() - suffix_len, ellipsis);
+    text
+}
+
+#[test]
+fn typing_whitespace_inside_a_function_should_not_invalidate_types() {
+    let (mut db, pos) = MockDatabase::with_position(
+        "
+        //- /lib.rs
+        fn foo() -> i32 {
+            <|>1 + 1
+        }
+    ",
+    );
+    {
+        let file = db.parse(pos.file_id);
+        let node =
+            algo::find_token_at_offset(file.syntax(), pos.offset).right_biased().unwrap().parent();
+        let events = db.log_executed(|| {
+            SourceAnalyzer::new(&db, pos.file_id, node, None);
+        });
+        assert!(format!("{:?}", events).contains("infer"))
+    }
+
+    let new_text = "
+        fn foo() -> i32 {
+            1
+            +
+            1
+        }
+    "
+    .to_string();
+
+    db.query_mut(ra_db::FileTextQuery).set(pos.file_id, Arc::new(new_text));
+
+    {
+        let file = db.parse(pos.file_id);
+        let node =
+            algo::find_token_at_offset(file.syntax(), pos.offset).right_biased().unwrap().parent();
+        let events = db.log_executed(|| {
+            SourceAnalyzer::new(&db, pos.file_id, node, None);
+        });
+        assert!(!format!("{:?}", events).contains("infer"), "{:#?}", events)
+    }
+}
+
+#[test]
+fn no_such_field_diagnostics() {
     let diagnostics = MockDatabase::with_files(
         r"
         //- /lib.rs
@@ -2849,3 +3730,54 @@ fn no_such_field_diagnostics() {
 "###
     );
 }
+
+#[test]
+fn type_mismatch_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r"
+        //- /lib.rs
+        struct S;
+        fn two(a: u32, b: u32) {}
+        fn diverges() -> u32 { return 92; }
+        fn test(s: S) {
+            let a: u32 = true;
+            let b: &[u32] = &[1, 2];
+            two(1);
+            s.frobnicate();
+        }
+        ",
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"true": type mismatch
+"two(1)": expected 2 arguments, found 1
+"s.frobnicate()": no method named `frobnicate`
+"###
+    );
+}
+
+#[test]
+fn variadic_arg_count_diagnostics() {
+    let diagnostics = MockDatabase::with_files(
+        r"
+        //- /lib.rs
+        extern {
+            fn printf(format: *const u8, ...) -> i32;
+        }
+        fn test() {
+            unsafe {
+                printf(0 as *const u8);
+                printf(0 as *const u8, 1, 2);
+                printf();
+            }
+        }
+        ",
+    )
+    .diagnostics();
+
+    assert_snapshot_matches!(diagnostics, @r###"
+"printf()": expected 1 argument, found 0
+"###
+    );
+}
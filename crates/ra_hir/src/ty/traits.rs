@@ -1,13 +1,17 @@
 //! Trait solving using Chalk.
-use std::sync::{Arc, Mutex};
+use std::{mem, sync::{Arc, Mutex}};
 
 use rustc_hash::FxHashSet;
 use log::debug;
 use chalk_ir::cast::Cast;
 use ra_prof::profile;
 
-use crate::{Crate, Trait, db::HirDatabase, ImplBlock};
-use super::{TraitRef, Ty, Canonical};
+use crate::{
+    Crate, Trait, db::HirDatabase, ImplBlock, DefWithBody,
+    expr::ExprId,
+    lang_item::LangItemTarget,
+};
+use super::{TraitRef, Ty, ApplicationTy, TypeCtor, Canonical, ProjectionTy, Substs};
 
 use self::chalk::{ToChalk, from_chalk};
 
@@ -24,6 +28,10 @@ const CHALK_SOLVER_MAX_SIZE: usize = 4;
 struct ChalkContext<'a, DB> {
     db: &'a DB,
     krate: Crate,
+    /// The closure types occurring in the goal. Chalk doesn't tell us the self
+    /// type when asking for the impls of a trait, so we provide the synthetic
+    /// `Fn` trait impls for exactly these closures.
+    closures: &'a [(DefWithBody, ExprId)],
 }
 
 pub(crate) fn solver_query(_db: &impl HirDatabase, _krate: Crate) -> Arc<Mutex<Solver>> {
@@ -56,9 +64,10 @@ pub(crate) fn impls_for_trait_query(
 fn solve(
     db: &impl HirDatabase,
     krate: Crate,
+    closures: &[(DefWithBody, ExprId)],
     goal: &chalk_ir::UCanonical<chalk_ir::InEnvironment<chalk_ir::Goal>>,
 ) -> Option<chalk_solve::Solution> {
-    let context = ChalkContext { db, krate };
+    let context = ChalkContext { db, krate, closures };
     let solver = db.solver(krate);
     debug!("solve goal: {:?}", goal);
     let solution = solver.lock().unwrap().solve_with_fuel(&context, goal, Some(1000));
@@ -74,6 +83,43 @@ pub enum Obligation {
     /// Prove that a certain type implements a trait (the type is the `Self` type
     /// parameter to the `TraitRef`).
     Trait(TraitRef),
+    /// Prove that a certain projection normalizes to a certain type.
+    Projection(ProjectionPredicate),
+}
+
+/// A projection type together with the type it is supposed to be equal to,
+/// i.e. `<T as Trait>::Item == U`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProjectionPredicate {
+    pub projection_ty: ProjectionTy,
+    pub ty: Ty,
+}
+
+impl ProjectionPredicate {
+    pub fn subst(mut self, substs: &Substs) -> ProjectionPredicate {
+        self.walk_mut_binders(
+            &mut |ty_mut, _binders| {
+                let ty = mem::replace(ty_mut, Ty::Unknown);
+                *ty_mut = ty.subst(substs);
+            },
+            0,
+        );
+        self
+    }
+
+    pub fn walk(&self, f: &mut impl FnMut(&Ty)) {
+        self.projection_ty.walk(f);
+        self.ty.walk(f);
+    }
+
+    pub(super) fn walk_mut_binders(
+        &mut self,
+        f: &mut impl FnMut(&mut Ty, usize),
+        binders: usize,
+    ) {
+        self.projection_ty.walk_mut_binders(f, binders);
+        self.ty.walk_mut_binders(f, binders);
+    }
 }
 
 /// Check using Chalk whether trait is implemented for given parameters including `Self` type.
@@ -83,20 +129,55 @@ pub(crate) fn implements_query(
     trait_ref: Canonical<TraitRef>,
 ) -> Option<Solution> {
     let _p = profile("implements_query");
+    let closures = closures_in_substs(&trait_ref.value.substs);
     let goal: chalk_ir::Goal = trait_ref.value.to_chalk(db).cast();
+    solve_goal(db, krate, &closures, goal, trait_ref.num_vars)
+}
+
+/// Normalize a projection using Chalk, i.e. find out what type
+/// `<T as Trait>::Item` actually is.
+pub(crate) fn normalize_query(
+    db: &impl HirDatabase,
+    krate: Crate,
+    projection: Canonical<ProjectionPredicate>,
+) -> Option<Solution> {
+    let _p = profile("normalize_query");
+    let closures = closures_in_substs(&projection.value.projection_ty.parameters);
+    let goal: chalk_ir::Goal = projection.value.to_chalk(db).cast();
+    solve_goal(db, krate, &closures, goal, projection.num_vars)
+}
+
+fn solve_goal(
+    db: &impl HirDatabase,
+    krate: Crate,
+    closures: &[(DefWithBody, ExprId)],
+    goal: chalk_ir::Goal,
+    num_vars: usize,
+) -> Option<Solution> {
     debug!("goal: {:?}", goal);
     let env = chalk_ir::Environment::new();
     let in_env = chalk_ir::InEnvironment::new(&env, goal);
     let parameter = chalk_ir::ParameterKind::Ty(chalk_ir::UniverseIndex::ROOT);
-    let canonical =
-        chalk_ir::Canonical { value: in_env, binders: vec![parameter; trait_ref.num_vars] };
+    let canonical = chalk_ir::Canonical { value: in_env, binders: vec![parameter; num_vars] };
     // We currently don't deal with universes (I think / hope they're not yet
     // relevant for our use cases?)
     let u_canonical = chalk_ir::UCanonical { canonical, universes: 1 };
-    let solution = solve(db, krate, &u_canonical);
+    let solution = solve(db, krate, closures, &u_canonical);
     solution.map(|solution| solution_from_chalk(db, solution))
 }
 
+fn closures_in_substs(substs: &Substs) -> Vec<(DefWithBody, ExprId)> {
+    let mut closures = Vec::new();
+    for ty in substs.iter() {
+        ty.walk(&mut |ty| {
+            if let Ty::Apply(ApplicationTy { ctor: TypeCtor::Closure { def, expr }, .. }) = ty {
+                closures.push((*def, *expr));
+            }
+        });
+    }
+    closures
+}
+
 fn solution_from_chalk(db: &impl HirDatabase, solution: chalk_solve::Solution) -> Solution {
     let convert_subst = |subst: chalk_ir::Canonical<chalk_ir::Substitution>| {
         let value = subst
@@ -168,3 +249,45 @@ pub enum Guidance {
     /// There's no useful information to feed back to type inference
     Unknown,
 }
+
+/// An impl. Usually this comes from an impl block, but some built-in types get
+/// synthetic impls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Impl {
+    /// A normal impl from an impl block.
+    ImplBlock(ImplBlock),
+    /// Closure types implement the Fn traits synthetically.
+    ClosureFnTraitImpl(ClosureFnTraitImplData),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClosureFnTraitImplData {
+    def: DefWithBody,
+    expr: ExprId,
+    fn_trait: FnTrait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FnTrait {
+    FnOnce,
+    FnMut,
+    Fn,
+}
+
+impl FnTrait {
+    fn lang_item_name(self) -> &'static str {
+        match self {
+            FnTrait::FnOnce => "fn_once",
+            FnTrait::FnMut => "fn_mut",
+            FnTrait::Fn => "fn",
+        }
+    }
+
+    /// Looks up the trait corresponding to this `Fn` trait via its lang item.
+    pub(crate) fn get(self, db: &impl HirDatabase, krate: Crate) -> Option<Trait> {
+        match db.lang_item(krate, self.lang_item_name().into())? {
+            LangItemTarget::Trait(t) => Some(t),
+            _ => None,
+        }
+    }
+}
@@ -10,13 +10,17 @@ use test_utils::tested_by;
 use ra_db::salsa::{InternId, InternKey};
 
 use crate::{
-    Trait, HasGenericParams, ImplBlock,
+    Trait, HasGenericParams, ImplBlock, Crate, TypeAlias, ImplItem,
     db::HirDatabase,
-    ty::{TraitRef, Ty, ApplicationTy, TypeCtor, Substs, GenericPredicate, CallableDef},
+    expr::Expr,
+    name::known,
+    nameres::Namespace,
+    ty::{TraitRef, Ty, ApplicationTy, TypeCtor, Substs, GenericPredicate, CallableDef, ProjectionTy},
     ty::display::HirDisplay,
     generics::GenericDef,
+    traits::TraitItem,
 };
-use super::ChalkContext;
+use super::{ChalkContext, Impl, ClosureFnTraitImplData, FnTrait, ProjectionPredicate};
 
 /// This represents a trait whose name we could not resolve.
 const UNKNOWN_TRAIT: chalk_ir::TraitId =
@@ -45,6 +49,7 @@ impl ToChalk for Ty {
                 let parameters = apply_ty.parameters.to_chalk(db);
                 chalk_ir::ApplicationTy { name, parameters }.cast()
             }
+            Ty::Projection(proj_ty) => proj_ty.to_chalk(db).cast(),
             Ty::Param { idx, .. } => {
                 PlaceholderIndex { ui: UniverseIndex::ROOT, idx: idx as usize }.to_ty()
             }
@@ -53,7 +58,9 @@ impl ToChalk for Ty {
             // FIXME this is clearly incorrect, but probably not too incorrect
             // and I'm not sure what to actually do with Ty::Unknown
             // maybe an alternative would be `for<T> T`? (meaningless in rust, but expressible in chalk's Ty)
-            Ty::Unknown => {
+            // FIXME: trait objects and opaque types are treated the same way
+            // for now, which means we can't prove anything about them
+            Ty::Unknown | Ty::Dyn(_) | Ty::Opaque(_) => {
                 PlaceholderIndex { ui: UniverseIndex::ROOT, idx: usize::max_value() }.to_ty()
             }
         }
@@ -76,7 +83,7 @@ impl ToChalk for Ty {
                     }
                 }
             }
-            chalk_ir::Ty::Projection(_) => unimplemented!(),
+            chalk_ir::Ty::Projection(proj) => Ty::Projection(from_chalk(db, proj)),
             chalk_ir::Ty::UnselectedProjection(_) => unimplemented!(),
             chalk_ir::Ty::ForAll(_) => unimplemented!(),
             chalk_ir::Ty::BoundVar(idx) => Ty::Bound(idx as u32),
@@ -132,6 +139,54 @@ impl ToChalk for Trait {
     }
 }
 
+impl ToChalk for TypeAlias {
+    type Chalk = chalk_ir::TypeId;
+
+    fn to_chalk(self, _db: &impl HirDatabase) -> chalk_ir::TypeId {
+        self.id.into()
+    }
+
+    fn from_chalk(_db: &impl HirDatabase, type_alias_id: chalk_ir::TypeId) -> TypeAlias {
+        TypeAlias { id: type_alias_id.into() }
+    }
+}
+
+impl ToChalk for ProjectionTy {
+    type Chalk = chalk_ir::ProjectionTy;
+
+    fn to_chalk(self, db: &impl HirDatabase) -> chalk_ir::ProjectionTy {
+        chalk_ir::ProjectionTy {
+            associated_ty_id: self.associated_ty.to_chalk(db),
+            parameters: self.parameters.to_chalk(db),
+        }
+    }
+
+    fn from_chalk(db: &impl HirDatabase, projection_ty: chalk_ir::ProjectionTy) -> ProjectionTy {
+        ProjectionTy {
+            associated_ty: from_chalk(db, projection_ty.associated_ty_id),
+            parameters: from_chalk(db, projection_ty.parameters),
+        }
+    }
+}
+
+impl ToChalk for ProjectionPredicate {
+    type Chalk = chalk_ir::Normalize;
+
+    fn to_chalk(self, db: &impl HirDatabase) -> chalk_ir::Normalize {
+        chalk_ir::Normalize {
+            projection: self.projection_ty.to_chalk(db),
+            ty: self.ty.to_chalk(db),
+        }
+    }
+
+    fn from_chalk(db: &impl HirDatabase, normalize: chalk_ir::Normalize) -> ProjectionPredicate {
+        ProjectionPredicate {
+            projection_ty: from_chalk(db, normalize.projection),
+            ty: from_chalk(db, normalize.ty),
+        }
+    }
+}
+
 impl ToChalk for TypeCtor {
     type Chalk = chalk_ir::StructId;
 
@@ -144,15 +199,15 @@ impl ToChalk for TypeCtor {
     }
 }
 
-impl ToChalk for ImplBlock {
+impl ToChalk for Impl {
     type Chalk = chalk_ir::ImplId;
 
     fn to_chalk(self, db: &impl HirDatabase) -> chalk_ir::ImplId {
-        db.intern_impl_block(self).into()
+        db.intern_impl(self).into()
     }
 
-    fn from_chalk(db: &impl HirDatabase, impl_id: chalk_ir::ImplId) -> ImplBlock {
-        db.lookup_intern_impl_block(impl_id.into())
+    fn from_chalk(db: &impl HirDatabase, impl_id: chalk_ir::ImplId) -> Impl {
+        db.lookup_intern_impl(impl_id.into())
     }
 }
 
@@ -164,6 +219,13 @@ impl ToChalk for GenericPredicate {
             GenericPredicate::Implemented(trait_ref) => {
                 make_binders(chalk_ir::WhereClause::Implemented(trait_ref.to_chalk(db)), 0)
             }
+            GenericPredicate::Projection(projection_pred) => make_binders(
+                chalk_ir::WhereClause::ProjectionEq(chalk_ir::ProjectionEq {
+                    projection: projection_pred.projection_ty.to_chalk(db),
+                    ty: projection_pred.ty.to_chalk(db),
+                }),
+                0,
+            ),
             GenericPredicate::Error => {
                 let impossible_trait_ref = chalk_ir::TraitRef {
                     trait_id: UNKNOWN_TRAIT,
@@ -193,7 +255,7 @@ fn make_binders<T>(value: T, num_vars: usize) -> chalk_ir::Binders<T> {
 fn blacklisted_trait(db: &impl HirDatabase, trait_: Trait) -> bool {
     let name = trait_.name(db).unwrap_or_else(crate::Name::missing).to_string();
     match &*name {
-        "Send" | "Sync" | "Sized" | "Fn" | "FnMut" | "FnOnce" => true,
+        "Send" | "Sync" | "Sized" => true,
         _ => false,
     }
 }
@@ -228,8 +290,26 @@ impl<'a, DB> chalk_solve::RustIrDatabase for ChalkContext<'a, DB>
 where
     DB: HirDatabase,
 {
-    fn associated_ty_data(&self, _ty: TypeId) -> Arc<AssociatedTyDatum> {
-        unimplemented!()
+    fn associated_ty_data(&self, id: TypeId) -> Arc<AssociatedTyDatum> {
+        debug!("associated_ty_data {:?}", id);
+        let type_alias: TypeAlias = from_chalk(self.db, id);
+        let trait_ = type_alias.parent_trait(self.db).expect("associated type not in trait");
+        let generic_params = type_alias.generic_params(self.db);
+        let parameter_kinds = generic_params
+            .params_including_parent()
+            .into_iter()
+            .map(|p| chalk_ir::ParameterKind::Ty(lalrpop_intern::intern(&p.name.to_string())))
+            .collect();
+        let datum = AssociatedTyDatum {
+            trait_id: trait_.to_chalk(self.db),
+            id,
+            name: lalrpop_intern::intern(&type_alias.name(self.db).to_string()),
+            parameter_kinds,
+            // FIXME add bounds and where clauses
+            bounds: vec![],
+            where_clauses: vec![],
+        };
+        Arc::new(datum)
     }
     fn trait_datum(&self, trait_id: chalk_ir::TraitId) -> Arc<TraitDatum> {
         debug!("trait_datum {:?}", trait_id);
@@ -263,7 +343,14 @@ where
             fundamental: false,
         };
         let where_clauses = convert_where_clauses(self.db, trait_.into(), &bound_vars);
-        let associated_ty_ids = Vec::new(); // FIXME add associated tys
+        let associated_ty_ids = trait_
+            .items(self.db)
+            .into_iter()
+            .filter_map(|item| match item {
+                TraitItem::TypeAlias(type_alias) => Some(type_alias.to_chalk(self.db)),
+                _ => None,
+            })
+            .collect();
         let trait_datum_bound =
             chalk_rust_ir::TraitDatumBound { trait_ref, where_clauses, flags, associated_ty_ids };
         let trait_datum = TraitDatum { binders: make_binders(trait_datum_bound, bound_vars.len()) };
@@ -310,6 +397,10 @@ where
                     krate != Some(self.krate),
                 )
             }
+            TypeCtor::Closure { def, .. } => {
+                let upstream = def.krate(self.db) != Some(self.krate);
+                (1, vec![], upstream)
+            }
             TypeCtor::Adt(adt) => {
                 let generic_params = adt.generic_params(self.db);
                 let bound_vars = Substs::bound_vars(&generic_params);
@@ -341,40 +432,16 @@ where
     }
     fn impl_datum(&self, impl_id: ImplId) -> Arc<ImplDatum> {
         debug!("impl_datum {:?}", impl_id);
-        let impl_block: ImplBlock = from_chalk(self.db, impl_id);
-        let generic_params = impl_block.generic_params(self.db);
-        let bound_vars = Substs::bound_vars(&generic_params);
-        let trait_ref = impl_block
-            .target_trait_ref(self.db)
-            .expect("FIXME handle unresolved impl block trait ref")
-            .subst(&bound_vars);
-        let impl_type = if impl_block.module().krate(self.db) == Some(self.krate) {
-            chalk_rust_ir::ImplType::Local
-        } else {
-            chalk_rust_ir::ImplType::External
-        };
-        let where_clauses = convert_where_clauses(self.db, impl_block.into(), &bound_vars);
-        let negative = impl_block.is_negative(self.db);
-        debug!(
-            "impl {:?}: {}{} where {:?}",
-            impl_id,
-            if negative { "!" } else { "" },
-            trait_ref.display(self.db),
-            where_clauses
-        );
-        let trait_ref = trait_ref.to_chalk(self.db);
-        let impl_datum_bound = chalk_rust_ir::ImplDatumBound {
-            trait_ref: if negative {
-                chalk_rust_ir::PolarizedTraitRef::Negative(trait_ref)
-            } else {
-                chalk_rust_ir::PolarizedTraitRef::Positive(trait_ref)
-            },
-            where_clauses,
-            associated_ty_values: Vec::new(), // FIXME add associated type values
-            impl_type,
-        };
-        let impl_datum = ImplDatum { binders: make_binders(impl_datum_bound, bound_vars.len()) };
-        Arc::new(impl_datum)
+        let impl_: Impl = from_chalk(self.db, impl_id);
+        match impl_ {
+            Impl::ImplBlock(impl_block) => {
+                impl_block_datum(self.db, self.krate, impl_id, impl_block)
+            }
+            Impl::ClosureFnTraitImpl(data) => {
+                closure_fn_trait_impl_datum(self.db, self.krate, impl_id, data)
+                    .unwrap_or_else(invalid_impl_datum)
+            }
+        }
     }
     fn impls_for_trait(&self, trait_id: chalk_ir::TraitId) -> Vec<ImplId> {
         debug!("impls_for_trait {:?}", trait_id);
@@ -386,12 +453,23 @@ where
         if blacklisted {
             return Vec::new();
         }
-        let result: Vec<_> = self
+        let mut result: Vec<_> = self
             .db
             .impls_for_trait(self.krate, trait_)
             .iter()
-            .map(|impl_block| impl_block.to_chalk(self.db))
+            .map(|impl_block| Impl::ImplBlock(*impl_block).to_chalk(self.db))
             .collect();
+        // closures implement the `Fn` traits
+        for &fn_trait in [FnTrait::FnOnce, FnTrait::FnMut, FnTrait::Fn].iter() {
+            if fn_trait.get(self.db, self.krate) != Some(trait_) {
+                continue;
+            }
+            for &(def, expr) in self.closures {
+                let impl_ =
+                    Impl::ClosureFnTraitImpl(ClosureFnTraitImplData { def, expr, fn_trait });
+                result.push(impl_.to_chalk(self.db));
+            }
+        }
         debug!("impls_for_trait returned {} impls", result.len());
         result
     }
@@ -411,7 +489,8 @@ where
         projection: &'p ProjectionTy,
     ) -> (Arc<AssociatedTyDatum>, &'p [Parameter], &'p [Parameter]) {
         debug!("split_projection {:?}", projection);
-        unimplemented!()
+        // we don't support GATs, so all parameters belong to the trait
+        (self.associated_ty_data(projection.associated_ty_id), &projection.parameters, &[])
     }
     fn custom_clauses(&self) -> Vec<chalk_ir::ProgramClause> {
         debug!("custom_clauses");
@@ -424,6 +503,137 @@ where
     }
 }
 
+fn impl_block_datum(
+    db: &impl HirDatabase,
+    krate: Crate,
+    impl_id: ImplId,
+    impl_block: ImplBlock,
+) -> Arc<ImplDatum> {
+    let generic_params = impl_block.generic_params(db);
+    let bound_vars = Substs::bound_vars(&generic_params);
+    let trait_ref = impl_block
+        .target_trait_ref(db)
+        .expect("FIXME handle unresolved impl block trait ref")
+        .subst(&bound_vars);
+    let impl_type = if impl_block.module().krate(db) == Some(krate) {
+        chalk_rust_ir::ImplType::Local
+    } else {
+        chalk_rust_ir::ImplType::External
+    };
+    let where_clauses = convert_where_clauses(db, impl_block.into(), &bound_vars);
+    let negative = impl_block.is_negative(db);
+    let trait_ = trait_ref.trait_;
+    let associated_ty_values = impl_block
+        .items(db)
+        .into_iter()
+        .filter_map(|item| match item {
+            ImplItem::TypeAlias(t) => Some(t),
+            _ => None,
+        })
+        .filter_map(|t| {
+            let assoc_ty = trait_.associated_type_by_name(db, &t.name(db))?;
+            let ty = db.type_for_def(t.into(), Namespace::Types).subst(&bound_vars);
+            Some(chalk_rust_ir::AssociatedTyValue {
+                impl_id,
+                associated_ty_id: assoc_ty.to_chalk(db),
+                value: make_binders(
+                    chalk_rust_ir::AssociatedTyValueBound { ty: ty.to_chalk(db) },
+                    0, // we don't support GATs yet
+                ),
+            })
+        })
+        .collect();
+    debug!(
+        "impl {:?}: {}{} where {:?}",
+        impl_id,
+        if negative { "!" } else { "" },
+        trait_ref.display(db),
+        where_clauses
+    );
+    let trait_ref = trait_ref.to_chalk(db);
+    let impl_datum_bound = chalk_rust_ir::ImplDatumBound {
+        trait_ref: if negative {
+            chalk_rust_ir::PolarizedTraitRef::Negative(trait_ref)
+        } else {
+            chalk_rust_ir::PolarizedTraitRef::Positive(trait_ref)
+        },
+        where_clauses,
+        associated_ty_values,
+        impl_type,
+    };
+    let impl_datum = ImplDatum { binders: make_binders(impl_datum_bound, bound_vars.len()) };
+    Arc::new(impl_datum)
+}
+
+/// An impl for the `UNKNOWN_TRAIT`, for when we can't build the actual impl.
+fn invalid_impl_datum() -> Arc<ImplDatum> {
+    let trait_ref = chalk_ir::TraitRef {
+        trait_id: UNKNOWN_TRAIT,
+        parameters: vec![chalk_ir::Ty::BoundVar(0).cast()],
+    };
+    let impl_datum_bound = chalk_rust_ir::ImplDatumBound {
+        trait_ref: chalk_rust_ir::PolarizedTraitRef::Positive(trait_ref),
+        where_clauses: Vec::new(),
+        associated_ty_values: Vec::new(),
+        impl_type: chalk_rust_ir::ImplType::External,
+    };
+    let impl_datum = ImplDatum { binders: make_binders(impl_datum_bound, 1) };
+    Arc::new(impl_datum)
+}
+
+fn closure_fn_trait_impl_datum(
+    db: &impl HirDatabase,
+    krate: Crate,
+    impl_id: ImplId,
+    data: ClosureFnTraitImplData,
+) -> Option<Arc<ImplDatum>> {
+    // for some closure |X, Y| -> Z:
+    // impl<T, U, V> Fn<(T, U)> for closure<fn(T, U) -> V>
+    let trait_ = data.fn_trait.get(db, krate)?;
+    let num_args: u16 = match &data.def.body(db)[data.expr] {
+        Expr::Lambda { args, .. } => args.len() as u16,
+        _ => {
+            log::warn!("closure for closure type {:?} not found", data);
+            0
+        }
+    };
+
+    let arg_ty = Ty::apply(
+        TypeCtor::Tuple { cardinality: num_args },
+        (0..num_args).map(|i| Ty::Bound(i.into())).collect::<Vec<_>>().into(),
+    );
+    let sig_ty = Ty::apply(
+        TypeCtor::FnPtr { num_args },
+        (0..num_args + 1).map(|i| Ty::Bound(i.into())).collect::<Vec<_>>().into(),
+    );
+    let self_ty = Ty::apply_one(TypeCtor::Closure { def: data.def, expr: data.expr }, sig_ty);
+    let trait_ref = TraitRef { trait_, substs: vec![self_ty, arg_ty].into() };
+
+    // `Output` is only defined on `FnOnce`, the other traits inherit it
+    let output_ty = Ty::Bound(num_args.into());
+    let associated_ty_values = trait_
+        .associated_type_by_name(db, &known::output_type())
+        .map(|output_ty_id| chalk_rust_ir::AssociatedTyValue {
+            impl_id,
+            associated_ty_id: output_ty_id.to_chalk(db),
+            value: make_binders(
+                chalk_rust_ir::AssociatedTyValueBound { ty: output_ty.to_chalk(db) },
+                0,
+            ),
+        })
+        .into_iter()
+        .collect();
+
+    let impl_datum_bound = chalk_rust_ir::ImplDatumBound {
+        trait_ref: chalk_rust_ir::PolarizedTraitRef::Positive(trait_ref.to_chalk(db)),
+        where_clauses: Vec::new(),
+        associated_ty_values,
+        impl_type: chalk_rust_ir::ImplType::External,
+    };
+    let impl_datum = ImplDatum { binders: make_binders(impl_datum_bound, num_args as usize + 1) };
+    Some(Arc::new(impl_datum))
+}
+
 fn id_from_chalk<T: InternKey>(chalk_id: chalk_ir::RawId) -> T {
     T::from_intern_id(InternId::from(chalk_id.index))
 }
@@ -443,6 +653,18 @@ impl From<crate::ids::TraitId> for chalk_ir::TraitId {
     }
 }
 
+impl From<chalk_ir::TypeId> for crate::ids::TypeAliasId {
+    fn from(type_id: chalk_ir::TypeId) -> Self {
+        id_from_chalk(type_id.0)
+    }
+}
+
+impl From<crate::ids::TypeAliasId> for chalk_ir::TypeId {
+    fn from(type_id: crate::ids::TypeAliasId) -> Self {
+        chalk_ir::TypeId(id_to_chalk(type_id))
+    }
+}
+
 impl From<chalk_ir::StructId> for crate::ids::TypeCtorId {
     fn from(struct_id: chalk_ir::StructId) -> Self {
         id_from_chalk(struct_id.0)
@@ -1,7 +1,7 @@
 //! HIR for references to types. Paths in these are not yet resolved. They can
 //! be directly created from an ast::TypeRef, without further queries.
 
-use ra_syntax::ast::{self, TypeAscriptionOwner};
+use ra_syntax::ast::{self, TypeAscriptionOwner, TypeBoundsOwner};
 
 use crate::Path;
 
@@ -49,8 +49,17 @@ pub enum TypeRef {
     /// A fn pointer. Last element of the vector is the return type.
     Fn(Vec<TypeRef>),
     // For
-    // ImplTrait,
-    // DynTrait,
+    ImplTrait(Vec<TypeBound>),
+    DynTrait(Vec<TypeBound>),
+    Error,
+}
+
+/// A single bound of an `impl Trait` or `dyn Trait` type, like the `Clone` in
+/// `impl Iterator + Clone`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum TypeBound {
+    Path(Path),
+    // also for<> bounds
     Error,
 }
 
@@ -90,8 +99,10 @@ impl TypeRef {
             }
             // for types are close enough for our purposes to the inner type for now...
             ForType(inner) => TypeRef::from_ast_opt(inner.type_ref()),
-            ImplTraitType(_inner) => TypeRef::Error,
-            DynTraitType(_inner) => TypeRef::Error,
+            ImplTraitType(inner) => {
+                TypeRef::ImplTrait(type_bounds_from_ast(inner.type_bound_list()))
+            }
+            DynTraitType(inner) => TypeRef::DynTrait(type_bounds_from_ast(inner.type_bound_list())),
         }
     }
 
@@ -107,3 +118,36 @@ impl TypeRef {
         TypeRef::Tuple(Vec::new())
     }
 }
+
+pub(crate) fn type_bounds_from_ast(type_bounds_opt: Option<&ast::TypeBoundList>) -> Vec<TypeBound> {
+    if let Some(type_bounds) = type_bounds_opt {
+        // lifetime bounds don't matter for type inference, so we skip them
+        type_bounds
+            .bounds()
+            .filter(|bound| bound.lifetime_token().is_none())
+            .map(TypeBound::from_ast)
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+impl TypeBound {
+    pub(crate) fn from_ast(node: &ast::TypeBound) -> Self {
+        match node.type_ref().map(|it| it.kind()) {
+            Some(ast::TypeRefKind::PathType(path_type)) => {
+                let path = path_type.path().and_then(Path::from_ast);
+                path.map(TypeBound::Path).unwrap_or(TypeBound::Error)
+            }
+            // FIXME: for<> bounds
+            _ => TypeBound::Error,
+        }
+    }
+
+    pub fn as_path(&self) -> Option<&Path> {
+        match self {
+            TypeBound::Path(p) => Some(p),
+            _ => None,
+        }
+    }
+}
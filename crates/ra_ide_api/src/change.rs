@@ -5,10 +5,11 @@ use std::{
 
 use rustc_hash::FxHashMap;
 use ra_db::{
-    SourceRootId, FileId, CrateGraph, SourceDatabase, SourceRoot,
+    SourceRootId, FileId, CrateGraph, SourceDatabase, SourceRoot, ReparseHint,
     salsa::{Database, SweepStrategy},
 };
 use ra_syntax::SourceFile;
+use ra_text_edit::AtomTextEdit;
 use ra_prof::profile;
 use relative_path::RelativePathBuf;
 use rayon::prelude::*;
@@ -23,7 +24,7 @@ use crate::{
 pub struct AnalysisChange {
     new_roots: Vec<(SourceRootId, bool)>,
     roots_changed: FxHashMap<SourceRootId, RootChange>,
-    files_changed: Vec<(FileId, Arc<String>)>,
+    files_changed: Vec<(FileId, Arc<String>, Option<AtomTextEdit>)>,
     libraries_added: Vec<LibraryData>,
     crate_graph: Option<CrateGraph>,
 }
@@ -71,7 +72,19 @@ impl AnalysisChange {
     }
 
     pub fn change_file(&mut self, file_id: FileId, new_text: Arc<String>) {
-        self.files_changed.push((file_id, new_text))
+        self.files_changed.push((file_id, new_text, None))
+    }
+
+    /// Like `change_file`, but also records the `edit` which turned the old
+    /// text of the file into `new_text`, so that the file can be reparsed
+    /// incrementally.
+    pub fn change_file_with_edit(
+        &mut self,
+        file_id: FileId,
+        new_text: Arc<String>,
+        edit: AtomTextEdit,
+    ) {
+        self.files_changed.push((file_id, new_text, Some(edit)))
     }
 
     pub fn remove_file(&mut self, root_id: SourceRootId, file_id: FileId, path: RelativePathBuf) {
@@ -170,7 +183,9 @@ impl RootDatabase {
         for (root_id, root_change) in change.roots_changed {
             self.apply_root_change(root_id, root_change);
         }
-        for (file_id, text) in change.files_changed {
+        for (file_id, text, edit) in change.files_changed {
+            let hint = edit.map(|edit| Arc::new(ReparseHint::new(self.parse(file_id), edit)));
+            self.set_file_reparse_hint(file_id, hint);
             self.set_file_text(file_id, text)
         }
         if !change.libraries_added.is_empty() {
@@ -192,12 +207,14 @@ impl RootDatabase {
         let mut source_root = SourceRoot::clone(&self.source_root(root_id));
         for add_file in root_change.added {
             self.set_file_text(add_file.file_id, add_file.text);
+            self.set_file_reparse_hint(add_file.file_id, None);
             self.set_file_relative_path(add_file.file_id, add_file.path.clone());
             self.set_file_source_root(add_file.file_id, root_id);
             source_root.files.insert(add_file.path, add_file.file_id);
         }
         for remove_file in root_change.removed {
             self.set_file_text(remove_file.file_id, Default::default());
+            self.set_file_reparse_hint(remove_file.file_id, None);
             source_root.files.remove(&remove_file.path);
         }
         self.set_source_root(root_id, Arc::new(source_root));
@@ -230,3 +247,39 @@ impl RootDatabase {
         self.query(hir::db::BodyWithSourceMapQuery).sweep(sweep);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ra_db::{FileId, SourceDatabase, SourceRootId};
+    use ra_syntax::{AstNode, TextRange, TextUnit};
+    use ra_text_edit::AtomTextEdit;
+
+    use crate::{AnalysisChange, AnalysisHost};
+
+    #[test]
+    fn change_file_with_edit_reparses_incrementally() {
+        let mut host = AnalysisHost::default();
+        let root = SourceRootId(0);
+        let file_id = FileId(1);
+        let mut change = AnalysisChange::new();
+        change.add_root(root, true);
+        change.add_file(root, file_id, "main.rs".into(), Arc::new("fn foo() {}".to_string()));
+        host.apply_change(change);
+        host.db.parse(file_id);
+
+        let range = TextRange::from_to(TextUnit::from(3), TextUnit::from(6));
+        let edit = AtomTextEdit::replace(range, "bar".to_string());
+        let mut change = AnalysisChange::new();
+        change.change_file_with_edit(file_id, Arc::new("fn bar() {}".to_string()), edit);
+        host.apply_change(change);
+
+        let hint = host.db.file_reparse_hint(file_id).unwrap();
+        assert!(!hint.is_used());
+        let tree = host.db.parse(file_id);
+        assert_eq!(tree.syntax().text(), "fn bar() {}");
+        // The old tree is dropped once it has been used for the reparse.
+        assert!(hint.is_used());
+    }
+}
@@ -11,8 +11,13 @@ mod complete_snippet;
 mod complete_path;
 mod complete_scope;
 mod complete_postfix;
+mod complete_trait_impl;
 
-use ra_db::SourceDatabase;
+use std::sync::Arc;
+
+use ra_db::{SourceDatabase, salsa};
+use ra_syntax::SmolStr;
+use rustc_hash::FxHashMap;
 
 use crate::{
     db,
@@ -26,7 +31,10 @@ use crate::{
 #[cfg(test)]
 use crate::completion::completion_item::{do_completion, check_completion};
 
-pub use crate::completion::completion_item::{CompletionItem, CompletionItemKind, InsertTextFormat};
+pub use crate::completion::completion_item::{
+    CompletionItem, CompletionItemKind, CompletionScore, CompletionResolveData, ResolvedCompletion,
+    InsertTextFormat, ImportOrigin,
+};
 
 /// Main entry point for completion. We run completion as a two-phase process.
 ///
@@ -67,5 +75,36 @@ pub(crate) fn completions(db: &db::RootDatabase, position: FilePosition) -> Opti
     complete_struct_literal::complete_struct_literal(&mut acc, &ctx);
     complete_pattern::complete_pattern(&mut acc, &ctx);
     complete_postfix::complete_postfix(&mut acc, &ctx);
+    complete_trait_impl::complete_trait_impl(&mut acc, &ctx);
     Some(acc)
 }
+
+/// Computes the lazily computed parts of a completion item, when the client
+/// resolves the item. `position` is the position the item was completed at.
+/// Definitions which are gone by then are ignored.
+pub(crate) fn resolve_completion(
+    db: &db::RootDatabase,
+    position: FilePosition,
+    data: &CompletionResolveData,
+) -> ResolvedCompletion {
+    let def = data.definition.as_ref().and_then(|it| hir::ModuleDef::from_raw(db, it));
+    let (detail, documentation) = match (def, &data.field) {
+        (Some(def), Some(field)) => (None, presentation::field_documentation(db, def, field)),
+        (Some(def), None) => presentation::definition_details(db, def),
+        (None, _) => (None, None),
+    };
+    let additional_edits =
+        data.import.as_ref().and_then(|path| complete_scope::import_edit(db, position, path));
+    ResolvedCompletion { detail, documentation, additional_edits }
+}
+
+#[salsa::query_group(CompletionDatabaseStorage)]
+pub(crate) trait CompletionDatabase: hir::db::HirDatabase {
+    /// The shortest paths by which the public items of the dependencies of
+    /// `krate` can be imported into it.
+    #[salsa::invoke(complete_scope::extern_import_paths)]
+    fn extern_import_paths(
+        &self,
+        krate: hir::Crate,
+    ) -> Arc<FxHashMap<hir::ModuleDef, Vec<SmolStr>>>;
+}
@@ -15,7 +15,7 @@ pub(super) fn complete_dot(acc: &mut Completions, ctx: &CompletionContext) {
 }
 
 fn complete_fields(acc: &mut Completions, ctx: &CompletionContext, receiver: Ty) {
-    for receiver in receiver.autoderef(ctx.db) {
+    for receiver in ctx.analyzer.autoderef(ctx.db, receiver) {
         match receiver {
             Ty::Apply(a_ty) => match a_ty.ctor {
                 TypeCtor::Adt(AdtDef::Struct(s)) => {
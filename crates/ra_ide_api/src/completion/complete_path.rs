@@ -40,7 +40,7 @@ pub(super) fn complete_path(acc: &mut Completions, ctx: &CompletionContext) {
             let ty = s.ty(ctx.db);
             let krate = ctx.module.and_then(|m| m.krate(ctx.db));
             if let Some(krate) = krate {
-                ty.iterate_impl_items(ctx.db, krate, |item| {
+                ty.clone().iterate_impl_items(ctx.db, krate, |item| {
                     match item {
                         hir::ImplItem::Method(func) => {
                             let sig = func.signature(ctx.db);
@@ -54,6 +54,14 @@ pub(super) fn complete_path(acc: &mut Completions, ctx: &CompletionContext) {
                     None::<()>
                 });
             }
+            ctx.analyzer.iterate_path_candidates(ctx.db, ty, None, |_ty, func| {
+                // inherent functions are already completed above
+                let is_trait_fn = func.parent_trait(ctx.db).is_some();
+                if is_trait_fn && !func.signature(ctx.db).has_self_param() {
+                    acc.add_function(ctx, func);
+                }
+                None::<()>
+            });
         }
         _ => return,
     };
@@ -260,6 +268,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn completes_derived_trait_functions() {
+        let completions = do_completion(
+            "
+            //- /main.rs
+            #[derive(Default)]
+            struct S;
+
+            fn foo() { let _ = S::<|> }
+
+            //- /std/lib.rs
+            #[prelude_import]
+            use default::*;
+
+            mod default {
+                trait Default {
+                    fn default() -> Self;
+                }
+            }
+            ",
+            CompletionKind::Reference,
+        );
+        let labels: Vec<_> = completions.iter().map(|it| it.label()).collect();
+        assert_eq!(labels, ["default"]);
+    }
+
     #[test]
     fn completes_use_paths_across_crates() {
         check_reference_completion(
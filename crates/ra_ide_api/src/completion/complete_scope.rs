@@ -1,55 +1,330 @@
-use rustc_hash::FxHashMap;
-use ra_text_edit::TextEditBuilder;
-use ra_syntax::{SmolStr, ast, AstNode};
+use std::{collections::VecDeque, sync::Arc};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use ra_db::SourceDatabase;
+use ra_text_edit::{TextEdit, TextEditBuilder};
+use ra_syntax::{
+    SmolStr, SyntaxNode, TextRange, AstNode, TreeArc, SyntaxKind::*, ast,
+    algo::find_token_at_offset,
+};
 use ra_assists::auto_import;
+use hir::{Either, db::HirDatabase};
 
-use crate::completion::{CompletionItem, Completions, CompletionKind, CompletionContext};
+use crate::{
+    FilePosition, Query,
+    db::RootDatabase,
+    completion::{
+        CompletionItem, CompletionItemKind, Completions, CompletionKind, CompletionContext,
+        ImportOrigin, CompletionDatabase,
+    },
+    symbol_index::{self, FileSymbol},
+};
 
 pub(super) fn complete_scope(acc: &mut Completions, ctx: &CompletionContext) {
     if ctx.is_trivial_path {
         let names = ctx.analyzer.all_names(ctx.db);
+        let names_in_scope = names.keys().map(|name| name.to_string()).collect();
         names.into_iter().for_each(|(name, res)| acc.add_resolution(ctx, name.to_string(), &res));
 
-        // auto-import
-        // We fetch ident from the original file, because we need to pre-filter auto-imports
-        if ast::NameRef::cast(ctx.token.parent()).is_some() {
-            let import_resolver = ImportResolver::new();
-            let import_names = import_resolver.all_names(ctx.token.text());
-            import_names.into_iter().for_each(|(name, path)| {
-                let edit = {
-                    let mut builder = TextEditBuilder::default();
-                    builder.replace(ctx.source_range(), name.to_string());
-                    auto_import::auto_import_text_edit(
-                        ctx.token.parent(),
-                        ctx.token.parent(),
-                        &path,
-                        &mut builder,
-                    );
-                    builder.finish()
-                };
+        complete_imports(acc, ctx, &names_in_scope);
+    }
+}
 
-                // Hack: copied this check form conv.rs beacause auto import can produce edits
-                // that invalidate assert in conv_with.
-                if edit
-                    .as_atoms()
-                    .iter()
-                    .filter(|atom| !ctx.source_range().is_subrange(&atom.delete))
-                    .all(|atom| ctx.source_range().intersection(&atom.delete).is_none())
-                {
-                    CompletionItem::new(
-                        CompletionKind::Reference,
-                        ctx.source_range(),
-                        build_import_label(&name, &path),
-                    )
-                    .text_edit(edit)
-                    .add_to(acc);
+/// Completes items of the workspace and its dependencies which are not in
+/// scope yet. The edit adding a `use` for them is computed when the item is
+/// resolved, see `import_edit`.
+fn complete_imports(
+    acc: &mut Completions,
+    ctx: &CompletionContext,
+    names_in_scope: &FxHashSet<String>,
+) {
+    // We fetch ident from the original file, because we need to pre-filter auto-imports
+    if ast::NameRef::cast(ctx.token.parent()).is_none() || ctx.token.text().len() < 2 {
+        return;
+    }
+    if ctx.use_item_syntax.is_some() {
+        return;
+    }
+    let mut candidates = import_candidates(ctx);
+    candidates.retain(|it| !names_in_scope.contains(it.name.as_str()));
+    candidates.sort_by(|a, b| {
+        (!a.is_local, a.path.len(), &a.path).cmp(&(!b.is_local, b.path.len(), &b.path))
+    });
+    candidates.dedup_by(|a, b| a.path == b.path);
+
+    for candidate in candidates {
+        let origin =
+            if candidate.is_local { ImportOrigin::CurrentCrate } else { ImportOrigin::Dependency };
+        CompletionItem::new(
+            CompletionKind::Reference,
+            ctx.source_range(),
+            build_import_label(&candidate.name, &candidate.path),
+        )
+        .kind(candidate.kind)
+        .lookup_by(candidate.name.to_string())
+        .insert_text(candidate.name.to_string())
+        .definition(ctx.db, candidate.def)
+        .import(candidate.path)
+        .import_origin(origin)
+        .add_to(acc);
+    }
+}
+
+/// Computes the edit adding a `use` for `path`, for an item completed at
+/// `position`.
+pub(super) fn import_edit(
+    db: &RootDatabase,
+    position: FilePosition,
+    path: &[SmolStr],
+) -> Option<TextEdit> {
+    let file = db.parse(position.file_id);
+    let token = find_token_at_offset(file.syntax(), position.offset).left_biased()?;
+    let source_range = match token.kind() {
+        IDENT => token.range(),
+        _ => TextRange::offset_len(position.offset, 0.into()),
+    };
+    let mut builder = TextEditBuilder::default();
+    auto_import::auto_import_text_edit(token.parent(), token.parent(), path, &mut builder);
+    let edit = builder.finish();
+
+    // Hack: the client can't apply additional edits which touch the
+    // completed identifier.
+    if edit.as_atoms().iter().any(|atom| source_range.intersection(&atom.delete).is_some()) {
+        return None;
+    }
+    Some(edit)
+}
+
+struct ImportCandidate {
+    name: SmolStr,
+    /// The path to `use`, starting with `crate` or the name of a dependency.
+    path: Vec<SmolStr>,
+    kind: CompletionItemKind,
+    def: hir::ModuleDef,
+    /// Whether the item is defined in the current crate.
+    is_local: bool,
+}
+
+/// An item found in the symbol index which can be imported, if it is visible.
+struct ImportableItem {
+    symbol: FileSymbol,
+    kind: CompletionItemKind,
+    /// The module the item is defined in.
+    module: hir::Module,
+    def: hir::ModuleDef,
+    node: TreeArc<SyntaxNode>,
+}
+
+fn import_candidates(ctx: &CompletionContext) -> Vec<ImportCandidate> {
+    let current_module = match ctx.module {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let krate = match current_module.krate(ctx.db) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let mut query = Query::new(ctx.token.text().to_string());
+    query.limit(40);
+    let mut symbols = symbol_index::world_symbols(ctx.db, query);
+    let mut query = Query::new(ctx.token.text().to_string());
+    query.libs();
+    query.limit(40);
+    symbols.extend(symbol_index::world_symbols(ctx.db, query));
+
+    let items: Vec<ImportableItem> =
+        symbols.into_iter().filter_map(|symbol| importable_item(ctx, symbol)).collect();
+    let extern_paths = ctx.db.extern_import_paths(krate);
+
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let is_local = item.module.krate(ctx.db) == Some(krate);
+            let path = if is_local {
+                local_import_path(ctx, current_module, &item)?
+            } else {
+                extern_paths.get(&item.def)?.clone()
+            };
+            Some(ImportCandidate {
+                name: item.symbol.name,
+                path,
+                kind: item.kind,
+                def: item.def,
+                is_local,
+            })
+        })
+        .collect()
+}
+
+fn importable_item(ctx: &CompletionContext, symbol: FileSymbol) -> Option<ImportableItem> {
+    let kind = match symbol.ptr.kind() {
+        FN_DEF => CompletionItemKind::Function,
+        STRUCT_DEF => CompletionItemKind::Struct,
+        ENUM_DEF => CompletionItemKind::Enum,
+        TRAIT_DEF => CompletionItemKind::Trait,
+        MODULE => CompletionItemKind::Module,
+        TYPE_ALIAS_DEF => CompletionItemKind::TypeAlias,
+        CONST_DEF => CompletionItemKind::Const,
+        STATIC_DEF => CompletionItemKind::Static,
+        _ => return None,
+    };
+    let source_file = ctx.db.parse(symbol.file_id);
+    let node = symbol.ptr.to_node(source_file.syntax()).to_owned();
+    // Only items directly in a module can be imported, not the ones in impls
+    // or function bodies.
+    let parent = node.parent()?;
+    let is_module_item = match parent.kind() {
+        SOURCE_FILE | EXTERN_ITEM_LIST => true,
+        ITEM_LIST => parent.parent().map(|it| it.kind()) == Some(MODULE),
+        _ => false,
+    };
+    if !is_module_item {
+        return None;
+    }
+    let module = hir::source_binder::module_from_child_node(ctx.db, symbol.file_id, parent)?;
+    // Make sure the item is actually defined in the module, and not, for
+    // example, disabled by `#[cfg]`.
+    let def = module.scope(ctx.db).entries().find_map(|(name, res)| {
+        if res.import.is_some() || name.to_string() != symbol.name {
+            return None;
+        }
+        match kind {
+            CompletionItemKind::Function
+            | CompletionItemKind::Const
+            | CompletionItemKind::Static => res.def.values,
+            _ => res.def.types,
+        }
+    })?;
+    Some(ImportableItem { symbol, kind, module, def, node })
+}
+
+/// Returns the `crate::` path of an item of the current crate, if the item and
+/// all the modules on the path to it are visible from `current_module`.
+fn local_import_path(
+    ctx: &CompletionContext,
+    current_module: hir::Module,
+    item: &ImportableItem,
+) -> Option<Vec<SmolStr>> {
+    let ancestors = current_module.path_to_root(ctx.db);
+    // Private items are visible in the module they are declared in and its
+    // descendants.
+    let is_visible = |parent: Option<hir::Module>, node: &SyntaxNode| {
+        has_visibility(node) || parent.map_or(true, |it| ancestors.contains(&it))
+    };
+    if !is_visible(Some(item.module), &item.node) {
+        return None;
+    }
+    let mut path = vec![SmolStr::new("crate")];
+    for module in item.module.path_to_root(ctx.db).into_iter().rev() {
+        let decl = match module.declaration_source(ctx.db) {
+            Some((_, decl)) => decl,
+            // The crate root
+            None => continue,
+        };
+        if !is_visible(module.parent(ctx.db), decl.syntax()) {
+            return None;
+        }
+        path.push(module.name(ctx.db)?.to_string().into());
+    }
+    path.push(item.symbol.name.clone());
+    Some(path)
+}
+
+/// Finds the shortest paths by which the items of the dependencies of `krate`
+/// can be imported, by walking the public items and re-exports of each
+/// dependency, starting at its crate root. This finds `std::collections::HashMap`
+/// although `HashMap` is defined in a private module, and the items of `core`
+/// re-exported by `std`.
+///
+/// This only depends on the dependencies, so it is not recomputed when the
+/// crate itself changes.
+pub(super) fn extern_import_paths(
+    db: &impl HirDatabase,
+    krate: hir::Crate,
+) -> Arc<FxHashMap<hir::ModuleDef, Vec<SmolStr>>> {
+    let mut res = FxHashMap::default();
+    let mut visited = FxHashSet::default();
+    let mut queue = VecDeque::new();
+    let mut deps = krate.dependencies(db);
+    deps.sort_by_key(|dep| dep.name.to_string());
+    for dep in deps {
+        if let Some(root) = dep.krate.root_module(db) {
+            if visited.insert(root) {
+                queue.push_back((root, vec![SmolStr::new(dep.name.to_string())]));
+            }
+        }
+    }
+    while let Some((module, path)) = queue.pop_front() {
+        let scope = module.scope(db);
+        let mut entries: Vec<_> = scope.entries().collect();
+        // Deterministic paths if there are several of the same length
+        entries.sort_by_key(|(name, _)| name.to_string());
+        for (name, resolution) in entries {
+            for &def in resolution.def.types.iter().chain(resolution.def.values.iter()) {
+                if res.contains_key(&def) {
+                    continue;
+                }
+                if !is_visible_from_other_crates(db, module, resolution.import, def) {
+                    continue;
                 }
-            });
+                let mut def_path = path.clone();
+                def_path.push(name.to_string().into());
+                if let hir::ModuleDef::Module(it) = def {
+                    if visited.insert(it) {
+                        queue.push_back((it, def_path.clone()));
+                    }
+                }
+                res.insert(def, def_path);
+            }
+        }
+    }
+    Arc::new(res)
+}
+
+/// Checks whether the entry for `def` in the scope of `module` is `pub`, either
+/// as an item defined there or as a re-export.
+fn is_visible_from_other_crates(
+    db: &impl HirDatabase,
+    module: hir::Module,
+    import: Option<hir::ImportId>,
+    def: hir::ModuleDef,
+) -> bool {
+    if let Some(import) = import {
+        return match module.import_source(db, import) {
+            Either::A(use_tree) => use_tree
+                .syntax()
+                .ancestors()
+                .find_map(ast::UseItem::cast)
+                .map_or(false, |it| is_pub(it.syntax())),
+            Either::B(extern_crate) => is_pub(extern_crate.syntax()),
+        };
+    }
+    match def {
+        hir::ModuleDef::Module(it) => {
+            it.declaration_source(db).map_or(false, |(_, decl)| is_pub(decl.syntax()))
         }
+        hir::ModuleDef::Function(it) => is_pub(it.source(db).1.syntax()),
+        hir::ModuleDef::Struct(it) => is_pub(it.source(db).1.syntax()),
+        hir::ModuleDef::Enum(it) => is_pub(it.source(db).1.syntax()),
+        // Variants are as visible as their enum.
+        hir::ModuleDef::EnumVariant(_) => true,
+        hir::ModuleDef::Const(it) => is_pub(it.source(db).1.syntax()),
+        hir::ModuleDef::Static(it) => is_pub(it.source(db).1.syntax()),
+        hir::ModuleDef::Trait(it) => is_pub(it.source(db).1.syntax()),
+        hir::ModuleDef::TypeAlias(it) => is_pub(it.source(db).1.syntax()),
     }
 }
 
-fn build_import_label(name: &str, path: &Vec<SmolStr>) -> String {
+fn is_pub(node: &SyntaxNode) -> bool {
+    // `pub(crate)` and the like are not visible from other crates.
+    node.children().find(|it| it.kind() == VISIBILITY).map_or(false, |it| it.text() == "pub")
+}
+
+fn has_visibility(node: &SyntaxNode) -> bool {
+    node.children().any(|it| it.kind() == VISIBILITY)
+}
+
+fn build_import_label(name: &str, path: &[SmolStr]) -> String {
     let mut buf = String::with_capacity(64);
     buf.push_str(name);
     buf.push_str(" (");
@@ -58,7 +333,7 @@ fn build_import_label(name: &str, path: &Vec<SmolStr>) -> String {
     buf
 }
 
-fn fmt_import_path(path: &Vec<SmolStr>, buf: &mut String) {
+fn fmt_import_path(path: &[SmolStr], buf: &mut String) {
     let mut segments = path.iter();
     if let Some(s) = segments.next() {
         buf.push_str(&s);
@@ -69,59 +344,9 @@ fn fmt_import_path(path: &Vec<SmolStr>, buf: &mut String) {
     }
 }
 
-#[derive(Debug, Clone, Default)]
-pub(crate) struct ImportResolver {
-    // todo: use fst crate or something like that
-    dummy_names: Vec<(SmolStr, Vec<SmolStr>)>,
-}
-
-impl ImportResolver {
-    pub(crate) fn new() -> Self {
-        let dummy_names = vec![
-            (SmolStr::new("fmt"), vec![SmolStr::new("std"), SmolStr::new("fmt")]),
-            (SmolStr::new("io"), vec![SmolStr::new("std"), SmolStr::new("io")]),
-            (SmolStr::new("iter"), vec![SmolStr::new("std"), SmolStr::new("iter")]),
-            (SmolStr::new("hash"), vec![SmolStr::new("std"), SmolStr::new("hash")]),
-            (
-                SmolStr::new("Debug"),
-                vec![SmolStr::new("std"), SmolStr::new("fmt"), SmolStr::new("Debug")],
-            ),
-            (
-                SmolStr::new("Display"),
-                vec![SmolStr::new("std"), SmolStr::new("fmt"), SmolStr::new("Display")],
-            ),
-            (
-                SmolStr::new("Hash"),
-                vec![SmolStr::new("std"), SmolStr::new("hash"), SmolStr::new("Hash")],
-            ),
-            (
-                SmolStr::new("Hasher"),
-                vec![SmolStr::new("std"), SmolStr::new("hash"), SmolStr::new("Hasher")],
-            ),
-            (
-                SmolStr::new("Iterator"),
-                vec![SmolStr::new("std"), SmolStr::new("iter"), SmolStr::new("Iterator")],
-            ),
-        ];
-
-        ImportResolver { dummy_names }
-    }
-
-    // Returns a map of importable items filtered by name.
-    // The map associates item name with its full path.
-    // todo: should return Resolutions
-    pub(crate) fn all_names(&self, name: &str) -> FxHashMap<SmolStr, Vec<SmolStr>> {
-        if name.len() > 1 {
-            self.dummy_names.iter().filter(|(n, _)| n.contains(name)).cloned().collect()
-        } else {
-            FxHashMap::default()
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::completion::{CompletionKind, check_completion};
+    use crate::completion::{CompletionKind, ImportOrigin, check_completion, do_completion};
 
     fn check_reference_completion(name: &str, code: &str) {
         check_completion(name, code, CompletionKind::Reference);
@@ -289,4 +514,158 @@ mod tests {
             ",
         );
     }
+
+    #[test]
+    fn completes_items_to_import() {
+        let completions = do_completion(
+            "
+            //- /lib.rs
+            fn foo() { let x: HashMa<|> }
+
+            //- /std/lib.rs
+            pub mod collections {
+                /// A hash map.
+                pub struct HashMap;
+            }
+            ",
+            CompletionKind::Reference,
+        );
+        let item = completions
+            .iter()
+            .find(|it| it.label() == "HashMap (std::collections::HashMap)")
+            .expect("no import completion");
+        assert_eq!(item.lookup(), "HashMap");
+        assert_eq!(item.kind(), Some(crate::completion::CompletionItemKind::Struct));
+        assert_eq!(item.documentation().unwrap().contents(), "A hash map.");
+        assert!(item
+            .text_edit()
+            .as_atoms()
+            .iter()
+            .any(|it| it.insert.contains("use std::collections::HashMap")));
+    }
+
+    #[test]
+    fn dont_complete_imports_of_items_in_scope() {
+        let completions = do_completion(
+            "
+            //- /lib.rs
+            use std::collections::HashMap;
+            fn foo() { let x: HashMa<|> }
+
+            //- /std/lib.rs
+            pub mod collections {
+                pub struct HashMap;
+            }
+            ",
+            CompletionKind::Reference,
+        );
+        assert!(completions.iter().any(|it| it.label() == "HashMap"));
+        assert!(!completions.iter().any(|it| it.label().starts_with("HashMap (")));
+    }
+
+    #[test]
+    fn completes_imports_from_current_crate_first() {
+        use crate::mock_analysis::analysis_and_position;
+
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod bar {
+                pub struct Bazz;
+            }
+            fn foo() { let x: Baz<|> }
+
+            //- /std/lib.rs
+            pub mod collections {
+                pub struct Bazz;
+            }
+            ",
+        );
+        let completions: Vec<crate::completion::CompletionItem> =
+            crate::completion::completions(&analysis.db, position).unwrap().into();
+        let imports: Vec<_> = completions
+            .iter()
+            .filter(|it| it.label().starts_with("Bazz"))
+            .map(|it| (it.label(), it.import_origin()))
+            .collect();
+        assert_eq!(
+            imports,
+            vec![
+                ("Bazz (crate::bar::Bazz)", Some(ImportOrigin::CurrentCrate)),
+                ("Bazz (std::collections::Bazz)", Some(ImportOrigin::Dependency)),
+            ]
+        );
+    }
+
+    #[test]
+    fn dont_complete_imports_of_private_items_of_dependencies() {
+        let completions = do_completion(
+            "
+            //- /lib.rs
+            fn foo() { let x: HashMa<|> }
+
+            //- /std/lib.rs
+            pub mod collections {
+                pub struct HashMap;
+                pub(crate) struct HashMapRaw;
+            }
+            mod private {
+                pub struct HashMapImpl;
+            }
+            ",
+            CompletionKind::Reference,
+        );
+        let labels: Vec<&str> = completions
+            .iter()
+            .map(|it| it.label())
+            .filter(|it| it.starts_with("HashMap"))
+            .collect();
+        assert_eq!(labels, vec!["HashMap (std::collections::HashMap)"]);
+    }
+
+    #[test]
+    fn completes_imports_of_reexported_items_of_dependencies() {
+        let completions = do_completion(
+            "
+            //- /lib.rs
+            fn foo() { let x: HashMa<|> }
+
+            //- /std/lib.rs
+            pub mod collections {
+                pub use self::hash::HashMap;
+                mod hash {
+                    pub struct HashMap;
+                }
+            }
+            ",
+            CompletionKind::Reference,
+        );
+        let labels: Vec<&str> = completions
+            .iter()
+            .map(|it| it.label())
+            .filter(|it| it.starts_with("HashMap"))
+            .collect();
+        assert_eq!(labels, vec!["HashMap (std::collections::HashMap)"]);
+    }
+
+    #[test]
+    fn dont_complete_imports_of_private_items_of_the_current_crate() {
+        let completions = do_completion(
+            "
+            //- /lib.rs
+            mod bar {
+                mod private {
+                    pub struct Bazz;
+                }
+                struct Bazzz;
+                pub struct Bazy;
+            }
+            fn foo() { let x: Baz<|> }
+            ",
+            CompletionKind::Reference,
+        );
+        let labels: Vec<&str> =
+            completions.iter().map(|it| it.label()).filter(|it| it.starts_with("Baz")).collect();
+        assert_eq!(labels, vec!["Bazy (crate::bar::Bazy)"]);
+    }
 }
@@ -0,0 +1,211 @@
+use ra_assists::{add_body_and_strip_docstring, missing_impl_items};
+use ra_syntax::{
+    AstNode, TextRange,
+    ast::{self, NameOwner, TypeAscriptionOwner},
+};
+
+use crate::{
+    completion::{
+        CompletionContext, CompletionItem, CompletionItemKind, CompletionKind, Completions,
+    },
+    display::{function_label, const_label, type_label},
+};
+
+/// Completes the members of the implemented trait which are missing from a
+/// trait impl, when a member is being declared with `fn`, `type` or `const`.
+/// The whole member is inserted, like the `add_missing_impl_members` assist
+/// does, with the type parameters of the trait replaced by the impl's types.
+pub(super) fn complete_trait_impl(acc: &mut Completions, ctx: &CompletionContext) {
+    let (impl_block, (member_start, member_kind)) = match (ctx.impl_block_syntax, ctx.impl_member) {
+        (Some(impl_block), Some(member)) => (impl_block, member),
+        _ => return,
+    };
+    let range = TextRange::from_to(member_start, ctx.source_range().end());
+
+    for item in missing_impl_items(ctx.db, &ctx.analyzer, impl_block) {
+        if item.syntax().kind() != member_kind {
+            continue;
+        }
+        match item.kind() {
+            ast::ImplItemKind::FnDef(it) => add_fn(acc, range, it),
+            ast::ImplItemKind::TypeAliasDef(it) => add_type_alias(acc, range, it),
+            ast::ImplItemKind::ConstDef(it) => add_const(acc, range, it),
+        }
+    }
+}
+
+fn add_fn(acc: &mut Completions, range: TextRange, fn_def: &ast::FnDef) {
+    let name = match fn_def.name() {
+        Some(it) => it,
+        None => return,
+    };
+    let text = add_body_and_strip_docstring(fn_def).syntax().text().to_string();
+    CompletionItem::new(CompletionKind::Magic, range, function_label(fn_def))
+        .kind(CompletionItemKind::Function)
+        .lookup_by(format!("fn {}", name.text()))
+        .insert_text(text)
+        .add_to(acc);
+}
+
+fn add_type_alias(acc: &mut Completions, range: TextRange, type_alias: &ast::TypeAliasDef) {
+    let name = match type_alias.name() {
+        Some(it) => it,
+        None => return,
+    };
+    CompletionItem::new(CompletionKind::Magic, range, type_label(type_alias))
+        .kind(CompletionItemKind::TypeAlias)
+        .lookup_by(format!("type {}", name.text()))
+        .insert_snippet(format!("type {} = $0;", name.text()))
+        .add_to(acc);
+}
+
+fn add_const(acc: &mut Completions, range: TextRange, const_def: &ast::ConstDef) {
+    let (name, ty) = match (const_def.name(), const_def.ascribed_type()) {
+        (Some(name), Some(ty)) => (name, ty),
+        _ => return,
+    };
+    CompletionItem::new(CompletionKind::Magic, range, const_label(const_def))
+        .kind(CompletionItemKind::Const)
+        .lookup_by(format!("const {}", name.text()))
+        .insert_snippet(format!("const {}: {} = $0;", name.text(), ty.syntax().text()))
+        .add_to(acc);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::{do_completion, CompletionItem, CompletionKind};
+    use insta::assert_debug_snapshot_matches;
+
+    fn do_trait_impl_completion(code: &str) -> Vec<CompletionItem> {
+        do_completion(code, CompletionKind::Magic)
+    }
+
+    #[test]
+    fn completes_missing_fns() {
+        assert_debug_snapshot_matches!(
+        do_trait_impl_completion(
+                "
+                trait Foo {
+                    fn foo(&self);
+                    fn bar(&self) -> u32;
+                    type Output;
+                }
+                struct S;
+                impl Foo for S {
+                    fn bar(&self) -> u32 { 92 }
+                    fn f<|>
+                }
+                ",
+        ),
+            @r###"[
+    CompletionItem {
+        label: "fn foo(&self)",
+        source_range: [284; 288),
+        delete: [284; 288),
+        insert: "fn foo(&self) { unimplemented!() }",
+        kind: Function,
+        lookup: "fn foo"
+    }
+]"###
+        );
+    }
+
+    #[test]
+    fn substitutes_type_params() {
+        assert_debug_snapshot_matches!(
+        do_trait_impl_completion(
+                "
+                trait Foo<T> {
+                    /// Docs are not copied.
+                    fn foo(&self, t: T) -> Option<T>;
+                    const C: T;
+                }
+                struct S;
+                impl Foo<u32> for S {
+                    fn <|>
+                }
+                ",
+        ),
+            @r###"[
+    CompletionItem {
+        label: "fn foo(&self, t: u32) -> Option<u32>",
+        source_range: [265; 268),
+        delete: [265; 268),
+        insert: "fn foo(&self, t: u32) -> Option<u32> { unimplemented!() }",
+        kind: Function,
+        lookup: "fn foo"
+    }
+]"###
+        );
+    }
+
+    #[test]
+    fn completes_missing_types_and_consts() {
+        assert_debug_snapshot_matches!(
+        do_trait_impl_completion(
+                "
+                trait Foo<T> {
+                    type Output;
+                    const C: T;
+                }
+                struct S;
+                impl Foo<u8> for S {
+                    const <|>
+                }
+                ",
+        ),
+            @r###"[
+    CompletionItem {
+        label: "const C: u8;",
+        source_range: [198; 204),
+        delete: [198; 204),
+        insert: "const C: u8 = $0;",
+        kind: Const,
+        lookup: "const C"
+    }
+]"###
+        );
+    }
+
+    #[test]
+    fn completes_missing_types() {
+        assert_debug_snapshot_matches!(
+        do_trait_impl_completion(
+                "
+                trait Foo<T> {
+                    type Output;
+                    const C: T;
+                }
+                struct S;
+                impl Foo<u8> for S {
+                    type <|>
+                }
+                ",
+        ),
+            @r###"[
+    CompletionItem {
+        label: "type Output;",
+        source_range: [198; 203),
+        delete: [198; 203),
+        insert: "type Output = $0;",
+        kind: TypeAlias,
+        lookup: "type Output"
+    }
+]"###
+        );
+    }
+
+    #[test]
+    fn dont_complete_outside_of_trait_impls() {
+        let completions = do_trait_impl_completion(
+            "
+            trait Foo { fn foo(&self); }
+            struct S;
+            impl S {
+                fn <|>
+            }
+            ",
+        );
+        assert!(completions.is_empty());
+    }
+}
@@ -1,13 +1,13 @@
 use ra_text_edit::AtomTextEdit;
 use ra_syntax::{
-    AstNode, SyntaxNode, SourceFile, TextUnit, TextRange, SyntaxToken,
-    ast,
+    AstNode, SyntaxNode, SourceFile, TextUnit, TextRange, SyntaxToken, SyntaxKind,
+    ast::{self, NameOwner, TypeAscriptionOwner},
     algo::{find_token_at_offset, find_covering_element, find_node_at_offset},
     SyntaxKind::*,
 };
-use hir::source_binder;
+use hir::{source_binder, AdtDef};
 
-use crate::{db, FilePosition};
+use crate::{db, call_info::call_info, FilePosition};
 
 /// `CompletionContext` is created early during completion to figure out, where
 /// exactly is the cursor, syntax-wise.
@@ -21,6 +21,12 @@ pub(crate) struct CompletionContext<'a> {
     pub(super) function_syntax: Option<&'a ast::FnDef>,
     pub(super) use_item_syntax: Option<&'a ast::UseItem>,
     pub(super) struct_lit_syntax: Option<&'a ast::StructLit>,
+    /// The trait impl a member is being declared in, like in
+    /// `impl Foo for S { fn <|> }`.
+    pub(super) impl_block_syntax: Option<&'a ast::ImplBlock>,
+    /// The start and the kind (`FN_DEF`, `TYPE_ALIAS_DEF` or `CONST_DEF`) of
+    /// the member being declared in `impl_block_syntax`.
+    pub(super) impl_member: Option<(TextUnit, SyntaxKind)>,
     pub(super) is_param: bool,
     /// If a name-binding or reference to a const in a pattern.
     /// Irrefutable patterns (like let) are excluded.
@@ -38,6 +44,12 @@ pub(crate) struct CompletionContext<'a> {
     pub(super) dot_receiver: Option<&'a ast::Expr>,
     /// If this is a call (method or function) in particular, i.e. the () are already there.
     pub(super) is_call: bool,
+    /// The type the completed expression should have, like the type of the
+    /// parameter an argument is passed to.
+    pub(super) expected_type: Option<hir::Ty>,
+    /// The name the completed expression is bound to, like the name of the
+    /// parameter or of the struct field.
+    pub(super) expected_name: Option<String>,
 }
 
 impl<'a> CompletionContext<'a> {
@@ -59,6 +71,8 @@ impl<'a> CompletionContext<'a> {
             function_syntax: None,
             use_item_syntax: None,
             struct_lit_syntax: None,
+            impl_block_syntax: None,
+            impl_member: None,
             is_param: false,
             is_pat_binding: false,
             is_trivial_path: false,
@@ -68,8 +82,10 @@ impl<'a> CompletionContext<'a> {
             is_new_item: false,
             dot_receiver: None,
             is_call: false,
+            expected_type: None,
+            expected_name: None,
         };
-        ctx.fill(original_file, position.offset);
+        ctx.fill(original_file, position);
         Some(ctx)
     }
 
@@ -82,7 +98,8 @@ impl<'a> CompletionContext<'a> {
         }
     }
 
-    fn fill(&mut self, original_file: &'a SourceFile, offset: TextUnit) {
+    fn fill(&mut self, original_file: &'a SourceFile, position: FilePosition) {
+        let offset = position.offset;
         // Insert a fake ident to get a valid parse tree. We will use this file
         // to determine context, though the original_file will be used for
         // actual completion.
@@ -100,6 +117,7 @@ impl<'a> CompletionContext<'a> {
                 return;
             }
             self.classify_name_ref(original_file, name_ref);
+            self.fill_expected_type(original_file, position, name_ref);
         }
 
         // Otherwise, see if this is a declaration. We can use heuristics to
@@ -118,6 +136,42 @@ impl<'a> CompletionContext<'a> {
                 self.is_param = true;
                 return;
             }
+            if let Some(item) = name.syntax().parent().and_then(ast::ImplItem::cast) {
+                self.classify_impl_member(original_file, item);
+            }
+        }
+    }
+
+    fn classify_impl_member(&mut self, original_file: &'a SourceFile, item: &ast::ImplItem) {
+        // Only the name is typed so far, the rest of the declaration is
+        // going to be inserted.
+        let only_name_typed = match item.kind() {
+            ast::ImplItemKind::FnDef(it) => it.param_list().is_none(),
+            ast::ImplItemKind::TypeAliasDef(it) => it.type_ref().is_none(),
+            ast::ImplItemKind::ConstDef(it) => it.ascribed_type().is_none(),
+        };
+        let in_impl_block = item
+            .syntax()
+            .parent()
+            .and_then(|it| it.parent())
+            .and_then(ast::ImplBlock::cast)
+            .map_or(false, |it| it.target_trait().is_some());
+        if !only_name_typed || !in_impl_block {
+            return;
+        }
+        // Attributes and doc comments are kept, so the member starts after
+        // them.
+        let start = item
+            .syntax()
+            .children_with_tokens()
+            .find(|it| match it.kind() {
+                ATTR | COMMENT | WHITESPACE => false,
+                _ => true,
+            })
+            .map(|it| it.range().start());
+        if let Some(start) = start {
+            self.impl_block_syntax = find_node_at_offset(original_file.syntax(), self.offset);
+            self.impl_member = Some((start, item.syntax().kind()));
         }
     }
 
@@ -219,6 +273,132 @@ impl<'a> CompletionContext<'a> {
             self.is_call = true;
         }
     }
+
+    fn fill_expected_type(
+        &mut self,
+        original_file: &'a SourceFile,
+        position: FilePosition,
+        name_ref: &ast::NameRef,
+    ) {
+        // Skip the expression being completed, like `foo<|>` or `foo.bar<|>`,
+        // to get to the node which determines what is expected.
+        let expr = match name_ref
+            .syntax()
+            .ancestors()
+            .take_while(|it| match it.kind() {
+                NAME_REF | PATH_SEGMENT | PATH | PATH_EXPR | FIELD_EXPR => true,
+                _ => false,
+            })
+            .last()
+        {
+            Some(it) => it,
+            None => return,
+        };
+        let (ty, name) = match expr.parent() {
+            Some(parent) => self.expected_type_and_name(original_file, position, expr, parent),
+            None => return,
+        };
+        // Unit is the type of almost every tail expression, so it doesn't
+        // tell anything.
+        self.expected_type = ty.filter(|it| *it != hir::Ty::Unknown && *it != hir::Ty::unit());
+        self.expected_name = name;
+    }
+
+    fn expected_type_and_name(
+        &self,
+        original_file: &'a SourceFile,
+        position: FilePosition,
+        expr: &SyntaxNode,
+        parent: &SyntaxNode,
+    ) -> (Option<hir::Ty>, Option<String>) {
+        let db = self.db;
+        if let Some(let_stmt) = ast::LetStmt::cast(parent) {
+            // `let x: u32 = <|>`
+            let pat = match let_stmt.pat() {
+                Some(it) if is_same_node(let_stmt.initializer(), expr) => it,
+                _ => return (None, None),
+            };
+            let name = ast::BindPat::cast(pat.syntax())
+                .and_then(|it| it.name())
+                .map(|it| it.text().to_string());
+            let ty = find_node_with_range::<ast::Pat>(original_file.syntax(), pat.syntax().range())
+                .and_then(|pat| self.analyzer.type_of_pat(db, pat));
+            return (ty, name);
+        }
+        if let Some(arg_list) = ast::ArgList::cast(parent) {
+            // `foo(<|>)`
+            let info = match call_info(db, position) {
+                Some(it) => it,
+                None => return (None, None),
+            };
+            let param = match info.active_parameter {
+                Some(it) => it,
+                None => return (None, None),
+            };
+            let name = info.signature.parameter_names.get(param).cloned();
+            let call = arg_list.syntax().parent();
+            let sig = if let Some(call) = call.and_then(ast::CallExpr::cast) {
+                // The callee comes before the fake ident, so it has the same
+                // range in the original file.
+                call.expr()
+                    .and_then(|callee| {
+                        find_node_with_range::<ast::Expr>(
+                            original_file.syntax(),
+                            callee.syntax().range(),
+                        )
+                    })
+                    .and_then(|callee| self.analyzer.type_of(db, callee))
+                    .and_then(|ty| ty.callable_sig(db))
+            } else if call.and_then(ast::MethodCallExpr::cast).is_some() {
+                find_node_at_offset::<ast::MethodCallExpr>(original_file.syntax(), self.offset)
+                    .and_then(|call| self.analyzer.resolve_method_call(call))
+                    .and_then(|function| function.ty(db).callable_sig(db))
+            } else {
+                None
+            };
+            let ty = sig.and_then(|sig| sig.params().get(param).cloned());
+            return (ty, name);
+        }
+        if let Some(field) = ast::NamedField::cast(parent) {
+            // `Foo { bar: <|> }`
+            let name = match field.name_ref() {
+                Some(it) if is_same_node(field.expr(), expr) => it.text().to_string(),
+                _ => return (None, None),
+            };
+            let ty = find_node_at_offset::<ast::StructLit>(original_file.syntax(), self.offset)
+                .and_then(|struct_lit| self.analyzer.type_of(db, struct_lit.into()))
+                .and_then(|ty| match ty.as_adt()? {
+                    (AdtDef::Struct(s), substs) => {
+                        let field =
+                            s.fields(db).into_iter().find(|it| it.name(db).to_string() == name)?;
+                        Some(field.ty(db).subst(substs))
+                    }
+                    (AdtDef::Enum(_), _) => None,
+                });
+            return (ty, Some(name));
+        }
+        // `return <|>` or the tail expression of a function body
+        let is_return = parent.kind() == RETURN_EXPR
+            || ast::Block::cast(parent).map_or(false, |block| {
+                is_same_node(block.expr(), expr)
+                    && block.syntax().parent().map(|it| it.kind()) == Some(FN_DEF)
+            });
+        let is_in_closure = expr
+            .ancestors()
+            .find(|it| it.kind() == FN_DEF || it.kind() == LAMBDA_EXPR)
+            .map_or(true, |it| it.kind() == LAMBDA_EXPR);
+        if is_return && !is_in_closure {
+            let ty = match (self.module, self.function_syntax) {
+                (Some(module), Some(fn_def)) => {
+                    let function = source_binder::function_from_module(db, module, fn_def);
+                    function.ty(db).callable_sig(db).map(|sig| sig.ret().clone())
+                }
+                _ => None,
+            };
+            return (ty, None);
+        }
+        (None, None)
+    }
 }
 
 fn find_node_with_range<N: AstNode>(syntax: &SyntaxNode, range: TextRange) -> Option<&N> {
@@ -231,3 +411,7 @@ fn is_node<N: AstNode>(node: &SyntaxNode) -> bool {
         Some(n) => n.syntax().range() == node.range(),
     }
 }
+
+fn is_same_node(expr: Option<&ast::Expr>, node: &SyntaxNode) -> bool {
+    expr.map_or(false, |it| it.syntax() == node)
+}
@@ -1,7 +1,7 @@
 use std::fmt;
 
-use hir::Documentation;
-use ra_syntax::TextRange;
+use hir::{Documentation, RawModuleDef, db::HirDatabase};
+use ra_syntax::{SmolStr, TextRange};
 use ra_text_edit::{TextEditBuilder, TextEdit};
 
 /// `CompletionItem` describes a single completion variant in the editor pop-up.
@@ -42,6 +42,17 @@ pub struct CompletionItem {
     /// Additional info to show in the UI pop up.
     detail: Option<String>,
     documentation: Option<Documentation>,
+
+    /// How well the item fits the expected type and name at the cursor.
+    score: Option<CompletionScore>,
+
+    /// For items which are not in scope yet, where the `use` added for them
+    /// imports them from.
+    import_origin: Option<ImportOrigin>,
+
+    /// What is needed to compute the parts of the item which are only
+    /// computed when the item is resolved.
+    resolve_data: Option<CompletionResolveData>,
 }
 
 // We use custom debug for CompletionItem to make `insta`'s diffs more readable.
@@ -68,6 +79,12 @@ impl fmt::Debug for CompletionItem {
         if let Some(documentation) = self.documentation() {
             s.field("documentation", &documentation);
         }
+        if let Some(score) = self.score() {
+            s.field("score", &score);
+        }
+        if let Some(import_origin) = self.import_origin() {
+            s.field("import_origin", &import_origin);
+        }
         s.finish()
     }
 }
@@ -92,6 +109,51 @@ pub enum CompletionItemKind {
     Macro,
 }
 
+/// Identifies the parts of a completion item which are computed lazily, when
+/// the client resolves the item with `Analysis::resolve_completion`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompletionResolveData {
+    /// The definition of the item, as encoded by `hir::ModuleDef::to_raw`.
+    /// The documentation and detail of the item are computed from it.
+    pub definition: Option<RawModuleDef>,
+    /// For fields, the name of the field of the `definition` struct or
+    /// variant. Only the documentation of the field is computed.
+    pub field: Option<SmolStr>,
+    /// The path to `use` for items which are not in scope yet.
+    pub import: Option<Vec<SmolStr>>,
+}
+
+/// The lazily computed parts of a completion item.
+#[derive(Debug, Default)]
+pub struct ResolvedCompletion {
+    pub detail: Option<String>,
+    pub documentation: Option<Documentation>,
+    /// Edits to apply together with the completion, like adding a `use`.
+    pub additional_edits: Option<TextEdit>,
+}
+
+/// The relevance of a completion item, used to sort the items matching the
+/// expected type or name at the cursor first. Items without a score go last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompletionScore {
+    /// The name of the item is the expected one, like the name of the
+    /// parameter an argument is passed to.
+    NameMatch,
+    /// The type of the item is the expected one.
+    TypeMatch,
+    /// Both the type and the name of the item are the expected ones.
+    TypeAndNameMatch,
+}
+
+/// Where an item which is completed together with a `use` for it comes from.
+/// Such items are sorted after the ones in scope, and the items of the current
+/// crate go before the ones of its dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportOrigin {
+    CurrentCrate,
+    Dependency,
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub(crate) enum CompletionKind {
     /// Parser-based keyword completion.
@@ -128,6 +190,9 @@ impl CompletionItem {
             lookup: None,
             kind: None,
             text_edit: None,
+            score: None,
+            import_origin: None,
+            resolve_data: None,
         }
     }
     /// What user sees in pop-up in the UI.
@@ -162,6 +227,20 @@ impl CompletionItem {
     pub fn kind(&self) -> Option<CompletionItemKind> {
         self.kind
     }
+
+    pub fn score(&self) -> Option<CompletionScore> {
+        self.score
+    }
+
+    pub fn import_origin(&self) -> Option<ImportOrigin> {
+        self.import_origin
+    }
+
+    /// What is needed to resolve the item, if some of its parts are computed
+    /// lazily.
+    pub fn resolve_data(&self) -> Option<&CompletionResolveData> {
+        self.resolve_data.as_ref()
+    }
 }
 
 /// A helper to make `CompletionItem`s.
@@ -177,6 +256,9 @@ pub(crate) struct Builder {
     lookup: Option<String>,
     kind: Option<CompletionItemKind>,
     text_edit: Option<TextEdit>,
+    score: Option<CompletionScore>,
+    import_origin: Option<ImportOrigin>,
+    resolve_data: Option<CompletionResolveData>,
 }
 
 impl Builder {
@@ -206,6 +288,9 @@ impl Builder {
             lookup: self.lookup,
             kind: self.kind,
             completion_kind: self.completion_kind,
+            score: self.score,
+            import_origin: self.import_origin,
+            resolve_data: self.resolve_data,
         }
     }
     pub(crate) fn lookup_by(mut self, lookup: impl Into<String>) -> Builder {
@@ -248,6 +333,47 @@ impl Builder {
         self.documentation = docs.map(Into::into);
         self
     }
+    pub(crate) fn set_score(mut self, score: Option<CompletionScore>) -> Builder {
+        self.score = score;
+        self
+    }
+    /// Defers computing the documentation and detail of the item until it is
+    /// resolved.
+    pub(crate) fn definition(
+        mut self,
+        db: &impl HirDatabase,
+        definition: impl Into<hir::ModuleDef>,
+    ) -> Builder {
+        let data = self.resolve_data.get_or_insert_with(Default::default);
+        data.definition = Some(definition.into().to_raw(db));
+        self
+    }
+    /// Defers computing the documentation of `field` until the item is
+    /// resolved.
+    pub(crate) fn field_definition(
+        mut self,
+        db: &impl HirDatabase,
+        field: hir::StructField,
+    ) -> Builder {
+        let parent: hir::ModuleDef = match field.parent_def(db) {
+            hir::VariantDef::Struct(it) => it.into(),
+            hir::VariantDef::EnumVariant(it) => it.into(),
+        };
+        let data = self.resolve_data.get_or_insert_with(Default::default);
+        data.definition = Some(parent.to_raw(db));
+        data.field = Some(field.name(db).as_smolstr().clone());
+        self
+    }
+    /// Defers computing the edit adding a `use` for `path` until the item is
+    /// resolved.
+    pub(crate) fn import(mut self, path: Vec<SmolStr>) -> Builder {
+        self.resolve_data.get_or_insert_with(Default::default).import = Some(path);
+        self
+    }
+    pub(crate) fn import_origin(mut self, origin: ImportOrigin) -> Builder {
+        self.import_origin = Some(origin);
+        self
+    }
 }
 
 impl<'a> Into<CompletionItem> for Builder {
@@ -281,6 +407,31 @@ impl Into<Vec<CompletionItem>> for Completions {
     }
 }
 
+#[cfg(test)]
+impl CompletionItem {
+    fn resolved(mut self, db: &crate::db::RootDatabase, position: ra_db::FilePosition) -> Self {
+        let data = match self.resolve_data.take() {
+            Some(it) => it,
+            None => return self,
+        };
+        let resolved = crate::completion::resolve_completion(db, position, &data);
+        if resolved.detail.is_some() {
+            self.detail = resolved.detail;
+        }
+        if resolved.documentation.is_some() {
+            self.documentation = resolved.documentation;
+        }
+        if let Some(edit) = resolved.additional_edits {
+            let mut builder = TextEditBuilder::default();
+            for atom in self.text_edit.as_atoms().iter().chain(edit.as_atoms()) {
+                builder.replace(atom.delete, atom.insert.clone());
+            }
+            self.text_edit = builder.finish();
+        }
+        self
+    }
+}
+
 #[cfg(test)]
 pub(crate) fn do_completion(code: &str, kind: CompletionKind) -> Vec<CompletionItem> {
     use crate::mock_analysis::{single_file_with_position, analysis_and_position};
@@ -292,8 +443,12 @@ pub(crate) fn do_completion(code: &str, kind: CompletionKind) -> Vec<CompletionI
     };
     let completions = completions(&analysis.db, position).unwrap();
     let completion_items: Vec<CompletionItem> = completions.into();
-    let mut kind_completions: Vec<CompletionItem> =
-        completion_items.into_iter().filter(|c| c.completion_kind == kind).collect();
+    // Resolve the items, so that the tests see everything the client does.
+    let mut kind_completions: Vec<CompletionItem> = completion_items
+        .into_iter()
+        .filter(|c| c.completion_kind == kind)
+        .map(|c| c.resolved(&analysis.db, position))
+        .collect();
     kind_completions.sort_by_key(|c| c.label.clone());
     kind_completions
 }
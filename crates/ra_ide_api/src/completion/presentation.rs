@@ -1,15 +1,16 @@
 //! This modules takes care of rendering various defenitions as completion items.
 use join_to_string::join;
 use test_utils::tested_by;
-use hir::{Docs, PerNs, Resolution, HirDisplay};
-use ra_syntax::ast::NameOwner;
+use hir::{AdtDef, Docs, Documentation, PerNs, Resolution, HirDisplay, Ty};
+use ra_syntax::{SmolStr, ast::NameOwner};
 
-use crate::completion::{
-    Completions, CompletionKind, CompletionItemKind, CompletionContext, CompletionItem,
-};
-
-use crate::display::{
-    function_label, const_label, type_label,
+use crate::{
+    db::RootDatabase,
+    completion::{
+        Completions, CompletionKind, CompletionItemKind, CompletionContext, CompletionItem,
+        CompletionScore,
+    },
+    display::{function_label, const_label, type_label},
 };
 
 impl Completions {
@@ -19,21 +20,21 @@ impl Completions {
         field: hir::StructField,
         substs: &hir::Substs,
     ) {
-        CompletionItem::new(
-            CompletionKind::Reference,
-            ctx.source_range(),
-            field.name(ctx.db).to_string(),
-        )
-        .kind(CompletionItemKind::Field)
-        .detail(field.ty(ctx.db).subst(substs).display(ctx.db).to_string())
-        .set_documentation(field.docs(ctx.db))
-        .add_to(self);
+        let name = field.name(ctx.db).to_string();
+        let ty = field.ty(ctx.db).subst(substs);
+        CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name.clone())
+            .kind(CompletionItemKind::Field)
+            .detail(ty.display(ctx.db).to_string())
+            .field_definition(ctx.db, field)
+            .set_score(compute_score(ctx, is_expected_type(ctx, &ty), &name))
+            .add_to(self);
     }
 
     pub(crate) fn add_pos_field(&mut self, ctx: &CompletionContext, field: usize, ty: &hir::Ty) {
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), field.to_string())
             .kind(CompletionItemKind::Field)
             .detail(ty.display(ctx.db).to_string())
+            .set_score(compute_score(ctx, is_expected_type(ctx, ty), &field.to_string()))
             .add_to(self);
     }
 
@@ -57,29 +58,42 @@ impl Completions {
             }
             Some(it) => it,
         };
-        let (kind, docs) = match def {
-            Resolution::Def(Module(it)) => (CompletionItemKind::Module, it.docs(ctx.db)),
+        let kind = match def {
+            Resolution::Def(Module(..)) => CompletionItemKind::Module,
             Resolution::Def(Function(func)) => {
                 return self.add_function_with_name(ctx, Some(local_name), *func);
             }
-            Resolution::Def(Struct(it)) => (CompletionItemKind::Struct, it.docs(ctx.db)),
-            Resolution::Def(Enum(it)) => (CompletionItemKind::Enum, it.docs(ctx.db)),
-            Resolution::Def(EnumVariant(it)) => (CompletionItemKind::EnumVariant, it.docs(ctx.db)),
-            Resolution::Def(Const(it)) => (CompletionItemKind::Const, it.docs(ctx.db)),
-            Resolution::Def(Static(it)) => (CompletionItemKind::Static, it.docs(ctx.db)),
-            Resolution::Def(Trait(it)) => (CompletionItemKind::Trait, it.docs(ctx.db)),
-            Resolution::Def(TypeAlias(it)) => (CompletionItemKind::TypeAlias, it.docs(ctx.db)),
-            Resolution::GenericParam(..) => (CompletionItemKind::TypeParam, None),
-            Resolution::LocalBinding(..) => (CompletionItemKind::Binding, None),
-            Resolution::SelfType(..) => (
-                CompletionItemKind::TypeParam, // (does this need its own kind?)
-                None,
-            ),
+            Resolution::Def(Struct(..)) => CompletionItemKind::Struct,
+            Resolution::Def(Enum(..)) => CompletionItemKind::Enum,
+            Resolution::Def(EnumVariant(..)) => CompletionItemKind::EnumVariant,
+            Resolution::Def(Const(..)) => CompletionItemKind::Const,
+            Resolution::Def(Static(..)) => CompletionItemKind::Static,
+            Resolution::Def(Trait(..)) => CompletionItemKind::Trait,
+            Resolution::Def(TypeAlias(..)) => CompletionItemKind::TypeAlias,
+            Resolution::GenericParam(..) => CompletionItemKind::TypeParam,
+            Resolution::LocalBinding(..) => CompletionItemKind::Binding,
+            Resolution::SelfType(..) => {
+                CompletionItemKind::TypeParam // (does this need its own kind?)
+            }
+        };
+        let type_match = match def {
+            Resolution::Def(EnumVariant(it)) => is_expected_variant(ctx, *it),
+            Resolution::Def(Const(it)) => is_expected_type(ctx, &it.ty(ctx.db)),
+            Resolution::Def(Static(it)) => is_expected_type(ctx, &it.ty(ctx.db)),
+            Resolution::LocalBinding(..) => {
+                ctx.analyzer.type_of_local(def).map_or(false, |ty| is_expected_type(ctx, &ty))
+            }
+            _ => false,
         };
-        CompletionItem::new(CompletionKind::Reference, ctx.source_range(), local_name)
-            .kind(kind)
-            .set_documentation(docs)
-            .add_to(self)
+        let score = compute_score(ctx, type_match, &local_name);
+        let mut builder =
+            CompletionItem::new(CompletionKind::Reference, ctx.source_range(), local_name)
+                .kind(kind)
+                .set_score(score);
+        if let Resolution::Def(def) = def {
+            builder = builder.definition(ctx.db, *def);
+        }
+        builder.add_to(self)
     }
 
     pub(crate) fn add_function(&mut self, ctx: &CompletionContext, func: hir::Function) {
@@ -94,8 +108,11 @@ impl Completions {
     ) {
         let sig = func.signature(ctx.db);
         let name = name.unwrap_or_else(|| sig.name().to_string());
-        let (_, ast_node) = func.source(ctx.db);
-        let detail = function_label(&ast_node);
+        let type_match = func
+            .ty(ctx.db)
+            .callable_sig(ctx.db)
+            .map_or(false, |it| is_expected_type(ctx, it.ret()));
+        let score = compute_score(ctx, type_match, &name);
 
         let mut builder = CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name)
             .kind(if sig.has_self_param() {
@@ -103,8 +120,8 @@ impl Completions {
             } else {
                 CompletionItemKind::Function
             })
-            .set_documentation(func.docs(ctx.db))
-            .detail(detail);
+            .definition(ctx.db, func)
+            .set_score(score);
         // If not an import, add parenthesis automatically.
         if ctx.use_item_syntax.is_none() && !ctx.is_call {
             tested_by!(inserts_parens_for_function_calls);
@@ -125,13 +142,12 @@ impl Completions {
             Some(name) => name,
             _ => return,
         };
-        let (_, ast_node) = constant.source(ctx.db);
-        let detail = const_label(&ast_node);
+        let score = compute_score(ctx, is_expected_type(ctx, &constant.ty(ctx.db)), name.text());
 
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name.text().to_string())
             .kind(CompletionItemKind::Const)
-            .set_documentation(constant.docs(ctx.db))
-            .detail(detail)
+            .definition(ctx.db, constant)
+            .set_score(score)
             .add_to(self);
     }
 
@@ -141,13 +157,10 @@ impl Completions {
             Some(name) => name,
             _ => return,
         };
-        let (_, ast_node) = type_alias.source(ctx.db);
-        let detail = type_label(&ast_node);
 
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name.text().to_string())
             .kind(CompletionItemKind::TypeAlias)
-            .set_documentation(type_alias.docs(ctx.db))
-            .detail(detail)
+            .definition(ctx.db, type_alias)
             .add_to(self);
     }
 
@@ -161,25 +174,103 @@ impl Completions {
             .separator(", ")
             .surround_with("(", ")")
             .to_string();
+        let score = compute_score(ctx, is_expected_variant(ctx, variant), &name.to_string());
 
         CompletionItem::new(CompletionKind::Reference, ctx.source_range(), name.to_string())
             .kind(CompletionItemKind::EnumVariant)
-            .set_documentation(variant.docs(ctx.db))
             .detail(detail)
+            .definition(ctx.db, variant)
+            .set_score(score)
             .add_to(self);
     }
 }
 
+/// Computes the detail and the documentation of `def`, for a completion item
+/// which is resolved.
+pub(super) fn definition_details(
+    db: &RootDatabase,
+    def: hir::ModuleDef,
+) -> (Option<String>, Option<Documentation>) {
+    use hir::ModuleDef::*;
+
+    match def {
+        Module(it) => (None, it.docs(db)),
+        Function(it) => (Some(function_label(&it.source(db).1)), it.docs(db)),
+        Struct(it) => (None, it.docs(db)),
+        Enum(it) => (None, it.docs(db)),
+        EnumVariant(it) => (None, it.docs(db)),
+        Const(it) => (Some(const_label(&it.source(db).1)), it.docs(db)),
+        Static(it) => (None, it.docs(db)),
+        Trait(it) => (None, it.docs(db)),
+        TypeAlias(it) => (Some(type_label(&it.source(db).1)), it.docs(db)),
+    }
+}
+
+/// Computes the documentation of the field `name` of `def`, for a completion
+/// item which is resolved.
+pub(super) fn field_documentation(
+    db: &RootDatabase,
+    def: hir::ModuleDef,
+    name: &SmolStr,
+) -> Option<Documentation> {
+    let fields = match def {
+        hir::ModuleDef::Struct(it) => it.fields(db),
+        hir::ModuleDef::EnumVariant(it) => it.fields(db),
+        _ => return None,
+    };
+    fields.into_iter().find(|it| it.name(db).as_smolstr() == name)?.docs(db)
+}
+
+fn is_expected_type(ctx: &CompletionContext, ty: &Ty) -> bool {
+    ctx.expected_type.as_ref() == Some(ty)
+}
+
+/// Variants are matched by their enum only, as their generic parameters are
+/// not known before the expression is inferred.
+fn is_expected_variant(ctx: &CompletionContext, variant: hir::EnumVariant) -> bool {
+    match ctx.expected_type.as_ref().and_then(|it| it.as_adt()) {
+        Some((adt, _)) => adt == AdtDef::Enum(variant.parent_enum(ctx.db)),
+        None => false,
+    }
+}
+
+fn compute_score(ctx: &CompletionContext, type_match: bool, name: &str) -> Option<CompletionScore> {
+    let name_match = ctx.expected_name.as_ref().map_or(false, |it| it == name);
+    match (type_match, name_match) {
+        (true, true) => Some(CompletionScore::TypeAndNameMatch),
+        (true, false) => Some(CompletionScore::TypeMatch),
+        (false, true) => Some(CompletionScore::NameMatch),
+        (false, false) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use test_utils::covers;
 
-    use crate::completion::{CompletionKind, check_completion};
+    use crate::{
+        AnalysisChange,
+        mock_analysis::MockAnalysis,
+        completion::{CompletionKind, CompletionScore, check_completion, do_completion},
+    };
 
     fn check_reference_completion(code: &str, expected_completions: &str) {
         check_completion(code, expected_completions, CompletionKind::Reference);
     }
 
+    fn check_scores(code: &str, expected: &[(&str, Option<CompletionScore>)]) {
+        let completions = do_completion(code, CompletionKind::Reference);
+        for &(label, score) in expected {
+            let item = completions
+                .iter()
+                .find(|it| it.label() == label)
+                .unwrap_or_else(|| panic!("no completion for `{}`", label));
+            assert_eq!(item.score(), score, "wrong score for `{}`", label);
+        }
+    }
+
     #[test]
     fn inserts_parens_for_function_calls() {
         covers!(inserts_parens_for_function_calls);
@@ -248,4 +339,154 @@ mod tests {
         )
     }
 
+    #[test]
+    fn scores_by_let_ascription() {
+        check_scores(
+            r"
+            struct Duration;
+            struct Instant;
+            fn foo(timeout: Duration, start: Instant) {
+                let timeout: Duration = <|>
+            }
+            ",
+            &[("timeout", Some(CompletionScore::TypeAndNameMatch)), ("start", None), ("foo", None)],
+        );
+    }
+
+    #[test]
+    fn scores_by_argument() {
+        check_scores(
+            r"
+            fn bar(count: u32, name: &str) {}
+            fn foo(count: u32, other: u32, name: u32) {
+                bar(<|>)
+            }
+            ",
+            &[
+                ("count", Some(CompletionScore::TypeAndNameMatch)),
+                ("other", Some(CompletionScore::TypeMatch)),
+                ("name", Some(CompletionScore::NameMatch)),
+            ],
+        );
+    }
+
+    #[test]
+    fn scores_by_method_argument() {
+        check_scores(
+            r"
+            struct S;
+            impl S {
+                fn bar(&self, first: u32, second: u32) {}
+            }
+            fn foo(s: S, first: u32, second: i64) {
+                s.bar(<|>)
+            }
+            ",
+            &[("first", Some(CompletionScore::TypeAndNameMatch)), ("second", None), ("s", None)],
+        );
+    }
+
+    #[test]
+    fn scores_by_struct_literal_field() {
+        check_scores(
+            r"
+            struct S { count: u32 }
+            fn foo(count: u32, other: i32) {
+                S { count: <|> };
+            }
+            ",
+            &[("count", Some(CompletionScore::TypeAndNameMatch)), ("other", None)],
+        );
+    }
+
+    #[test]
+    fn scores_by_return_type() {
+        check_scores(
+            r"
+            enum E { A, B }
+            const C: i64 = 0;
+            fn ret() -> i64 { 0 }
+            fn foo(a: u32, b: i64) -> i64 {
+                <|>
+            }
+            ",
+            &[
+                ("b", Some(CompletionScore::TypeMatch)),
+                ("C", Some(CompletionScore::TypeMatch)),
+                ("ret", Some(CompletionScore::TypeMatch)),
+                ("a", None),
+                ("E", None),
+            ],
+        );
+    }
+
+    #[test]
+    fn scores_enum_variants_by_their_enum() {
+        check_scores(
+            r"
+            enum E { A, B }
+            fn foo(e: E) {
+                let x: E = E::<|>
+            }
+            ",
+            &[("A", Some(CompletionScore::TypeMatch)), ("B", Some(CompletionScore::TypeMatch))],
+        );
+    }
+
+    #[test]
+    fn resolves_docs_after_an_item_is_inserted_before_the_definition() {
+        let (mock, position) = MockAnalysis::with_files_and_position(
+            "
+            //- /main.rs
+            /// Does foo.
+            fn foo() {}
+            fn main() { fo<|> }
+            ",
+        );
+        let mut host = mock.analysis_host();
+        let completions = host.analysis().completions(position).unwrap().unwrap();
+        let foo = completions.iter().find(|it| it.label() == "foo").unwrap();
+        let data = foo.resolve_data().unwrap().clone();
+
+        let text = host.analysis().file_text(position.file_id);
+        let mut change = AnalysisChange::new();
+        let text = format!("/// Does bar.\nfn bar() {{}}\n{}", text);
+        change.change_file(position.file_id, Arc::new(text));
+        host.apply_change(change);
+
+        let resolved = host.analysis().resolve_completion(position, &data).unwrap();
+        assert_eq!(resolved.documentation.unwrap().contents(), "Does foo.");
+    }
+
+    #[test]
+    fn ignores_definitions_which_dont_exist_anymore() {
+        let (mock, position) = MockAnalysis::with_files_and_position(
+            "
+            //- /main.rs
+            /// Does foo.
+            fn foo() {}
+            fn main() { fo<|> }
+            ",
+        );
+        let analysis = mock.analysis();
+        let completions = analysis.completions(position).unwrap().unwrap();
+        let foo = completions.iter().find(|it| it.label() == "foo").unwrap();
+        let data = foo.resolve_data().unwrap();
+        let documentation = |ids: [u32; 4], name: &str| {
+            let mut data = data.clone();
+            let raw = data.definition.as_mut().unwrap();
+            raw.ids = ids;
+            raw.name = name.into();
+            let resolved = analysis.resolve_completion(position, &data).unwrap();
+            resolved.documentation.map(|it| it.contents().to_string())
+        };
+        let [kind, krate, module, _] = data.definition.as_ref().unwrap().ids;
+        // An item at an unknown position is found by its name.
+        let docs = documentation([kind, krate, module, 1000], "foo");
+        assert_eq!(docs, Some("Does foo.".to_string()));
+        assert_eq!(documentation([kind, krate, module, 1000], "bar"), None);
+        assert_eq!(documentation([kind, 1000, module, 0], "foo"), None);
+        assert_eq!(documentation([kind, krate, 1000, 0], "foo"), None);
+        assert_eq!(documentation([1000, krate, module, 0], "foo"), None);
+    }
 }
@@ -8,12 +8,13 @@ use ra_db::{
     salsa,
 };
 
-use crate::{LineIndex, symbol_index::{self, SymbolsDatabase}};
+use crate::{LineIndex, completion, symbol_index::{self, SymbolsDatabase}};
 
 #[salsa::database(
     ra_db::SourceDatabaseStorage,
     LineIndexDatabaseStorage,
     symbol_index::SymbolsDatabaseStorage,
+    completion::CompletionDatabaseStorage,
     hir::db::HirDatabaseStorage,
     hir::db::DefDatabaseStorage
 )]
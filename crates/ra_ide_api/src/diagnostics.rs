@@ -1,14 +1,19 @@
 use std::cell::RefCell;
 
 use itertools::Itertools;
-use hir::{source_binder, diagnostics::{Diagnostic as _, DiagnosticSink}};
+use hir::{
+    source_binder, HirDisplay,
+    diagnostics::{Diagnostic as _, DiagnosticSink},
+};
 use ra_db::SourceDatabase;
 use ra_syntax::{
     T, Location, SourceFile, TextRange, SyntaxNode,
+    SyntaxKind::WHITESPACE,
     ast::{self, AstNode, NamedFieldList, NamedField},
 };
 use ra_assists::ast_editor::{AstEditor, AstBuilder};
 use ra_text_edit::{TextEdit, TextEditBuilder};
+use ra_fmt::leading_indent;
 use ra_prof::profile;
 
 use crate::{Diagnostic, FileId, FileSystemEdit, SourceChange, SourceFileEdit, db::RootDatabase};
@@ -17,6 +22,8 @@ use crate::{Diagnostic, FileId, FileSystemEdit, SourceChange, SourceFileEdit, db
 pub enum Severity {
     Error,
     WeakWarning,
+    /// Not a problem with the code, like code which is disabled by `#[cfg]`.
+    Hint,
 }
 
 pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic> {
@@ -30,6 +37,14 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         check_unnecessary_braces_in_use_statement(&mut res, file_id, node);
         check_struct_shorthand_initialization(&mut res, file_id, node);
     }
+    for range in hir::source_binder::inactive_code(db, file_id) {
+        res.push(Diagnostic {
+            range,
+            message: "code is inactive due to #[cfg] directives".to_string(),
+            severity: Severity::Hint,
+            fix: None,
+        });
+    }
     let res = RefCell::new(res);
     let mut sink = DiagnosticSink::new(|d| {
         res.borrow_mut().push(Diagnostic {
@@ -70,6 +85,50 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             severity: Severity::Error,
             fix: Some(fix),
         })
+    })
+    .on::<hir::diagnostics::MissingMatchArms, _>(|d| {
+        let file_id = d.file().original_file(db);
+        let source_file = db.parse(file_id);
+        let arm_list = d.arms.to_node(source_file.syntax());
+        let fix = missing_match_arms_edit(arm_list, &d.witnesses).map(|edit| {
+            SourceChange::source_file_edit_from("add missing match arms", file_id, edit)
+        });
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: d.message(),
+            severity: Severity::Error,
+            fix,
+        })
+    })
+    .on::<hir::diagnostics::TypeMismatch, _>(|d| {
+        // without a fully known type on both sides, the mismatch is likely to
+        // be caused by something we don't infer yet
+        if d.expected.contains_unknown() || d.actual.contains_unknown() {
+            return;
+        }
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: format!("expected {}, found {}", d.expected.display(db), d.actual.display(db)),
+            severity: Severity::Error,
+            fix: None,
+        })
+    })
+    .on::<hir::diagnostics::UnresolvedMethodCall, _>(|d| {
+        if d.receiver_ty.contains_unknown() {
+            return;
+        }
+        // methods from derives and macro-generated impls are not known to us,
+        // so this is only a warning
+        res.borrow_mut().push(Diagnostic {
+            range: d.highlight_range(),
+            message: format!(
+                "no method named `{}` found for type `{}`",
+                d.method_name,
+                d.receiver_ty.display(db)
+            ),
+            severity: Severity::WeakWarning,
+            fix: None,
+        })
     });
     if let Some(m) = source_binder::module_from_file_id(db, file_id) {
         m.diagnostics(db, &mut sink);
@@ -78,6 +137,54 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
     res.into_inner()
 }
 
+/// Adds an arm for each of the `witnesses` after the last arm of the list.
+fn missing_match_arms_edit(arm_list: &ast::MatchArmList, witnesses: &[String]) -> Option<TextEdit> {
+    let r_curly = arm_list.syntax().last_token().filter(|it| it.kind() == T!['}'])?;
+    let indent = leading_indent(arm_list.syntax()).unwrap_or("");
+    let mut buf = String::new();
+    let offset = match arm_list.arms().last() {
+        Some(last_arm) => {
+            let comma = last_arm
+                .syntax()
+                .next_sibling_or_token()
+                .and_then(|it| it.as_token())
+                .filter(|it| it.kind() == T![,]);
+            match comma {
+                Some(comma) => comma.range().end(),
+                None => {
+                    let is_block = last_arm.expr().map_or(false, |expr| match expr.kind() {
+                        ast::ExprKind::BlockExpr(_) => true,
+                        _ => false,
+                    });
+                    if !is_block {
+                        buf.push(',');
+                    }
+                    last_arm.syntax().range().end()
+                }
+            }
+        }
+        None => arm_list.syntax().first_token()?.range().end(),
+    };
+    for witness in witnesses {
+        buf.push_str(&format!("\n{}    {} => (),", indent, witness));
+    }
+    // Move the closing `}` onto its own line, replacing the whitespace in
+    // front of it if there is nothing else between it and the new arms.
+    let mut range = TextRange::offset_len(offset, 0.into());
+    match r_curly.prev_token().filter(|it| it.kind() == WHITESPACE) {
+        Some(ws) if ws.text().contains('\n') => (),
+        ws => {
+            if let Some(ws) = ws.filter(|it| it.range().start() == offset) {
+                range = TextRange::from_to(offset, ws.range().end());
+            }
+            buf.push_str(&format!("\n{}", indent));
+        }
+    }
+    let mut builder = TextEditBuilder::default();
+    builder.replace(range, buf);
+    Some(builder.finish())
+}
+
 fn syntax_errors(acc: &mut Vec<Diagnostic>, source_file: &SourceFile) {
     fn location_to_range(location: Location) -> TextRange {
         match location {
@@ -306,6 +413,53 @@ mod tests {
         check_no_diagnostic(content);
     }
 
+    #[test]
+    fn test_add_missing_match_arms() {
+        let before = r"
+            enum E { A, B, C }
+
+            fn test_fn(e: E) {
+                match e {
+                    E::A => (),
+                    E::B => {}
+                }
+            }
+        ";
+        let after = r"
+            enum E { A, B, C }
+
+            fn test_fn(e: E) {
+                match e {
+                    E::A => (),
+                    E::B => {}
+                    E::C => (),
+                }
+            }
+        ";
+        check_apply_diagnostic_fix(before, after);
+    }
+
+    #[test]
+    fn test_add_missing_match_arms_single_line() {
+        let before = r"
+            enum E { A, B }
+
+            fn test_fn(e: E) {
+                match e { E::A => () }
+            }
+        ";
+        let after = r"
+            enum E { A, B }
+
+            fn test_fn(e: E) {
+                match e { E::A => (),
+                    E::B => (),
+                }
+            }
+        ";
+        check_apply_diagnostic_fix(before, after);
+    }
+
     #[test]
     fn test_unresolved_module_diagnostic() {
         let (analysis, file_id) = single_file("mod foo;");
@@ -334,6 +488,65 @@ mod tests {
 ]"####);
     }
 
+    #[test]
+    fn test_type_diagnostics() {
+        let (analysis, file_id) = single_file(
+            r#"
+struct S;
+fn two(a: u32, b: u32) {}
+fn main(s: S) {
+    let a: u32 = true;
+    let b: u32 = unknown();
+    two(1);
+    s.frobnicate();
+    unknown().frobnicate();
+}
+"#,
+        );
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "expected u32, found bool",
+                "expected 2 arguments, found 1",
+                "no method named `frobnicate` found for type `S`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inactive_code_diagnostic() {
+        let (analysis, file_id) = single_file(
+            r#"
+#[cfg(test)]
+mod tests {
+    #[cfg(test)]
+    fn foo() {}
+}
+fn main() {
+    #[cfg(feature = "foo")]
+    let x = 92;
+}
+"#,
+        );
+        let diagnostics = analysis.diagnostics(file_id).unwrap();
+        assert_debug_snapshot_matches!(diagnostics, @r####"[
+    Diagnostic {
+        message: "code is inactive due to #[cfg] directives",
+        range: [1; 60),
+        fix: None,
+        severity: Hint
+    },
+    Diagnostic {
+        message: "code is inactive due to #[cfg] directives",
+        range: [77; 116),
+        fix: None,
+        severity: Hint
+    }
+]"####);
+    }
+
     #[test]
     fn test_check_unnecessary_braces_in_use_statement() {
         check_not_applicable(
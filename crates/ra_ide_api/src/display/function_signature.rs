@@ -19,6 +19,8 @@ pub struct FunctionSignature {
     pub generic_parameters: Vec<String>,
     /// Parameters of the function
     pub parameters: Vec<String>,
+    /// Parameter names of the function
+    pub parameter_names: Vec<String>,
     /// Optional return type
     pub ret_type: Option<String>,
     /// Where predicates
@@ -52,6 +54,26 @@ impl From<&'_ ast::FnDef> for FunctionSignature {
             res
         }
 
+        fn param_name_list(node: &ast::FnDef) -> Vec<String> {
+            let mut res = vec![];
+            if let Some(param_list) = node.param_list() {
+                if param_list.self_param().is_some() {
+                    res.push("self".to_string())
+                }
+
+                res.extend(param_list.params().map(|param| {
+                    param
+                        .pat()
+                        .and_then(|pat| match pat.kind() {
+                            ast::PatKind::BindPat(it) => Some(it.name()?.text().to_string()),
+                            _ => None,
+                        })
+                        .unwrap_or_default()
+                }));
+            }
+            res
+        }
+
         FunctionSignature {
             visibility: node.visibility().map(|n| n.syntax().text().to_string()),
             name: node.name().map(|n| n.text().to_string()),
@@ -60,6 +82,7 @@ impl From<&'_ ast::FnDef> for FunctionSignature {
                 .and_then(|r| r.type_ref())
                 .map(|n| n.syntax().text().to_string()),
             parameters: param_list(node),
+            parameter_names: param_name_list(node),
             generic_parameters: generic_parameters(node),
             where_predicates: where_predicates(node),
             // docs are processed separately
@@ -190,13 +190,9 @@ impl NavigationTarget {
         db: &RootDatabase,
         impl_block: hir::ImplBlock,
     ) -> NavigationTarget {
-        let (file_id, node) = impl_block.source(db);
-        NavigationTarget::from_syntax(
-            file_id.as_original_file(),
-            "impl".into(),
-            None,
-            node.syntax(),
-        )
+        let (file_id, source) = impl_block.source(db);
+        let node = source.either(|it| it.syntax().to_owned(), |it| it.syntax().to_owned());
+        NavigationTarget::from_syntax(file_id.as_original_file(), "impl".into(), None, &node)
     }
 
     pub(crate) fn from_impl_item(db: &RootDatabase, impl_item: hir::ImplItem) -> NavigationTarget {
@@ -176,4 +176,16 @@ mod tests {
             &["impl IMPL_BLOCK FileId(2) [0; 31)", "impl IMPL_BLOCK FileId(3) [0; 31)"],
         );
     }
+
+    #[test]
+    fn goto_implementation_to_builtin_derive() {
+        check_goto(
+            "
+            //- /lib.rs
+            #[derive(Clone, my::Debug)]
+            struct Foo<|>;
+            ",
+            &["impl ATTR FileId(1) [0; 27)"],
+        );
+    }
 }
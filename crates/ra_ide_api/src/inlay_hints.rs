@@ -0,0 +1,254 @@
+use std::iter::successors;
+
+use hir::{HirDisplay, SourceAnalyzer, Ty};
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxNode, TextRange,
+    ast::{self, ArgListOwner, TypeAscriptionOwner},
+    algo::visit::{visitor, Visitor},
+};
+
+use crate::{db::RootDatabase, FileId, FunctionSignature};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlayKind {
+    /// The inferred type of a binding.
+    TypeHint,
+    /// The name of the parameter an argument is passed to.
+    ParameterHint,
+}
+
+#[derive(Debug)]
+pub struct InlayHint {
+    pub range: TextRange,
+    pub kind: InlayKind,
+    pub label: SmolStr,
+}
+
+pub(crate) fn inlay_hints(db: &RootDatabase, file_id: FileId) -> Vec<InlayHint> {
+    let file = db.parse(file_id);
+    file.syntax()
+        .descendants()
+        .flat_map(|node| get_inlay_hints(db, file_id, node).unwrap_or_default())
+        .collect()
+}
+
+fn get_inlay_hints(
+    db: &RootDatabase,
+    file_id: FileId,
+    node: &SyntaxNode,
+) -> Option<Vec<InlayHint>> {
+    visitor()
+        .visit(|let_stmt: &ast::LetStmt| {
+            if let_stmt.ascribed_type().is_some() {
+                return None;
+            }
+            let pat = let_stmt.pat()?;
+            let analyzer = SourceAnalyzer::new(db, file_id, let_stmt.syntax(), None);
+            Some(type_hints(db, &analyzer, pat, let_stmt.initializer()))
+        })
+        .visit(|closure: &ast::LambdaExpr| {
+            let analyzer = SourceAnalyzer::new(db, file_id, closure.syntax(), None);
+            let hints = closure
+                .param_list()?
+                .params()
+                .filter(|param| param.ascribed_type().is_none())
+                .filter_map(|param| param.pat())
+                .flat_map(|pat| type_hints(db, &analyzer, pat, None))
+                .collect();
+            Some(hints)
+        })
+        .visit(|call: &ast::CallExpr| {
+            let analyzer = SourceAnalyzer::new(db, file_id, call.syntax(), None);
+            let (callable_def, _subst) = analyzer.type_of(db, call.expr()?)?.as_callable()?;
+            let function = match callable_def {
+                hir::CallableDef::Function(it) => it,
+                _ => return None,
+            };
+            Some(parameter_hints(db, function, call.arg_list()?, false))
+        })
+        .visit(|method_call: &ast::MethodCallExpr| {
+            let analyzer = SourceAnalyzer::new(db, file_id, method_call.syntax(), None);
+            let function = analyzer.resolve_method_call(method_call)?;
+            Some(parameter_hints(db, function, method_call.arg_list()?, true))
+        })
+        .accept(node)?
+}
+
+/// Returns a hint for each binding in `pat`, unless its type is obvious from
+/// the `initializer`.
+fn type_hints(
+    db: &RootDatabase,
+    analyzer: &SourceAnalyzer,
+    pat: &ast::Pat,
+    initializer: Option<&ast::Expr>,
+) -> Vec<InlayHint> {
+    let is_single_binding = ast::BindPat::cast(pat.syntax()).is_some();
+    pat.syntax()
+        .descendants()
+        .filter_map(ast::BindPat::cast)
+        .filter_map(|bind_pat| {
+            let ty = analyzer.type_of_pat(db, bind_pat.into())?;
+            if ty == Ty::Unknown {
+                return None;
+            }
+            let label = ty.display(db).to_string();
+            if is_single_binding && initializer.map_or(false, |it| is_obvious(it, &label)) {
+                return None;
+            }
+            Some(InlayHint {
+                range: bind_pat.syntax().range(),
+                kind: InlayKind::TypeHint,
+                label: label.into(),
+            })
+        })
+        .collect()
+}
+
+/// Checks whether the type of `expr` is already spelled out in it, as in
+/// `Foo { .. }`, `Foo(..)` or `Foo::new()`.
+fn is_obvious(expr: &ast::Expr, type_label: &str) -> bool {
+    let type_name = type_label.split('<').next().unwrap_or(type_label);
+    match expr.kind() {
+        ast::ExprKind::StructLit(_) => true,
+        ast::ExprKind::CallExpr(call) => {
+            let path = match call.expr().map(|it| it.kind()) {
+                Some(ast::ExprKind::PathExpr(it)) => it.path(),
+                _ => None,
+            };
+            successors(path, |it| it.qualifier())
+                .take(2)
+                .filter_map(|it| it.segment()?.name_ref())
+                .any(|name_ref| name_ref.text() == type_name)
+        }
+        _ => false,
+    }
+}
+
+fn parameter_hints(
+    db: &RootDatabase,
+    function: hir::Function,
+    arg_list: &ast::ArgList,
+    is_method_call: bool,
+) -> Vec<InlayHint> {
+    let signature = FunctionSignature::from_hir(db, function);
+    let skip = if is_method_call && function.signature(db).has_self_param() { 1 } else { 0 };
+    signature
+        .parameter_names
+        .into_iter()
+        .skip(skip)
+        .zip(arg_list.args())
+        .filter(|(name, arg)| !is_obvious_argument(name, arg))
+        .map(|(name, arg)| InlayHint {
+            range: arg.syntax().range(),
+            kind: InlayKind::ParameterHint,
+            label: name.into(),
+        })
+        .collect()
+}
+
+fn is_obvious_argument(param_name: &str, arg: &ast::Expr) -> bool {
+    param_name.is_empty()
+        || param_name == "self"
+        || param_name.starts_with('_')
+        || arg.syntax().text() == param_name
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file;
+
+    use super::*;
+
+    fn check_hints(code: &str, expected: &[(&str, InlayKind, &str)]) {
+        let (analysis, file_id) = single_file(code);
+        let hints = analysis.inlay_hints(file_id).unwrap();
+        let actual: Vec<(String, InlayKind, &str)> = hints
+            .iter()
+            .map(|hint| (code[hint.range].to_string(), hint.kind, hint.label.as_str()))
+            .collect();
+        let expected: Vec<(String, InlayKind, &str)> =
+            expected.iter().map(|&(text, kind, label)| (text.to_string(), kind, label)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn let_statement_type_hints() {
+        check_hints(
+            r#"
+struct Foo { x: u32 }
+struct Bar(u32);
+impl Foo {
+    fn new() -> Foo { Foo { x: 0 } }
+}
+
+fn main() {
+    let a = 92;
+    let b: u32 = 92;
+    let (c, d) = (1u8, "hello");
+    let e = Foo::new();
+    let f = Foo { x: 1 };
+    let g = Bar(1);
+    let h = Some(Bar(1));
+    let _ = 1;
+    let i = unknown();
+}
+"#,
+            &[
+                ("a", InlayKind::TypeHint, "i32"),
+                ("c", InlayKind::TypeHint, "u8"),
+                ("d", InlayKind::TypeHint, "&str"),
+                ("h", InlayKind::TypeHint, "Option<Bar>"),
+            ],
+        );
+    }
+
+    #[test]
+    fn closure_parameter_type_hints() {
+        check_hints(
+            r#"
+fn main() {
+    let add = |x: u64| { let y = x; y };
+}
+"#,
+            &[("add", InlayKind::TypeHint, "|u64| -> u64"), ("y", InlayKind::TypeHint, "u64")],
+        );
+    }
+
+    #[test]
+    fn unannotated_closure_parameter_type_hints() {
+        check_hints(
+            r#"
+#[lang = "fn_once"]
+trait FnOnce<Args> {}
+
+fn apply<F: FnOnce(u32)>(f: F) {}
+
+fn main() {
+    apply(|x| x + 1);
+}
+"#,
+            &[("|x| x + 1", InlayKind::ParameterHint, "f"), ("x", InlayKind::TypeHint, "u32")],
+        );
+    }
+
+    #[test]
+    fn argument_name_hints() {
+        check_hints(
+            r#"
+struct Foo;
+impl Foo {
+    fn method(&self, count: u32, _unused: u32) {}
+}
+fn sum(first: u32, second: u32) -> u32 { first + second }
+
+fn main(foo: Foo) {
+    let second: u32 = 2;
+    sum(1, second);
+    foo.method(3, 4);
+}
+"#,
+            &[("1", InlayKind::ParameterHint, "first"), ("3", InlayKind::ParameterHint, "count")],
+        );
+    }
+}
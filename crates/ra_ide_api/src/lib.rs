@@ -23,6 +23,7 @@ mod goto_type_definition;
 mod extend_selection;
 mod hover;
 mod call_info;
+mod inlay_hints;
 mod syntax_highlighting;
 mod parent_module;
 mod references;
@@ -45,6 +46,7 @@ mod test_utils;
 
 use std::sync::Arc;
 
+use ra_cfg::CfgOptions;
 use ra_syntax::{SourceFile, TreeArc, TextRange, TextUnit};
 use ra_text_edit::TextEdit;
 use ra_db::{
@@ -60,11 +62,15 @@ use crate::{
 
 pub use crate::{
     change::{AnalysisChange, LibraryData},
-    completion::{CompletionItem, CompletionItemKind, InsertTextFormat},
+    completion::{
+        CompletionItem, CompletionItemKind, CompletionScore, CompletionResolveData,
+        ResolvedCompletion, InsertTextFormat, ImportOrigin,
+    },
     runnables::{Runnable, RunnableKind},
-    references::ReferenceSearchResult,
+    references::{ReferenceSearchResult, Reference, ReferenceKind, RenameError},
     assists::{Assist, AssistId},
     hover::{HoverResult},
+    inlay_hints::{InlayHint, InlayKind},
     line_index::{LineIndex, LineCol},
     line_index_utils::translate_offset_with_edit,
     folding_ranges::{Fold, FoldKind},
@@ -75,9 +81,9 @@ pub use crate::{
 
 pub use ra_db::{
     Canceled, CrateGraph, CrateId, FileId, FilePosition, FileRange, SourceRootId,
-    Edition
+    Edition, Env,
 };
-pub use hir::Documentation;
+pub use hir::{Documentation, RawModuleDef};
 
 // We use jemalloc mainly to get heap usage statistics, actual performance
 // difference is not measures.
@@ -300,7 +306,12 @@ impl Analysis {
         change.add_root(source_root, true);
         let mut crate_graph = CrateGraph::default();
         let file_id = FileId(0);
-        crate_graph.add_crate_root(file_id, Edition::Edition2018);
+        crate_graph.add_crate_root(
+            file_id,
+            Edition::Edition2018,
+            CfgOptions::default(),
+            Env::default(),
+        );
         change.add_file(source_root, file_id, "main.rs".into(), Arc::new(text));
         change.set_crate_graph(crate_graph);
         host.apply_change(change);
@@ -442,6 +453,12 @@ impl Analysis {
         self.with_db(|db| call_info::call_info(db, position))
     }
 
+    /// Returns inferred types of bindings and parameter names of arguments to
+    /// be shown inline in the editor.
+    pub fn inlay_hints(&self, file_id: FileId) -> Cancelable<Vec<InlayHint>> {
+        self.with_db(|db| inlay_hints::inlay_hints(db, file_id))
+    }
+
     /// Returns a `mod name;` declaration which created the current module.
     pub fn parent_module(&self, position: FilePosition) -> Cancelable<Vec<NavigationTarget>> {
         self.with_db(|db| parent_module::parent_module(db, position))
@@ -472,6 +489,16 @@ impl Analysis {
         self.with_db(|db| completion::completions(db, position).map(Into::into))
     }
 
+    /// Computes the documentation, detail and additional edits of a completion
+    /// item, which are left out by `completions` to keep it fast.
+    pub fn resolve_completion(
+        &self,
+        position: FilePosition,
+        data: &CompletionResolveData,
+    ) -> Cancelable<ResolvedCompletion> {
+        self.with_db(|db| completion::resolve_completion(db, position, data))
+    }
+
     /// Computes assists (aks code actons aka intentions) for the given
     /// position.
     pub fn assists(&self, frange: FileRange) -> Cancelable<Vec<Assist>> {
@@ -489,12 +516,12 @@ impl Analysis {
     }
 
     /// Returns the edit required to rename reference at the position to the new
-    /// name.
+    /// name, or the reason why the rename is not possible.
     pub fn rename(
         &self,
         position: FilePosition,
         new_name: &str,
-    ) -> Cancelable<Option<SourceChange>> {
+    ) -> Cancelable<Option<Result<SourceChange, RenameError>>> {
         self.with_db(|db| references::rename(db, position, new_name))
     }
 
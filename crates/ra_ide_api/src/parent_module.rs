@@ -28,10 +28,12 @@ pub(crate) fn crate_for(db: &RootDatabase, file_id: FileId) -> Vec<CrateId> {
 
 #[cfg(test)]
 mod tests {
+    use ra_cfg::CfgOptions;
+
     use crate::{
         AnalysisChange, CrateGraph,
         mock_analysis::{analysis_and_position, MockAnalysis},
-        Edition::Edition2018,
+        Edition::Edition2018, Env,
 };
 
     #[test]
@@ -48,6 +50,21 @@ mod tests {
         nav.assert_match("foo MODULE FileId(1) [0; 8)");
     }
 
+    #[test]
+    fn test_resolve_parent_module_for_path_attr() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+            //- /lib.rs
+            #[path = "platform/linux.rs"]
+            mod imp;
+            //- /platform/linux.rs
+            <|>// empty
+            "#,
+        );
+        let nav = analysis.parent_module(pos).unwrap().pop().unwrap();
+        nav.assert_match("imp MODULE FileId(1) [0; 38)");
+    }
+
     #[test]
     fn test_resolve_parent_module_for_inline() {
         let (analysis, pos) = analysis_and_position(
@@ -80,7 +97,12 @@ mod tests {
         assert!(host.analysis().crate_for(mod_file).unwrap().is_empty());
 
         let mut crate_graph = CrateGraph::default();
-        let crate_id = crate_graph.add_crate_root(root_file, Edition2018);
+        let crate_id = crate_graph.add_crate_root(
+            root_file,
+            Edition2018,
+            CfgOptions::default(),
+            Env::default(),
+        );
         let mut change = AnalysisChange::new();
         change.set_crate_graph(crate_graph);
         host.apply_change(change);
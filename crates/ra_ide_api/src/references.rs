@@ -1,9 +1,15 @@
+mod classify;
+mod search_scope;
+
+use std::{collections::BTreeMap, fmt};
+
 use relative_path::{RelativePath, RelativePathBuf};
-use hir::{ModuleSource, source_binder, Either};
+use rustc_hash::FxHashMap;
+use hir::{ModuleSource, Either, Container, FieldSource, ModuleDef, db::HirDatabase};
 use ra_db::{SourceDatabase};
 use ra_syntax::{
-    AstNode, SyntaxNode, SourceFile,
-    ast,
+    AstNode, SourceFile,
+    ast::{self, NameOwner},
     algo::find_node_at_offset,
 };
 
@@ -17,12 +23,34 @@ use crate::{
     SourceChange,
     SourceFileEdit,
     TextRange,
+    symbol_index::SymbolsDatabase,
+};
+
+use self::{
+    classify::{classify_name, classify_name_ref, classify_field_pat_name, Definition},
+    search_scope::{search_scope, search_crates},
 };
 
 #[derive(Debug, Clone)]
 pub struct ReferenceSearchResult {
     declaration: NavigationTarget,
-    references: Vec<FileRange>,
+    references: Vec<Reference>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub file_range: FileRange,
+    pub kind: ReferenceKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// The referenced item is read, called or otherwise used.
+    Read,
+    /// The reference is the left hand side of an assignment.
+    Write,
+    /// The reference is part of a `use` item.
+    Import,
 }
 
 impl ReferenceSearchResult {
@@ -30,7 +58,7 @@ impl ReferenceSearchResult {
         &self.declaration
     }
 
-    pub fn references(&self) -> &[FileRange] {
+    pub fn references(&self) -> &[Reference] {
         &self.references
     }
 
@@ -48,10 +76,10 @@ impl IntoIterator for ReferenceSearchResult {
     type Item = FileRange;
     type IntoIter = std::vec::IntoIter<FileRange>;
 
-    fn into_iter(mut self) -> Self::IntoIter {
+    fn into_iter(self) -> Self::IntoIter {
         let mut v = Vec::with_capacity(self.len());
         v.push(FileRange { file_id: self.declaration.file_id(), range: self.declaration.range() });
-        v.append(&mut self.references);
+        v.extend(self.references.into_iter().map(|it| it.file_range));
         v.into_iter()
     }
 }
@@ -61,13 +89,31 @@ pub(crate) fn find_all_refs(
     position: FilePosition,
 ) -> Option<ReferenceSearchResult> {
     let file = db.parse(position.file_id);
-    let (binding, analyzer) = find_binding(db, &file, position)?;
+    if let Some(refs) = find_local_refs(db, &file, position) {
+        return Some(refs);
+    }
+    let def = find_definition(db, &file, position)?;
+    Some(find_def_refs(db, def))
+}
+
+/// Finds the references to a local binding within its body.
+fn find_local_refs(
+    db: &RootDatabase,
+    file: &SourceFile,
+    position: FilePosition,
+) -> Option<ReferenceSearchResult> {
+    let (binding, analyzer) = find_binding(db, file, position)?;
     let declaration = NavigationTarget::from_bind_pat(position.file_id, binding);
 
     let references = analyzer
         .find_all_refs(binding)
         .into_iter()
-        .map(move |ref_desc| FileRange { file_id: position.file_id, range: ref_desc.range })
+        .filter_map(|ref_desc| {
+            let name_ref =
+                find_node_at_offset::<ast::NameRef>(file.syntax(), ref_desc.range.start())?;
+            let file_range = FileRange { file_id: position.file_id, range: ref_desc.range };
+            Some(Reference { file_range, kind: reference_kind(name_ref) })
+        })
         .collect::<Vec<_>>();
 
     return Some(ReferenceSearchResult { declaration, references });
@@ -94,118 +140,352 @@ pub(crate) fn find_all_refs(
     }
 }
 
-pub(crate) fn rename(
+fn find_definition(
     db: &RootDatabase,
+    file: &SourceFile,
     position: FilePosition,
-    new_name: &str,
-) -> Option<SourceChange> {
-    let source_file = db.parse(position.file_id);
-    let syntax = source_file.syntax();
+) -> Option<Definition> {
+    let syntax = file.syntax();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(syntax, position.offset) {
+        return classify_name_ref(db, position.file_id, name_ref, &mut FxHashMap::default());
+    }
+    let name = find_node_at_offset::<ast::Name>(syntax, position.offset)?;
+    classify_name(db, position.file_id, name)
+}
+
+/// Finds the `NameRef`s resolving to `def` in all files which can see it, as
+/// well as the struct patterns naming `def` if it is a field. Files which don't
+/// contain the name of `def` at all are skipped without being parsed.
+fn find_def_refs(db: &RootDatabase, def: Definition) -> ReferenceSearchResult {
+    let declaration = def.to_nav(db);
+    let name = declaration.name().as_str();
+    let is_field = match def {
+        Definition::Field(_) => true,
+        _ => false,
+    };
+    let mut references = Vec::new();
+    for file_id in search_scope(db, &declaration) {
+        let text = db.file_text(file_id);
+        if !text.contains(name) {
+            continue;
+        }
+        let file = db.parse(file_id);
+        let mut analyzers = FxHashMap::default();
+        for node in file.syntax().descendants() {
+            let kind = if let Some(name_ref) = ast::NameRef::cast(node) {
+                if name_ref.text().as_str() != name
+                    || classify_name_ref(db, file_id, name_ref, &mut analyzers) != Some(def)
+                {
+                    continue;
+                }
+                reference_kind(name_ref)
+            } else if let Some(pat_name) = ast::Name::cast(node).filter(|_| is_field) {
+                if pat_name.text().as_str() != name
+                    || classify_field_pat_name(db, file_id, pat_name, &mut analyzers) != Some(def)
+                {
+                    continue;
+                }
+                ReferenceKind::Read
+            } else {
+                continue;
+            };
+            let file_range = FileRange { file_id, range: node.range() };
+            references.push(Reference { file_range, kind });
+        }
+    }
+    ReferenceSearchResult { declaration, references }
+}
 
-    if let Some((ast_name, ast_module)) = find_name_and_module_at_offset(syntax, position) {
-        rename_mod(db, ast_name, ast_module, position, new_name)
+fn reference_kind(name_ref: &ast::NameRef) -> ReferenceKind {
+    if name_ref.syntax().ancestors().any(|it| ast::UseItem::cast(it).is_some()) {
+        return ReferenceKind::Import;
+    }
+    // The innermost expression is the path or field access containing `name_ref`.
+    let is_write = name_ref.syntax().ancestors().find_map(ast::Expr::cast).and_then(|expr| {
+        let bin_expr = expr.syntax().parent().and_then(ast::BinExpr::cast)?;
+        let lhs = bin_expr.lhs()?;
+        Some(bin_expr.op_kind()?.is_assignment() && lhs.syntax().range() == expr.syntax().range())
+    });
+    if is_write == Some(true) {
+        ReferenceKind::Write
     } else {
-        rename_reference(db, position, new_name)
+        ReferenceKind::Read
     }
 }
 
-fn find_name_and_module_at_offset(
-    syntax: &SyntaxNode,
-    position: FilePosition,
-) -> Option<(&ast::Name, &ast::Module)> {
-    let ast_name = find_node_at_offset::<ast::Name>(syntax, position.offset);
-    let ast_name_parent = ast::Module::cast(ast_name?.syntax().parent()?);
+/// The reason why a rename was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// Another item with the new name is already defined in the same scope.
+    NameCollision { new_name: String },
+    /// The item is defined or used in a library, which can't be edited.
+    LibraryCode { name: String },
+}
 
-    if let (Some(ast_module), Some(name)) = (ast_name_parent, ast_name) {
-        return Some((name, ast_module));
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenameError::NameCollision { new_name } => {
+                write!(f, "`{}` is already defined in this scope", new_name)
+            }
+            RenameError::LibraryCode { name } => {
+                write!(f, "`{}` is defined or used in a library and can't be renamed", name)
+            }
+        }
     }
-    None
 }
 
-fn source_edit_from_fileid_range(
-    file_id: FileId,
-    range: TextRange,
+pub(crate) fn rename(
+    db: &RootDatabase,
+    position: FilePosition,
     new_name: &str,
-) -> SourceFileEdit {
-    SourceFileEdit {
-        file_id,
-        edit: {
-            let mut builder = ra_text_edit::TextEditBuilder::default();
-            builder.replace(range, new_name.into());
-            builder.finish()
-        },
+) -> Option<Result<SourceChange, RenameError>> {
+    let file = db.parse(position.file_id);
+    let (all_refs, is_field) = match find_local_refs(db, &file, position) {
+        Some(refs) => (vec![refs], false),
+        None => {
+            let def = find_definition(db, &file, position)?;
+            // Modules are renamed together with their files.
+            if let Definition::Def(ModuleDef::Module(module)) = def {
+                return rename_mod(db, module, new_name);
+            }
+            let defs = related_definitions(db, def);
+            if defs.iter().any(|&it| name_collides(db, it, new_name)) {
+                let new_name = new_name.to_string();
+                return Some(Err(RenameError::NameCollision { new_name }));
+            }
+            let all_refs = defs.into_iter().map(|it| find_def_refs(db, it)).collect::<Vec<_>>();
+            let is_field = match def {
+                Definition::Field(_) => true,
+                _ => false,
+            };
+            (all_refs, is_field)
+        }
+    };
+
+    let mut edits: BTreeMap<FileId, Vec<(TextRange, String)>> = BTreeMap::new();
+    for refs in all_refs {
+        let old_name = refs.declaration().name().clone();
+        for FileRange { file_id, range } in refs {
+            if !is_local(db, file_id) {
+                return Some(Err(RenameError::LibraryCode { name: old_name.to_string() }));
+            }
+            // Field shorthands are expanded, so that the other name is kept
+            let edit = match field_shorthand(db, FileRange { file_id, range }) {
+                None => (range, new_name.to_string()),
+                Some((range, prefix)) if is_field => {
+                    (range, format!("{}: {}{}", new_name, prefix, old_name))
+                }
+                Some((range, prefix)) => (range, format!("{}: {}{}", old_name, prefix, new_name)),
+            };
+            edits.entry(file_id).or_default().push(edit);
+        }
     }
+
+    Some(Ok(SourceChange::source_file_edits("rename", source_file_edits(edits))))
 }
 
+/// Renames `module` at its declaration and all of its references and moves
+/// its file, if it has one. Crate roots can't be renamed.
 fn rename_mod(
     db: &RootDatabase,
-    ast_name: &ast::Name,
-    ast_module: &ast::Module,
-    position: FilePosition,
+    module: hir::Module,
     new_name: &str,
-) -> Option<SourceChange> {
-    let mut source_file_edits = Vec::new();
+) -> Option<Result<SourceChange, RenameError>> {
+    let (decl_file_id, decl) = module.declaration_source(db)?;
+    let decl_file_id = decl_file_id.as_original_file();
+    let decl_name = decl.name()?;
+    let def = Definition::Def(module.into());
+    if name_collides(db, def, new_name) {
+        return Some(Err(RenameError::NameCollision { new_name: new_name.to_string() }));
+    }
+
+    let mut edits: BTreeMap<FileId, Vec<(TextRange, String)>> = BTreeMap::new();
+    edits.entry(decl_file_id).or_default().push((decl_name.syntax().range(), new_name.into()));
+    for reference in find_def_refs(db, def).references() {
+        let FileRange { file_id, range } = reference.file_range;
+        edits.entry(file_id).or_default().push((range, new_name.to_string()));
+    }
+    if !edits.keys().all(|&file_id| is_local(db, file_id)) {
+        return Some(Err(RenameError::LibraryCode { name: decl_name.text().to_string() }));
+    }
+
     let mut file_system_edits = Vec::new();
-    if let Some(module) = source_binder::module_from_declaration(db, position.file_id, &ast_module)
-    {
-        let (file_id, module_source) = module.definition_source(db);
-        let file_id = file_id.as_original_file();
-        match module_source {
-            ModuleSource::SourceFile(..) => {
-                let mod_path: RelativePathBuf = db.file_relative_path(file_id);
-                // mod is defined in path/to/dir/mod.rs
-                let dst_path = if mod_path.file_stem() == Some("mod") {
-                    mod_path
-                        .parent()
-                        .and_then(|p| p.parent())
-                        .or_else(|| Some(RelativePath::new("")))
-                        .map(|p| p.join(new_name).join("mod.rs"))
-                } else {
-                    Some(mod_path.with_file_name(new_name).with_extension("rs"))
+    let (file_id, module_source) = module.definition_source(db);
+    let file_id = file_id.as_original_file();
+    match module_source {
+        ModuleSource::SourceFile(..) => {
+            let mod_path: RelativePathBuf = db.file_relative_path(file_id);
+            // mod is defined in path/to/dir/mod.rs
+            let dst_path = if mod_path.file_stem() == Some("mod") {
+                mod_path
+                    .parent()
+                    .and_then(|p| p.parent())
+                    .or_else(|| Some(RelativePath::new("")))
+                    .map(|p| p.join(new_name).join("mod.rs"))
+            } else {
+                Some(mod_path.with_file_name(new_name).with_extension("rs"))
+            };
+            if let Some(path) = dst_path {
+                let move_file = FileSystemEdit::MoveFile {
+                    src: file_id,
+                    dst_source_root: db.file_source_root(file_id),
+                    dst_path: path,
                 };
-                if let Some(path) = dst_path {
-                    let move_file = FileSystemEdit::MoveFile {
-                        src: file_id,
-                        dst_source_root: db.file_source_root(position.file_id),
-                        dst_path: path,
-                    };
-                    file_system_edits.push(move_file);
-                }
+                file_system_edits.push(move_file);
             }
-            ModuleSource::Module(..) => {}
         }
+        ModuleSource::Module(..) => {}
     }
 
-    let edit = SourceFileEdit {
-        file_id: position.file_id,
-        edit: {
-            let mut builder = ra_text_edit::TextEditBuilder::default();
-            builder.replace(ast_name.syntax().range(), new_name.into());
-            builder.finish()
-        },
-    };
-    source_file_edits.push(edit);
-
-    Some(SourceChange::from_edits("rename", source_file_edits, file_system_edits))
+    Some(Ok(SourceChange::from_edits("rename", source_file_edits(edits), file_system_edits)))
 }
 
-fn rename_reference(
-    db: &RootDatabase,
-    position: FilePosition,
-    new_name: &str,
-) -> Option<SourceChange> {
-    let refs = find_all_refs(db, position)?;
+fn is_local(db: &RootDatabase, file_id: FileId) -> bool {
+    db.local_roots().contains(&db.file_source_root(file_id))
+}
 
-    let edit = refs
+fn source_file_edits(edits: BTreeMap<FileId, Vec<(TextRange, String)>>) -> Vec<SourceFileEdit> {
+    edits
         .into_iter()
-        .map(|range| source_edit_from_fileid_range(range.file_id, range.range, new_name))
-        .collect::<Vec<_>>();
+        .map(|(file_id, mut replacements)| {
+            replacements.sort_by_key(|(range, _)| range.start());
+            replacements.dedup_by_key(|(range, _)| *range);
+            let mut builder = ra_text_edit::TextEditBuilder::default();
+            for (range, replacement) in replacements {
+                builder.replace(range, replacement);
+            }
+            SourceFileEdit { file_id, edit: builder.finish() }
+        })
+        .collect()
+}
 
-    if edit.is_empty() {
+/// If the name at `range` is part of a field shorthand, like `x` in
+/// `Foo { x }` or in `let Foo { ref x } = foo;`, returns the range to replace
+/// with the expanded field and the text which precedes the name of the
+/// binding, like `ref `.
+fn field_shorthand(db: &RootDatabase, range: FileRange) -> Option<(TextRange, String)> {
+    let file = db.parse(range.file_id);
+    let syntax = file.syntax();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(syntax, range.range.start()) {
+        let field = name_ref.syntax().parent().and_then(ast::NamedField::cast)?;
+        if name_ref.syntax().range() != range.range || field.expr().is_some() {
+            return None;
+        }
+        return Some((range.range, String::new()));
+    }
+    let name = find_node_at_offset::<ast::Name>(syntax, range.range.start())?;
+    let bind_pat = name.syntax().parent().and_then(ast::BindPat::cast)?;
+    if name.syntax().range() != range.range
+        || bind_pat.syntax().parent().and_then(ast::FieldPatList::cast).is_none()
+    {
         return None;
     }
+    let prefix = bind_pat.syntax().text().slice(..range.range.start()).to_string();
+    Some((bind_pat.syntax().range(), prefix))
+}
 
-    Some(SourceChange::source_file_edits("rename", edit))
+/// Returns the definitions which have to be renamed together with `def`: an
+/// item of a trait is renamed together with the corresponding items in all
+/// impls of the trait.
+fn related_definitions(db: &RootDatabase, def: Definition) -> Vec<Definition> {
+    let trait_ = match container(db, def) {
+        Some(Container::Trait(it)) => it,
+        Some(Container::ImplBlock(it)) => match it.target_trait_ref(db) {
+            Some(trait_ref) => trait_ref.trait_(),
+            None => return vec![def],
+        },
+        None => return vec![def],
+    };
+    let name = def.to_nav(db).name().clone();
+    let has_same_name = |it: &Definition| *it.to_nav(db).name() == name;
+
+    let mut res: Vec<Definition> =
+        trait_.items(db).into_iter().map(Definition::from).filter(|it| has_same_name(it)).collect();
+    let trait_nav = NavigationTarget::from_def(db, trait_.into());
+    for krate in search_crates(db, &trait_nav) {
+        for impl_block in db.impls_for_trait(krate, trait_).iter() {
+            for item in impl_block.items(db).into_iter().map(Definition::from) {
+                if has_same_name(&item) && !res.contains(&item) {
+                    res.push(item);
+                }
+            }
+        }
+    }
+    if !res.contains(&def) {
+        res.push(def);
+    }
+    res
+}
+
+/// Checks whether something named `new_name` is already defined next to `def`
+/// in the same namespace.
+fn name_collides(db: &RootDatabase, def: Definition, new_name: &str) -> bool {
+    if let Some(container) = container(db, def) {
+        let siblings: Vec<Definition> = match container {
+            Container::Trait(it) => it.items(db).into_iter().map(Definition::from).collect(),
+            Container::ImplBlock(it) => it.items(db).into_iter().map(Definition::from).collect(),
+        };
+        return siblings.into_iter().any(|it| it.to_nav(db).name().as_str() == new_name);
+    }
+    let def = match def {
+        Definition::Def(it) => it,
+        Definition::Field(field) => {
+            let field_def = match field.source(db).1 {
+                FieldSource::Named(it) => it,
+                FieldSource::Pos(_) => return false,
+            };
+            let fields = match field_def.syntax().parent() {
+                Some(it) => it,
+                None => return false,
+            };
+            return fields
+                .children()
+                .filter_map(ast::NamedFieldDef::cast)
+                .filter_map(|it| it.name())
+                .any(|it| it.text().as_str() == new_name);
+        }
+        Definition::Macro(_) => return false,
+    };
+    let module = match def {
+        ModuleDef::EnumVariant(var) => {
+            return var
+                .parent_enum(db)
+                .variants(db)
+                .into_iter()
+                .filter_map(|it| it.name(db))
+                .any(|it| it.to_string() == new_name);
+        }
+        ModuleDef::Function(it) => it.module(db),
+        ModuleDef::Struct(it) => it.module(db),
+        ModuleDef::Enum(it) => it.module(db),
+        ModuleDef::Const(it) => it.module(db),
+        ModuleDef::Static(it) => it.module(db),
+        ModuleDef::Trait(it) => it.module(db),
+        ModuleDef::TypeAlias(it) => it.module(db),
+        ModuleDef::Module(it) => match it.parent(db) {
+            Some(parent) => parent,
+            None => return false,
+        },
+    };
+    // Items defined inside of function bodies are not part of the module
+    // scope, so nothing is found for them here.
+    let scope = module.scope(db);
+    let in_types = scope.entries().any(|(_, res)| res.def.types == Some(def));
+    let in_values = scope.entries().any(|(_, res)| res.def.values == Some(def));
+    scope.entries().any(|(name, res)| {
+        name.to_string() == new_name
+            && ((in_types && res.def.types.is_some()) || (in_values && res.def.values.is_some()))
+    })
+}
+
+fn container(db: &RootDatabase, def: Definition) -> Option<Container> {
+    match def {
+        Definition::Def(ModuleDef::Function(it)) => it.container(db),
+        Definition::Def(ModuleDef::Const(it)) => it.container(db),
+        Definition::Def(ModuleDef::TypeAlias(it)) => it.container(db),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
@@ -215,8 +495,9 @@ mod tests {
     use crate::{
         mock_analysis::single_file_with_position,
         mock_analysis::analysis_and_position,
-        FileId, ReferenceSearchResult
-};
+        mock_analysis::MockAnalysis,
+        FileId, FileSystemEdit, ReferenceSearchResult, ReferenceKind, RenameError,
+    };
 
     #[test]
     fn test_find_all_refs_for_local() {
@@ -259,11 +540,150 @@ mod tests {
         assert_eq!(refs.len(), 2);
     }
 
+    #[test]
+    fn test_find_all_refs_for_struct_across_files() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            use crate::foo::Foo;
+
+            fn main() {
+                let f: Foo = Foo;
+            }
+
+            //- /foo.rs
+            pub struct Foo<|>;
+
+            fn bar() -> Foo { Foo }
+            ",
+        );
+        let refs = analysis.find_all_refs(position).unwrap().unwrap();
+        assert_eq!(refs.len(), 6);
+        assert_eq!(
+            reference_kinds(&refs),
+            vec![
+                ReferenceKind::Import,
+                ReferenceKind::Read,
+                ReferenceKind::Read,
+                ReferenceKind::Read,
+                ReferenceKind::Read
+            ]
+        );
+        let files = refs.references().iter().map(|r| r.file_range.file_id).collect::<Vec<_>>();
+        assert_eq!(files, vec![FileId(1), FileId(1), FileId(1), FileId(2), FileId(2)]);
+    }
+
+    #[test]
+    fn test_find_all_refs_for_field() {
+        let code = r#"
+    struct Foo {
+        x<|>: i32,
+    }
+
+    fn f(foo: &mut Foo) {
+        foo.x = 1;
+        let y = foo.x;
+        Foo { x: y };
+    }"#;
+
+        let refs = get_all_refs(code);
+        assert_eq!(
+            reference_kinds(&refs),
+            vec![ReferenceKind::Write, ReferenceKind::Read, ReferenceKind::Read]
+        );
+    }
+
+    #[test]
+    fn test_find_all_refs_for_fn_in_dependency() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            use foo::bar;
+
+            fn main() {
+                bar();
+                foo::bar();
+            }
+
+            //- /foo/lib.rs
+            pub fn bar<|>() {}
+            ",
+        );
+        let refs = analysis.find_all_refs(position).unwrap().unwrap();
+        assert_eq!(
+            reference_kinds(&refs),
+            vec![ReferenceKind::Import, ReferenceKind::Read, ReferenceKind::Read]
+        );
+    }
+
+    #[test]
+    fn test_find_all_refs_for_trait_method() {
+        let code = r#"
+    trait Foo {
+        fn foo(&self);
+    }
+
+    struct S;
+    impl Foo for S {
+        fn foo(&self) {}
+    }
+
+    fn f(s: S) {
+        s.foo<|>();
+    }"#;
+
+        let refs = get_all_refs(code);
+        assert_eq!(refs.declaration().name().as_str(), "foo");
+        assert_eq!(refs.len(), 2);
+    }
+
+    #[test]
+    fn test_find_all_refs_for_module() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            use crate::foo::Foo;
+
+            fn main() {
+                let f = foo<|>::Foo;
+            }
+
+            //- /foo.rs
+            pub struct Foo;
+            ",
+        );
+        let refs = analysis.find_all_refs(position).unwrap().unwrap();
+        assert_eq!(refs.declaration().name().as_str(), "foo");
+        assert_eq!(refs.declaration().file_id(), FileId(1));
+        assert_eq!(reference_kinds(&refs), vec![ReferenceKind::Import, ReferenceKind::Read]);
+    }
+
+    #[test]
+    fn test_find_all_refs_for_fn_shadowed_by_local() {
+        let code = r#"
+    fn foo<|>() {}
+
+    fn main() {
+        foo();
+        let foo = 1;
+        foo;
+    }"#;
+
+        let refs = get_all_refs(code);
+        assert_eq!(reference_kinds(&refs), vec![ReferenceKind::Read]);
+    }
+
     fn get_all_refs(text: &str) -> ReferenceSearchResult {
         let (analysis, position) = single_file_with_position(text);
         analysis.find_all_refs(position).unwrap().unwrap()
     }
 
+    fn reference_kinds(refs: &ReferenceSearchResult) -> Vec<ReferenceKind> {
+        refs.references().iter().map(|r| r.kind).collect()
+    }
+
     #[test]
     fn test_rename_for_local() {
         test_rename(
@@ -355,7 +775,7 @@ mod tests {
             ",
         );
         let new_name = "foo2";
-        let source_change = analysis.rename(position, new_name).unwrap();
+        let source_change = analysis.rename(position, new_name).unwrap().transpose().unwrap();
         assert_debug_snapshot_matches!("rename_mod", &source_change);
     }
 
@@ -370,13 +790,221 @@ mod tests {
             ",
         );
         let new_name = "foo2";
-        let source_change = analysis.rename(position, new_name).unwrap();
+        let source_change = analysis.rename(position, new_name).unwrap().transpose().unwrap();
         assert_debug_snapshot_matches!("rename_mod_in_dir", &source_change);
     }
 
+    #[test]
+    fn test_rename_mod_from_use_site() {
+        let (mock, position) = MockAnalysis::with_files_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            use crate::foo::Bar;
+            struct Baz;
+            fn main() { foo<|>::baz(); }
+            //- /foo.rs
+            pub struct Bar;
+            pub fn baz() {}
+            ",
+        );
+        let lib = mock.id_of("/lib.rs");
+        let analysis = mock.analysis();
+        let source_change = analysis.rename(position, "quux").unwrap().unwrap().unwrap();
+        assert_eq!(source_change.source_file_edits.len(), 1);
+        let edit = &source_change.source_file_edits[0];
+        assert_eq!(edit.file_id, lib);
+        assert_eq_text!(
+            "mod quux;\nuse crate::quux::Bar;\nstruct Baz;\nfn main() { quux::baz(); }",
+            edit.edit.apply(&analysis.file_text(lib)).trim()
+        );
+        match source_change.file_system_edits.as_slice() {
+            [FileSystemEdit::MoveFile { dst_path, .. }] => assert_eq!(dst_path.as_str(), "quux.rs"),
+            edits => panic!("unexpected file system edits: {:?}", edits),
+        }
+
+        let err = analysis.rename(position, "Baz").unwrap().unwrap().unwrap_err();
+        assert_eq!(err, RenameError::NameCollision { new_name: "Baz".to_string() });
+    }
+
+    #[test]
+    fn test_rename_struct_across_files() {
+        let (mock, position) = MockAnalysis::with_files_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            use crate::foo::Foo;
+            fn main() { let f: Foo = Foo; }
+            //- /foo.rs
+            pub struct Foo<|>;
+            ",
+        );
+        let lib = mock.id_of("/lib.rs");
+        let foo = mock.id_of("/foo.rs");
+        let analysis = mock.analysis();
+        let source_change = analysis.rename(position, "Bar").unwrap().unwrap().unwrap();
+        assert_eq!(source_change.source_file_edits.len(), 2);
+        let apply = |file_id: FileId| {
+            let edit = source_change.source_file_edits.iter().find(|it| it.file_id == file_id);
+            edit.unwrap().edit.apply(&analysis.file_text(file_id))
+        };
+        assert_eq_text!(
+            "mod foo;\nuse crate::foo::Bar;\nfn main() { let f: Bar = Bar; }",
+            apply(lib).trim()
+        );
+        assert_eq_text!("pub struct Bar;", apply(foo).trim());
+    }
+
+    #[test]
+    fn test_rename_field_in_shorthand() {
+        test_rename(
+            r#"
+    struct Foo {
+        i<|>: i32,
+    }
+
+    fn foo(i: i32) -> Foo {
+        Foo { i }
+    }"#,
+            "j",
+            r#"
+    struct Foo {
+        j: i32,
+    }
+
+    fn foo(i: i32) -> Foo {
+        Foo { j: i }
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_field_in_field_pat_shorthand() {
+        test_rename(
+            r#"
+    struct Foo {
+        i<|>: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { ref i } = foo;
+        let Foo { i: j } = foo;
+        i + j
+    }"#,
+            "k",
+            r#"
+    struct Foo {
+        k: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { k: ref i } = foo;
+        let Foo { k: j } = foo;
+        i + j
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_local_in_field_pat_shorthand() {
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { mut i } = foo;
+        i<|>
+    }"#,
+            "j",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(foo: Foo) -> i32 {
+        let Foo { i: mut j } = foo;
+        j
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_local_in_field_shorthand() {
+        test_rename(
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(i<|>: i32) -> Foo {
+        Foo { i }
+    }"#,
+            "j",
+            r#"
+    struct Foo {
+        i: i32,
+    }
+
+    fn foo(j: i32) -> Foo {
+        Foo { i: j }
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_trait_method_in_impls() {
+        test_rename(
+            r#"
+    trait Foo {
+        fn foo(&self);
+    }
+
+    struct S;
+
+    impl Foo for S {
+        fn foo(&self) {}
+    }
+
+    fn f(s: S) {
+        s.foo<|>();
+    }"#,
+            "bar",
+            r#"
+    trait Foo {
+        fn bar(&self);
+    }
+
+    struct S;
+
+    impl Foo for S {
+        fn bar(&self) {}
+    }
+
+    fn f(s: S) {
+        s.bar();
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_name_collision() {
+        let (analysis, position) = single_file_with_position(
+            r#"
+    fn foo<|>() {}
+    fn bar() {}
+    struct Baz {}"#,
+        );
+        let err = analysis.rename(position, "bar").unwrap().unwrap().unwrap_err();
+        assert_eq!(err, RenameError::NameCollision { new_name: "bar".to_string() });
+        // `Baz` is only defined in the type namespace
+        assert!(analysis.rename(position, "Baz").unwrap().unwrap().is_ok());
+    }
+
     fn test_rename(text: &str, new_name: &str, expected: &str) {
         let (analysis, position) = single_file_with_position(text);
-        let source_change = analysis.rename(position, new_name).unwrap();
+        let source_change = analysis.rename(position, new_name).unwrap().transpose().unwrap();
         let mut text_edit_builder = ra_text_edit::TextEditBuilder::default();
         let mut file_id: Option<FileId> = None;
         if let Some(change) = source_change {
@@ -0,0 +1,222 @@
+//! Maps names at definition and use sites to the item they refer to, so that
+//! references from different files can be compared with each other.
+
+use hir::{source_binder, ImplItem, ModuleDef, TraitItem};
+use ra_db::FileId;
+use ra_syntax::{
+    AstNode, SmolStr, SyntaxNode, SyntaxNodePtr,
+    SyntaxKind::{FN_DEF, CONST_DEF, STATIC_DEF},
+    ast::{self, NameOwner},
+    algo::visit::{visitor, Visitor},
+};
+use rustc_hash::FxHashMap;
+
+use crate::{db::RootDatabase, NavigationTarget};
+
+/// Something which can be referenced by name from other files.
+///
+/// Local bindings are handled separately, as they are only visible within a
+/// single body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Definition {
+    Def(ModuleDef),
+    Field(hir::StructField),
+    Macro(hir::MacroByExampleDef),
+}
+
+impl From<ImplItem> for Definition {
+    fn from(item: ImplItem) -> Definition {
+        let def = match item {
+            ImplItem::Method(it) => ModuleDef::Function(it),
+            ImplItem::Const(it) => ModuleDef::Const(it),
+            ImplItem::TypeAlias(it) => ModuleDef::TypeAlias(it),
+        };
+        Definition::Def(def)
+    }
+}
+
+impl From<TraitItem> for Definition {
+    fn from(item: TraitItem) -> Definition {
+        let def = match item {
+            TraitItem::Function(it) => ModuleDef::Function(it),
+            TraitItem::Const(it) => ModuleDef::Const(it),
+            TraitItem::TypeAlias(it) => ModuleDef::TypeAlias(it),
+        };
+        Definition::Def(def)
+    }
+}
+
+impl Definition {
+    pub(crate) fn to_nav(self, db: &RootDatabase) -> NavigationTarget {
+        match self {
+            Definition::Def(ModuleDef::Module(module)) => {
+                NavigationTarget::from_module_to_decl(db, module)
+            }
+            Definition::Def(def) => NavigationTarget::from_def(db, def),
+            Definition::Field(field) => NavigationTarget::from_field(db, field),
+            Definition::Macro(mac) => NavigationTarget::from_macro_def(db, mac),
+        }
+    }
+}
+
+/// Classifies the name of an item at its definition site.
+pub(crate) fn classify_name(
+    db: &RootDatabase,
+    file_id: FileId,
+    name: &ast::Name,
+) -> Option<Definition> {
+    let parent = name.syntax().parent()?;
+    let module = source_binder::module_from_child_node(db, file_id, parent)?;
+
+    let variant = |it: &ast::EnumVariant| {
+        let enum_def = it.syntax().ancestors().find_map(ast::EnumDef::cast)?;
+        let name = it.name()?;
+        source_binder::enum_from_module(db, module, enum_def)
+            .variants(db)
+            .into_iter()
+            .find(|var| var.name(db).map_or(false, |it| is_named(&it, name.text())))
+    };
+
+    if let Some(field_def) = ast::NamedFieldDef::cast(parent) {
+        let owner = field_def.syntax().parent()?.parent()?;
+        let fields = if let Some(struct_def) = ast::StructDef::cast(owner) {
+            source_binder::struct_from_module(db, module, struct_def).fields(db)
+        } else {
+            variant(ast::EnumVariant::cast(owner)?)?.fields(db)
+        };
+        let field = fields.into_iter().find(|it| is_named(&it.name(db), name.text()))?;
+        return Some(Definition::Field(field));
+    }
+
+    let def = visitor::<Option<ModuleDef>>()
+        .visit(|it: &ast::Module| {
+            source_binder::module_from_declaration(db, file_id, it).map(Into::into)
+        })
+        .visit(|it: &ast::FnDef| Some(source_binder::function_from_module(db, module, it).into()))
+        .visit(|it: &ast::StructDef| Some(source_binder::struct_from_module(db, module, it).into()))
+        .visit(|it: &ast::EnumDef| Some(source_binder::enum_from_module(db, module, it).into()))
+        .visit(|it: &ast::EnumVariant| variant(it).map(Into::into))
+        .visit(|it: &ast::TraitDef| Some(source_binder::trait_from_module(db, module, it).into()))
+        .visit(|it: &ast::ConstDef| Some(source_binder::const_from_module(db, module, it).into()))
+        .visit(|it: &ast::StaticDef| Some(source_binder::static_from_module(db, module, it).into()))
+        .visit(|it: &ast::TypeAliasDef| {
+            Some(source_binder::type_alias_from_module(db, module, it).into())
+        })
+        .accept(parent)??;
+    Some(Definition::Def(def))
+}
+
+/// Classifies the item a `NameRef` refers to, using the same special cases
+/// as goto definition.
+///
+/// `analyzers` caches the analyzers of the bodies of the file seen so far, so
+/// that each body is analyzed once when classifying all names of a file.
+pub(crate) fn classify_name_ref(
+    db: &RootDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+    analyzers: &mut FxHashMap<SyntaxNodePtr, hir::SourceAnalyzer>,
+) -> Option<Definition> {
+    let analyzer_outside_of_body;
+    let analyzer = match body_analyzer(db, file_id, name_ref.syntax(), analyzers) {
+        Some(it) => it,
+        None => {
+            analyzer_outside_of_body =
+                hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+            &analyzer_outside_of_body
+        }
+    };
+
+    if let Some(method_call) = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast) {
+        let func = analyzer.resolve_method_call(method_call)?;
+        return Some(Definition::Def(func.into()));
+    }
+
+    if let Some(macro_call) = name_ref
+        .syntax()
+        .parent()
+        .and_then(|node| node.parent())
+        .and_then(|node| node.parent())
+        .and_then(ast::MacroCall::cast)
+    {
+        if let Some(mac) = analyzer.resolve_macro_call(db, macro_call) {
+            return Some(Definition::Macro(mac));
+        }
+    }
+
+    if let Some(field_expr) = name_ref.syntax().parent().and_then(ast::FieldExpr::cast) {
+        let field = analyzer.resolve_field(field_expr)?;
+        return Some(Definition::Field(field));
+    }
+
+    if let Some(named_field) = name_ref.syntax().parent().and_then(ast::NamedField::cast) {
+        let struct_lit = named_field.syntax().ancestors().find_map(ast::StructLit::cast)?;
+        let ty = analyzer.type_of(db, struct_lit.into())?;
+        if let Some((hir::AdtDef::Struct(s), _)) = ty.as_adt() {
+            let field = s.fields(db).into_iter().find(|it| is_named(&it.name(db), name_ref.text()));
+            return field.map(Definition::Field);
+        }
+    }
+
+    let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+    let is_local_candidate = path.qualifier().is_none()
+        && path.syntax().parent().and_then(ast::PathExpr::cast).is_some();
+    if is_local_candidate && analyzer.resolve_local_name(name_ref).is_some() {
+        return None;
+    }
+    match analyzer.resolve_path(db, path)? {
+        hir::PathResolution::Def(def) => Some(Definition::Def(def)),
+        hir::PathResolution::AssocItem(item) => Some(item.into()),
+        hir::PathResolution::Macro(mac) => Some(Definition::Macro(mac)),
+        hir::PathResolution::LocalBinding(_)
+        | hir::PathResolution::GenericParam(_)
+        | hir::PathResolution::SelfType(_) => None,
+    }
+}
+
+/// Classifies the field named by a field pattern or by a shorthand binding in
+/// a struct pattern, like `x` in `let Foo { x } = foo;`.
+pub(crate) fn classify_field_pat_name(
+    db: &RootDatabase,
+    file_id: FileId,
+    name: &ast::Name,
+    analyzers: &mut FxHashMap<SyntaxNodePtr, hir::SourceAnalyzer>,
+) -> Option<Definition> {
+    let pat = name.syntax().parent()?;
+    if ast::FieldPat::cast(pat).is_none() && ast::BindPat::cast(pat).is_none() {
+        return None;
+    }
+    let struct_pat = pat.parent().and_then(ast::FieldPatList::cast)?.syntax().parent()?;
+    let struct_pat = ast::StructPat::cast(struct_pat)?;
+    let analyzer = body_analyzer(db, file_id, struct_pat.syntax(), analyzers)?;
+    let ty = analyzer.type_of_pat(db, struct_pat.into())?;
+    if let Some((hir::AdtDef::Struct(s), _)) = ty.as_adt() {
+        let field = s.fields(db).into_iter().find(|it| is_named(&it.name(db), name.text()));
+        return field.map(Definition::Field);
+    }
+    None
+}
+
+/// Returns the analyzer of the body containing `node`, if any.
+///
+/// The analyzer of a body is created for the body as a whole, so it doesn't
+/// see the local bindings, which are resolved with `resolve_local_name`.
+fn body_analyzer<'a>(
+    db: &RootDatabase,
+    file_id: FileId,
+    node: &SyntaxNode,
+    analyzers: &'a mut FxHashMap<SyntaxNodePtr, hir::SourceAnalyzer>,
+) -> Option<&'a hir::SourceAnalyzer> {
+    let body = node.ancestors().find(|it| match it.kind() {
+        FN_DEF | CONST_DEF | STATIC_DEF => true,
+        _ => false,
+    })?;
+    let analyzer = analyzers
+        .entry(SyntaxNodePtr::new(body))
+        .or_insert_with(|| hir::SourceAnalyzer::new(db, file_id, body, None));
+    Some(analyzer)
+}
+
+fn is_named(name: &hir::Name, text: &SmolStr) -> bool {
+    name.to_string() == text.as_str()
+}
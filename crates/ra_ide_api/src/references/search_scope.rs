@@ -0,0 +1,62 @@
+//! Determines the set of files which may contain references to a definition:
+//! the files of the crate which defines it and of all crates depending on that
+//! crate, directly or transitively.
+
+use hir::{source_binder, Crate};
+use ra_db::{FileId, SourceDatabase};
+
+use crate::{db::RootDatabase, symbol_index::SymbolsDatabase, NavigationTarget};
+
+pub(crate) fn search_scope(db: &RootDatabase, declaration: &NavigationTarget) -> Vec<FileId> {
+    let crates = search_crates(db, declaration);
+    if crates.is_empty() {
+        return vec![declaration.file_id()];
+    }
+
+    let crate_graph = db.crate_graph();
+    let mut source_roots: Vec<_> = crates
+        .iter()
+        .map(|it| db.file_source_root(crate_graph.crate_root(it.crate_id())))
+        .collect();
+    source_roots.sort();
+    source_roots.dedup();
+
+    let mut files = Vec::new();
+    for root in source_roots {
+        files.extend(db.source_root(root).files.values().cloned());
+    }
+    files.sort();
+    files
+}
+
+/// Returns the crate defining `declaration`, followed by all crates depending
+/// on it.
+pub(crate) fn search_crates(db: &RootDatabase, declaration: &NavigationTarget) -> Vec<Crate> {
+    let krate = match source_binder::module_from_file_id(db, declaration.file_id())
+        .and_then(|module| module.krate(db))
+    {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+
+    let all_crates: Vec<Crate> = db
+        .local_roots()
+        .iter()
+        .chain(db.library_roots().iter())
+        .flat_map(|&root| Crate::source_root_crates(db, root))
+        .collect();
+    let mut crates = vec![krate];
+    loop {
+        let dependents: Vec<Crate> = all_crates
+            .iter()
+            .filter(|it| !crates.contains(it))
+            .filter(|it| it.dependencies(db).iter().any(|dep| crates.contains(&dep.krate)))
+            .cloned()
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        crates.extend(dependents);
+    }
+    crates
+}
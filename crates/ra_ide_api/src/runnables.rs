@@ -1,7 +1,8 @@
 use itertools::Itertools;
 use ra_syntax::{
     TextRange, SyntaxNode,
-    ast::{self, AstNode, NameOwner, ModuleItemOwner, AttrsOwner},
+    ast::{self, AstNode, NameOwner, ModuleItemOwner, AttrsOwner, DocCommentsOwner},
+    algo::visit::{visitor, Visitor},
 };
 use ra_db::SourceDatabase;
 
@@ -13,11 +14,14 @@ pub struct Runnable {
     pub kind: RunnableKind,
 }
 
+/// The paths are relative to the crate root. Rustdoc names the tests for the
+/// code blocks in the doc comment of an item after the path of the item.
 #[derive(Debug)]
 pub enum RunnableKind {
-    Test { name: String },
+    Test { path: String },
     TestMod { path: String },
-    Bench { name: String },
+    Bench { path: String },
+    DocTest { path: String },
     Bin,
 }
 
@@ -27,25 +31,28 @@ pub(crate) fn runnables(db: &RootDatabase, file_id: FileId) -> Vec<Runnable> {
 }
 
 fn runnable(db: &RootDatabase, file_id: FileId, item: &SyntaxNode) -> Option<Runnable> {
-    if let Some(fn_def) = ast::FnDef::cast(item) {
-        runnable_fn(fn_def)
-    } else if let Some(m) = ast::Module::cast(item) {
-        runnable_mod(db, file_id, m)
-    } else {
-        None
-    }
+    visitor()
+        .visit(|it: &ast::FnDef| runnable_fn(db, file_id, it))
+        .visit(|it: &ast::Module| runnable_mod(db, file_id, it))
+        .visit(|it: &ast::StructDef| runnable_doc_test(db, file_id, it))
+        .visit(|it: &ast::EnumDef| runnable_doc_test(db, file_id, it))
+        .visit(|it: &ast::TraitDef| runnable_doc_test(db, file_id, it))
+        .visit(|it: &ast::TypeAliasDef| runnable_doc_test(db, file_id, it))
+        .visit(|it: &ast::ConstDef| runnable_doc_test(db, file_id, it))
+        .visit(|it: &ast::StaticDef| runnable_doc_test(db, file_id, it))
+        .accept(item)?
 }
 
-fn runnable_fn(fn_def: &ast::FnDef) -> Option<Runnable> {
+fn runnable_fn(db: &RootDatabase, file_id: FileId, fn_def: &ast::FnDef) -> Option<Runnable> {
     let name = fn_def.name()?.text();
     let kind = if name == "main" {
         RunnableKind::Bin
     } else if fn_def.has_atom_attr("test") {
-        RunnableKind::Test { name: name.to_string() }
+        RunnableKind::Test { path: item_path(db, file_id, fn_def)? }
     } else if fn_def.has_atom_attr("bench") {
-        RunnableKind::Bench { name: name.to_string() }
+        RunnableKind::Bench { path: item_path(db, file_id, fn_def)? }
     } else {
-        return None;
+        return runnable_doc_test(db, file_id, fn_def);
     };
     Some(Runnable { range: fn_def.syntax().range(), kind })
 }
@@ -69,11 +76,69 @@ fn runnable_mod(db: &RootDatabase, file_id: FileId, module: &ast::Module) -> Opt
     Some(Runnable { range, kind: RunnableKind::TestMod { path } })
 }
 
+fn runnable_doc_test<N>(db: &RootDatabase, file_id: FileId, item: &N) -> Option<Runnable>
+where
+    N: NameOwner + DocCommentsOwner,
+{
+    if !has_runnable_doc_test(&item.doc_comment_text()?) {
+        return None;
+    }
+    let path = item_path(db, file_id, item)?;
+    Some(Runnable { range: item.syntax().range(), kind: RunnableKind::DocTest { path } })
+}
+
+/// Returns the path of an item relative to the crate root, like
+/// `foo::tests::test_bar`. Like in rustdoc, items of impls and traits are
+/// prefixed with the type or trait.
+fn item_path(db: &RootDatabase, file_id: FileId, item: &impl NameOwner) -> Option<String> {
+    let module = hir::source_binder::module_from_child_node(db, file_id, item.syntax())?;
+    let mut path = module
+        .path_to_root(db)
+        .into_iter()
+        .rev()
+        .filter_map(|it| it.name(db))
+        .map(|it| it.to_string())
+        .collect::<Vec<_>>();
+    let container = item.syntax().parent().and_then(|it| it.parent());
+    if let Some(impl_block) = container.and_then(ast::ImplBlock::cast) {
+        path.push(impl_block.target_type()?.syntax().text().to_string());
+    } else if let Some(trait_def) = container.and_then(ast::TraitDef::cast) {
+        path.push(trait_def.name()?.text().to_string());
+    }
+    path.push(item.name()?.text().to_string());
+    Some(path.join("::"))
+}
+
+/// Checks whether the doc comment contains a code block that rustdoc
+/// compiles as Rust and doesn't ignore.
+fn has_runnable_doc_test(docs: &str) -> bool {
+    const RUSTDOC_FENCE: &str = "```";
+    const RUSTDOC_CODE_BLOCK_ATTRIBUTES: &[&str] =
+        &["", "rust", "should_panic", "no_run", "compile_fail", "edition2015", "edition2018"];
+
+    let mut in_code_block = false;
+    for line in docs.lines() {
+        let line = line.trim_start();
+        if !line.starts_with(RUSTDOC_FENCE) {
+            continue;
+        }
+        in_code_block = !in_code_block;
+        if in_code_block
+            && line[RUSTDOC_FENCE.len()..]
+                .split(',')
+                .all(|attr| RUSTDOC_CODE_BLOCK_ATTRIBUTES.contains(&attr.trim()))
+        {
+            return true;
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot_matches;
 
-    use crate::mock_analysis::analysis_and_position;
+    use crate::{mock_analysis::analysis_and_position, RunnableKind};
 
     #[test]
     fn test_runnables() {
@@ -164,4 +229,50 @@ mod tests {
         assert!(runnables.is_empty())
     }
 
+    #[test]
+    fn test_runnables_doc_test() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        /// ```
+        /// let x = 5;
+        /// ```
+        fn foo() {}
+
+        /// ```text
+        /// not rust
+        /// ```
+        struct Bar;
+
+        impl Bar {
+            /// ```no_run
+            /// Bar::new();
+            /// ```
+            fn new() -> Bar { Bar }
+        }
+
+        mod baz {
+            /// ```
+            /// baz::QUX;
+            /// ```
+            const QUX: u32 = 92;
+        }
+
+        /// ```ignore
+        /// not compiled
+        /// ```
+        fn ignored() {}
+        "#,
+        );
+        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let doc_tests: Vec<&str> = runnables
+            .iter()
+            .filter_map(|it| match &it.kind {
+                RunnableKind::DocTest { path } => Some(path.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(doc_tests, vec!["foo", "Bar::new", "baz::QUX"]);
+    }
 }
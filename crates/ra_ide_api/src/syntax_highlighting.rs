@@ -1,6 +1,14 @@
-use rustc_hash::FxHashSet;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
 
-use ra_syntax::{ast, AstNode, TextRange, Direction, SyntaxKind, SyntaxKind::*, SyntaxElement, T};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use ra_syntax::{
+    ast, AstNode, TextRange, Direction, SmolStr, SyntaxKind, SyntaxKind::*, SyntaxElement,
+    SyntaxNodePtr, T,
+};
 use ra_db::SourceDatabase;
 
 use crate::{FileId, db::RootDatabase};
@@ -9,6 +17,9 @@ use crate::{FileId, db::RootDatabase};
 pub struct HighlightedRange {
     pub range: TextRange,
     pub tag: &'static str,
+    /// For local bindings, a hash identifying the variable, so that editors
+    /// can give each variable a color of its own.
+    pub binding_hash: Option<u64>,
 }
 
 fn is_control_keyword(kind: SyntaxKind) -> bool {
@@ -31,17 +42,45 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
 
     // Visited nodes to handle highlighting priorities
     let mut highlighted: FxHashSet<SyntaxElement> = FxHashSet::default();
+    // Tags and hashes of the local bindings seen so far, by the range of their
+    // pattern.
+    let mut bindings: FxHashMap<TextRange, (&'static str, u64)> = FxHashMap::default();
+    let mut bindings_shadow_count: FxHashMap<SmolStr, u32> = FxHashMap::default();
+    // Analyzers of the bodies seen so far, so that each body is analyzed once.
+    let mut analyzers: FxHashMap<SyntaxNodePtr, hir::SourceAnalyzer> = FxHashMap::default();
     let mut res = Vec::new();
     for node in source_file.syntax().descendants_with_tokens() {
         if highlighted.contains(&node) {
             continue;
         }
+        let mut binding_hash = None;
         let tag = match node.kind() {
             COMMENT => "comment",
             STRING | RAW_STRING | RAW_BYTE_STRING | BYTE_STRING => "string",
             ATTR => "attribute",
-            NAME_REF => "text",
-            NAME => "function",
+            NAME_REF => {
+                let name_ref = node.as_node().and_then(ast::NameRef::cast).unwrap();
+                let (tag, hash) =
+                    highlight_name_ref(db, file_id, name_ref, &bindings, &mut analyzers);
+                binding_hash = hash;
+                tag
+            }
+            NAME => {
+                let name = node.as_node().and_then(ast::Name::cast).unwrap();
+                match name.syntax().parent().and_then(ast::BindPat::cast) {
+                    Some(bind_pat) => {
+                        let shadow_count =
+                            bindings_shadow_count.entry(name.text().clone()).or_default();
+                        *shadow_count += 1;
+                        let hash = calc_binding_hash(file_id, name.text(), *shadow_count);
+                        let tag = highlight_binding(bind_pat);
+                        bindings.insert(bind_pat.syntax().range(), (tag, hash));
+                        binding_hash = Some(hash);
+                        tag
+                    }
+                    None => highlight_name(name),
+                }
+            }
             INT_NUMBER | FLOAT_NUMBER | CHAR | BYTE => "literal",
             LIFETIME => "parameter",
             T![unsafe] => "keyword.unsafe",
@@ -64,6 +103,7 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                                 res.push(HighlightedRange {
                                     range: TextRange::from_to(range_start, range_end),
                                     tag: "macro",
+                                    binding_hash: None,
                                 })
                             }
                         }
@@ -72,11 +112,159 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                 continue;
             }
         };
-        res.push(HighlightedRange { range: node.range(), tag })
+        res.push(HighlightedRange { range: node.range(), tag, binding_hash })
+    }
+    for range in hir::source_binder::inactive_code(db, file_id) {
+        res.push(HighlightedRange { range, tag: "inactive", binding_hash: None })
     }
     res
 }
 
+fn highlight_name(name: &ast::Name) -> &'static str {
+    let parent = match name.syntax().parent() {
+        Some(it) => it,
+        None => return "text",
+    };
+    match parent.kind() {
+        FN_DEF => {
+            let fn_def = ast::FnDef::cast(parent).unwrap();
+            match fn_def.param_list().and_then(|it| it.self_param()) {
+                Some(_) => "method",
+                None => "function",
+            }
+        }
+        STRUCT_DEF => "struct",
+        ENUM_DEF => "enum",
+        ENUM_VARIANT => "variant",
+        TRAIT_DEF => "trait",
+        TYPE_ALIAS_DEF => "type",
+        TYPE_PARAM => "type.param",
+        CONST_DEF => "constant",
+        STATIC_DEF => "static",
+        MODULE => "module",
+        NAMED_FIELD_DEF => "field",
+        MACRO_CALL => "macro",
+        _ => "text",
+    }
+}
+
+fn highlight_binding(bind_pat: &ast::BindPat) -> &'static str {
+    let is_param = bind_pat.syntax().ancestors().any(|it| ast::Param::cast(it).is_some());
+    if is_param {
+        "parameter"
+    } else if bind_pat.is_mutable() {
+        "variable.mut"
+    } else {
+        "variable"
+    }
+}
+
+fn highlight_name_ref(
+    db: &RootDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+    bindings: &FxHashMap<TextRange, (&'static str, u64)>,
+    analyzers: &mut FxHashMap<SyntaxNodePtr, hir::SourceAnalyzer>,
+) -> (&'static str, Option<u64>) {
+    let parent = match name_ref.syntax().parent() {
+        Some(it) => it,
+        None => return ("text", None),
+    };
+    if ast::NamedField::cast(parent).is_some() {
+        return ("field", None);
+    }
+    // The analyzer of a body is created for the body as a whole, so it doesn't
+    // see the local bindings, which are resolved with `resolve_local_name`.
+    let body = name_ref.syntax().ancestors().find(|it| match it.kind() {
+        FN_DEF | CONST_DEF | STATIC_DEF => true,
+        _ => false,
+    });
+    let analyzer_outside_of_body;
+    let analyzer = match body {
+        Some(body) => &*analyzers
+            .entry(SyntaxNodePtr::new(body))
+            .or_insert_with(|| hir::SourceAnalyzer::new(db, file_id, body, None)),
+        None => {
+            analyzer_outside_of_body =
+                hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+            &analyzer_outside_of_body
+        }
+    };
+    if let Some(method_call) = ast::MethodCallExpr::cast(parent) {
+        let tag =
+            analyzer.resolve_method_call(method_call).map_or("text", |it| function_tag(db, it));
+        return (tag, None);
+    }
+    if let Some(field_expr) = ast::FieldExpr::cast(parent) {
+        let tag = if analyzer.resolve_field(field_expr).is_some() { "field" } else { "text" };
+        return (tag, None);
+    }
+    let path = match ast::PathSegment::cast(parent) {
+        Some(segment) => segment.parent_path(),
+        None => return ("text", None),
+    };
+    let is_local_candidate = path.qualifier().is_none()
+        && path.syntax().parent().and_then(ast::PathExpr::cast).is_some();
+    if is_local_candidate {
+        if let Some(entry) = analyzer.resolve_local_name(name_ref) {
+            return match entry.ptr() {
+                hir::Either::A(pat) => match bindings.get(&pat.syntax_node_ptr().range()) {
+                    Some(&(tag, hash)) => (tag, Some(hash)),
+                    None => ("variable", None),
+                },
+                hir::Either::B(_) => ("parameter", None),
+            };
+        }
+    }
+    let tag = match analyzer.resolve_path(db, path) {
+        Some(hir::PathResolution::Def(def)) => def_tag(db, def),
+        // Local bindings are resolved by `resolve_local_name` above.
+        Some(hir::PathResolution::LocalBinding(_)) => "variable",
+        Some(hir::PathResolution::GenericParam(_)) => "type.param",
+        Some(hir::PathResolution::SelfType(_)) => "type",
+        Some(hir::PathResolution::Macro(_)) => "macro",
+        Some(hir::PathResolution::AssocItem(hir::ImplItem::Method(it))) => function_tag(db, it),
+        Some(hir::PathResolution::AssocItem(hir::ImplItem::Const(_))) => "constant",
+        Some(hir::PathResolution::AssocItem(hir::ImplItem::TypeAlias(_))) => "type",
+        None => "text",
+    };
+    (tag, None)
+}
+
+fn def_tag(db: &RootDatabase, def: hir::ModuleDef) -> &'static str {
+    match def {
+        hir::ModuleDef::Module(_) => "module",
+        hir::ModuleDef::Function(it) => function_tag(db, it),
+        hir::ModuleDef::Struct(_) => "struct",
+        hir::ModuleDef::Enum(_) => "enum",
+        hir::ModuleDef::EnumVariant(_) => "variant",
+        hir::ModuleDef::Const(_) => "constant",
+        // Foreign statics are unsafe to use, so they are highlighted like
+        // `unsafe` itself.
+        hir::ModuleDef::Static(it) if it.is_foreign(db) => "static.unsafe",
+        hir::ModuleDef::Static(_) => "static",
+        hir::ModuleDef::Trait(_) => "trait",
+        hir::ModuleDef::TypeAlias(_) => "type",
+    }
+}
+
+fn function_tag(db: &RootDatabase, function: hir::Function) -> &'static str {
+    let signature = function.signature(db);
+    if signature.is_foreign() {
+        "function.unsafe"
+    } else if signature.has_self_param() {
+        "method"
+    } else {
+        "function"
+    }
+}
+
+fn calc_binding_hash(file_id: FileId, text: &SmolStr, shadow_count: u32) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (file_id, text, shadow_count).hash(&mut hasher);
+    hasher.finish()
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot_matches;
@@ -95,4 +283,110 @@ fn main() {}
         let result = analysis.highlight(file_id);
         assert_debug_snapshot_matches!("highlighting", result);
     }
+
+    #[test]
+    fn test_highlighting_foreign_items() {
+        let (analysis, file_id) = single_file(
+            r#"
+extern "C" {
+    fn abs(x: i32) -> i32;
+    static errno: i32;
+}
+fn main() {
+    unsafe { abs(errno) };
+}
+"#,
+        );
+        let result = analysis.highlight(file_id).unwrap();
+        let unsafe_refs: Vec<_> =
+            result.iter().filter(|it| it.tag.ends_with(".unsafe")).map(|it| it.tag).collect();
+        assert_eq!(unsafe_refs, vec!["keyword.unsafe", "function.unsafe", "static.unsafe"]);
+    }
+
+    #[test]
+    fn test_highlighting_resolved_names() {
+        let code = r#"
+struct Foo { x: u32 }
+enum E { V }
+trait T {}
+const C: u32 = 0;
+static S: u32 = 0;
+mod m {}
+impl Foo {
+    fn get(&self) -> u32 { self.x }
+}
+fn f<P>(foo: Foo, p: P) {
+    let mut a = foo.get();
+    a = foo.x + C + S;
+    let e = E::V;
+    f(foo, p);
+}
+"#;
+        let (analysis, file_id) = single_file(code);
+        let result = analysis.highlight(file_id).unwrap();
+        let names: Vec<(&str, &str)> = result
+            .iter()
+            .filter(|it| !it.tag.starts_with("keyword") && it.tag != "literal" && it.tag != "text")
+            .map(|it| (&code[it.range], it.tag))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Foo", "struct"),
+                ("x", "field"),
+                ("E", "enum"),
+                ("V", "variant"),
+                ("T", "trait"),
+                ("C", "constant"),
+                ("S", "static"),
+                ("m", "module"),
+                ("Foo", "struct"),
+                ("get", "method"),
+                ("x", "field"),
+                ("f", "function"),
+                ("P", "type.param"),
+                ("foo", "parameter"),
+                ("Foo", "struct"),
+                ("p", "parameter"),
+                ("P", "type.param"),
+                ("a", "variable.mut"),
+                ("foo", "parameter"),
+                ("get", "method"),
+                ("a", "variable.mut"),
+                ("foo", "parameter"),
+                ("x", "field"),
+                ("C", "constant"),
+                ("S", "static"),
+                ("e", "variable"),
+                ("E", "enum"),
+                ("V", "variant"),
+                ("f", "function"),
+                ("foo", "parameter"),
+                ("p", "parameter"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlighting_binding_hashes() {
+        let code = r#"
+fn main() {
+    let x = 1;
+    let y = x;
+    let x = "shadowed";
+    let z = x;
+}
+"#;
+        let (analysis, file_id) = single_file(code);
+        let result = analysis.highlight(file_id).unwrap();
+        let hashes: Vec<u64> = result
+            .iter()
+            .filter(|it| &code[it.range] == "x")
+            .map(|it| it.binding_hash.unwrap())
+            .collect();
+        assert_eq!(hashes.len(), 4);
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[2], hashes[3]);
+        assert_ne!(hashes[0], hashes[2]);
+    }
 }
@@ -0,0 +1,453 @@
+//! Runs `cargo check` in the background and converts the messages of the
+//! compiler into LSP diagnostics, including quick fixes for the replacements
+//! suggested by rustc.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+use failure::format_err;
+use lsp_types::{
+    CodeAction, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
+    NumberOrString, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use thread_worker::Worker;
+
+use crate::Result;
+
+/// The command which is run to check a workspace.
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl Default for CheckOptions {
+    fn default() -> CheckOptions {
+        CheckOptions {
+            command: "cargo".to_string(),
+            args: vec!["check".to_string(), "--message-format=json".to_string()],
+        }
+    }
+}
+
+/// A diagnostic reported by the compiler, together with the fixes it suggests.
+#[derive(Debug, Clone)]
+pub struct CheckDiagnostic {
+    pub diagnostic: Diagnostic,
+    pub fixes: Vec<CodeAction>,
+}
+
+/// The diagnostics of the last `cargo check` run, by file.
+#[derive(Debug, Default)]
+pub struct CheckDiagnostics {
+    map: FxHashMap<Url, Vec<CheckDiagnostic>>,
+}
+
+impl CheckDiagnostics {
+    pub fn get(&self, uri: &Url) -> &[CheckDiagnostic] {
+        self.map.get(uri).map(|it| it.as_slice()).unwrap_or_default()
+    }
+
+    pub fn uris(&self) -> impl Iterator<Item = &Url> {
+        self.map.keys()
+    }
+
+    fn add(&mut self, uri: Url, diagnostic: CheckDiagnostic) {
+        self.map.entry(uri).or_default().push(diagnostic)
+    }
+}
+
+#[derive(Debug)]
+pub enum CheckTask {
+    /// `cargo check` has finished, the diagnostics replace the previous ones.
+    Finished(CheckDiagnostics),
+    /// `cargo check` could not be run.
+    Failed(String),
+}
+
+/// Spawns a worker which checks all `workspace_roots` whenever it receives a
+/// message. A check which is still running when a new message arrives is
+/// killed and restarted.
+pub fn check_worker(workspace_roots: Vec<PathBuf>, options: CheckOptions) -> Worker<(), CheckTask> {
+    Worker::<(), CheckTask>::spawn("cargo check", 1, move |input_receiver, output_sender| {
+        check_loop(&workspace_roots, &options, &input_receiver, &output_sender)
+    })
+}
+
+enum CheckOutcome {
+    Finished(CheckDiagnostics),
+    Restarted,
+    Shutdown,
+}
+
+fn check_loop(
+    workspace_roots: &[PathBuf],
+    options: &CheckOptions,
+    input_receiver: &Receiver<()>,
+    output_sender: &Sender<CheckTask>,
+) {
+    while input_receiver.recv().is_ok() {
+        loop {
+            let task = match check_workspaces(workspace_roots, options, input_receiver) {
+                Ok(CheckOutcome::Finished(diagnostics)) => CheckTask::Finished(diagnostics),
+                Ok(CheckOutcome::Restarted) => continue,
+                Ok(CheckOutcome::Shutdown) => return,
+                Err(e) => CheckTask::Failed(e.to_string()),
+            };
+            output_sender.send(task).unwrap();
+            break;
+        }
+    }
+}
+
+fn check_workspaces(
+    workspace_roots: &[PathBuf],
+    options: &CheckOptions,
+    input_receiver: &Receiver<()>,
+) -> Result<CheckOutcome> {
+    let mut diagnostics = CheckDiagnostics::default();
+    for workspace_root in workspace_roots {
+        let mut child = Command::new(&options.command)
+            .args(&options.args)
+            .current_dir(workspace_root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // The output is read on separate threads, so that a restart or a
+        // shutdown is noticed even while cargo is not printing anything.
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (line_sender, line_receiver) = unbounded();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                if line_sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr_thread = thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        });
+
+        let mut n_messages = 0;
+        loop {
+            let line = select! {
+                recv(input_receiver) -> msg => {
+                    kill(child);
+                    return Ok(match msg {
+                        Ok(()) => CheckOutcome::Restarted,
+                        Err(_) => CheckOutcome::Shutdown,
+                    });
+                }
+                recv(line_receiver) -> line => match line {
+                    Ok(Ok(line)) => line,
+                    Ok(Err(e)) => {
+                        kill(child);
+                        return Err(e.into());
+                    }
+                    // stdout is closed, cargo has finished
+                    Err(_) => break,
+                },
+            };
+            let message = match serde_json::from_str::<CargoMessage>(&line) {
+                Ok(CargoMessage { message: Some(message), .. }) => message,
+                // Not every line is a compiler message, e.g. build scripts
+                // can print arbitrary output.
+                _ => continue,
+            };
+            n_messages += 1;
+            if let Some((uri, diagnostic)) = map_rust_diagnostic(&message, workspace_root) {
+                diagnostics.add(uri, diagnostic);
+            }
+        }
+        let status = child.wait()?;
+        let stderr = stderr_thread.join().unwrap_or_default();
+        // Without any messages, a failure is not caused by errors in the
+        // code, but by a broken manifest, a missing toolchain and alike.
+        if !status.success() && n_messages == 0 {
+            return Err(format_err!(
+                "`{}` failed ({}): {}",
+                options.command,
+                status,
+                stderr.trim()
+            ));
+        }
+    }
+    Ok(CheckOutcome::Finished(diagnostics))
+}
+
+fn kill(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoMessage {
+    message: Option<RustDiagnostic>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustDiagnostic {
+    message: String,
+    code: Option<RustDiagnosticCode>,
+    level: String,
+    spans: Vec<RustDiagnosticSpan>,
+    children: Vec<RustDiagnostic>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustDiagnosticCode {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustDiagnosticSpan {
+    file_name: String,
+    line_start: u64,
+    line_end: u64,
+    column_start: u64,
+    column_end: u64,
+    is_primary: bool,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+    expansion: Option<Box<RustDiagnosticExpansion>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustDiagnosticExpansion {
+    span: RustDiagnosticSpan,
+}
+
+fn map_rust_diagnostic(
+    rd: &RustDiagnostic,
+    workspace_root: &Path,
+) -> Option<(Url, CheckDiagnostic)> {
+    let primary_span = rd.spans.iter().find(|it| it.is_primary)?;
+    let location = map_span_to_location(primary_span, workspace_root)?;
+
+    let mut message = rd.message.clone();
+    if let Some(label) = &primary_span.label {
+        message.push('\n');
+        message.push_str(label);
+    }
+
+    let mut related_information = Vec::new();
+    for secondary_span in rd.spans.iter().filter(|it| !it.is_primary) {
+        if let (Some(label), Some(location)) =
+            (&secondary_span.label, map_span_to_location(secondary_span, workspace_root))
+        {
+            related_information
+                .push(DiagnosticRelatedInformation { location, message: label.clone() });
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    for child in rd.children.iter() {
+        if let Some(edit) = map_suggestion(child, workspace_root) {
+            suggestions.push((child.message.clone(), edit));
+            continue;
+        }
+        let child_location = child
+            .spans
+            .iter()
+            .find(|it| it.is_primary)
+            .and_then(|it| map_span_to_location(it, workspace_root));
+        match child_location {
+            Some(location) => related_information
+                .push(DiagnosticRelatedInformation { location, message: child.message.clone() }),
+            None => message.push_str(&format!("\n{}: {}", child.level, child.message)),
+        }
+    }
+
+    let diagnostic = Diagnostic {
+        range: location.range,
+        severity: map_level(&rd.level),
+        code: rd.code.as_ref().map(|it| NumberOrString::String(it.code.clone())),
+        source: Some("rustc".to_string()),
+        message,
+        related_information: if related_information.is_empty() {
+            None
+        } else {
+            Some(related_information)
+        },
+    };
+    let fixes = suggestions
+        .into_iter()
+        .map(|(title, edit)| CodeAction {
+            title,
+            kind: Some("quickfix".to_string()),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(edit),
+            command: None,
+        })
+        .collect();
+    Some((location.uri, CheckDiagnostic { diagnostic, fixes }))
+}
+
+/// Converts the replacements suggested by a child diagnostic into an edit.
+fn map_suggestion(rd: &RustDiagnostic, workspace_root: &Path) -> Option<WorkspaceEdit> {
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for span in rd.spans.iter() {
+        let new_text = match (&span.suggested_replacement, &span.suggestion_applicability) {
+            // The replacement contains placeholders like `/* fields */`,
+            // which the user has to fill in first.
+            (Some(_), Some(applicability)) if applicability == "HasPlaceholders" => return None,
+            (Some(new_text), _) => new_text.clone(),
+            (None, _) => continue,
+        };
+        let location = map_span_to_location(span, workspace_root)?;
+        changes.entry(location.uri).or_default().push(TextEdit { range: location.range, new_text });
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    Some(WorkspaceEdit { changes: Some(changes), document_changes: None })
+}
+
+fn map_span_to_location(span: &RustDiagnosticSpan, workspace_root: &Path) -> Option<Location> {
+    // Spans inside of macros point to pseudo files like `<std macros>`, use
+    // the macro call in the user's code instead.
+    if span.file_name.starts_with('<') {
+        let expansion = span.expansion.as_ref()?;
+        return map_span_to_location(&expansion.span, workspace_root);
+    }
+    let uri = Url::from_file_path(workspace_root.join(&span.file_name)).ok()?;
+    // FIXME: rustc counts columns in chars, while LSP counts UTF-16 code units.
+    let range = Range::new(
+        Position::new(span.line_start - 1, span.column_start - 1),
+        Position::new(span.line_end - 1, span.column_end - 1),
+    );
+    Some(Location::new(uri, range))
+}
+
+fn map_level(level: &str) -> Option<DiagnosticSeverity> {
+    let severity = match level {
+        "error" | "error: internal compiler error" => DiagnosticSeverity::Error,
+        "warning" => DiagnosticSeverity::Warning,
+        "note" => DiagnosticSeverity::Information,
+        "help" => DiagnosticSeverity::Hint,
+        _ => return None,
+    };
+    Some(severity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANNED_OUTPUT: &str = r##"{"reason":"compiler-artifact","package_id":"dep 0.1.0","filenames":[]}
+{"reason":"compiler-message","package_id":"foo 0.1.0","message":{"message":"mismatched types","code":{"code":"E0308","explanation":null},"level":"error","spans":[{"file_name":"src/lib.rs","byte_start":38,"byte_end":39,"line_start":2,"line_end":2,"column_start":18,"column_end":19,"is_primary":true,"text":[],"label":"expected &String, found struct `std::string::String`","suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"consider borrowing here","code":null,"level":"help","spans":[{"file_name":"src/lib.rs","byte_start":38,"byte_end":39,"line_start":2,"line_end":2,"column_start":18,"column_end":19,"is_primary":true,"text":[],"label":null,"suggested_replacement":"&s","suggestion_applicability":"MachineApplicable","expansion":null}],"children":[],"rendered":null}],"rendered":"error[E0308]: mismatched types"}}
+{"reason":"compiler-message","package_id":"foo 0.1.0","message":{"message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"src/main.rs","byte_start":20,"byte_end":21,"line_start":3,"line_end":3,"column_start":9,"column_end":10,"is_primary":true,"text":[],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"#[warn(unused_variables)] on by default","code":null,"level":"note","spans":[],"children":[],"rendered":null}],"rendered":"warning: unused variable: `x`"}}
+"##;
+
+    fn parse(line: &str) -> RustDiagnostic {
+        serde_json::from_str::<CargoMessage>(line).unwrap().message.unwrap()
+    }
+
+    #[test]
+    fn test_map_rust_diagnostic() {
+        let workspace_root = std::env::temp_dir();
+        let message = parse(CANNED_OUTPUT.lines().nth(1).unwrap());
+        let (uri, check_diagnostic) = map_rust_diagnostic(&message, &workspace_root).unwrap();
+        assert_eq!(uri, Url::from_file_path(workspace_root.join("src/lib.rs")).unwrap());
+
+        let diagnostic = &check_diagnostic.diagnostic;
+        assert_eq!(diagnostic.range, Range::new(Position::new(1, 17), Position::new(1, 18)));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::Error));
+        assert_eq!(diagnostic.code, Some(NumberOrString::String("E0308".to_string())));
+        assert_eq!(
+            diagnostic.message,
+            "mismatched types\nexpected &String, found struct `std::string::String`"
+        );
+
+        assert_eq!(check_diagnostic.fixes.len(), 1);
+        let fix = &check_diagnostic.fixes[0];
+        assert_eq!(fix.title, "consider borrowing here");
+        let edits = &fix.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits, &vec![TextEdit { range: diagnostic.range, new_text: "&s".to_string() }]);
+    }
+
+    #[test]
+    fn test_map_rust_diagnostic_note() {
+        let workspace_root = std::env::temp_dir();
+        let message = parse(CANNED_OUTPUT.lines().nth(2).unwrap());
+        let (_uri, check_diagnostic) = map_rust_diagnostic(&message, &workspace_root).unwrap();
+        let diagnostic = &check_diagnostic.diagnostic;
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::Warning));
+        assert_eq!(
+            diagnostic.message,
+            "unused variable: `x`\nnote: #[warn(unused_variables)] on by default"
+        );
+        assert!(check_diagnostic.fixes.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_worker_with_fake_cargo() {
+        let workspace_root = tempfile::tempdir().unwrap();
+        let fake_cargo = workspace_root.path().join("fake_cargo.sh");
+        std::fs::write(&fake_cargo, format!("cat <<'EOF'\n{}EOF\n", CANNED_OUTPUT)).unwrap();
+
+        let options = CheckOptions {
+            command: "sh".to_string(),
+            args: vec![fake_cargo.to_str().unwrap().to_string()],
+        };
+        let worker = check_worker(vec![workspace_root.path().to_path_buf()], options);
+        worker.sender().send(()).unwrap();
+        let diagnostics = match worker.receiver().recv().unwrap() {
+            CheckTask::Finished(it) => it,
+            CheckTask::Failed(e) => panic!("cargo check failed: {}", e),
+        };
+
+        let lib_rs = Url::from_file_path(workspace_root.path().join("src/lib.rs")).unwrap();
+        let main_rs = Url::from_file_path(workspace_root.path().join("src/main.rs")).unwrap();
+        assert_eq!(diagnostics.uris().count(), 2);
+        assert_eq!(diagnostics.get(&lib_rs).len(), 1);
+        assert_eq!(diagnostics.get(&main_rs).len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_worker_reports_failures_without_messages() {
+        let workspace_root = tempfile::tempdir().unwrap();
+        let fake_cargo = workspace_root.path().join("fake_cargo.sh");
+        std::fs::write(&fake_cargo, "echo 'error: failed to parse manifest' >&2\nexit 101\n")
+            .unwrap();
+
+        let options = CheckOptions {
+            command: "sh".to_string(),
+            args: vec![fake_cargo.to_str().unwrap().to_string()],
+        };
+        let worker = check_worker(vec![workspace_root.path().to_path_buf()], options);
+        worker.sender().send(()).unwrap();
+        match worker.receiver().recv().unwrap() {
+            CheckTask::Finished(_) => panic!("the check should have failed"),
+            CheckTask::Failed(e) => assert!(e.contains("failed to parse manifest"), "{}", e),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_worker_shuts_down_while_cargo_is_silent() {
+        let workspace_root = tempfile::tempdir().unwrap();
+        let options = CheckOptions { command: "sleep".to_string(), args: vec!["60".to_string()] };
+        let worker = check_worker(vec![workspace_root.path().to_path_buf()], options);
+        worker.sender().send(()).unwrap();
+        let start = std::time::Instant::now();
+        // Dropping the worker closes its input and joins the thread.
+        drop(worker);
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+    }
+}
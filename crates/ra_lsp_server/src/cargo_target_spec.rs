@@ -12,15 +12,21 @@ pub(crate) fn runnable_args(
     kind: &RunnableKind,
 ) -> Result<Vec<String>> {
     let spec = CargoTargetSpec::for_file(world, file_id)?;
+    Ok(cargo_args(spec, kind))
+}
+
+/// The arguments of the cargo command running `kind` in the target `spec`.
+fn cargo_args(spec: Option<CargoTargetSpec>, kind: &RunnableKind) -> Vec<String> {
     let mut res = Vec::new();
     match kind {
-        RunnableKind::Test { name } => {
+        RunnableKind::Test { path } => {
             res.push("test".to_string());
             if let Some(spec) = spec {
                 spec.push_to(&mut res);
             }
             res.push("--".to_string());
-            res.push(name.to_string());
+            res.push(path.to_string());
+            res.push("--exact".to_string());
             res.push("--nocapture".to_string());
         }
         RunnableKind::TestMod { path } => {
@@ -32,13 +38,29 @@ pub(crate) fn runnable_args(
             res.push(path.to_string());
             res.push("--nocapture".to_string());
         }
-        RunnableKind::Bench { name } => {
+        RunnableKind::Bench { path } => {
             res.push("bench".to_string());
             if let Some(spec) = spec {
                 spec.push_to(&mut res);
             }
             res.push("--".to_string());
-            res.push(name.to_string());
+            res.push(path.to_string());
+            res.push("--exact".to_string());
+            res.push("--nocapture".to_string());
+        }
+        RunnableKind::DocTest { path } => {
+            res.push("test".to_string());
+            // `--doc` selects the library target by itself and can't be
+            // combined with `--lib`.
+            if let Some(spec) = spec {
+                res.push("--package".to_string());
+                res.push(spec.package);
+            }
+            res.push("--doc".to_string());
+            // Doc tests are named like `src/lib.rs - foo::Bar (line 12)`, so
+            // `--exact` can't be used.
+            res.push("--".to_string());
+            res.push(path.to_string());
             res.push("--nocapture".to_string());
         }
         RunnableKind::Bin => {
@@ -48,7 +70,7 @@ pub(crate) fn runnable_args(
             }
         }
     }
-    Ok(res)
+    res
 }
 
 pub struct CargoTargetSpec {
@@ -106,3 +128,36 @@ impl CargoTargetSpec {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doc_test_args_select_doc_tests_without_exact_matching() {
+        let spec = CargoTargetSpec {
+            package: "foo".to_string(),
+            target: "foo".to_string(),
+            target_kind: TargetKind::Lib,
+        };
+        let kind = RunnableKind::DocTest { path: "Bar::new".to_string() };
+        assert_eq!(
+            cargo_args(Some(spec), &kind),
+            vec!["test", "--package", "foo", "--doc", "--", "Bar::new", "--nocapture"]
+        );
+    }
+
+    #[test]
+    fn test_args_match_exactly() {
+        let spec = CargoTargetSpec {
+            package: "foo".to_string(),
+            target: "foo".to_string(),
+            target_kind: TargetKind::Lib,
+        };
+        let kind = RunnableKind::Test { path: "tests::bar".to_string() };
+        assert_eq!(
+            cargo_args(Some(spec), &kind),
+            vec!["test", "--package", "foo", "--lib", "--", "tests::bar", "--exact", "--nocapture"]
+        );
+    }
+}
@@ -2,12 +2,13 @@ use lsp_types::{
     self, CreateFile, Documentation, DocumentChangeOperation, DocumentChanges, Location, LocationLink,
     MarkupContent, MarkupKind, Position, Range, RenameFile, ResourceOp, SymbolKind, TextDocumentEdit, TextDocumentIdentifier,
     TextDocumentItem, TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
-    WorkspaceEdit,
+    WorkspaceEdit, DocumentHighlightKind, TextDocumentContentChangeEvent,
 };
 use ra_ide_api::{
     CompletionItem, CompletionItemKind, FileId, FilePosition, FileRange, FileSystemEdit,
     NavigationTarget, SourceChange, SourceFileEdit, RangeInfo,
-    LineCol, LineIndex, translate_offset_with_edit, InsertTextFormat
+    LineCol, LineIndex, translate_offset_with_edit, InsertTextFormat, ReferenceKind,
+    CompletionScore, ImportOrigin,
 };
 use ra_syntax::{SyntaxKind, TextRange, TextUnit};
 use ra_text_edit::{AtomTextEdit, TextEdit};
@@ -78,6 +79,17 @@ impl Conv for CompletionItemKind {
     }
 }
 
+impl Conv for ReferenceKind {
+    type Output = DocumentHighlightKind;
+
+    fn conv(self) -> <Self as Conv>::Output {
+        match self {
+            ReferenceKind::Read | ReferenceKind::Import => DocumentHighlightKind::Read,
+            ReferenceKind::Write => DocumentHighlightKind::Write,
+        }
+    }
+}
+
 impl ConvWith for CompletionItem {
     type Ctx = LineIndex;
     type Output = ::lsp_types::CompletionItem;
@@ -122,6 +134,21 @@ impl ConvWith for CompletionItem {
             InsertTextFormat::Snippet => lsp_types::InsertTextFormat::Snippet,
             InsertTextFormat::PlainText => lsp_types::InsertTextFormat::PlainText,
         });
+        // Clients sort by `sort_text`, falling back to the label, so prefix
+        // the label with the rank of the item to put the best matches first.
+        // Items which need a `use` go after the ones in scope.
+        let import_rank = match self.import_origin() {
+            None => 0,
+            Some(ImportOrigin::CurrentCrate) => 1,
+            Some(ImportOrigin::Dependency) => 2,
+        };
+        let rank = match self.score() {
+            Some(CompletionScore::TypeAndNameMatch) => 0,
+            Some(CompletionScore::TypeMatch) => 1,
+            Some(CompletionScore::NameMatch) => 2,
+            None => 3,
+        };
+        res.sort_text = Some(format!("{}{}{}", import_rank, rank, self.label()));
 
         res
     }
@@ -165,6 +192,37 @@ impl ConvWith for Range {
     }
 }
 
+/// Applies the changes of a `textDocument/didChange` notification to `text`.
+///
+/// If the notification consists of a single ranged change, it is returned as
+/// an edit of the old text, so that the file can be reparsed incrementally.
+pub fn apply_document_changes(
+    text: &mut String,
+    content_changes: Vec<TextDocumentContentChangeEvent>,
+) -> Option<AtomTextEdit> {
+    let is_single_change = content_changes.len() == 1;
+    let mut edit = None;
+    for change in content_changes {
+        match change.range {
+            Some(range) => {
+                // Each change is relative to the text produced by the previous one.
+                let range: TextRange = range.conv_with(&LineIndex::new(text));
+                text.replace_range(range.start().to_usize()..range.end().to_usize(), &change.text);
+                edit = Some(AtomTextEdit::replace(range, change.text));
+            }
+            None => {
+                *text = change.text;
+                edit = None;
+            }
+        }
+    }
+    if is_single_change {
+        edit
+    } else {
+        None
+    }
+}
+
 impl Conv for ra_ide_api::Documentation {
     type Output = lsp_types::Documentation;
     fn conv(self) -> Documentation {
@@ -451,3 +509,100 @@ where
         self.iter.next().map(|item| item.conv_with(self.ctx))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_ide_api::mock_analysis::analysis_and_position;
+
+    use super::*;
+
+    fn change(
+        range: Option<((u64, u64), (u64, u64))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|((l1, c1), (l2, c2))| {
+                Range::new(Position::new(l1, c1), Position::new(l2, c2))
+            }),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_document_changes() {
+        let mut text = String::new();
+        let edit = apply_document_changes(&mut text, vec![change(None, "fn main() {}")]);
+        assert_eq!(text, "fn main() {}");
+        assert!(edit.is_none());
+
+        let edit = apply_document_changes(&mut text, vec![change(Some(((0, 11), (0, 11))), "\n")]);
+        assert_eq!(text, "fn main() {\n}");
+        assert_eq!(edit, Some(AtomTextEdit::insert(11.into(), "\n".to_string())));
+
+        let edit = apply_document_changes(
+            &mut text,
+            vec![
+                change(Some(((1, 0), (1, 0))), "    92\n"),
+                change(Some(((1, 4), (1, 6))), "foo()"),
+                change(Some(((0, 3), (0, 7))), "bar"),
+            ],
+        );
+        assert_eq!(text, "fn bar() {\n    foo()\n}");
+        assert!(edit.is_none());
+    }
+
+    /// Returns the labels of the completions at the cursor, in the order the
+    /// client shows them.
+    fn sorted_completions(fixture: &str) -> Vec<String> {
+        let (analysis, position) = analysis_and_position(fixture);
+        let line_index = analysis.file_line_index(position.file_id);
+        let mut items: Vec<lsp_types::CompletionItem> = analysis
+            .completions(position)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|it| it.conv_with(&line_index))
+            .collect();
+        items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+        items.into_iter().map(|it| it.label).collect()
+    }
+
+    #[test]
+    fn test_completion_sort_text_ranks_by_score() {
+        let labels = sorted_completions(
+            "
+            //- /lib.rs
+            fn bar(count: u32, name: &str) {}
+            fn foo(count: u32, other: u32, name: u32) {
+                bar(<|>)
+            }
+            ",
+        );
+        assert_eq!(labels[..3], ["count", "other", "name"]);
+    }
+
+    #[test]
+    fn test_completion_sort_text_puts_imports_last() {
+        let mut labels = sorted_completions(
+            "
+            //- /lib.rs
+            struct Bazzer;
+            mod bar {
+                pub struct Bazz;
+            }
+            fn foo() { let x: Baz<|> }
+
+            //- /std/lib.rs
+            pub mod collections {
+                pub struct Bazz;
+            }
+            ",
+        );
+        labels.retain(|it| it.starts_with("Bazz"));
+        assert_eq!(
+            labels,
+            vec!["Bazzer", "Bazz (crate::bar::Bazz)", "Bazz (std::collections::Bazz)"]
+        );
+    }
+}
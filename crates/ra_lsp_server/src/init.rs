@@ -17,11 +17,22 @@ pub struct InitializationOptions {
     /// Defaults to `true`
     #[serde(deserialize_with = "nullable_bool_true")]
     pub show_workspace_loaded: bool,
+
+    /// Whether `cargo check` should be run when a file is saved, to report
+    /// the errors of the compiler as diagnostics
+    ///
+    /// Defaults to `true`
+    #[serde(deserialize_with = "nullable_bool_true")]
+    pub cargo_check_enable: bool,
 }
 
 impl Default for InitializationOptions {
     fn default() -> InitializationOptions {
-        InitializationOptions { publish_decorations: false, show_workspace_loaded: true }
+        InitializationOptions {
+            publish_decorations: false,
+            show_workspace_loaded: true,
+            cargo_check_enable: true,
+        }
     }
 }
 
@@ -54,8 +65,11 @@ mod test {
         assert_eq!(default, serde_json::from_str(r#"{}"#).unwrap());
         assert_eq!(
             default,
-            serde_json::from_str(r#"{"publishDecorations":null, "showWorkspaceLoaded":null}"#)
-                .unwrap()
+            serde_json::from_str(
+                r#"{"publishDecorations":null, "showWorkspaceLoaded":null,
+                    "cargoCheckEnable":null}"#
+            )
+            .unwrap()
         );
     }
 }
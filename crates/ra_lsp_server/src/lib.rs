@@ -1,4 +1,5 @@
 mod caps;
+mod cargo_check;
 mod cargo_target_spec;
 mod conv;
 mod main_loop;
@@ -3,22 +3,25 @@ mod subscriptions;
 
 use std::{fmt, path::PathBuf, sync::Arc};
 
-use crossbeam_channel::{select, unbounded, Receiver, RecvError, Sender};
+use crossbeam_channel::{never, select, unbounded, Receiver, RecvError, Sender};
 use failure::{bail, format_err};
 use failure_derive::Fail;
 use gen_lsp_server::{
     handle_shutdown, ErrorCode, RawMessage, RawNotification, RawRequest, RawResponse,
 };
-use lsp_types::NumberOrString;
+use lsp_types::{NumberOrString, Url};
 use ra_ide_api::{Canceled, FileId, LibraryData};
 use ra_vfs::VfsTask;
 use rustc_hash::FxHashSet;
 use serde::{de::DeserializeOwned, Serialize};
 use threadpool::ThreadPool;
+use thread_worker::Worker;
 
 use crate::{
+    cargo_check::{check_worker, CheckOptions, CheckTask},
+    conv::apply_document_changes,
     main_loop::subscriptions::Subscriptions,
-    project_model::workspace_loader,
+    project_model::{workspace_loader, ProjectWorkspace},
     req,
     server_world::{ServerWorld, ServerWorldState},
     Result,
@@ -78,6 +81,19 @@ pub fn main_loop(
         loaded_workspaces
     };
 
+    let check_watcher = if options.cargo_check_enable {
+        let workspace_roots = workspaces
+            .iter()
+            .filter_map(|ws| match ws {
+                ProjectWorkspace::Cargo { cargo, .. } => Some(cargo.workspace_root().to_path_buf()),
+                ProjectWorkspace::Json { .. } => None,
+            })
+            .collect();
+        Some(check_worker(workspace_roots, CheckOptions::default()))
+    } else {
+        None
+    };
+
     let mut state = ServerWorldState::new(ws_roots, workspaces);
 
     log::info!("server initialized, serving requests");
@@ -94,6 +110,7 @@ pub fn main_loop(
         &mut state,
         &mut pending_requests,
         &mut subs,
+        check_watcher.as_ref(),
     );
 
     log::info!("waiting for tasks to finish...");
@@ -102,6 +119,7 @@ pub fn main_loop(
     log::info!("joining threadpool...");
     drop(pool);
     log::info!("...threadpool has finished");
+    drop(check_watcher);
 
     let vfs = Arc::try_unwrap(state.vfs).expect("all snapshots should be dead");
     drop(vfs);
@@ -114,6 +132,7 @@ enum Event {
     Task(Task),
     Vfs(VfsTask),
     Lib(LibraryData),
+    CheckWatcher(CheckTask),
 }
 
 impl fmt::Debug for Event {
@@ -147,6 +166,7 @@ impl fmt::Debug for Event {
             Event::Task(it) => fmt::Debug::fmt(it, f),
             Event::Vfs(it) => fmt::Debug::fmt(it, f),
             Event::Lib(it) => fmt::Debug::fmt(it, f),
+            Event::CheckWatcher(it) => fmt::Debug::fmt(it, f),
         }
     }
 }
@@ -161,6 +181,7 @@ fn main_loop_inner(
     state: &mut ServerWorldState,
     pending_requests: &mut FxHashSet<u64>,
     subs: &mut Subscriptions,
+    check_watcher: Option<&Worker<(), CheckTask>>,
 ) -> Result<()> {
     // We try not to index more than THREADPOOL_SIZE - 3 libraries at the same
     // time to always have a thread ready to react to input.
@@ -169,6 +190,7 @@ fn main_loop_inner(
     let mut send_workspace_notification = true;
 
     let (libdata_sender, libdata_receiver) = unbounded();
+    let check_receiver = check_watcher.map(|it| it.receiver().clone()).unwrap_or_else(never);
     loop {
         state.maybe_collect_garbage();
         log::trace!("selecting");
@@ -182,7 +204,11 @@ fn main_loop_inner(
                 Ok(task) => Event::Vfs(task),
                 Err(RecvError) => bail!("vfs died"),
             },
-            recv(libdata_receiver) -> data => Event::Lib(data.unwrap())
+            recv(libdata_receiver) -> data => Event::Lib(data.unwrap()),
+            recv(check_receiver) -> task => match task {
+                Ok(task) => Event::CheckWatcher(task),
+                Err(RecvError) => bail!("cargo check worker died"),
+            },
         };
         log::info!("loop_turn = {:?}", event);
         let _p = profile("loop_turn");
@@ -197,6 +223,10 @@ fn main_loop_inner(
                 state.add_lib(lib);
                 in_flight_libraries -= 1;
             }
+            Event::CheckWatcher(task) => {
+                on_check_task(task, state, msg_sender, subs);
+                state_changed = true;
+            }
             Event::Msg(msg) => match msg {
                 RawMessage::Request(req) => {
                     let req = match handle_shutdown(req, msg_sender) {
@@ -226,7 +256,7 @@ fn main_loop_inner(
                     }
                 }
                 RawMessage::Notification(not) => {
-                    on_notification(msg_sender, state, pending_requests, subs, not)?;
+                    on_notification(msg_sender, state, pending_requests, subs, check_watcher, not)?;
                     state_changed = true;
                 }
                 RawMessage::Response(resp) => log::error!("unexpected response: {:?}", resp),
@@ -311,6 +341,7 @@ fn on_request(
         .on::<req::Runnables>(handlers::handle_runnables)?
         .on::<req::DecorationsRequest>(handlers::handle_decorations)?
         .on::<req::Completion>(handlers::handle_completion)?
+        .on::<req::ResolveCompletionItem>(handlers::handle_completion_resolve)?
         .on::<req::CodeActionRequest>(handlers::handle_code_action)?
         .on::<req::CodeLensRequest>(handlers::handle_code_lens)?
         .on::<req::CodeLensResolve>(handlers::handle_code_lens_resolve)?
@@ -322,6 +353,7 @@ fn on_request(
         .on::<req::References>(handlers::handle_references)?
         .on::<req::Formatting>(handlers::handle_formatting)?
         .on::<req::DocumentHighlightRequest>(handlers::handle_document_highlight)?
+        .on::<req::InlayHints>(handlers::handle_inlay_hints)?
         .finish();
     match req {
         Ok(id) => {
@@ -338,6 +370,7 @@ fn on_notification(
     state: &mut ServerWorldState,
     pending_requests: &mut FxHashSet<u64>,
     subs: &mut Subscriptions,
+    check_watcher: Option<&Worker<(), CheckTask>>,
     not: RawNotification,
 ) -> Result<()> {
     let not = match not.cast::<req::Cancel>() {
@@ -374,16 +407,38 @@ fn on_notification(
         Err(not) => not,
     };
     let not = match not.cast::<req::DidChangeTextDocument>() {
-        Ok(mut params) => {
+        Ok(params) => {
             let uri = params.text_document.uri;
             let path = uri.to_file_path().map_err(|()| format_err!("invalid uri: {}", uri))?;
-            let text =
-                params.content_changes.pop().ok_or_else(|| format_err!("empty changes"))?.text;
+            let file_id = match state.vfs.read().path2file(path.as_path()) {
+                Some(it) => FileId(it.0.into()),
+                None => return Ok(()),
+            };
+            let mut text = String::clone(&state.analysis_host.analysis().file_text(file_id));
+            // Remember single edits, so that the file can be reparsed incrementally.
+            match apply_document_changes(&mut text, params.content_changes) {
+                Some(edit) => {
+                    state.pending_edits.insert(file_id, edit);
+                }
+                None => {
+                    state.pending_edits.remove(&file_id);
+                }
+            }
             state.vfs.write().change_file_overlay(path.as_path(), text);
             return Ok(());
         }
         Err(not) => not,
     };
+    let not = match not.cast::<req::DidSaveTextDocument>() {
+        Ok(_params) => {
+            if let Some(check_watcher) = check_watcher {
+                // If the channel is full, a check is already pending.
+                let _ = check_watcher.sender().try_send(());
+            }
+            return Ok(());
+        }
+        Err(not) => not,
+    };
     let not = match not.cast::<req::DidCloseTextDocument>() {
         Ok(params) => {
             let uri = params.text_document.uri;
@@ -391,7 +446,10 @@ fn on_notification(
             if let Some(file_id) = state.vfs.write().remove_file_overlay(path.as_path()) {
                 subs.remove_sub(FileId(file_id.0.into()));
             }
-            let params = req::PublishDiagnosticsParams { uri, diagnostics: Vec::new() };
+            // Only the diagnostics of `cargo check` remain for closed files.
+            let diagnostics =
+                state.check_diagnostics.get(&uri).iter().map(|it| it.diagnostic.clone()).collect();
+            let params = req::PublishDiagnosticsParams { uri, diagnostics };
             let not = RawNotification::new::<req::PublishDiagnostics>(&params);
             msg_sender.send(not.into()).unwrap();
             return Ok(());
@@ -402,6 +460,43 @@ fn on_notification(
     Ok(())
 }
 
+fn on_check_task(
+    task: CheckTask,
+    state: &mut ServerWorldState,
+    msg_sender: &Sender<RawMessage>,
+    subs: &Subscriptions,
+) {
+    let diagnostics = match task {
+        CheckTask::Finished(it) => Arc::new(it),
+        CheckTask::Failed(e) => {
+            log::error!("cargo check failed: {}", e);
+            return;
+        }
+    };
+    let old_diagnostics = std::mem::replace(&mut state.check_diagnostics, diagnostics);
+
+    // Diagnostics of subscribed files are merged with our own ones when the
+    // file notifications are updated, all other files are published here.
+    let world = state.snapshot();
+    let subscriptions = subs.subscriptions();
+    let mut uris: Vec<Url> =
+        old_diagnostics.uris().chain(state.check_diagnostics.uris()).cloned().collect();
+    uris.sort();
+    uris.dedup();
+    for uri in uris {
+        if let Ok(file_id) = world.uri_to_file_id(&uri) {
+            if subscriptions.contains(&file_id) {
+                continue;
+            }
+        }
+        let diagnostics =
+            state.check_diagnostics.get(&uri).iter().map(|it| it.diagnostic.clone()).collect();
+        let params = req::PublishDiagnosticsParams { uri, diagnostics };
+        let not = RawNotification::new::<req::PublishDiagnostics>(&params);
+        msg_sender.send(not.into()).unwrap();
+    }
+}
+
 struct PoolDispatcher<'a> {
     req: Option<RawRequest>,
     res: Option<u64>,
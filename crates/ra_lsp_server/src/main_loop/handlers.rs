@@ -9,9 +9,9 @@ use lsp_types::{
 };
 use ra_ide_api::{
     FileId, FilePosition, FileRange, FoldKind, Query, RangeInfo, RunnableKind, Severity, Cancelable,
-    AssistId,
+    AssistId, InlayKind,
 };
-use ra_syntax::{AstNode, SyntaxKind, TextUnit, TextRange};
+use ra_syntax::{AstNode, SmolStr, SyntaxKind, TextUnit, TextRange};
 use ra_prof::profile;
 use rustc_hash::FxHashMap;
 use serde::{Serialize, Deserialize};
@@ -342,9 +342,10 @@ pub fn handle_runnables(
         let r = req::Runnable {
             range: runnable.range.conv_with(&line_index),
             label: match &runnable.kind {
-                RunnableKind::Test { name } => format!("test {}", name),
+                RunnableKind::Test { path } => format!("test {}", path),
                 RunnableKind::TestMod { path } => format!("test-mod {}", path),
-                RunnableKind::Bench { name } => format!("bench {}", name),
+                RunnableKind::Bench { path } => format!("bench {}", path),
+                RunnableKind::DocTest { path } => format!("doctest {}", path),
                 RunnableKind::Bin => "run binary".to_string(),
             },
             bin: "cargo".to_string(),
@@ -428,11 +429,74 @@ pub fn handle_completion(
         Some(items) => items,
     };
     let line_index = world.analysis().file_line_index(position.file_id);
-    let items = items.into_iter().map(|item| item.conv_with(&line_index)).collect();
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let data = item.resolve_data().map(|it| CompletionItemData {
+                position: req::TextDocumentPositionParams::new(
+                    params.text_document.clone(),
+                    params.position,
+                ),
+                definition: it.definition.as_ref().map(|def| (def.ids, def.name.to_string())),
+                field: it.field.as_ref().map(|field| field.to_string()),
+                import: it
+                    .import
+                    .as_ref()
+                    .map(|path| path.iter().map(|it| it.to_string()).collect()),
+            });
+            let mut res = item.conv_with(&line_index);
+            res.data = data.map(|it| to_value(it).unwrap());
+            res
+        })
+        .collect();
 
     Ok(Some(req::CompletionResponse::Array(items)))
 }
 
+/// What is needed to compute the parts of a completion item that are left
+/// out of the completion response, see `handle_completion_resolve`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionItemData {
+    position: req::TextDocumentPositionParams,
+    /// The ids and the name of the definition of the item, as encoded by
+    /// `hir::ModuleDef::to_raw`.
+    definition: Option<([u32; 4], String)>,
+    field: Option<String>,
+    import: Option<Vec<String>>,
+}
+
+pub fn handle_completion_resolve(
+    world: ServerWorld,
+    mut item: lsp_types::CompletionItem,
+) -> Result<lsp_types::CompletionItem> {
+    let data: CompletionItemData = match item.data.take() {
+        Some(data) => serde_json::from_value(data)?,
+        None => return Ok(item),
+    };
+    let position = data.position.try_conv_with(&world)?;
+    let resolve_data = ra_ide_api::CompletionResolveData {
+        definition: data
+            .definition
+            .map(|(ids, name)| ra_ide_api::RawModuleDef { ids, name: name.into() }),
+        field: data.field.map(Into::into),
+        import: data.import.map(|path| path.iter().map(SmolStr::new).collect()),
+    };
+    let resolved = world.analysis().resolve_completion(position, &resolve_data)?;
+
+    if resolved.detail.is_some() {
+        item.detail = resolved.detail;
+    }
+    if let Some(documentation) = resolved.documentation {
+        item.documentation = Some(documentation.conv());
+    }
+    if let Some(edit) = resolved.additional_edits {
+        let line_index = world.analysis().file_line_index(position.file_id);
+        item.additional_text_edits.get_or_insert_with(Vec::new).extend(edit.conv_with(&line_index));
+    }
+    Ok(item)
+}
+
 pub fn handle_folding_range(
     world: ServerWorld,
     params: FoldingRangeParams,
@@ -523,8 +587,7 @@ pub fn handle_prepare_rename(
 
     // Refs should always have a declaration
     let r = refs.declaration();
-    let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.analysis().file_line_index(r.file_id());
     let loc = to_location(r.file_id(), r.range(), &world, &line_index)?;
 
     Ok(Some(PrepareRenameResponse::Range(loc.range)))
@@ -547,7 +610,10 @@ pub fn handle_rename(world: ServerWorld, params: RenameParams) -> Result<Option<
         world.analysis().rename(FilePosition { file_id, offset }, &*params.new_name)?;
     let change = match optional_change {
         None => return Ok(None),
-        Some(it) => it,
+        Some(Err(err)) => {
+            return Err(LspError::new(ErrorCode::InvalidParams as i32, err.to_string()).into());
+        }
+        Some(Ok(it)) => it,
     };
 
     let source_change_req = change.try_conv_with(&world)?;
@@ -568,16 +634,16 @@ pub fn handle_references(
         Some(refs) => refs,
     };
 
+    // References may be spread over several files, each with its own line index
+    let location = |r: FileRange| {
+        let line_index = world.analysis().file_line_index(r.file_id);
+        to_location(r.file_id, r.range, &world, &line_index).ok()
+    };
     let locations = if params.context.include_declaration {
-        refs.into_iter()
-            .filter_map(|r| to_location(r.file_id, r.range, &world, &line_index).ok())
-            .collect()
+        refs.into_iter().filter_map(location).collect()
     } else {
         // Only iterate over the references if include_declaration was false
-        refs.references()
-            .iter()
-            .filter_map(|r| to_location(r.file_id, r.range, &world, &line_index).ok())
-            .collect()
+        refs.references().iter().map(|r| r.file_range).filter_map(location).collect()
     };
 
     Ok(Some(locations))
@@ -665,6 +731,14 @@ pub fn handle_code_action(
         res.push(action);
     }
 
+    let fixes_from_cargo_check = world
+        .check_diagnostics
+        .get(&params.text_document.uri)
+        .iter()
+        .filter(|it| ranges_overlap(it.diagnostic.range, params.range))
+        .flat_map(|it| it.fixes.iter().cloned());
+    res.extend(fixes_from_cargo_check);
+
     for assist in assists {
         let title = assist.change.label.clone();
         let edit = assist.change.try_conv_with(&world)?;
@@ -690,6 +764,13 @@ pub fn handle_code_action(
     Ok(Some(CodeActionResponse::Actions(res)))
 }
 
+/// Checks whether two LSP ranges overlap, without converting them to offsets:
+/// the diagnostics of `cargo check` might be outdated.
+fn ranges_overlap(a: Range, b: Range) -> bool {
+    let pos = |it: Position| (it.line, it.character);
+    pos(a.start) <= pos(b.end) && pos(b.start) <= pos(a.end)
+}
+
 pub fn handle_code_lens(
     world: ServerWorld,
     params: req::CodeLensParams,
@@ -705,6 +786,7 @@ pub fn handle_code_lens(
         let title = match &runnable.kind {
             RunnableKind::Test { .. } | RunnableKind::TestMod { .. } => Some("▶️Run Test"),
             RunnableKind::Bench { .. } => Some("Run Bench"),
+            RunnableKind::DocTest { .. } => Some("▶️Run Doctest"),
             _ => None,
         };
 
@@ -824,11 +906,40 @@ pub fn handle_document_highlight(
         Some(refs) => refs,
     };
 
-    Ok(Some(
-        refs.into_iter()
-            .map(|r| DocumentHighlight { range: r.range.conv_with(&line_index), kind: None })
-            .collect(),
-    ))
+    // Only the references in the current document are highlighted
+    let mut res = Vec::new();
+    let declaration = refs.declaration();
+    if declaration.file_id() == file_id {
+        let range = declaration.range().conv_with(&line_index);
+        res.push(DocumentHighlight { range, kind: None });
+    }
+    for r in refs.references().iter().filter(|r| r.file_range.file_id == file_id) {
+        let range = r.file_range.range.conv_with(&line_index);
+        res.push(DocumentHighlight { range, kind: Some(r.kind.conv()) });
+    }
+    Ok(Some(res))
+}
+
+pub fn handle_inlay_hints(
+    world: ServerWorld,
+    params: req::InlayHintsParams,
+) -> Result<Vec<req::InlayHint>> {
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let analysis = world.analysis();
+    let line_index = analysis.file_line_index(file_id);
+    let res = analysis
+        .inlay_hints(file_id)?
+        .into_iter()
+        .map(|hint| req::InlayHint {
+            range: hint.range.conv_with(&line_index),
+            kind: match hint.kind {
+                InlayKind::TypeHint => req::InlayKind::TypeHint,
+                InlayKind::ParameterHint => req::InlayKind::ParameterHint,
+            },
+            label: hint.label.to_string(),
+        })
+        .collect();
+    Ok(res)
 }
 
 pub fn publish_diagnostics(
@@ -849,6 +960,7 @@ pub fn publish_diagnostics(
             message: d.message,
             related_information: None,
         })
+        .chain(world.check_diagnostics.get(&uri).iter().map(|it| it.diagnostic.clone()))
         .collect();
     Ok(req::PublishDiagnosticsParams { uri, diagnostics })
 }
@@ -867,7 +979,11 @@ fn highlight(world: &ServerWorld, file_id: FileId) -> Result<Vec<Decoration>> {
         .analysis()
         .highlight(file_id)?
         .into_iter()
-        .map(|h| Decoration { range: h.range.conv_with(&line_index), tag: h.tag })
+        .map(|h| Decoration {
+            range: h.range.conv_with(&line_index),
+            tag: h.tag,
+            binding_hash: h.binding_hash.map(|x| x.to_string()),
+        })
         .collect();
     Ok(res)
 }
@@ -878,5 +994,6 @@ fn to_diagnostic_severity(severity: Severity) -> DiagnosticSeverity {
     match severity {
         Error => DiagnosticSeverity::Error,
         WeakWarning => DiagnosticSeverity::Hint,
+        Hint => DiagnosticSeverity::Hint,
     }
 }
@@ -5,7 +5,7 @@ use thread_worker::Worker;
 use crate::Result;
 
 pub use ra_project_model::{
-    ProjectWorkspace, CargoWorkspace, Package, Target, TargetKind, Sysroot,
+    ProjectWorkspace, CargoWorkspace, Package, Target, TargetKind, Sysroot, get_rustc_cfg_options,
 };
 
 pub fn workspace_loader() -> Worker<PathBuf, Result<ProjectWorkspace>> {
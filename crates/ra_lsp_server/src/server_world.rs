@@ -8,14 +8,17 @@ use ra_ide_api::{
     Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, LibraryData,
     SourceRootId
 };
+use ra_text_edit::AtomTextEdit;
 use ra_vfs::{Vfs, VfsChange, VfsFile, VfsRoot};
+use rustc_hash::{FxHashMap, FxHashSet};
 use relative_path::RelativePathBuf;
 use parking_lot::RwLock;
 use failure::{Error, format_err};
 use gen_lsp_server::ErrorCode;
 
 use crate::{
-    project_model::ProjectWorkspace,
+    cargo_check::CheckDiagnostics,
+    project_model::{ProjectWorkspace, get_rustc_cfg_options},
     vfs_filter::IncludeRustFiles,
     Result,
     LspError,
@@ -28,12 +31,17 @@ pub struct ServerWorldState {
     pub workspaces: Arc<Vec<ProjectWorkspace>>,
     pub analysis_host: AnalysisHost,
     pub vfs: Arc<RwLock<Vfs>>,
+    /// Edits of open documents which have not been applied to the analysis
+    /// yet, used to reparse the files incrementally.
+    pub pending_edits: FxHashMap<FileId, AtomTextEdit>,
+    pub check_diagnostics: Arc<CheckDiagnostics>,
 }
 
 pub struct ServerWorld {
     pub workspaces: Arc<Vec<ProjectWorkspace>>,
     pub analysis: Analysis,
     pub vfs: Arc<RwLock<Vfs>>,
+    pub check_diagnostics: Arc<CheckDiagnostics>,
 }
 
 impl ServerWorldState {
@@ -42,15 +50,22 @@ impl ServerWorldState {
 
         let mut roots = Vec::new();
         roots.extend(folder_roots.iter().cloned().map(IncludeRustFiles::member));
+        // Roots of packages which are not workspace members are libraries, even
+        // if they are inside of a folder root.
+        let mut library_roots = FxHashSet::default();
         for ws in workspaces.iter() {
-            roots.extend(IncludeRustFiles::from_roots(ws.to_roots()));
+            let ws_roots = ws.to_roots();
+            library_roots
+                .extend(ws_roots.iter().filter(|it| !it.is_member()).map(|it| it.path().clone()));
+            roots.extend(IncludeRustFiles::from_roots(ws_roots));
         }
 
         let (mut vfs, vfs_roots) = Vfs::new(roots);
         let roots_to_scan = vfs_roots.len();
         for r in vfs_roots {
             let vfs_root_path = vfs.root2path(r);
-            let is_local = folder_roots.iter().any(|it| vfs_root_path.starts_with(it));
+            let is_local = !library_roots.contains(&vfs_root_path)
+                && folder_roots.iter().any(|it| vfs_root_path.starts_with(it));
             change.add_root(SourceRootId(r.0.into()), is_local);
         }
 
@@ -60,8 +75,12 @@ impl ServerWorldState {
             let vfs_file = vfs.load(path);
             vfs_file.map(|f| FileId(f.0.into()))
         };
+        // `cfg(test)` code is always analyzed, so that tests get completion and
+        // diagnostics like the rest of the code.
+        let default_cfg_options = get_rustc_cfg_options().atom("test".into());
+
         for ws in workspaces.iter() {
-            crate_graph.extend(ws.to_crate_graph(&mut load));
+            crate_graph.extend(ws.to_crate_graph(&default_cfg_options, &mut load));
         }
         change.set_crate_graph(crate_graph);
 
@@ -73,6 +92,8 @@ impl ServerWorldState {
             workspaces: Arc::new(workspaces),
             analysis_host,
             vfs: Arc::new(RwLock::new(vfs)),
+            pending_edits: FxHashMap::default(),
+            check_diagnostics: Default::default(),
         }
     }
 
@@ -117,7 +138,11 @@ impl ServerWorldState {
                     change.remove_file(SourceRootId(root.0.into()), FileId(file.0.into()), path)
                 }
                 VfsChange::ChangeFile { file, text } => {
-                    change.change_file(FileId(file.0.into()), text);
+                    let file_id = FileId(file.0.into());
+                    match self.pending_edits.remove(&file_id) {
+                        Some(edit) => change.change_file_with_edit(file_id, text, edit),
+                        None => change.change_file(file_id, text),
+                    }
                 }
             }
         }
@@ -137,6 +162,7 @@ impl ServerWorldState {
             workspaces: Arc::clone(&self.workspaces),
             analysis: self.analysis_host.analysis(),
             vfs: Arc::clone(&self.vfs),
+            check_diagnostics: Arc::clone(&self.check_diagnostics),
         }
     }
 
@@ -503,7 +503,8 @@ fn expand_tt(
             crate::Leaf::Punct(punct) => tt::Leaf::from(punct.clone()).into(),
             crate::Leaf::Var(v) => {
                 if v.text == "crate" {
-                    // FIXME: Properly handle $crate token
+                    // `$crate` is kept as an ident, it is resolved to the
+                    // crate of the macro definition during name resolution.
                     tt::Leaf::from(tt::Ident { text: "$crate".into(), id: TokenId::unspecified() })
                         .into()
                 } else if !ctx.bindings.contains(&v.text) {
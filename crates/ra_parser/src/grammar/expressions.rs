@@ -335,6 +335,16 @@ fn lhs(
             p.bump();
             PREFIX_EXPR
         }
+        // test box_expr
+        // fn foo() {
+        //     let x = box 1i32;
+        //     let y = (box 1i32, box 2i32);
+        // }
+        IDENT if p.at_contextual_kw("box") && EXPR_FIRST.contains(p.nth(1)) => {
+            m = p.start();
+            p.bump_remap(T![box]);
+            BOX_EXPR
+        }
         // test full_range_expr
         // fn foo() { xs[..]; }
         T![..] | T![..=] => {
@@ -380,7 +390,7 @@ fn postfix_expr(
             T![.] if p.nth(1) == IDENT && (p.nth(2) == T!['('] || p.nth(2) == T![::]) => {
                 method_call_expr(p, lhs)
             }
-            T![.] => field_expr(p, lhs),
+            T![.] => dot_expr(p, lhs),
             // test postfix_range
             // fn foo() { let x = 1..; }
             T![..] | T![..=] if !EXPR_FIRST.contains(p.nth(1)) => {
@@ -454,10 +464,20 @@ fn method_call_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
 //     x.1i32;
 //     x.0x01;
 // }
-fn field_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
+fn dot_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
     assert!(p.at(T![.]));
     let m = lhs.precede(p);
     p.bump();
+    // test await_expr
+    // fn foo() {
+    //     x.await;
+    //     x.0.await;
+    //     x.0().await?.hello();
+    // }
+    if p.at(IDENT) && p.at_contextual_kw("await") {
+        p.bump_remap(T![await]);
+        return m.complete(p, AWAIT_EXPR);
+    }
     if p.at(IDENT) {
         name_ref(p)
     } else if p.at(INT_NUMBER) {
@@ -60,6 +60,16 @@ pub(super) fn atom_expr(p: &mut Parser, r: Restrictions) -> Option<(CompletedMar
     if let Some(m) = literal(p) {
         return Some((m, BlockLike::NotBlock));
     }
+    // test try_block_expr
+    // fn foo() {
+    //     let _ = try {};
+    // }
+    if p.at(IDENT) && p.at_contextual_kw("try") && p.nth(1) == T!['{'] {
+        let m = p.start();
+        p.bump_remap(T![try]);
+        block(p);
+        return Some((m.complete(p, TRY_BLOCK_EXPR), BlockLike::Block));
+    }
     if paths::is_path_start(p) || p.at(T![<]) {
         return Some(path_expr(p, r));
     }
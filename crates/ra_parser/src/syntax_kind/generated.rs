@@ -106,6 +106,9 @@ pub enum SyntaxKind {
     AUTO_KW,
     DEFAULT_KW,
     UNION_KW,
+    BOX_KW,
+    TRY_KW,
+    AWAIT_KW,
     INT_NUMBER,
     FLOAT_NUMBER,
     CHAR,
@@ -176,6 +179,7 @@ pub enum SyntaxKind {
     BREAK_EXPR,
     LABEL,
     BLOCK_EXPR,
+    TRY_BLOCK_EXPR,
     RETURN_EXPR,
     MATCH_EXPR,
     MATCH_ARM_LIST,
@@ -189,9 +193,11 @@ pub enum SyntaxKind {
     METHOD_CALL_EXPR,
     FIELD_EXPR,
     TRY_EXPR,
+    AWAIT_EXPR,
     CAST_EXPR,
     REF_EXPR,
     PREFIX_EXPR,
+    BOX_EXPR,
     RANGE_EXPR,
     BIN_EXPR,
     BLOCK,
@@ -334,6 +340,9 @@ macro_rules! T {
     (auto) => { $crate::SyntaxKind::AUTO_KW };
     (default) => { $crate::SyntaxKind::DEFAULT_KW };
     (union) => { $crate::SyntaxKind::UNION_KW };
+    (box) => { $crate::SyntaxKind::BOX_KW };
+    (try) => { $crate::SyntaxKind::TRY_KW };
+    (await) => { $crate::SyntaxKind::AWAIT_KW };
 }
 
 impl From<u16> for SyntaxKind {
@@ -391,6 +400,9 @@ impl SyntaxKind {
             | AUTO_KW
             | DEFAULT_KW
             | UNION_KW
+            | BOX_KW
+            | TRY_KW
+            | AWAIT_KW
                 => true,
             _ => false
         }
@@ -562,6 +574,9 @@ impl SyntaxKind {
             AUTO_KW => &SyntaxInfo { name: "AUTO_KW" },
             DEFAULT_KW => &SyntaxInfo { name: "DEFAULT_KW" },
             UNION_KW => &SyntaxInfo { name: "UNION_KW" },
+            BOX_KW => &SyntaxInfo { name: "BOX_KW" },
+            TRY_KW => &SyntaxInfo { name: "TRY_KW" },
+            AWAIT_KW => &SyntaxInfo { name: "AWAIT_KW" },
             INT_NUMBER => &SyntaxInfo { name: "INT_NUMBER" },
             FLOAT_NUMBER => &SyntaxInfo { name: "FLOAT_NUMBER" },
             CHAR => &SyntaxInfo { name: "CHAR" },
@@ -632,6 +647,7 @@ impl SyntaxKind {
             BREAK_EXPR => &SyntaxInfo { name: "BREAK_EXPR" },
             LABEL => &SyntaxInfo { name: "LABEL" },
             BLOCK_EXPR => &SyntaxInfo { name: "BLOCK_EXPR" },
+            TRY_BLOCK_EXPR => &SyntaxInfo { name: "TRY_BLOCK_EXPR" },
             RETURN_EXPR => &SyntaxInfo { name: "RETURN_EXPR" },
             MATCH_EXPR => &SyntaxInfo { name: "MATCH_EXPR" },
             MATCH_ARM_LIST => &SyntaxInfo { name: "MATCH_ARM_LIST" },
@@ -645,9 +661,11 @@ impl SyntaxKind {
             METHOD_CALL_EXPR => &SyntaxInfo { name: "METHOD_CALL_EXPR" },
             FIELD_EXPR => &SyntaxInfo { name: "FIELD_EXPR" },
             TRY_EXPR => &SyntaxInfo { name: "TRY_EXPR" },
+            AWAIT_EXPR => &SyntaxInfo { name: "AWAIT_EXPR" },
             CAST_EXPR => &SyntaxInfo { name: "CAST_EXPR" },
             REF_EXPR => &SyntaxInfo { name: "REF_EXPR" },
             PREFIX_EXPR => &SyntaxInfo { name: "PREFIX_EXPR" },
+            BOX_EXPR => &SyntaxInfo { name: "BOX_EXPR" },
             RANGE_EXPR => &SyntaxInfo { name: "RANGE_EXPR" },
             BIN_EXPR => &SyntaxInfo { name: "BIN_EXPR" },
             BLOCK => &SyntaxInfo { name: "BLOCK" },
@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use cargo_metadata::{MetadataCommand, CargoOpt};
+use cargo_metadata::MetadataCommand;
 use ra_arena::{Arena, RawId, impl_arena_id};
 use rustc_hash::FxHashMap;
 use failure::format_err;
@@ -38,6 +38,7 @@ struct PackageData {
     is_member: bool,
     dependencies: Vec<PackageDependency>,
     edition: Edition,
+    features: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -90,6 +91,9 @@ impl Package {
     pub fn edition(self, ws: &CargoWorkspace) -> Edition {
         ws.packages[self].edition
     }
+    pub fn features(self, ws: &CargoWorkspace) -> &[String] {
+        &ws.packages[self].features
+    }
     pub fn targets<'a>(self, ws: &'a CargoWorkspace) -> impl Iterator<Item = Target> + 'a {
         ws.packages[self].targets.iter().cloned()
     }
@@ -123,7 +127,7 @@ impl Target {
 impl CargoWorkspace {
     pub fn from_cargo_metadata(cargo_toml: &Path) -> Result<CargoWorkspace> {
         let mut meta = MetadataCommand::new();
-        meta.manifest_path(cargo_toml).features(CargoOpt::AllFeatures);
+        meta.manifest_path(cargo_toml);
         if let Some(parent) = cargo_toml.parent() {
             meta.current_dir(parent);
         }
@@ -143,6 +147,7 @@ impl CargoWorkspace {
                 is_member,
                 edition: Edition::from_string(&meta_pkg.edition),
                 dependencies: Vec::new(),
+                features: Vec::new(),
             });
             let pkg_data = &mut packages[pkg];
             pkg_by_id.insert(meta_pkg.id.clone(), pkg);
@@ -159,6 +164,9 @@ impl CargoWorkspace {
         let resolve = meta.resolve.expect("metadata executed with deps");
         for node in resolve.nodes {
             let source = pkg_by_id[&node.id];
+            // Only the features cargo resolved as enabled (the default ones
+            // and those requested by dependents) are active for `cfg`.
+            packages[source].features = node.features;
             for dep_node in node.deps {
                 let dep =
                     PackageDependency { name: dep_node.name.into(), pkg: pkg_by_id[&dep_node.pkg] };
@@ -176,4 +184,8 @@ impl CargoWorkspace {
     pub fn target_by_root(&self, root: &Path) -> Option<Target> {
         self.packages().filter_map(|pkg| pkg.targets(self).find(|it| it.root(self) == root)).next()
     }
+
+    pub fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
 }
@@ -6,12 +6,14 @@ use std::{
     fs::File,
     io::BufReader,
     path::{Path, PathBuf},
+    process::Command,
 };
 
 use failure::bail;
 use rustc_hash::FxHashMap;
 
-use ra_db::{CrateGraph, FileId, Edition};
+use ra_cfg::CfgOptions;
+use ra_db::{CrateGraph, FileId, Edition, Env};
 
 use serde_json::from_reader;
 
@@ -133,7 +135,11 @@ impl ProjectWorkspace {
         }
     }
 
-    pub fn to_crate_graph(&self, load: &mut dyn FnMut(&Path) -> Option<FileId>) -> CrateGraph {
+    pub fn to_crate_graph(
+        &self,
+        default_cfg_options: &CfgOptions,
+        load: &mut dyn FnMut(&Path) -> Option<FileId>,
+    ) -> CrateGraph {
         let mut crate_graph = CrateGraph::default();
         match self {
             ProjectWorkspace::Json { project } => {
@@ -145,7 +151,22 @@ impl ProjectWorkspace {
                             json_project::Edition::Edition2015 => Edition::Edition2015,
                             json_project::Edition::Edition2018 => Edition::Edition2018,
                         };
-                        crates.insert(crate_id, crate_graph.add_crate_root(file_id, edition));
+                        let mut cfg_options = default_cfg_options.clone();
+                        for name in krate.atom_cfgs.iter() {
+                            cfg_options = cfg_options.atom(name.into());
+                        }
+                        for (key, value) in krate.key_value_cfgs.iter() {
+                            cfg_options = cfg_options.key_value(key.into(), value.into());
+                        }
+                        crates.insert(
+                            crate_id,
+                            crate_graph.add_crate_root(
+                                file_id,
+                                edition,
+                                cfg_options,
+                                Env::default(),
+                            ),
+                        );
                     }
                 }
 
@@ -173,7 +194,12 @@ impl ProjectWorkspace {
                     if let Some(file_id) = load(krate.root(&sysroot)) {
                         sysroot_crates.insert(
                             krate,
-                            crate_graph.add_crate_root(file_id, Edition::Edition2015),
+                            crate_graph.add_crate_root(
+                                file_id,
+                                Edition::Edition2015,
+                                default_cfg_options.clone(),
+                                Env::default(),
+                            ),
                         );
                     }
                 }
@@ -201,7 +227,19 @@ impl ProjectWorkspace {
                         let root = tgt.root(&cargo);
                         if let Some(file_id) = load(root) {
                             let edition = pkg.edition(&cargo);
-                            let crate_id = crate_graph.add_crate_root(file_id, edition);
+                            let mut cfg_options = default_cfg_options.clone();
+                            for feature in pkg.features(&cargo) {
+                                cfg_options = cfg_options.feature(feature.into());
+                            }
+                            let mut env = Env::default();
+                            env.set("CARGO_PKG_NAME", pkg.name(&cargo).to_string());
+                            env.set(
+                                "CARGO_MANIFEST_DIR",
+                                pkg.root(&cargo).to_string_lossy().into_owned(),
+                            );
+                            // FIXME: set `OUT_DIR` once build scripts are run
+                            let crate_id =
+                                crate_graph.add_crate_root(file_id, edition, cfg_options, env);
                             if tgt.kind(&cargo) == TargetKind::Lib {
                                 lib_tgt = Some(crate_id);
                                 pkg_to_lib_crate.insert(pkg, crate_id);
@@ -269,6 +307,28 @@ impl ProjectWorkspace {
     }
 }
 
+/// Returns the cfg options of the host target, as reported by `rustc --print cfg`.
+///
+/// If `rustc` can't be run, an empty set of options is returned.
+pub fn get_rustc_cfg_options() -> CfgOptions {
+    let mut cfg_options = CfgOptions::default();
+    match (|| -> Result<String> {
+        let output = Command::new("rustc").args(&["--print", "cfg"]).output()?;
+        if !output.status.success() {
+            bail!("rustc --print cfg exited with {}", output.status);
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    })() {
+        Ok(rustc_cfgs) => {
+            for line in rustc_cfgs.lines() {
+                cfg_options = cfg_options.add_from_rustc_output(line);
+            }
+        }
+        Err(e) => log::error!("failed to get rustc cfgs: {}", e),
+    }
+    cfg_options
+}
+
 fn find_rust_project_json(path: &Path) -> Option<PathBuf> {
     if path.ends_with("rust-project.json") {
         return Some(path.to_path_buf());
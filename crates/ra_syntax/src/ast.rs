@@ -18,7 +18,7 @@ pub use self::{
     traits::*,
     tokens::*,
     extensions::{PathSegmentKind, StructKind,FieldKind, SelfParamKind},
-    expr_extensions::{ElseBranch, PrefixOp, BinOp, LiteralKind,ArrayExprKind},
+    expr_extensions::{ElseBranch, PrefixOp, BinOp, RangeOp, LiteralKind,ArrayExprKind},
 };
 
 /// The main trait to go from untyped `SyntaxNode`  to a typed ast. The
@@ -1,7 +1,7 @@
 //! Various extension methods to ast Expr Nodes, which are hard to code-generate.
 
 use crate::{
-    SyntaxToken, SyntaxElement, SmolStr,
+    SyntaxNode, SyntaxToken, SyntaxElement, SmolStr,
     ast::{self, AstNode, AstChildren, children, child_opt},
     SyntaxKind::*,
     T
@@ -130,6 +130,26 @@ pub enum BinOp {
     BitXorAssign,
 }
 
+impl BinOp {
+    /// Checks whether this is `=` or one of the compound assignment operators.
+    pub fn is_assignment(self) -> bool {
+        match self {
+            BinOp::Assignment
+            | BinOp::AddAssign
+            | BinOp::DivAssign
+            | BinOp::MulAssign
+            | BinOp::RemAssign
+            | BinOp::ShrAssign
+            | BinOp::ShlAssign
+            | BinOp::SubAssign
+            | BinOp::BitOrAssign
+            | BinOp::BitAndAssign
+            | BinOp::BitXorAssign => true,
+            _ => false,
+        }
+    }
+}
+
 impl ast::BinExpr {
     fn op_details(&self) -> Option<(SyntaxToken, BinOp)> {
         self.syntax().children_with_tokens().filter_map(|it| it.as_token()).find_map(|c| {
@@ -194,6 +214,84 @@ impl ast::BinExpr {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RangeOp {
+    /// `..`
+    Exclusive,
+    /// `..=`
+    Inclusive,
+}
+
+impl ast::RangeExpr {
+    fn op_details(&self) -> Option<(usize, SyntaxToken, RangeOp)> {
+        self.syntax().children_with_tokens().enumerate().find_map(|(ix, child)| {
+            let token = child.as_token()?;
+            let op = match token.kind() {
+                T![..] => RangeOp::Exclusive,
+                T![..=] => RangeOp::Inclusive,
+                _ => return None,
+            };
+            Some((ix, token, op))
+        })
+    }
+
+    pub fn op_kind(&self) -> Option<RangeOp> {
+        self.op_details().map(|t| t.2)
+    }
+
+    pub fn op_token(&self) -> Option<SyntaxToken> {
+        self.op_details().map(|t| t.1)
+    }
+
+    pub fn start(&self) -> Option<&ast::Expr> {
+        let op_ix = self.op_details()?.0;
+        self.syntax()
+            .children_with_tokens()
+            .take(op_ix)
+            .find_map(|it| ast::Expr::cast(it.as_node()?))
+    }
+
+    pub fn end(&self) -> Option<&ast::Expr> {
+        let op_ix = self.op_details()?.0;
+        self.syntax()
+            .children_with_tokens()
+            .skip(op_ix + 1)
+            .find_map(|it| ast::Expr::cast(it.as_node()?))
+    }
+}
+
+impl ast::IndexExpr {
+    pub fn base(&self) -> Option<&ast::Expr> {
+        children(self).nth(0)
+    }
+
+    pub fn index(&self) -> Option<&ast::Expr> {
+        children(self).nth(1)
+    }
+}
+
+impl ast::Label {
+    pub fn lifetime_token(&self) -> Option<SyntaxToken> {
+        lifetime_token(self.syntax())
+    }
+}
+
+impl ast::BreakExpr {
+    pub fn lifetime_token(&self) -> Option<SyntaxToken> {
+        lifetime_token(self.syntax())
+    }
+}
+
+impl ast::ContinueExpr {
+    pub fn lifetime_token(&self) -> Option<SyntaxToken> {
+        lifetime_token(self.syntax())
+    }
+}
+
+fn lifetime_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.children_with_tokens().filter_map(|it| it.as_token()).find(|it| it.kind() == LIFETIME)
+}
+
 pub enum ArrayExprKind<'a> {
     Repeat { initializer: Option<&'a ast::Expr>, repeat: Option<&'a ast::Expr> },
     ElementList(AstChildren<'a, ast::Expr>),
@@ -4,7 +4,7 @@
 use itertools::Itertools;
 
 use crate::{
-    SmolStr, SyntaxToken,
+    SmolStr, SyntaxToken, SyntaxNode,
     ast::{self, AstNode, children, child_opt},
     SyntaxKind::*,
     SyntaxElement, T,
@@ -89,6 +89,11 @@ impl ast::Attr {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathSegmentKind<'a> {
     Name(&'a ast::NameRef),
+    /// A qualified path segment like `<T>` or `<T as Trait>`.
+    Type {
+        type_ref: Option<&'a ast::TypeRef>,
+        trait_ref: Option<&'a ast::PathType>,
+    },
     SelfKw,
     SuperKw,
     CrateKw,
@@ -110,6 +115,15 @@ impl ast::PathSegment {
                 T![self] => PathSegmentKind::SelfKw,
                 T![super] => PathSegmentKind::SuperKw,
                 T![crate] => PathSegmentKind::CrateKw,
+                T![<] => {
+                    // <T> or <T as Trait>
+                    // T is any TypeRef, Trait has to be a PathType
+                    let mut type_refs = self.syntax().children().filter_map(ast::TypeRef::cast);
+                    let type_ref = type_refs.next();
+                    let trait_ref =
+                        type_refs.next().and_then(|it| ast::PathType::cast(it.syntax()));
+                    PathSegmentKind::Type { type_ref, trait_ref }
+                }
                 _ => return None,
             }
         };
@@ -237,6 +251,38 @@ impl ast::FnDef {
             .and_then(|it| it.as_token())
             .filter(|it| it.kind() == T![;])
     }
+
+    /// Returns the `extern` block this function is declared in, if it is a
+    /// foreign function.
+    pub fn extern_block(&self) -> Option<&ast::ExternBlock> {
+        self.syntax().parent()?.parent().and_then(ast::ExternBlock::cast)
+    }
+}
+
+impl ast::StaticDef {
+    /// Returns the `extern` block this static is declared in, if it is a
+    /// foreign static.
+    pub fn extern_block(&self) -> Option<&ast::ExternBlock> {
+        self.syntax().parent()?.parent().and_then(ast::ExternBlock::cast)
+    }
+}
+
+impl ast::Abi {
+    /// The name of the ABI without the quotes, `"C"` if it is not given.
+    pub fn name(&self) -> SmolStr {
+        let string = self.syntax().children_with_tokens().find(|it| it.kind() == STRING);
+        match string.and_then(|it| it.as_token()) {
+            Some(token) => token.text().trim_matches('"').into(),
+            None => "C".into(),
+        }
+    }
+}
+
+impl ast::ParamList {
+    /// Checks for the `...` of a C-variadic function, `fn printf(fmt: *const u8, ...)`.
+    pub fn is_variadic(&self) -> bool {
+        self.syntax().children_with_tokens().any(|it| it.kind() == T![...])
+    }
 }
 
 impl ast::LetStmt {
@@ -300,6 +346,45 @@ impl ast::BindPat {
     }
 }
 
+impl ast::LiteralPat {
+    pub fn is_negated(&self) -> bool {
+        self.syntax().children_with_tokens().any(|n| n.kind() == T![-])
+    }
+}
+
+impl ast::TuplePat {
+    /// The number of `args` before the `..`, if there is one.
+    pub fn ellipsis_pos(&self) -> Option<usize> {
+        ellipsis_pos(self.syntax())
+    }
+}
+
+impl ast::TupleStructPat {
+    /// The number of `args` before the `..`, if there is one.
+    pub fn ellipsis_pos(&self) -> Option<usize> {
+        ellipsis_pos(self.syntax())
+    }
+}
+
+impl ast::SlicePat {
+    /// The number of `args` before the `..`, if there is one.
+    pub fn ellipsis_pos(&self) -> Option<usize> {
+        ellipsis_pos(self.syntax())
+    }
+}
+
+fn ellipsis_pos(node: &SyntaxNode) -> Option<usize> {
+    let mut pats_before = 0;
+    for child in node.children_with_tokens() {
+        match child {
+            SyntaxElement::Token(token) if token.kind() == T![..] => return Some(pats_before),
+            SyntaxElement::Node(node) if ast::Pat::cast(node).is_some() => pats_before += 1,
+            _ => {}
+        }
+    }
+    None
+}
+
 impl ast::PointerType {
     pub fn is_mut(&self) -> bool {
         self.syntax().children_with_tokens().any(|n| n.kind() == T![mut])
@@ -369,6 +454,15 @@ impl ast::WherePred {
     }
 }
 
+impl ast::TypeBound {
+    pub fn lifetime_token(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(|it| it.as_token())
+            .find(|it| it.kind() == LIFETIME)
+    }
+}
+
 impl ast::TraitDef {
     pub fn is_auto(&self) -> bool {
         self.syntax().children_with_tokens().any(|t| t.kind() == T![auto])
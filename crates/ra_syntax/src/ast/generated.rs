@@ -17,6 +17,34 @@ use crate::{
     ast::{self, AstNode},
 };
 
+// Abi
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Abi {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for Abi {
+    type Repr = rowan::SyntaxNode;
+}
+
+impl AstNode for Abi {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            ABI => Some(Abi::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for Abi {
+    type Owned = TreeArc<Abi>;
+    fn to_owned(&self) -> TreeArc<Abi> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+
+impl Abi {}
+
 // Alias
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -214,6 +242,38 @@ impl Attr {
     }
 }
 
+// AwaitExpr
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct AwaitExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for AwaitExpr {
+    type Repr = rowan::SyntaxNode;
+}
+
+impl AstNode for AwaitExpr {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            AWAIT_EXPR => Some(AwaitExpr::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for AwaitExpr {
+    type Owned = TreeArc<AwaitExpr>;
+    fn to_owned(&self) -> TreeArc<AwaitExpr> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+
+impl AwaitExpr {
+    pub fn expr(&self) -> Option<&Expr> {
+        super::child_opt(self)
+    }
+}
+
 // BinExpr
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -339,11 +399,47 @@ impl ToOwned for BlockExpr {
 
 
 impl BlockExpr {
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
+
     pub fn block(&self) -> Option<&Block> {
         super::child_opt(self)
     }
 }
 
+// BoxExpr
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct BoxExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for BoxExpr {
+    type Repr = rowan::SyntaxNode;
+}
+
+impl AstNode for BoxExpr {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            BOX_EXPR => Some(BoxExpr::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for BoxExpr {
+    type Owned = TreeArc<BoxExpr>;
+    fn to_owned(&self) -> TreeArc<BoxExpr> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+
+impl BoxExpr {
+    pub fn expr(&self) -> Option<&Expr> {
+        super::child_opt(self)
+    }
+}
+
 // BreakExpr
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -705,6 +801,7 @@ pub enum ExprKind<'a> {
     BreakExpr(&'a BreakExpr),
     Label(&'a Label),
     BlockExpr(&'a BlockExpr),
+    TryBlockExpr(&'a TryBlockExpr),
     ReturnExpr(&'a ReturnExpr),
     MatchExpr(&'a MatchExpr),
     StructLit(&'a StructLit),
@@ -713,9 +810,11 @@ pub enum ExprKind<'a> {
     MethodCallExpr(&'a MethodCallExpr),
     FieldExpr(&'a FieldExpr),
     TryExpr(&'a TryExpr),
+    AwaitExpr(&'a AwaitExpr),
     CastExpr(&'a CastExpr),
     RefExpr(&'a RefExpr),
     PrefixExpr(&'a PrefixExpr),
+    BoxExpr(&'a BoxExpr),
     RangeExpr(&'a RangeExpr),
     BinExpr(&'a BinExpr),
     Literal(&'a Literal),
@@ -786,6 +885,11 @@ impl<'a> From<&'a BlockExpr> for &'a Expr {
         Expr::cast(&n.syntax).unwrap()
     }
 }
+impl<'a> From<&'a TryBlockExpr> for &'a Expr {
+    fn from(n: &'a TryBlockExpr) -> &'a Expr {
+        Expr::cast(&n.syntax).unwrap()
+    }
+}
 impl<'a> From<&'a ReturnExpr> for &'a Expr {
     fn from(n: &'a ReturnExpr) -> &'a Expr {
         Expr::cast(&n.syntax).unwrap()
@@ -826,6 +930,11 @@ impl<'a> From<&'a TryExpr> for &'a Expr {
         Expr::cast(&n.syntax).unwrap()
     }
 }
+impl<'a> From<&'a AwaitExpr> for &'a Expr {
+    fn from(n: &'a AwaitExpr) -> &'a Expr {
+        Expr::cast(&n.syntax).unwrap()
+    }
+}
 impl<'a> From<&'a CastExpr> for &'a Expr {
     fn from(n: &'a CastExpr) -> &'a Expr {
         Expr::cast(&n.syntax).unwrap()
@@ -841,6 +950,11 @@ impl<'a> From<&'a PrefixExpr> for &'a Expr {
         Expr::cast(&n.syntax).unwrap()
     }
 }
+impl<'a> From<&'a BoxExpr> for &'a Expr {
+    fn from(n: &'a BoxExpr) -> &'a Expr {
+        Expr::cast(&n.syntax).unwrap()
+    }
+}
 impl<'a> From<&'a RangeExpr> for &'a Expr {
     fn from(n: &'a RangeExpr) -> &'a Expr {
         Expr::cast(&n.syntax).unwrap()
@@ -879,6 +993,7 @@ impl AstNode for Expr {
             | BREAK_EXPR
             | LABEL
             | BLOCK_EXPR
+            | TRY_BLOCK_EXPR
             | RETURN_EXPR
             | MATCH_EXPR
             | STRUCT_LIT
@@ -887,9 +1002,11 @@ impl AstNode for Expr {
             | METHOD_CALL_EXPR
             | FIELD_EXPR
             | TRY_EXPR
+            | AWAIT_EXPR
             | CAST_EXPR
             | REF_EXPR
             | PREFIX_EXPR
+            | BOX_EXPR
             | RANGE_EXPR
             | BIN_EXPR
             | LITERAL
@@ -921,6 +1038,7 @@ impl Expr {
             BREAK_EXPR => ExprKind::BreakExpr(BreakExpr::cast(&self.syntax).unwrap()),
             LABEL => ExprKind::Label(Label::cast(&self.syntax).unwrap()),
             BLOCK_EXPR => ExprKind::BlockExpr(BlockExpr::cast(&self.syntax).unwrap()),
+            TRY_BLOCK_EXPR => ExprKind::TryBlockExpr(TryBlockExpr::cast(&self.syntax).unwrap()),
             RETURN_EXPR => ExprKind::ReturnExpr(ReturnExpr::cast(&self.syntax).unwrap()),
             MATCH_EXPR => ExprKind::MatchExpr(MatchExpr::cast(&self.syntax).unwrap()),
             STRUCT_LIT => ExprKind::StructLit(StructLit::cast(&self.syntax).unwrap()),
@@ -929,9 +1047,11 @@ impl Expr {
             METHOD_CALL_EXPR => ExprKind::MethodCallExpr(MethodCallExpr::cast(&self.syntax).unwrap()),
             FIELD_EXPR => ExprKind::FieldExpr(FieldExpr::cast(&self.syntax).unwrap()),
             TRY_EXPR => ExprKind::TryExpr(TryExpr::cast(&self.syntax).unwrap()),
+            AWAIT_EXPR => ExprKind::AwaitExpr(AwaitExpr::cast(&self.syntax).unwrap()),
             CAST_EXPR => ExprKind::CastExpr(CastExpr::cast(&self.syntax).unwrap()),
             REF_EXPR => ExprKind::RefExpr(RefExpr::cast(&self.syntax).unwrap()),
             PREFIX_EXPR => ExprKind::PrefixExpr(PrefixExpr::cast(&self.syntax).unwrap()),
+            BOX_EXPR => ExprKind::BoxExpr(BoxExpr::cast(&self.syntax).unwrap()),
             RANGE_EXPR => ExprKind::RangeExpr(RangeExpr::cast(&self.syntax).unwrap()),
             BIN_EXPR => ExprKind::BinExpr(BinExpr::cast(&self.syntax).unwrap()),
             LITERAL => ExprKind::Literal(Literal::cast(&self.syntax).unwrap()),
@@ -969,12 +1089,50 @@ impl ToOwned for ExprStmt {
 }
 
 
+impl ast::AttrsOwner for ExprStmt {}
 impl ExprStmt {
     pub fn expr(&self) -> Option<&Expr> {
         super::child_opt(self)
     }
 }
 
+// ExternBlock
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct ExternBlock {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for ExternBlock {
+    type Repr = rowan::SyntaxNode;
+}
+
+impl AstNode for ExternBlock {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            EXTERN_BLOCK => Some(ExternBlock::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for ExternBlock {
+    type Owned = TreeArc<ExternBlock>;
+    fn to_owned(&self) -> TreeArc<ExternBlock> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+
+impl ast::AttrsOwner for ExternBlock {}
+impl ExternBlock {
+    pub fn abi(&self) -> Option<&Abi> {
+        super::child_opt(self)
+    }
+
+    pub fn extern_item_list(&self) -> Option<&ExternItemList> {
+        super::child_opt(self)
+    }
+}
+
 // ExternCrateItem
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -1001,6 +1159,7 @@ impl ToOwned for ExternCrateItem {
 }
 
 
+impl ast::AttrsOwner for ExternCrateItem {}
 impl ExternCrateItem {
     pub fn name_ref(&self) -> Option<&NameRef> {
         super::child_opt(self)
@@ -1011,6 +1170,94 @@ impl ExternCrateItem {
     }
 }
 
+// ExternItem
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct ExternItem {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for ExternItem {
+    type Repr = rowan::SyntaxNode;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternItemKind<'a> {
+    FnDef(&'a FnDef),
+    StaticDef(&'a StaticDef),
+}
+impl<'a> From<&'a FnDef> for &'a ExternItem {
+    fn from(n: &'a FnDef) -> &'a ExternItem {
+        ExternItem::cast(&n.syntax).unwrap()
+    }
+}
+impl<'a> From<&'a StaticDef> for &'a ExternItem {
+    fn from(n: &'a StaticDef) -> &'a ExternItem {
+        ExternItem::cast(&n.syntax).unwrap()
+    }
+}
+
+
+impl AstNode for ExternItem {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            | FN_DEF
+            | STATIC_DEF => Some(ExternItem::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for ExternItem {
+    type Owned = TreeArc<ExternItem>;
+    fn to_owned(&self) -> TreeArc<ExternItem> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+impl ExternItem {
+    pub fn kind(&self) -> ExternItemKind {
+        match self.syntax.kind() {
+            FN_DEF => ExternItemKind::FnDef(FnDef::cast(&self.syntax).unwrap()),
+            STATIC_DEF => ExternItemKind::StaticDef(StaticDef::cast(&self.syntax).unwrap()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl ast::AttrsOwner for ExternItem {}
+impl ExternItem {}
+
+// ExternItemList
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct ExternItemList {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for ExternItemList {
+    type Repr = rowan::SyntaxNode;
+}
+
+impl AstNode for ExternItemList {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            EXTERN_ITEM_LIST => Some(ExternItemList::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for ExternItemList {
+    type Owned = TreeArc<ExternItemList>;
+    fn to_owned(&self) -> TreeArc<ExternItemList> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+
+impl ExternItemList {
+    pub fn extern_items(&self) -> impl Iterator<Item = &ExternItem> {
+        super::children(self)
+    }
+}
+
 // FieldExpr
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -1148,6 +1395,10 @@ impl ast::TypeParamsOwner for FnDef {}
 impl ast::AttrsOwner for FnDef {}
 impl ast::DocCommentsOwner for FnDef {}
 impl FnDef {
+    pub fn abi(&self) -> Option<&Abi> {
+        super::child_opt(self)
+    }
+
     pub fn param_list(&self) -> Option<&ParamList> {
         super::child_opt(self)
     }
@@ -1225,6 +1476,10 @@ impl ToOwned for ForExpr {
 
 impl ast::LoopBodyOwner for ForExpr {}
 impl ForExpr {
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
+
     pub fn pat(&self) -> Option<&Pat> {
         super::child_opt(self)
     }
@@ -1393,6 +1648,7 @@ impl ImplItem {
     }
 }
 
+impl ast::AttrsOwner for ImplItem {}
 impl ImplItem {}
 
 // ImplTraitType
@@ -1577,6 +1833,7 @@ impl ToOwned for LetStmt {
 
 
 impl ast::TypeAscriptionOwner for LetStmt {}
+impl ast::AttrsOwner for LetStmt {}
 impl LetStmt {
     pub fn pat(&self) -> Option<&Pat> {
         super::child_opt(self)
@@ -1731,7 +1988,11 @@ impl ToOwned for LoopExpr {
 
 
 impl ast::LoopBodyOwner for LoopExpr {}
-impl LoopExpr {}
+impl LoopExpr {
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
+}
 
 // MacroCall
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -2077,6 +2338,7 @@ pub enum ModuleItemKind<'a> {
     ImplBlock(&'a ImplBlock),
     UseItem(&'a UseItem),
     ExternCrateItem(&'a ExternCrateItem),
+    ExternBlock(&'a ExternBlock),
     ConstDef(&'a ConstDef),
     StaticDef(&'a StaticDef),
     Module(&'a Module),
@@ -2121,6 +2383,11 @@ impl<'a> From<&'a ExternCrateItem> for &'a ModuleItem {
         ModuleItem::cast(&n.syntax).unwrap()
     }
 }
+impl<'a> From<&'a ExternBlock> for &'a ModuleItem {
+    fn from(n: &'a ExternBlock) -> &'a ModuleItem {
+        ModuleItem::cast(&n.syntax).unwrap()
+    }
+}
 impl<'a> From<&'a ConstDef> for &'a ModuleItem {
     fn from(n: &'a ConstDef) -> &'a ModuleItem {
         ModuleItem::cast(&n.syntax).unwrap()
@@ -2149,6 +2416,7 @@ impl AstNode for ModuleItem {
             | IMPL_BLOCK
             | USE_ITEM
             | EXTERN_CRATE_ITEM
+            | EXTERN_BLOCK
             | CONST_DEF
             | STATIC_DEF
             | MODULE => Some(ModuleItem::from_repr(syntax.into_repr())),
@@ -2174,6 +2442,7 @@ impl ModuleItem {
             IMPL_BLOCK => ModuleItemKind::ImplBlock(ImplBlock::cast(&self.syntax).unwrap()),
             USE_ITEM => ModuleItemKind::UseItem(UseItem::cast(&self.syntax).unwrap()),
             EXTERN_CRATE_ITEM => ModuleItemKind::ExternCrateItem(ExternCrateItem::cast(&self.syntax).unwrap()),
+            EXTERN_BLOCK => ModuleItemKind::ExternBlock(ExternBlock::cast(&self.syntax).unwrap()),
             CONST_DEF => ModuleItemKind::ConstDef(ConstDef::cast(&self.syntax).unwrap()),
             STATIC_DEF => ModuleItemKind::StaticDef(StaticDef::cast(&self.syntax).unwrap()),
             MODULE => ModuleItemKind::Module(Module::cast(&self.syntax).unwrap()),
@@ -2182,6 +2451,7 @@ impl ModuleItem {
     }
 }
 
+impl ast::AttrsOwner for ModuleItem {}
 impl ModuleItem {}
 
 // Name
@@ -2266,6 +2536,7 @@ impl ToOwned for NamedField {
 }
 
 
+impl ast::AttrsOwner for NamedField {}
 impl NamedField {
     pub fn name_ref(&self) -> Option<&NameRef> {
         super::child_opt(self)
@@ -2849,6 +3120,14 @@ impl PathSegment {
     pub fn type_arg_list(&self) -> Option<&TypeArgList> {
         super::child_opt(self)
     }
+
+    pub fn param_list(&self) -> Option<&ParamList> {
+        super::child_opt(self)
+    }
+
+    pub fn ret_type(&self) -> Option<&RetType> {
+        super::child_opt(self)
+    }
 }
 
 // PathType
@@ -3340,7 +3619,11 @@ impl ToOwned for SlicePat {
 }
 
 
-impl SlicePat {}
+impl SlicePat {
+    pub fn args(&self) -> impl Iterator<Item = &Pat> {
+        super::children(self)
+    }
+}
 
 // SliceType
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -3672,6 +3955,38 @@ impl TraitDef {
     }
 }
 
+// TryBlockExpr
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct TryBlockExpr {
+    pub(crate) syntax: SyntaxNode,
+}
+unsafe impl TransparentNewType for TryBlockExpr {
+    type Repr = rowan::SyntaxNode;
+}
+
+impl AstNode for TryBlockExpr {
+    fn cast(syntax: &SyntaxNode) -> Option<&Self> {
+        match syntax.kind() {
+            TRY_BLOCK_EXPR => Some(TryBlockExpr::from_repr(syntax.into_repr())),
+            _ => None,
+        }
+    }
+    fn syntax(&self) -> &SyntaxNode { &self.syntax }
+}
+
+impl ToOwned for TryBlockExpr {
+    type Owned = TreeArc<TryBlockExpr>;
+    fn to_owned(&self) -> TreeArc<TryBlockExpr> { TreeArc::cast(self.syntax.to_owned()) }
+}
+
+
+impl TryBlockExpr {
+    pub fn block(&self) -> Option<&Block> {
+        super::child_opt(self)
+    }
+}
+
 // TryExpr
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -4447,6 +4762,10 @@ impl ToOwned for WhileExpr {
 
 impl ast::LoopBodyOwner for WhileExpr {}
 impl WhileExpr {
+    pub fn label(&self) -> Option<&Label> {
+        super::child_opt(self)
+    }
+
     pub fn condition(&self) -> Option<&Condition> {
         super::child_opt(self)
     }
@@ -0,0 +1,4 @@
+fn foo() {
+    let x = box 1i32;
+    let y = (box 1i32, box 2i32);
+}
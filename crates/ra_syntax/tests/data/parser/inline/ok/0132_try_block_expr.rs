@@ -0,0 +1,3 @@
+fn foo() {
+    let _ = try {};
+}
@@ -6,7 +6,7 @@ pub use crate::text_edit::{TextEdit, TextEditBuilder};
 use text_unit::{TextRange, TextUnit};
 
 /// Must not overlap with other `AtomTextEdit`s
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AtomTextEdit {
     /// Refers to offsets in the original text
     pub delete: TextRange,
@@ -15,9 +15,11 @@ export interface CargoWatchOptions {
 
 export class Config {
     public highlightingOn = true;
+    public rainbowHighlightingOn = false;
     public enableEnhancedTyping = true;
     public raLspServerPath = RA_LSP_DEBUG || 'ra_lsp_server';
     public showWorkspaceLoadedNotification = true;
+    public cargoCheckEnable = true;
     public cargoWatchOptions: CargoWatchOptions = {
         enableOnStartup: 'ask',
         trace: 'off',
@@ -39,12 +41,22 @@ export class Config {
             this.highlightingOn = config.get('highlightingOn') as boolean;
         }
 
+        if (config.has('rainbowHighlightingOn')) {
+            this.rainbowHighlightingOn = config.get(
+                'rainbowHighlightingOn'
+            ) as boolean;
+        }
+
         if (config.has('showWorkspaceLoadedNotification')) {
             this.showWorkspaceLoadedNotification = config.get(
                 'showWorkspaceLoadedNotification'
             ) as boolean;
         }
 
+        if (config.has('cargoCheckEnable')) {
+            this.cargoCheckEnable = config.get('cargoCheckEnable') as boolean;
+        }
+
         if (!this.highlightingOn && Server) {
             Server.highlighter.removeHighlights();
         }
@@ -6,8 +6,16 @@ import { Server } from './server';
 export interface Decoration {
     range: lc.Range;
     tag: string;
+    bindingHash?: string;
 }
 
+// The binding hash is a decimal u64, so its last digits are as good as random
+// and give every variable a stable color.
+const colorForBinding = (bindingHash: string): string => {
+    const hue = Number(bindingHash.slice(-8)) % 360;
+    return `hsl(${hue}, 60%, 60%)`;
+};
+
 export class Highlighter {
     private static initDecorations(): Map<
         string,
@@ -32,12 +40,28 @@ export class Highlighter {
             colorContrib('keyword.control'),
             colorContrib('keyword.unsafe'),
             colorContrib('function'),
+            colorContrib('function.unsafe'),
+            colorContrib('method'),
             colorContrib('parameter'),
+            colorContrib('variable'),
+            colorContrib('variable.mut'),
+            colorContrib('field'),
+            colorContrib('struct'),
+            colorContrib('enum'),
+            colorContrib('variant'),
+            colorContrib('trait'),
+            colorContrib('type'),
+            colorContrib('type.param'),
+            colorContrib('constant'),
+            colorContrib('static'),
+            colorContrib('static.unsafe'),
+            colorContrib('module'),
             colorContrib('builtin'),
             colorContrib('text'),
             colorContrib('attribute'),
             colorContrib('literal'),
-            colorContrib('macro')
+            colorContrib('macro'),
+            colorContrib('inactive')
         ];
 
         return new Map<string, vscode.TextEditorDecorationType>(decorations);
@@ -48,7 +72,18 @@ export class Highlighter {
         vscode.TextEditorDecorationType
     > | null = null;
 
+    // Decorations for the colors of local bindings, by binding hash
+    private bindingDecorations: Map<
+        string,
+        vscode.TextEditorDecorationType
+    > = new Map();
+
     public removeHighlights() {
+        for (const decoration of this.bindingDecorations.values()) {
+            decoration.dispose();
+        }
+        this.bindingDecorations.clear();
+
         if (this.decorations == null) {
             return;
         }
@@ -75,13 +110,24 @@ export class Highlighter {
             byTag.set(tag, []);
         }
 
+        const byBinding: Map<string, vscode.Range[]> = new Map();
+        for (const hash of this.bindingDecorations.keys()) {
+            byBinding.set(hash, []);
+        }
+
         for (const d of highlights) {
             if (!byTag.get(d.tag)) {
                 continue;
             }
-            byTag
-                .get(d.tag)!
-                .push(Server.client.protocol2CodeConverter.asRange(d.range));
+            const range = Server.client.protocol2CodeConverter.asRange(d.range);
+            if (Server.config.rainbowHighlightingOn && d.bindingHash) {
+                if (!byBinding.has(d.bindingHash)) {
+                    byBinding.set(d.bindingHash, []);
+                }
+                byBinding.get(d.bindingHash)!.push(range);
+            } else {
+                byTag.get(d.tag)!.push(range);
+            }
         }
 
         for (const tag of byTag.keys()) {
@@ -91,5 +137,16 @@ export class Highlighter {
             const ranges = byTag.get(tag)!;
             editor.setDecorations(dec, ranges);
         }
+
+        for (const [hash, ranges] of byBinding.entries()) {
+            let dec = this.bindingDecorations.get(hash);
+            if (dec == null) {
+                dec = vscode.window.createTextEditorDecorationType({
+                    color: colorForBinding(hash)
+                });
+                this.bindingDecorations.set(hash, dec);
+            }
+            editor.setDecorations(dec, ranges);
+        }
     }
 }